@@ -0,0 +1,25 @@
+//! Feeds arbitrary bytes to the converter as a zsh history file. Converting must neither panic
+//! nor fail, and every entry must come out as a single fish history record. Run with
+//! `cargo +nightly fuzz run convert`.
+#![no_main]
+
+use std::sync::LazyLock;
+
+use libfuzzer_sys::fuzz_target;
+use zsh_history_to_fish::{Converter, DEFAULT_MAX_LINE_BYTES};
+
+static RUNTIME: LazyLock<tokio::runtime::Runtime> = LazyLock::new(|| {
+    tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap()
+});
+
+fuzz_target!(|data: &[u8]| {
+    RUNTIME.block_on(async {
+        let converter = Converter::from_bytes(data);
+        let (entries, _) = converter.convert_with_errors().await.unwrap();
+        for entry in entries {
+            assert_eq!(entry.to_string().lines().count(), 2, "{entry:?}");
+            // an invalid byte decodes to U+FFFD, which takes three bytes
+            assert!(entry.cmd.len() <= 3 * DEFAULT_MAX_LINE_BYTES);
+        }
+    });
+});
@@ -0,0 +1,214 @@
+//! Suggesting fish abbreviations for the commands typed most often.
+
+use std::{
+    collections::{HashMap, HashSet},
+    sync::LazyLock,
+};
+
+use regex::Regex;
+
+use crate::history_converter::Entry;
+
+/// Which commands to suggest abbreviations for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AbbrOptions {
+    /// Suggest at most this many abbreviations.
+    pub top: usize,
+    /// Only suggest abbreviations for commands typed at least this many times.
+    pub min_count: usize,
+    /// Only suggest abbreviations for commands at least this many characters long.
+    pub min_length: usize,
+}
+
+impl Default for AbbrOptions {
+    fn default() -> Self {
+        Self { top: 20, min_count: 5, min_length: 10 }
+    }
+}
+
+/// A suggested abbreviation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Abbreviation {
+    /// What to type, the initials of the words of the expansion.
+    pub name: String,
+    /// What it expands to.
+    pub expansion: String,
+    /// How many commands start with the expansion.
+    pub count: usize,
+}
+
+/// Suggest abbreviations for the command prefixes typed most often, like `gcm` for
+/// `git commit -m`, most frequent first.
+///
+/// Every prefix of whole words of a command counts, unless it ends within quotes, so
+/// `git commit -m fix` and `git commit -m wip` both count for `git commit -m`. A prefix which is
+/// typed exactly as often as a longer one is left out for the longer one. Multi-line commands, and
+/// commands which look like they contain a password or a token, are not considered at all. Names
+/// taken by a more frequent abbreviation get a numeric suffix, like `gcm2`.
+pub fn suggest_abbreviations(entries: &[Entry], options: &AbbrOptions) -> Vec<Abbreviation> {
+    let mut counts = HashMap::<String, usize>::new();
+    for entry in entries {
+        let cmd = entry.cmd.trim();
+        if cmd.contains('\n') || looks_secret(cmd) {
+            continue;
+        }
+        let words = cmd.split_whitespace().collect::<Vec<_>>();
+        // each prefix counts once per command
+        let prefixes = (1..=words.len())
+            .map(|n| words[..n].join(" "))
+            .filter(|prefix| prefix.len() >= options.min_length && balanced(prefix))
+            .collect::<HashSet<_>>();
+        for prefix in prefixes {
+            *counts.entry(prefix).or_default() += 1;
+        }
+    }
+
+    let frequent = counts
+        .into_iter()
+        .filter(|&(_, count)| count >= options.min_count)
+        .collect::<Vec<_>>();
+    // a longer prefix typed as often says the same with fewer keystrokes left
+    let mut candidates = frequent
+        .iter()
+        .filter(|(prefix, count)| {
+            !frequent.iter().any(|(longer, n)| {
+                n == count
+                    && longer.len() > prefix.len()
+                    && longer.starts_with(&format!("{prefix} "))
+            })
+        })
+        .cloned()
+        .collect::<Vec<_>>();
+    candidates.sort_by(|(a, m), (b, n)| n.cmp(m).then(b.len().cmp(&a.len())).then(a.cmp(b)));
+    candidates.truncate(options.top);
+
+    let mut names = HashSet::new();
+    candidates
+        .into_iter()
+        .map(|(expansion, count)| {
+            let initials = initials(&expansion);
+            let name = (1..)
+                .map(|n| match n {
+                    1 => initials.clone(),
+                    n => format!("{initials}{n}"),
+                })
+                .find(|name| names.insert(name.clone()))
+                .unwrap();
+            Abbreviation { name, expansion, count }
+        })
+        .collect()
+}
+
+// The first letter or digit of every word, lowercased, e.g. `gcm` for `git commit -m`.
+fn initials(expansion: &str) -> String {
+    let initials = expansion
+        .split_whitespace()
+        .filter_map(|word| word.chars().find(char::is_ascii_alphanumeric))
+        .map(|c| c.to_ascii_lowercase())
+        .collect::<String>();
+    match initials.is_empty() {
+        true => "a".to_string(),
+        false => initials,
+    }
+}
+
+// Whether `prefix` does not end within quotes.
+fn balanced(prefix: &str) -> bool {
+    prefix.matches('\'').count().is_multiple_of(2) && prefix.matches('"').count().is_multiple_of(2)
+}
+
+// Whether `cmd` looks like it contains a password, a token, or a key, which an abbreviation
+// should not spell out.
+fn looks_secret(cmd: &str) -> bool {
+    static SECRET: LazyLock<Regex> = LazyLock::new(|| {
+        Regex::new(concat!(
+            r"(?i)(password|passwd|secret|token|api[_-]?key|authorization|bearer|credential)",
+            r"|AKIA[0-9A-Z]{16}",
+            r"|\bgh[pousr]_[A-Za-z0-9]{20,}",
+            r"|\bxox[abpr]-",
+            r"|://[^/\s:@]+:[^/\s@]+@",
+            r"|\s-p\S",
+        ))
+        .unwrap()
+    });
+
+    SECRET.is_match(cmd)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Each command repeated as often as it says.
+    fn entries(cmds: &[(&str, usize)]) -> Vec<Entry> {
+        let cmds = cmds.iter().flat_map(|&(cmd, n)| std::iter::repeat_n(cmd, n));
+        cmds.map(|cmd| Entry { cmd: cmd.to_string(), ..Default::default() })
+            .collect()
+    }
+
+    fn suggested(cmds: &[(&str, usize)], options: AbbrOptions) -> Vec<(String, String, usize)> {
+        let abbreviations = suggest_abbreviations(&entries(cmds), &options);
+        let abbreviations = abbreviations.into_iter();
+        abbreviations
+            .map(|abbr| (abbr.name, abbr.expansion, abbr.count))
+            .collect()
+    }
+
+    fn abbr(name: &str, expansion: &str, count: usize) -> (String, String, usize) {
+        (name.into(), expansion.into(), count)
+    }
+
+    const OPTIONS: AbbrOptions = AbbrOptions { top: 10, min_count: 3, min_length: 10 };
+
+    #[test]
+    fn most_frequent_first_with_names_taken_once() {
+        let cmds = [
+            ("git commit -m fix", 3),
+            ("git commit -m wip", 3),
+            ("git checkout main", 4),
+            ("cargo build --release", 2),
+        ];
+        assert_eq!(
+            suggested(&cmds, OPTIONS),
+            [
+                // `git commit`, typed as often, is left for the longer prefix
+                abbr("gcm", "git commit -m", 6),
+                abbr("gcm2", "git checkout main", 4),
+                abbr("gcmf", "git commit -m fix", 3),
+                abbr("gcmw", "git commit -m wip", 3),
+            ]
+        );
+        let top = suggested(&cmds, AbbrOptions { top: 1, ..OPTIONS });
+        assert_eq!(top, [abbr("gcm", "git commit -m", 6)]);
+    }
+
+    #[test]
+    fn thresholds_leave_out_rare_and_short_commands() {
+        let cmds = [("cargo build --release", 3), ("make test", 5), ("ls -la", 9)];
+        assert_eq!(suggested(&cmds, OPTIONS), [abbr("cbr", "cargo build --release", 3)]);
+        assert_eq!(suggested(&cmds, AbbrOptions { min_count: 4, ..OPTIONS }), []);
+        let short = suggested(&cmds, AbbrOptions { min_length: 6, ..OPTIONS });
+        assert_eq!(short[..2], [abbr("ll", "ls -la", 9), abbr("mt", "make test", 5)]);
+    }
+
+    #[test]
+    fn secrets_multiline_commands_and_open_quotes_are_left_out() {
+        let cmds = [
+            ("mysql -psecret db", 5),
+            ("curl -H 'Authorization: x' api", 5),
+            ("for i in 1 2\ndo echo $i\ndone", 5),
+        ];
+        assert_eq!(suggested(&cmds, OPTIONS), []);
+
+        // `echo 'a`, typed twice as often, ends within the quotes
+        let cmds = [("echo 'a b' c", 5), ("echo 'a c' d", 5)];
+        assert_eq!(
+            suggested(&cmds, AbbrOptions { min_length: 1, ..OPTIONS }),
+            [
+                abbr("e", "echo", 10),
+                abbr("eabc", "echo 'a b' c", 5),
+                abbr("eacd", "echo 'a c' d", 5),
+            ]
+        );
+    }
+}
@@ -0,0 +1,271 @@
+//! Expanding zsh aliases in the commands, for a shell which does not know them.
+
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use crate::{history_converter::Entry, transform::Transform, zoxide::commands};
+
+/// The aliases of a zsh configuration, by name.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Aliases {
+    /// The regular aliases, which zsh expands in the first word of a command.
+    pub regular: HashMap<String, String>,
+    /// The global aliases, defined with `alias -g`, which zsh expands anywhere.
+    pub global: HashMap<String, String>,
+}
+
+/// Parse the alias definitions in `text`, like `alias gst='git status'` or `alias -g G='| grep'`
+/// lines, as in an `aliases.zsh`, or the `gst='git status'` lines `alias` prints. The values are
+/// unquoted the way zsh unquotes them when defining the alias. Suffix aliases, defined with
+/// `alias -s`, and other lines are skipped.
+pub fn parse_aliases(text: &str) -> Aliases {
+    let mut aliases = Aliases::default();
+    let lines = text.lines().map(str::trim);
+    for line in lines.filter(|line| !line.starts_with('#')) {
+        for words in commands(line) {
+            let (global, definitions) = match &words[..] {
+                [alias, rest @ ..] if alias == "alias" => {
+                    let flags = rest.iter().take_while(|word| word.starts_with('-'));
+                    let flags =
+                        flags.map(|flags| flags.trim_start_matches('-')).collect::<String>();
+                    if flags.contains('s') {
+                        continue;
+                    }
+                    let count = rest.iter().take_while(|word| word.starts_with('-')).count();
+                    (flags.contains('g'), &rest[count..])
+                }
+                // what `alias` prints
+                [_] => (false, &words[..]),
+                _ => continue,
+            };
+            for definition in definitions {
+                let Some((name, value)) = definition.split_once('=') else {
+                    continue;
+                };
+                if name.is_empty() {
+                    continue;
+                }
+                let map = match global {
+                    true => &mut aliases.global,
+                    false => &mut aliases.regular,
+                };
+                map.insert(name.to_string(), value.to_string());
+            }
+        }
+    }
+    aliases
+}
+
+/// Expand the aliases in `cmd`, or return `None` if it has none. A regular alias is expanded in
+/// the first word of each command of `cmd`, after any variable assignments, and with `global` a
+/// global alias in any word. Only unquoted words are expanded, as zsh does, and only once, so an
+/// alias referring to another or to itself is not expanded again.
+pub fn expand_aliases(cmd: &str, aliases: &Aliases, global: bool) -> Option<String> {
+    let mut expansions = Vec::new();
+    for (range, first) in words(cmd) {
+        let word = &cmd[range.clone()];
+        let value = match first {
+            true => aliases.regular.get(word),
+            false => None,
+        };
+        let value = value.or_else(|| global.then(|| aliases.global.get(word)).flatten());
+        if let Some(value) = value {
+            expansions.push((range, value));
+        }
+    }
+    if expansions.is_empty() {
+        return None;
+    }
+
+    let mut expanded = cmd.to_string();
+    for (range, value) in expansions.into_iter().rev() {
+        expanded.replace_range(range, value);
+    }
+    Some(expanded)
+}
+
+// The unquoted words of `cmd` which could be aliases, with whether they are in command position.
+fn words(cmd: &str) -> Vec<(std::ops::Range<usize>, bool)> {
+    let mut words = Vec::new();
+    // where the current word started, and whether it is quoted anywhere
+    let mut word: Option<(usize, bool)> = None;
+    let mut command_position = true;
+    let mut chars = cmd.char_indices();
+
+    let mut end_word = |word: &mut Option<(usize, bool)>, end: usize, first: &mut bool| {
+        if let Some((start, quoted)) = word.take() {
+            let assignment = cmd[start..end].split_once('=').is_some_and(|(name, _)| {
+                !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+            });
+            if !quoted && !assignment {
+                words.push((start..end, *first));
+            }
+            // the command follows the assignments before it
+            *first = *first && assignment;
+        }
+    };
+
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\'' | '"' | '\\' => {
+                word = Some((word.map_or(i, |(start, _)| start), true));
+                match c {
+                    '\\' => {
+                        chars.next();
+                    }
+                    quote => {
+                        while let Some((_, c)) = chars.next() {
+                            match c {
+                                '\\' if quote == '"' => {
+                                    chars.next();
+                                }
+                                c if c == quote => break,
+                                _ => {}
+                            }
+                        }
+                    }
+                }
+            }
+            c if c.is_whitespace() || matches!(c, ';' | '&' | '|' | '(' | ')') => {
+                end_word(&mut word, i, &mut command_position);
+                if c == '\n' || matches!(c, ';' | '&' | '|' | '(' | ')') {
+                    command_position = true;
+                }
+            }
+            _ => {
+                word.get_or_insert((i, false));
+            }
+        }
+    }
+    end_word(&mut word, cmd.len(), &mut command_position);
+    words
+}
+
+/// `--expand-aliases` as a [`Transform`], expanding the aliases in every command with
+/// [`expand_aliases`].
+#[derive(Debug, Clone)]
+pub struct ExpandAliases {
+    aliases: Aliases,
+    global: bool,
+    expanded: Arc<AtomicUsize>,
+}
+
+impl ExpandAliases {
+    /// Expand `aliases`, the global ones too with `global`.
+    pub fn new(aliases: Aliases, global: bool) -> Self {
+        Self { aliases, global, expanded: Arc::default() }
+    }
+
+    /// How many commands had aliases expanded so far.
+    pub fn expanded(&self) -> usize {
+        self.expanded.load(Ordering::Relaxed)
+    }
+}
+
+impl Transform for ExpandAliases {
+    fn apply(&self, mut entry: Entry) -> Option<Entry> {
+        if let Some(cmd) = expand_aliases(&entry.cmd, &self.aliases, self.global) {
+            entry.cmd = cmd;
+            self.expanded.fetch_add(1, Ordering::Relaxed);
+        }
+        Some(entry)
+    }
+
+    fn summary(&self) -> Option<String> {
+        match self.expanded() {
+            0 => None,
+            n => Some(format!("expanded aliases in {n} commands")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALIASES: &str = r#"# aliases.zsh
+alias gst='git status'
+alias kgp="kubectl get pods"
+alias dcu='docker compose up -d' ll='ls -l'
+alias -g G='| grep'
+alias -s txt=vim
+alias gl=gl
+alias gs=gst
+"#;
+
+    fn aliases() -> Aliases {
+        parse_aliases(ALIASES)
+    }
+
+    fn expand(cmd: &str) -> Option<String> {
+        expand_aliases(cmd, &aliases(), false)
+    }
+
+    #[test]
+    fn definitions_are_unquoted() {
+        let aliases = aliases();
+        assert_eq!(aliases.regular["gst"], "git status");
+        assert_eq!(aliases.regular["kgp"], "kubectl get pods");
+        assert_eq!(aliases.regular["ll"], "ls -l");
+        assert_eq!(aliases.global["G"], "| grep");
+        assert!(!aliases.regular.contains_key("txt") && !aliases.global.contains_key("txt"));
+    }
+
+    #[test]
+    fn what_alias_prints() {
+        let aliases = parse_aliases("gst='git status'\nquote='echo '\\''hi'\\'''\n");
+        assert_eq!(aliases.regular["gst"], "git status");
+        assert_eq!(aliases.regular["quote"], "echo 'hi'");
+    }
+
+    #[test]
+    fn the_first_word_is_expanded() {
+        assert_eq!(expand("gst").unwrap(), "git status");
+        assert_eq!(expand("kgp -n kube-system").unwrap(), "kubectl get pods -n kube-system");
+        assert_eq!(expand("FOO=1 dcu web").unwrap(), "FOO=1 docker compose up -d web");
+        assert_eq!(expand("cd src && gst; ll").unwrap(), "cd src && git status; ls -l");
+    }
+
+    #[test]
+    fn arguments_are_not_expanded() {
+        assert_eq!(expand("echo gst"), None);
+        assert_eq!(expand("man kgp"), None);
+        assert_eq!(expand("gstx"), None);
+        assert_eq!(expand("'gst'"), None);
+        assert_eq!(expand("\\gst"), None);
+    }
+
+    #[test]
+    fn global_aliases_only_when_asked() {
+        assert_eq!(expand("ps aux G ssh"), None);
+        let expanded = expand_aliases("ps aux G ssh", &aliases(), true);
+        assert_eq!(expanded.unwrap(), "ps aux | grep ssh");
+    }
+
+    #[test]
+    fn aliases_are_expanded_once() {
+        assert_eq!(expand("gl").unwrap(), "gl");
+        assert_eq!(expand("gs").unwrap(), "gst");
+    }
+
+    #[test]
+    fn quotes_in_values_are_kept() {
+        let aliases = parse_aliases(r#"alias say="echo 'hello world'""#);
+        let expanded = expand_aliases("say again", &aliases, false);
+        assert_eq!(expanded.unwrap(), "echo 'hello world' again");
+    }
+
+    #[test]
+    fn expanded_commands_are_counted() {
+        let expand = ExpandAliases::new(aliases(), false);
+        let entry = |cmd: &str| Entry { cmd: cmd.to_string(), ..Default::default() };
+        assert_eq!(expand.apply(entry("gst")).unwrap().cmd, "git status");
+        assert_eq!(expand.apply(entry("ls")).unwrap().cmd, "ls");
+        assert_eq!(expand.summary(), Some("expanded aliases in 1 commands".into()));
+    }
+}
@@ -0,0 +1,251 @@
+//! Reading and writing the SQLite database of atuin.
+
+use std::path::Path;
+
+use anyhow::Result;
+
+use crate::{bash::ParsedEntry, history_converter::Entry};
+
+/// The atuin release the databases [`write_atuin`] writes are known to work with.
+pub const ATUIN_VERSION: &str = "18.4";
+
+/// Read the history in an atuin database, `~/.local/share/atuin/history.db`, returning every
+/// entry with its row id in place of a line.
+///
+/// Each row of the `history` table becomes an entry, with its `timestamp` and `duration`, which
+/// atuin keeps in nanoseconds, in seconds. Deleted rows and blank commands are skipped. Only rows
+/// started within `since` and `until`, both inclusive, are read, which the query itself filters
+/// for. The database is opened read-only.
+pub fn read_atuin(path: &Path, since: Option<i64>, until: Option<i64>) -> Result<Vec<ParsedEntry>> {
+    query(path, since, until)
+}
+
+/// Write the entries into the `history` table of the atuin database at `path`, creating the
+/// database and the table if there are none, and returning how many rows were added.
+///
+/// Every row gets an id derived from its command and time, so entries which an earlier run wrote
+/// are skipped rather than added twice. What zsh does not record is filled in like atuin's own
+/// import does: the directory is `unknown` and the exit status -1, all rows share one session, and
+/// the hostname is `<host>:<user>` of the current user, as the ones atuin records itself.
+pub fn write_atuin(path: &Path, entries: &[Entry]) -> Result<usize> {
+    insert(path, entries)
+}
+
+#[cfg(feature = "sqlite")]
+const NANOS: i64 = 1_000_000_000;
+
+#[cfg(feature = "sqlite")]
+fn query(path: &Path, since: Option<i64>, until: Option<i64>) -> Result<Vec<ParsedEntry>> {
+    use rusqlite::params;
+
+    use crate::sqlite;
+
+    let db = sqlite::open(path)?;
+    let mut statement = db.prepare(
+        "SELECT rowid, command, timestamp, duration FROM history
+         WHERE deleted_at IS NULL AND trim(command) != ''
+           AND (?1 IS NULL OR timestamp >= ?1 * 1000000000)
+           AND (?2 IS NULL OR timestamp < (?2 + 1) * 1000000000)
+         ORDER BY timestamp, rowid",
+    )?;
+    let rows = statement.query_map(params![since, until], |row| {
+        // atuin records -1 for commands it did not see finish
+        let duration = row.get::<_, Option<i64>>(3)?.filter(|&duration| duration >= 0);
+        let entry = Entry {
+            cmd: row.get(1)?,
+            when: row.get::<_, i64>(2)?.div_euclid(NANOS),
+            duration: duration.map(|duration| (duration + NANOS / 2) / NANOS),
+            ..Default::default()
+        };
+        Ok((row.get::<_, i64>(0)? as usize, Ok(entry)))
+    })?;
+
+    Ok(rows.collect::<rusqlite::Result<_>>()?)
+}
+
+#[cfg(feature = "sqlite")]
+fn insert(path: &Path, entries: &[Entry]) -> Result<usize> {
+    use rusqlite::{params, Connection};
+    use uuid::Uuid;
+
+    // the columns of atuin's own migrations, so atuin takes the table for one it created
+    const SCHEMA: &str = "
+        CREATE TABLE IF NOT EXISTS history (
+            id TEXT PRIMARY KEY,
+            timestamp INTEGER NOT NULL,
+            duration INTEGER NOT NULL,
+            exit INTEGER NOT NULL,
+            command TEXT NOT NULL,
+            cwd TEXT NOT NULL,
+            session TEXT NOT NULL,
+            hostname TEXT NOT NULL,
+            deleted_at INTEGER,
+            UNIQUE(timestamp, cwd, command)
+        );
+        CREATE INDEX IF NOT EXISTS idx_history_timestamp ON history(timestamp);
+        CREATE INDEX IF NOT EXISTS idx_history_command ON history(command);
+        CREATE INDEX IF NOT EXISTS idx_history_command_timestamp ON history(command, timestamp);";
+
+    let namespace =
+        Uuid::new_v5(&Uuid::NAMESPACE_URL, b"https://github.com/0x6b/zsh-history-to-fish");
+    let session = Uuid::new_v5(&namespace, b"session").simple().to_string();
+    let hostname = format!(
+        "{}:{}",
+        hostname(),
+        std::env::var("USER")
+            .or_else(|_| std::env::var("LOGNAME"))
+            .unwrap_or_default()
+    );
+
+    let mut db = Connection::open(path)?;
+    db.execute_batch(SCHEMA)?;
+    let transaction = db.transaction()?;
+    let mut added = 0;
+    {
+        let mut statement = transaction.prepare(
+            "INSERT OR IGNORE INTO history
+                 (id, timestamp, duration, exit, command, cwd, session, hostname)
+             VALUES (?1, ?2, ?3, -1, ?4, 'unknown', ?5, ?6)",
+        )?;
+        for entry in entries {
+            let id = Uuid::new_v5(&namespace, format!("{}\0{}", entry.when, entry.cmd).as_bytes());
+            let duration = entry.duration.map_or(-1, |duration| duration * NANOS);
+            added += statement.execute(params![
+                id.simple().to_string(),
+                entry.when * NANOS,
+                duration,
+                entry.cmd,
+                session,
+                hostname,
+            ])?;
+        }
+    }
+    transaction.commit()?;
+
+    Ok(added)
+}
+
+// The name of this host, like `hostname` prints it.
+#[cfg(feature = "sqlite")]
+fn hostname() -> String {
+    ["/proc/sys/kernel/hostname", "/etc/hostname"]
+        .iter()
+        .find_map(|path| std::fs::read_to_string(path).ok())
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .or_else(|| std::env::var("HOSTNAME").ok())
+        .unwrap_or_else(|| "localhost".to_string())
+}
+
+#[cfg(not(feature = "sqlite"))]
+fn query(_: &Path, _: Option<i64>, _: Option<i64>) -> Result<Vec<ParsedEntry>> {
+    Err(crate::sqlite::unsupported())
+}
+
+#[cfg(not(feature = "sqlite"))]
+fn insert(_: &Path, _: &[Entry]) -> Result<usize> {
+    Err(crate::sqlite::unsupported())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A database with atuin's `history` table, holding a deleted row, a blank command, and one
+    // which was still running.
+    #[cfg(feature = "sqlite")]
+    fn fixture(dir: &Path) -> std::path::PathBuf {
+        let path = dir.join("history.db");
+        let db = rusqlite::Connection::open(&path).unwrap();
+        db.execute_batch(
+            "CREATE TABLE history (id TEXT PRIMARY KEY, timestamp INTEGER NOT NULL,
+                 duration INTEGER NOT NULL, exit INTEGER NOT NULL, command TEXT NOT NULL,
+                 cwd TEXT NOT NULL, session TEXT NOT NULL, hostname TEXT NOT NULL,
+                 deleted_at INTEGER, UNIQUE(timestamp, cwd, command));
+             INSERT INTO history VALUES
+                 ('a', 300000000000, 1500000000, 0, 'make build', '/src', 's', 'h', NULL),
+                 ('b', 100999999999, 400000000, 0, 'ls', '/', 's', 'h', NULL),
+                 ('c', 150000000000, 0, 0, 'rm secret', '/', 's', 'h', 160000000000),
+                 ('d', 200000000000, 0, 0, '   ', '/', 's', 'h', NULL),
+                 ('e', 400000000000, -1, -1, 'sleep 100', '/', 's', 'h', NULL);",
+        )
+        .unwrap();
+        path
+    }
+
+    // The timestamps, commands, and durations read.
+    #[cfg(feature = "sqlite")]
+    fn rows(entries: Vec<ParsedEntry>) -> Vec<(i64, String, Option<i64>)> {
+        let entries = entries.into_iter().map(|(_, entry)| entry.unwrap());
+        entries.map(|entry| (entry.when, entry.cmd, entry.duration)).collect()
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn nanoseconds_become_seconds() {
+        let dir = tempfile::tempdir().unwrap();
+        let entries = read_atuin(&fixture(dir.path()), None, None).unwrap();
+        assert_eq!(
+            rows(entries),
+            [
+                (100, "ls".into(), Some(0)),
+                (300, "make build".into(), Some(2)),
+                (400, "sleep 100".into(), None),
+            ]
+        );
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn since_and_until_are_whole_seconds() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = fixture(dir.path());
+        // `ls` started within second 100
+        let entries = read_atuin(&path, Some(100), Some(100)).unwrap();
+        assert_eq!(rows(entries), [(100, "ls".into(), Some(0))]);
+        let entries = read_atuin(&path, Some(101), Some(399)).unwrap();
+        assert_eq!(rows(entries), [(300, "make build".into(), Some(2))]);
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn the_database_is_left_alone() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = fixture(dir.path());
+        let before = std::fs::read(&path).unwrap();
+        read_atuin(&path, None, None).unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), before);
+        let missing = dir.path().join("missing.db");
+        assert!(read_atuin(&missing, None, None).is_err());
+        assert!(!missing.exists());
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn written_entries_read_back_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("history.db");
+        let entries = [
+            Entry {
+                cmd: "ls".into(),
+                when: 100,
+                duration: Some(3),
+                ..Default::default()
+            },
+            Entry { cmd: "pwd".into(), when: 200, ..Default::default() },
+        ];
+        assert_eq!(write_atuin(&path, &entries).unwrap(), 2);
+        // a second run adds nothing
+        assert_eq!(write_atuin(&path, &entries).unwrap(), 0);
+        let read = read_atuin(&path, None, None).unwrap();
+        assert_eq!(rows(read), [(100, "ls".into(), Some(3)), (200, "pwd".into(), None)]);
+    }
+
+    #[cfg(not(feature = "sqlite"))]
+    #[test]
+    fn needs_the_sqlite_feature() {
+        let error = read_atuin(Path::new("history.db"), None, None).unwrap_err();
+        assert!(error.to_string().contains("`sqlite` feature"), "{error}");
+        assert!(write_atuin(Path::new("history.db"), &[]).is_err());
+    }
+}
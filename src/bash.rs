@@ -0,0 +1,69 @@
+//! Reading bash history files.
+
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+use crate::{error::ParseErrorKind, history_converter::Entry};
+
+/// An entry parsed from a history, with the 1-based line it starts on, or why it could not be.
+pub type ParsedEntry = (usize, Result<Entry, ParseErrorKind>);
+
+/// Parse a bash history file, returning every entry with the line it starts on.
+///
+/// bash writes one command per line, and with `HISTTIMEFORMAT` set a `#<unix time>` comment
+/// before each. Like bash itself, once the file has such comments, all lines up to the next one
+/// are a single entry, which is how commands with embedded newlines (`cmdhist` and `lithist`)
+/// survive. Lines before the first comment, or in a file without any, are one entry each, with a
+/// `when` of 0.
+pub fn parse_bash_history(text: &str) -> Vec<ParsedEntry> {
+    static TIMESTAMP: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^#([0-9]+)$").unwrap());
+
+    let mut entries = Vec::new();
+    // the timestamp of the current entry, and the line of the timestamp
+    let mut current: Option<(Result<i64, ParseErrorKind>, usize)> = None;
+    let mut lines = Vec::new();
+
+    for (line_number, line) in (1..).zip(text.lines()) {
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        if let Some(caps) = TIMESTAMP.captures(line) {
+            if let Some((when, at)) = current.take() {
+                entries.extend(timestamped(when, at, &lines));
+            }
+            // only digits, so parsing can only fail by overflowing
+            let when = caps[1]
+                .parse()
+                .map_err(|_| ParseErrorKind::TimestampOverflow(caps[1].to_string()));
+            current = Some((when, line_number));
+            lines.clear();
+        } else if current.is_some() {
+            lines.push(line);
+        } else if !line.trim().is_empty() {
+            let entry = Entry { cmd: line.to_string(), ..Default::default() };
+            entries.push((line_number, Ok(entry)));
+        }
+    }
+    if let Some((when, at)) = current {
+        entries.extend(timestamped(when, at, &lines));
+    }
+
+    entries
+}
+
+// The entry made of the `lines` after the timestamp on line `at`, unless they are all blank.
+fn timestamped(
+    when: Result<i64, ParseErrorKind>,
+    at: usize,
+    lines: &[&str],
+) -> Option<ParsedEntry> {
+    let cmd = lines.join("\n");
+    if cmd.trim().is_empty() {
+        return None;
+    }
+    let entry = when.map(|when| Entry {
+        cmd: cmd.trim_end().to_string(),
+        when,
+        ..Default::default()
+    });
+    Some((at + 1, entry))
+}
@@ -0,0 +1,161 @@
+use std::fmt::Display;
+
+use anyhow::Result;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt};
+
+/// How bad a problem found in a fish history file is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// fish reads the file fine, but the history is probably not what was intended.
+    Warning,
+    /// fish misreads or skips the affected record.
+    Error,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Warning => write!(f, "warning"),
+            Self::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// A problem found in a fish history file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Issue {
+    /// The 1-based line the problem was found on.
+    pub line: usize,
+    /// How bad the problem is.
+    pub severity: Severity,
+    /// What the problem is.
+    pub message: String,
+}
+
+impl Display for Issue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {}: {}", self.line, self.severity, self.message)
+    }
+}
+
+// The record currently being checked.
+struct Record {
+    line: usize,
+    cmd: String,
+    when: Option<i64>,
+}
+
+/// Check a fish history file for structural problems: records without a timestamp, bad escape
+/// sequences, zero or decreasing timestamps, duplicate consecutive commands, invalid UTF-8, and
+/// lines fish does not understand.
+pub async fn check_fish_history<R>(mut reader: R) -> Result<Vec<Issue>>
+where
+    R: AsyncBufRead + Unpin,
+{
+    let mut buf = Vec::new();
+    let mut issues = Vec::new();
+    let mut record = None::<Record>;
+    let mut previous = None::<Record>;
+    let mut line_number = 0;
+
+    let mut issue =
+        |line, severity, message: String| issues.push(Issue { line, severity, message });
+    loop {
+        buf.clear();
+        if reader.read_until(b'\n', &mut buf).await? == 0 {
+            break; // EOF
+        }
+        line_number += 1;
+
+        let Some(bytes) = buf.strip_suffix(b"\n") else {
+            issue(line_number, Severity::Error, "truncated last line, fish ignores it".into());
+            break;
+        };
+        let line = match std::str::from_utf8(bytes) {
+            Ok(line) => line.to_string(),
+            Err(e) => {
+                issue(line_number, Severity::Error, format!("invalid UTF-8: {e}"));
+                String::from_utf8_lossy(bytes).into_owned()
+            }
+        };
+
+        if let Some(cmd) = line.strip_prefix("- cmd: ") {
+            if let Some(e) = bad_escape(cmd) {
+                issue(line_number, Severity::Error, e);
+            }
+            let finished =
+                record.replace(Record { line: line_number, cmd: cmd.into(), when: None });
+            if let Some(finished) = finished {
+                finish(&mut issue, finished, &mut previous);
+            }
+        } else if let Some(when) = line.strip_prefix("  when: ") {
+            match (&mut record, when.trim().parse::<i64>()) {
+                (None, _) => issue(line_number, Severity::Error, "`when:` outside a record".into()),
+                (Some(Record { when: Some(_), .. }), _) => {
+                    issue(line_number, Severity::Error, "duplicate `when:` in the record".into())
+                }
+                (Some(record), Ok(when)) => record.when = Some(when),
+                (Some(_), Err(e)) => {
+                    issue(line_number, Severity::Error, format!("invalid timestamp {when:?}: {e}"))
+                }
+            }
+        } else if line == "  paths:" {
+            if record.is_none() {
+                issue(line_number, Severity::Error, "`paths:` outside a record".into());
+            }
+        } else if let Some(path) = line.strip_prefix("    - ") {
+            if let Some(e) = bad_escape(path) {
+                issue(line_number, Severity::Error, e);
+            }
+        } else {
+            issue(line_number, Severity::Error, format!("unexpected line: {line:?}"));
+        }
+    }
+    if let Some(finished) = record {
+        finish(&mut issue, finished, &mut previous);
+    }
+
+    issues.sort_by_key(|issue| issue.line);
+    Ok(issues)
+}
+
+// Report the problems which can only be told once the whole record has been read.
+fn finish(
+    issue: &mut impl FnMut(usize, Severity, String),
+    record: Record,
+    previous: &mut Option<Record>,
+) {
+    match record.when {
+        None => issue(record.line, Severity::Error, "record has no `when:`".into()),
+        Some(0) => issue(record.line, Severity::Warning, "zero timestamp".into()),
+        Some(when) => {
+            if let Some(before) = previous.as_ref().and_then(|previous| previous.when) {
+                if when < before {
+                    let message = format!("timestamp {when} is older than the previous {before}");
+                    issue(record.line, Severity::Warning, message);
+                }
+            }
+        }
+    }
+
+    if previous.as_ref().is_some_and(|previous| previous.cmd == record.cmd) {
+        issue(record.line, Severity::Warning, "same command as the previous record".into());
+    }
+
+    *previous = Some(record);
+}
+
+// fish only writes `\\` and `\n` escape sequences.
+fn bad_escape(s: &str) -> Option<String> {
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            match chars.next() {
+                Some('\\' | 'n') => {}
+                Some(other) => return Some(format!("bad escape sequence `\\{other}`")),
+                None => return Some("dangling backslash at the end of the field".into()),
+            }
+        }
+    }
+    None
+}
@@ -0,0 +1,73 @@
+//! The completion scripts the `completions` command prints or installs.
+
+use std::{
+    ffi::OsString,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Context, Result};
+use clap::CommandFactory;
+use clap_complete::Shell;
+
+use crate::history_converter::Cli;
+
+/// Write the completion script for `shell` of the whole command line, with every command and
+/// option. The values of the options taking one of a few, like `--format` and `--from`, come from
+/// the same enums the command line is parsed with.
+///
+/// ```
+/// use clap_complete::Shell;
+/// use zsh_history_to_fish::write_completions;
+///
+/// let mut script = Vec::new();
+/// write_completions(Shell::Fish, &mut script);
+/// let script = String::from_utf8(script).unwrap();
+/// assert!(script.contains("-l format"));
+/// assert!(script.contains("fish-script"));
+/// assert!(script.contains("psreadline"));
+/// ```
+pub fn write_completions(shell: Shell, out: &mut impl Write) {
+    let mut command = <Cli as CommandFactory>::command();
+    let name = command.get_name().to_string();
+    clap_complete::generate(shell, &mut command, name, out);
+}
+
+/// Where fish loads the completions of this tool from: `$XDG_CONFIG_HOME/fish/completions`,
+/// falling back to `~/.config/fish/completions`. Environment variables are looked up through
+/// `env`, like [`fish_history_path`](crate::fish_history_path) does.
+pub fn fish_completions_path(env: impl Fn(&str) -> Option<OsString>) -> Result<PathBuf> {
+    // a relative XDG_CONFIG_HOME is ignored, as the XDG base directory spec requires
+    let config_home = match env("XDG_CONFIG_HOME").map(PathBuf::from) {
+        Some(dir) if dir.is_absolute() => dir,
+        _ => match env("HOME").filter(|home| !home.is_empty()) {
+            Some(home) => PathBuf::from(home).join(".config"),
+            None => bail!(
+                "cannot locate the fish config directory: neither XDG_CONFIG_HOME nor HOME is set"
+            ),
+        },
+    };
+    let name = <Cli as CommandFactory>::command().get_name().to_string();
+    Ok(config_home
+        .join("fish")
+        .join("completions")
+        .join(format!("{name}.fish")))
+}
+
+/// Write the fish completions to `path`, creating its directory. An existing file is only
+/// replaced with `force`.
+pub async fn install_fish_completions(path: &Path, force: bool) -> Result<()> {
+    if !force && tokio::fs::try_exists(path).await? {
+        bail!("refusing to overwrite {} (use --force)", path.display());
+    }
+    if let Some(dir) = path.parent() {
+        tokio::fs::create_dir_all(dir)
+            .await
+            .with_context(|| format!("failed to create {}", dir.display()))?;
+    }
+    let mut script = Vec::new();
+    write_completions(Shell::Fish, &mut script);
+    tokio::fs::write(path, script)
+        .await
+        .with_context(|| format!("failed to write {}", path.display()))
+}
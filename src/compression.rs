@@ -0,0 +1,144 @@
+use std::fmt::Display;
+
+use anyhow::Result;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt};
+
+/// The compression of a zsh history file, told by its magic bytes rather than its name, as
+/// archived histories are often misnamed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    Gzip,
+    Zstd,
+    Bzip2,
+    Xz,
+}
+
+impl Compression {
+    /// The compression of a file starting with `magic`, or `None` if it is not compressed.
+    pub fn detect(magic: &[u8]) -> Option<Self> {
+        const MAGIC: [(&[u8], Compression); 4] = [
+            (&[0x1f, 0x8b], Compression::Gzip),
+            (&[0x28, 0xb5, 0x2f, 0xfd], Compression::Zstd),
+            (b"BZh", Compression::Bzip2),
+            (&[0xfd, b'7', b'z', b'X', b'Z', 0x00], Compression::Xz),
+        ];
+        MAGIC
+            .iter()
+            .find(|(prefix, _)| magic.starts_with(prefix))
+            .map(|&(_, compression)| compression)
+    }
+}
+
+impl Display for Compression {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Gzip => write!(f, "gzip"),
+            Self::Zstd => write!(f, "zstd"),
+            Self::Bzip2 => write!(f, "bzip2"),
+            Self::Xz => write!(f, "xz"),
+        }
+    }
+}
+
+/// Wrap `reader` in a decoder if what it reads is compressed, returning the compression too.
+pub async fn decompress<R>(
+    mut reader: R,
+) -> Result<(Box<dyn AsyncBufRead + Send + Unpin>, Option<Compression>)>
+where
+    R: AsyncBufRead + Send + Unpin + 'static,
+{
+    let Some(compression) = Compression::detect(reader.fill_buf().await?) else {
+        return Ok((Box::new(reader), None));
+    };
+    Ok((decoder(reader, compression)?, Some(compression)))
+}
+
+#[cfg(feature = "compression")]
+fn decoder<R>(reader: R, compression: Compression) -> Result<Box<dyn AsyncBufRead + Send + Unpin>>
+where
+    R: AsyncBufRead + Send + Unpin + 'static,
+{
+    use async_compression::tokio::bufread::{BzDecoder, GzipDecoder, XzDecoder, ZstdDecoder};
+    use tokio::io::BufReader;
+
+    // `cat a.gz b.gz` is a valid gzip file, and likewise for the others
+    Ok(match compression {
+        Compression::Gzip => {
+            let mut decoder = GzipDecoder::new(reader);
+            decoder.multiple_members(true);
+            Box::new(BufReader::new(decoder))
+        }
+        Compression::Zstd => {
+            let mut decoder = ZstdDecoder::new(reader);
+            decoder.multiple_members(true);
+            Box::new(BufReader::new(decoder))
+        }
+        Compression::Bzip2 => {
+            let mut decoder = BzDecoder::new(reader);
+            decoder.multiple_members(true);
+            Box::new(BufReader::new(decoder))
+        }
+        Compression::Xz => {
+            let mut decoder = XzDecoder::new(reader);
+            decoder.multiple_members(true);
+            Box::new(BufReader::new(decoder))
+        }
+    })
+}
+
+#[cfg(not(feature = "compression"))]
+fn decoder<R>(_: R, compression: Compression) -> Result<Box<dyn AsyncBufRead + Send + Unpin>> {
+    anyhow::bail!(
+        "the zsh history is {compression}-compressed, but this build cannot decompress it; \
+         rebuild with the `compression` feature"
+    )
+}
+
+#[cfg(all(test, feature = "compression"))]
+mod tests {
+    use async_compression::tokio::bufread::GzipEncoder;
+    use tokio::io::AsyncReadExt;
+
+    use super::*;
+    use crate::{Converter, Uninitialized};
+
+    async fn gzip(data: &[u8]) -> Vec<u8> {
+        let mut compressed = Vec::new();
+        GzipEncoder::new(data).read_to_end(&mut compressed).await.unwrap();
+        compressed
+    }
+
+    async fn decompressed(data: Vec<u8>) -> (std::io::Result<Vec<u8>>, Option<Compression>) {
+        let (mut reader, compression) = decompress(std::io::Cursor::new(data)).await.unwrap();
+        let mut out = Vec::new();
+        (reader.read_to_end(&mut out).await.map(|_| out), compression)
+    }
+
+    #[tokio::test]
+    async fn archives_are_told_by_their_magic_bytes() {
+        let (read, compression) = decompressed(gzip(b"ls\npwd\n").await).await;
+        assert_eq!((read.unwrap(), compression), (b"ls\npwd\n".to_vec(), Some(Compression::Gzip)));
+        let (read, compression) = decompressed(b"ls\n".to_vec()).await;
+        assert_eq!((read.unwrap(), compression), (b"ls\n".to_vec(), None));
+    }
+
+    #[tokio::test]
+    async fn truncated_and_corrupt_archives_fail_to_read() {
+        let history = ": 1700000000:0;ls\n".repeat(100);
+        let compressed = gzip(history.as_bytes()).await;
+
+        let truncated = compressed[..compressed.len() / 2].to_vec();
+        let (read, compression) = decompressed(truncated).await;
+        assert_eq!(compression, Some(Compression::Gzip));
+        assert!(read.is_err());
+
+        let mut corrupt = compressed.clone();
+        corrupt[12..20].fill(0xff);
+        assert!(decompressed(corrupt.clone()).await.0.is_err());
+
+        // and the conversion names the compression it failed at
+        let converter = Converter::with_args_and_bytes(Uninitialized::default(), corrupt).unwrap();
+        let error = format!("{:#}", converter.convert().await.unwrap_err());
+        assert!(error.starts_with("failed to decompress - as gzip: "), "{error}");
+    }
+}
@@ -0,0 +1,521 @@
+//! The configuration file, `~/.config/zsh-history-to-fish/config.toml`, giving the options the
+//! command line does not.
+//!
+//! Its keys are the long names of the options, like `into-fish = true`, `format = "json"`, or
+//! `exclude = ["^ls$", "^cd "]` for an option given several times, and `zsh-history` for the
+//! histories. Its values are added to the command line as if given there, before the options
+//! which are, so an option on the command line always wins over the configuration file, which
+//! wins over the defaults. The `Z2F_` environment variables, like `Z2F_FORMAT=json`, come in
+//! between, winning over the configuration file. The options only for the command line, like
+//! `config` and `print-config`, are refused as keys.
+
+use std::{
+    ffi::OsString,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Context, Result};
+use clap::{parser::ValueSource, Arg, ArgAction, ArgMatches, CommandFactory, FromArgMatches};
+
+use crate::{
+    error::{Failure, FailureContext},
+    history_converter::Cli,
+    verbosity::{info, set_verbosity, Verbosity},
+};
+
+// The options which only make sense on the command line, which the `Z2F_` environment variables
+// do not give.
+pub(crate) const COMMAND_LINE_ONLY: [&str; 4] = ["help", "version", "config", "print-config"];
+
+/// The options to add to a command line from a configuration file, and the keys of the file
+/// which are no options, as [`config_args`] finds them.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ConfigArgs {
+    /// The options to add, like `--format=json`, and the histories.
+    pub args: Vec<String>,
+    /// The ids of the options added, like `format`.
+    pub ids: Vec<String>,
+    /// The keys which are no options of the command.
+    pub unknown: Vec<String>,
+}
+
+/// Where the configuration file is by default: `$XDG_CONFIG_HOME/zsh-history-to-fish/config.toml`,
+/// falling back to `~/.config/zsh-history-to-fish/config.toml`, or `None` if neither
+/// `XDG_CONFIG_HOME` nor `HOME` is set. Environment variables are looked up through `env`, like
+/// [`fish_history_path`](crate::fish_history_path) does.
+pub fn config_path(env: impl Fn(&str) -> Option<OsString>) -> Option<PathBuf> {
+    // a relative XDG_CONFIG_HOME is ignored, as the XDG base directory spec requires
+    let config_home = match env("XDG_CONFIG_HOME").map(PathBuf::from) {
+        Some(dir) if dir.is_absolute() => dir,
+        _ => PathBuf::from(env("HOME").filter(|home| !home.is_empty())?).join(".config"),
+    };
+    let name = <Cli as CommandFactory>::command().get_name().to_string();
+    Some(config_home.join(name).join("config.toml"))
+}
+
+/// The options the `config` gives to `command`, leaving out those in `given`, the ids of the
+/// options the command line gives, and those conflicting with any of them, so the command line
+/// always wins. A `true` turns a flag on, and `false` leaves it off, an array gives an option
+/// once for each of its values, and a number gives an option counting how often it is given,
+/// like `verbose`, that many times.
+///
+/// ```
+/// use clap::CommandFactory;
+/// use zsh_history_to_fish::{config_args, Uninitialized};
+///
+/// let config = r#"
+///     format = "json"
+///     unique = true
+///     exclude = ["^ls$", "^cd "]
+///     into-fish = true
+///     colour = "never"
+///     verbose = 2
+///     dry-run = false
+/// "#;
+/// let config = toml::from_str(config).unwrap();
+/// // the command line gives `--format` and `--output`, which conflicts with `--into-fish`
+/// let args = config_args(&Uninitialized::command(), &config, &["format", "output"]);
+/// assert_eq!(
+///     args.args,
+///     ["--exclude=^ls$", "--exclude=^cd ", "--unique", "--verbose", "--verbose"],
+/// );
+/// assert_eq!(args.unknown, ["colour"]);
+/// ```
+pub fn config_args(command: &clap::Command, config: &toml::Table, given: &[&str]) -> ConfigArgs {
+    let given = command
+        .get_arguments()
+        .filter(|arg| given.contains(&arg.get_id().as_str()))
+        .collect::<Vec<_>>();
+    let mut config_args = ConfigArgs::default();
+    for (key, value) in config {
+        let Some(arg) = command.get_arguments().find(|arg| option_name(arg) == *key) else {
+            config_args.unknown.push(key.clone());
+            continue;
+        };
+        if given.iter().any(|given| conflicting(command, arg, given)) {
+            continue;
+        }
+
+        let values = match value {
+            toml::Value::Array(values) => values.iter().collect(),
+            value => vec![value],
+        };
+        for value in values {
+            let value = match value {
+                toml::Value::String(value) => value.clone(),
+                value => value.to_string(),
+            };
+            let long = arg.get_long();
+            match (long, arg.get_action(), value.as_str()) {
+                (Some(long), ArgAction::SetTrue, "true") => {
+                    config_args.args.push(format!("--{long}"))
+                }
+                (Some(_), ArgAction::SetTrue, _) => {}
+                (Some(long), ArgAction::Count, count) => {
+                    let count = count.parse().unwrap_or(0);
+                    config_args
+                        .args
+                        .extend(std::iter::repeat_n(format!("--{long}"), count));
+                }
+                (Some(long), _, value) => config_args.args.push(format!("--{long}={value}")),
+                (None, _, value) => config_args.args.push(value.to_string()),
+            }
+        }
+        config_args.ids.push(arg.get_id().to_string());
+    }
+    config_args
+}
+
+// The key of `arg` in the configuration file: its long name, or for the histories, its id.
+pub(crate) fn option_name(arg: &Arg) -> String {
+    match arg.get_long() {
+        Some(long) => long.to_string(),
+        None => arg.get_id().as_str().replace('_', "-"),
+    }
+}
+
+// Whether `arg` cannot be given along with `other`, either of them conflicting with the other, or
+// both being in a group taking one of its options, like `--output` and `--into-fish`.
+fn conflicting(command: &clap::Command, arg: &Arg, other: &Arg) -> bool {
+    arg.get_id() == other.get_id()
+        || command.get_arg_conflicts_with(arg).contains(&other)
+        || command.get_arg_conflicts_with(other).contains(&arg)
+        || command.get_groups().any(|group| {
+            let args = group.get_args().collect::<Vec<_>>();
+            !group.clone().is_multiple()
+                && args.contains(&arg.get_id())
+                && args.contains(&other.get_id())
+        })
+}
+
+impl Cli {
+    /// Parse the command line, adding the options it does not give from the `Z2F_` environment
+    /// variables, then from the configuration file, from `--config` or `Z2F_CONFIG`, or from
+    /// [`config_path`] if it exists. Exits on `--help`, `--version`, and invalid options, like
+    /// [`Parser::parse`](clap::Parser::parse), and on `--print-config`, after printing the options
+    /// in effect.
+    pub fn parse_with_config() -> Result<Self> {
+        let mut argv = std::env::args_os().collect::<Vec<_>>();
+        let mut command = <Cli as CommandFactory>::command();
+        let matches = command
+            .try_get_matches_from_mut(&argv)
+            .unwrap_or_else(|error| error.exit());
+
+        // only the commands reading a history are configured, given right after the program
+        let (at, name, sub_matches) = match matches.subcommand() {
+            None => (1, None, &matches),
+            Some((name @ ("convert" | "merge" | "fish-to-zsh" | "stats"), sub_matches)) => {
+                (2, Some(name), sub_matches)
+            }
+            Some(_) => return Ok(Self::from_arg_matches(&matches)?),
+        };
+        let sub_command = match name {
+            Some(name) => command.find_subcommand(name).unwrap().clone(),
+            None => command.clone(),
+        };
+        let mut given = sub_matches
+            .ids()
+            .filter(|id| sub_matches.value_source(id.as_str()) == Some(ValueSource::CommandLine))
+            .map(|id| id.to_string())
+            .collect::<Vec<_>>();
+        // one configuration serves every command, so the options of `convert` the command does
+        // not take are left out of it, and only the keys no command takes are reported
+        let options =
+            |command: &clap::Command| command.get_arguments().map(option_name).collect::<Vec<_>>();
+        let (convert_options, command_options) = (options(&command), options(&sub_command));
+        let for_command = |mut config: toml::Table| {
+            config.retain(|key, _| {
+                let key = key.to_string();
+                command_options.contains(&key) || !convert_options.contains(&key)
+            });
+            config
+        };
+        let every_option = command
+            .get_arguments()
+            .filter(|arg| !command_options.contains(&option_name(arg)))
+            .fold(sub_command.clone(), |every, arg| every.arg(arg.clone()));
+
+        let (env, mut warnings) =
+            env_config(&every_option, |key| std::env::var_os(key)).fail_as(Failure::Usage)?;
+        let env = for_command(env);
+        for (key, value) in &env {
+            let var = toml::Table::from_iter([(key.clone(), value.clone())]);
+            for arg in config_args(&sub_command, &var, &[]).args {
+                check_env_arg(&mut command, &argv[..at], &arg, &env_var(key))
+                    .fail_as(Failure::Usage)?;
+            }
+        }
+        let env_args = config_args(&sub_command, &env, &ids(&given));
+        given.extend(env_args.ids.iter().cloned());
+        let mut layers = vec![("the environment".to_string(), env_args)];
+
+        let path = match sub_matches.get_one::<PathBuf>("config") {
+            Some(path) => Some(path.clone()),
+            None => match std::env::var_os("Z2F_CONFIG") {
+                Some(path) => Some(PathBuf::from(path)),
+                None => config_path(|key| std::env::var_os(key)).filter(|path| path.exists()),
+            },
+        };
+        if let Some(path) = &path {
+            let config = for_command(read_config(path).fail_as(Failure::Usage)?);
+            let config_args = config_args(&sub_command, &config, &ids(&given));
+            for key in &config_args.unknown {
+                warnings.push(format!("{}: {key} is no option, ignored it", path.display()));
+            }
+            layers.push((path.display().to_string(), config_args));
+        }
+
+        let args = layers.iter().flat_map(|(_, layer)| &layer.args);
+        argv.splice(at..at, args.map(OsString::from).collect::<Vec<_>>());
+        let matches = command
+            .try_get_matches_from_mut(&argv)
+            .with_context(|| match &path {
+                Some(path) => format!("invalid option in {} or the environment", path.display()),
+                None => "invalid option in the environment".to_string(),
+            })
+            .fail_as(Failure::Usage)?;
+        let sub_matches = match name {
+            Some(name) => matches.subcommand_matches(name).unwrap(),
+            None => &matches,
+        };
+        // the converter sets it again, but the warnings come first
+        set_verbosity(Verbosity::from_flags(
+            sub_matches.get_flag("quiet"),
+            sub_matches.get_count("verbose"),
+        ));
+        for warning in &warnings {
+            info!("{warning}");
+        }
+        if sub_matches.get_flag("print_config") {
+            print!("{}", effective_config(&sub_command, sub_matches, &layers));
+            std::process::exit(0);
+        }
+        Ok(Self::from_arg_matches(&matches)?)
+    }
+}
+
+// The names of the options whose ids are `ids`.
+fn ids(ids: &[String]) -> Vec<&str> {
+    ids.iter().map(String::as_str).collect()
+}
+
+/// The options the `Z2F_` environment variables give to `command`, as if from a configuration
+/// file, and warnings about the variables which are no options, like `Z2F_FORMT`. A variable is
+/// named after the long name of its option, like `Z2F_INTO_FISH` for `--into-fish`, or
+/// `Z2F_ZSH_HISTORY` for the history, and gives it once. A flag takes `1`, `true`, or `yes` to
+/// turn it on, and `0`, `false`, or `no` to leave it off, ignoring case. Environment variables are
+/// looked up through `env`, like [`fish_history_path`](crate::fish_history_path) does.
+///
+/// ```
+/// use std::ffi::OsString;
+///
+/// use clap::CommandFactory;
+/// use zsh_history_to_fish::{env_config, Uninitialized};
+///
+/// let vars = [("Z2F_FORMAT", "json"), ("Z2F_QUIET", "Yes"), ("Z2F_VERBOSE", "2")];
+/// let env = |key: &str| {
+///     let (_, value) = vars.iter().find(|(var, _)| *var == key)?;
+///     Some(OsString::from(value))
+/// };
+/// let (config, _) = env_config(&Uninitialized::command(), env).unwrap();
+/// assert_eq!(config["format"].as_str(), Some("json"));
+/// assert_eq!(config["quiet"].as_bool(), Some(true));
+/// assert_eq!(config["verbose"].as_integer(), Some(2));
+///
+/// let env = |key: &str| (key == "Z2F_QUIET").then(|| OsString::from("sure"));
+/// let error = env_config(&Uninitialized::command(), env).unwrap_err();
+/// assert_eq!(error.to_string(), "Z2F_QUIET is \"sure\", not 1, true, yes, 0, false, or no");
+/// ```
+pub fn env_config(
+    command: &clap::Command,
+    env: impl Fn(&str) -> Option<OsString>,
+) -> Result<(toml::Table, Vec<String>)> {
+    let mut config = toml::Table::new();
+    let mut known = vec!["Z2F_CONFIG".to_string()];
+    for arg in command.get_arguments() {
+        let key = option_name(arg);
+        let var = env_var(&key);
+        known.push(var.clone());
+        if COMMAND_LINE_ONLY.contains(&key.as_str()) {
+            continue;
+        }
+        let Some(value) = env(&var) else {
+            continue;
+        };
+        let Some(value) = value.to_str() else {
+            bail!("{var} is not valid UTF-8");
+        };
+        let value = match arg.get_action() {
+            ArgAction::SetTrue => match value.to_lowercase().as_str() {
+                "1" | "true" | "yes" => toml::Value::Boolean(true),
+                "0" | "false" | "no" => toml::Value::Boolean(false),
+                _ => bail!("{var} is {value:?}, not 1, true, yes, 0, false, or no"),
+            },
+            ArgAction::Count => match value.parse::<u8>() {
+                Ok(count) => toml::Value::Integer(count.into()),
+                Err(_) => bail!("{var} is {value:?}, not how many times to give --{key}"),
+            },
+            _ => toml::Value::String(value.to_string()),
+        };
+        config.insert(key, value);
+    }
+
+    // only looked up in the process environment, as `env` cannot list the variables set
+    let warnings = std::env::vars_os()
+        .filter_map(|(var, _)| var.into_string().ok())
+        .filter(|var| var.starts_with("Z2F_") && !known.contains(var))
+        .map(|var| format!("{var} is no option, ignored it"))
+        .collect();
+    Ok((config, warnings))
+}
+
+// The environment variable for the option named `key`.
+pub(crate) fn env_var(key: &str) -> String {
+    format!("Z2F_{}", key.to_uppercase().replace('-', "_"))
+}
+
+// Fail naming `var` if `arg`, from it, has a value its option does not take, by parsing it on
+// its own after `argv`, the program and its command.
+fn check_env_arg(
+    command: &mut clap::Command,
+    argv: &[OsString],
+    arg: &str,
+    var: &str,
+) -> Result<()> {
+    use clap::error::ErrorKind;
+
+    let argv = argv.iter().cloned().chain([OsString::from(arg)]);
+    match command.try_get_matches_from_mut(argv) {
+        Err(error)
+            if matches!(
+                error.kind(),
+                ErrorKind::InvalidValue | ErrorKind::ValueValidation | ErrorKind::InvalidUtf8
+            ) =>
+        {
+            Err(anyhow::Error::new(error).context(format!("invalid {var}")))
+        }
+        _ => Ok(()),
+    }
+}
+
+// The configuration file at `path`, which cannot give the options only for the command line, like
+// `print-config`.
+fn read_config(path: &Path) -> Result<toml::Table> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read {}", path.display()))?;
+    let config: toml::Table =
+        toml::from_str(&text).with_context(|| format!("failed to parse {}", path.display()))?;
+    if let Some(key) = COMMAND_LINE_ONLY.iter().find(|key| config.contains_key(**key)) {
+        bail!("{}: {key} is only taken on the command line", path.display());
+    }
+    Ok(config)
+}
+
+// The options in effect, as a configuration file, with where each comes from.
+fn effective_config(
+    command: &clap::Command,
+    matches: &ArgMatches,
+    layers: &[(String, ConfigArgs)],
+) -> String {
+    let mut out = String::new();
+    for arg in command.get_arguments() {
+        let id = arg.get_id().as_str();
+        if matches!(id, "help" | "version" | "config" | "print_config") {
+            continue;
+        }
+        let Some(source) = matches.value_source(id) else {
+            continue;
+        };
+        let value = match arg.get_action() {
+            ArgAction::SetTrue | ArgAction::SetFalse => toml::Value::Boolean(matches.get_flag(id)),
+            ArgAction::Count => toml::Value::Integer(matches.get_count(id).into()),
+            action => {
+                let values = matches.get_raw(id).into_iter().flatten();
+                let values = values
+                    .map(|value| {
+                        let value = value.to_string_lossy();
+                        match value.parse() {
+                            Ok(number) => toml::Value::Integer(number),
+                            Err(_) => toml::Value::String(value.into_owned()),
+                        }
+                    })
+                    .collect::<Vec<_>>();
+                match (action, &values[..]) {
+                    (ArgAction::Append, _) => toml::Value::Array(values),
+                    (_, [value]) => value.clone(),
+                    _ => toml::Value::Array(values),
+                }
+            }
+        };
+        let layer = layers
+            .iter()
+            .find(|(_, layer)| layer.ids.iter().any(|added| added == id));
+        let from = match (source, layer) {
+            (ValueSource::CommandLine, Some((from, _))) => from.as_str(),
+            (ValueSource::CommandLine, None) => "the command line",
+            (ValueSource::EnvVariable, _) => "the environment",
+            _ => "the default",
+        };
+        out.push_str(&format!("{} = {value} # from {from}\n", option_name(arg)));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Uninitialized;
+
+    fn config(text: &str) -> toml::Table {
+        toml::from_str(text).unwrap()
+    }
+
+    #[test]
+    fn command_line_wins() {
+        let config = config("format = 'json'\nunique = true\nexclude = ['^ls$']");
+        let command = Uninitialized::command();
+        let args = config_args(&command, &config, &["format", "exclude"]);
+        assert_eq!(args.args, ["--unique"]);
+        assert_eq!(args.ids, ["unique"]);
+    }
+
+    #[test]
+    fn conflicts_with_the_command_line_are_left_out() {
+        // `--into-fish` is in a group with `--output`, writing to one or the other
+        let config = config("into-fish = true\nreverse = true");
+        let command = Uninitialized::command();
+        let args = config_args(&command, &config, &["output"]);
+        assert_eq!(args.args, ["--reverse"]);
+        let args = config_args(&command, &config, &[]);
+        assert_eq!(args.args, ["--into-fish", "--reverse"]);
+    }
+
+    #[test]
+    fn values_become_options() {
+        let config = config(concat!(
+            "unique = false\n",
+            "verbose = 3\n",
+            "max-entries = 10\n",
+            "zsh-history = ['a', 'b']\n",
+            "colour = 'never'\n",
+        ));
+        let args = config_args(&Uninitialized::command(), &config, &[]);
+        assert_eq!(
+            args.args,
+            ["--max-entries=10", "--verbose", "--verbose", "--verbose", "a", "b"],
+        );
+        assert_eq!(args.unknown, ["colour"]);
+    }
+
+    #[test]
+    fn environment_is_read_per_option() {
+        let vars = [
+            ("Z2F_INTO_FISH", "1"),
+            ("Z2F_UNIQUE", "no"),
+            ("Z2F_ZSH_HISTORY", "h"),
+            ("Z2F_PRINT_CONFIG", "1"),
+        ];
+        let env = |key: &str| {
+            let (_, value) = vars.iter().find(|(var, _)| *var == key)?;
+            Some(OsString::from(value))
+        };
+        let (config, _) = env_config(&Uninitialized::command(), env).unwrap();
+        assert_eq!(config["into-fish"].as_bool(), Some(true));
+        assert_eq!(config["unique"].as_bool(), Some(false));
+        assert_eq!(config["zsh-history"].as_str(), Some("h"));
+        assert!(!config.contains_key("print-config"));
+    }
+
+    #[test]
+    fn command_line_only_keys_are_refused() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        for key in ["print-config = true", "config = 'other.toml'"] {
+            std::fs::write(&path, format!("unique = true\n{key}\n")).unwrap();
+            let error = read_config(&path).unwrap_err();
+            let key = key.split(' ').next().unwrap();
+            assert_eq!(
+                error.to_string(),
+                format!("{}: {key} is only taken on the command line", path.display()),
+            );
+        }
+        std::fs::write(&path, "unique = true\n").unwrap();
+        assert_eq!(read_config(&path).unwrap(), config("unique = true"));
+    }
+
+    #[test]
+    fn path_follows_xdg() {
+        let env = |vars: &'static [(&'static str, &'static str)]| {
+            move |key: &str| {
+                let (_, value) = vars.iter().find(|(var, _)| *var == key)?;
+                Some(OsString::from(value))
+            }
+        };
+        let name = "zsh-history-to-fish/config.toml";
+        let path = config_path(env(&[("XDG_CONFIG_HOME", "/xdg"), ("HOME", "/home")]));
+        assert_eq!(path, Some(Path::new("/xdg").join(name)));
+        let path = config_path(env(&[("XDG_CONFIG_HOME", "xdg"), ("HOME", "/home")]));
+        assert_eq!(path, Some(Path::new("/home/.config").join(name)));
+        assert_eq!(config_path(env(&[("HOME", "")])), None);
+    }
+}
@@ -0,0 +1,158 @@
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+use crate::{format::InputFormat, sqlite};
+
+/// How sure [`detect_format`] is of the format it tells.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Confidence {
+    /// Only the lines of one format were found.
+    High,
+    /// Lines of several formats were found, or none which only one format has.
+    Low,
+}
+
+/// The format [`detect_format`] tells, and how sure it is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Detection {
+    pub format: InputFormat,
+    pub confidence: Confidence,
+}
+
+/// Tell the format of a history from `bytes`, its first few KiB.
+///
+/// SQLite databases are taken for a zsh-histdb one, which [`database_format`] can tell apart from
+/// the others, and JSON for a xonsh session. Otherwise the
+/// lines are counted which only one format has: zsh's `: <start>:<duration>;` headers, fish's
+/// `- cmd:` records, bash's `#<unix time>` comments, and PSReadLine's backtick continuations, a
+/// UTF-8 BOM counting as one of the latter, as PowerShell writes one. The format with the most
+/// wins, and if there are none, the history is taken for a plain zsh one, which a plain bash
+/// history is too, with [`Confidence::Low`].
+///
+/// [`database_format`]: crate::database_format
+pub fn detect_format(bytes: &[u8]) -> Detection {
+    static ZSH_HEADER: LazyLock<Regex> =
+        LazyLock::new(|| Regex::new(r"^: [0-9]+:[0-9]+;").unwrap());
+    static BASH_TIMESTAMP: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^#[0-9]+$").unwrap());
+    const UTF8_BOM: &[u8] = b"\xef\xbb\xbf";
+
+    let certain = |format| Detection { format, confidence: Confidence::High };
+    if sqlite::is_database(bytes) {
+        return certain(InputFormat::Histdb);
+    }
+    let bom = bytes.starts_with(UTF8_BOM);
+    let text = String::from_utf8_lossy(bytes.strip_prefix(UTF8_BOM).unwrap_or(bytes));
+    if text.trim_start().starts_with('{') {
+        return Detection {
+            format: InputFormat::Xonsh,
+            confidence: match text.contains("\"cmds\"") {
+                true => Confidence::High,
+                false => Confidence::Low,
+            },
+        };
+    }
+
+    let mut counts = [
+        (InputFormat::Zsh, 0),
+        (InputFormat::Fish, 0),
+        (InputFormat::Bash, 0),
+        (InputFormat::Psreadline, usize::from(bom)),
+    ];
+    for line in text.lines() {
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        let format = match line {
+            _ if ZSH_HEADER.is_match(line) => 0,
+            _ if line.starts_with("- cmd: ") => 1,
+            _ if BASH_TIMESTAMP.is_match(line) => 2,
+            _ if line.ends_with('`') => 3,
+            _ => continue,
+        };
+        counts[format].1 += 1;
+    }
+
+    // the first of the most, as the formats are in the order of how telling their lines are
+    let (format, most) = counts.iter().rev().max_by_key(|(_, count)| count).copied().unwrap();
+    let others = counts.iter().filter(|(_, count)| *count > 0).count() - usize::from(most > 0);
+    match (most, others) {
+        (0, _) => Detection {
+            format: InputFormat::Zsh,
+            confidence: Confidence::Low,
+        },
+        (_, 0) => certain(format),
+        _ => Detection { format, confidence: Confidence::Low },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn detected(history: &[u8]) -> (InputFormat, Confidence) {
+        let Detection { format, confidence } = detect_format(history);
+        (format, confidence)
+    }
+
+    #[test]
+    fn zsh_extended_history() {
+        let history = b": 1700000000:0;ls\n: 1700000001:3;cargo build \\\n  --release\n";
+        assert_eq!(detected(history), (InputFormat::Zsh, Confidence::High));
+        let crlf = b": 1700000000:0;ls\r\n: 1700000001:0;pwd\r\n";
+        assert_eq!(detected(crlf), (InputFormat::Zsh, Confidence::High));
+    }
+
+    #[test]
+    fn plain_history_is_taken_for_zsh() {
+        // a plain bash history looks the same
+        assert_eq!(detected(b"ls\ncd src\n"), (InputFormat::Zsh, Confidence::Low));
+        assert_eq!(detected(b""), (InputFormat::Zsh, Confidence::Low));
+    }
+
+    #[test]
+    fn bash_timestamps() {
+        let history = b"#1700000000\nls\n#1700000001\ncd src\n";
+        assert_eq!(detected(history), (InputFormat::Bash, Confidence::High));
+        // a comment which is no timestamp tells nothing
+        assert_eq!(detected(b"# notes\nls\n"), (InputFormat::Zsh, Confidence::Low));
+    }
+
+    #[test]
+    fn fish_yaml() {
+        let history = b"- cmd: ls\n  when: 1700000000\n- cmd: cd src\n  when: 1700000001\n  \
+                        paths:\n    - src\n";
+        assert_eq!(detected(history), (InputFormat::Fish, Confidence::High));
+    }
+
+    #[test]
+    fn psreadline_bom_and_backticks() {
+        let history = b"\xef\xbb\xbfGet-ChildItem `\n  -Recurse\nls\n";
+        assert_eq!(detected(history), (InputFormat::Psreadline, Confidence::High));
+        // either alone
+        let history = b"Get-ChildItem `\r\n  -Recurse\r\n";
+        assert_eq!(detected(history), (InputFormat::Psreadline, Confidence::High));
+        assert_eq!(detected(b"\xef\xbb\xbfls\n"), (InputFormat::Psreadline, Confidence::High));
+    }
+
+    #[test]
+    fn xonsh_json() {
+        let history = br#"{"data": {"cmds": [{"inp": "ls", "ts": [1700000000.5, 1.0]}]}}"#;
+        assert_eq!(detected(history), (InputFormat::Xonsh, Confidence::High));
+        assert_eq!(detected(b"  {\"locs\": []}"), (InputFormat::Xonsh, Confidence::Low));
+    }
+
+    #[test]
+    fn sqlite_database() {
+        let header = b"SQLite format 3\0";
+        assert_eq!(detected(header), (InputFormat::Histdb, Confidence::High));
+    }
+
+    #[test]
+    fn ambiguous_history() {
+        // the format with the most lines, but not for sure
+        let history = b": 1700000000:0;ls\n- cmd: pwd\n- cmd: cd\n";
+        assert_eq!(detected(history), (InputFormat::Fish, Confidence::Low));
+        // and with as many, the more telling format
+        let history = b"#1700000000\nls\n: 1700000000:0;pwd\n";
+        assert_eq!(detected(history), (InputFormat::Zsh, Confidence::Low));
+    }
+}
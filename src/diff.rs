@@ -0,0 +1,30 @@
+use std::{fmt::Write, path::Path};
+
+use crate::history_converter::Entry;
+
+/// Render the entries which would be appended to the fish history at `path` as a unified-diff
+/// style preview. After `limit` added lines the remaining entries are only summarized.
+pub fn render_diff(path: &Path, additions: &[Entry], limit: usize) -> String {
+    let mut diff = String::new();
+    if additions.is_empty() {
+        return diff;
+    }
+
+    let _ = writeln!(diff, "--- {}", path.display());
+    let _ = writeln!(diff, "+++ {}", path.display());
+
+    let mut lines = 0;
+    for (i, entry) in additions.iter().enumerate() {
+        let record = entry.to_string();
+        if lines + record.lines().count() > limit {
+            let _ = writeln!(diff, "... and {} more entries", additions.len() - i);
+            break;
+        }
+        for line in record.lines() {
+            let _ = writeln!(diff, "+ {line}");
+            lines += 1;
+        }
+    }
+
+    diff
+}
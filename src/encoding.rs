@@ -0,0 +1,58 @@
+use std::{fmt::Display, str::FromStr};
+
+use chardetng::{EncodingDetector, Iso2022JpDetection, Utf8Detection};
+use encoding_rs::{Encoding, UTF_8};
+
+/// The character encoding of the zsh history file. zsh stores whatever bytes the terminal sent, so
+/// a history from an older machine may well not be UTF-8.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputEncoding {
+    /// Guess the encoding from the contents of the whole file.
+    Auto,
+    /// The given encoding.
+    Fixed(&'static Encoding),
+}
+
+impl Default for InputEncoding {
+    fn default() -> Self {
+        Self::Fixed(UTF_8)
+    }
+}
+
+impl InputEncoding {
+    /// The encoding to decode `bytes`, the unmetafied contents of the file, with.
+    pub fn resolve(&self, bytes: &[u8]) -> &'static Encoding {
+        match self {
+            Self::Auto => {
+                let mut detector = EncodingDetector::new(Iso2022JpDetection::Deny);
+                detector.feed(bytes, true);
+                detector.guess(None, Utf8Detection::Allow)
+            }
+            Self::Fixed(encoding) => encoding,
+        }
+    }
+}
+
+/// Parses `auto` or any label the WHATWG Encoding Standard knows, like `latin1`, `shift_jis`, or
+/// `euc-jp`.
+impl FromStr for InputEncoding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("auto") {
+            return Ok(Self::Auto);
+        }
+        Encoding::for_label(s.as_bytes())
+            .map(Self::Fixed)
+            .ok_or_else(|| format!("unknown encoding: {s}"))
+    }
+}
+
+impl Display for InputEncoding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Auto => write!(f, "auto"),
+            Self::Fixed(encoding) => write!(f, "{}", encoding.name().to_lowercase()),
+        }
+    }
+}
@@ -0,0 +1,329 @@
+use std::{fmt::Display, path::PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+/// A zsh history record which could not be converted, and was skipped.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    /// The zsh history the record is in, `-` for stdin or a history in memory.
+    pub path: PathBuf,
+    /// The 1-based line the record starts on.
+    pub line: usize,
+    /// What is wrong with the record.
+    pub kind: ParseErrorKind,
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {} of {}: {}", self.line, self.path.display(), self.kind)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// What is wrong with a zsh history record.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseErrorKind {
+    /// The timestamp in the header does not fit into an `i64`.
+    TimestampOverflow(String),
+    /// The record is longer than the maximum record length.
+    TooLong {
+        /// The maximum record length in bytes.
+        max: usize,
+        /// Where the record starts in the file, in bytes.
+        offset: usize,
+    },
+    /// The last record of the file is cut off, most likely because zsh is still writing it.
+    Incomplete,
+    /// The line does not fit the format of the history, like a line of an `fc -l` listing
+    /// without an index.
+    Unrecognized(String),
+}
+
+impl Display for ParseErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::TimestampOverflow(when) => {
+                write!(f, "timestamp {when} does not fit into 64 bits")
+            }
+            Self::TooLong { max, offset } => {
+                write!(f, "record at byte offset {offset} is longer than {max} bytes")
+            }
+            Self::Incomplete => {
+                write!(f, "incomplete record, zsh may still be writing the history")
+            }
+            Self::Unrecognized(line) => write!(f, "unrecognized line: {line}"),
+        }
+    }
+}
+
+/// A line of a history which was skipped, or converted despite looking wrong, as `--verbose`
+/// reports it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SkippedLine {
+    /// The history the line is in, `-` for stdin or a history in memory.
+    pub path: PathBuf,
+    /// The 1-based line, or the line the record starts on.
+    pub line: usize,
+    /// Why the line was skipped, or what looks wrong with it.
+    pub reason: SkipReason,
+    /// The first [`SkippedLine::MAX_CONTENT`] bytes of the line, or of the record starting on
+    /// it. Empty if the history is not kept by lines, like a database.
+    pub content: Vec<u8>,
+}
+
+impl SkippedLine {
+    /// How many bytes of a line are kept.
+    pub const MAX_CONTENT: usize = 256;
+
+    // How many characters of the line the preview shows.
+    const PREVIEW_CHARS: usize = 60;
+
+    /// A skipped line, keeping the start of its `content`.
+    pub fn new(path: PathBuf, line: usize, reason: SkipReason, content: &[u8]) -> Self {
+        let content = content[..content.len().min(Self::MAX_CONTENT)].to_vec();
+        Self { path, line, reason, content }
+    }
+
+    /// The bytes of the line in hex, for the lines even the preview cannot make sense of.
+    pub fn hex(&self) -> String {
+        let hex = self.content.iter().map(|b| format!("{b:02x}")).collect::<Vec<_>>();
+        hex.join(" ")
+    }
+}
+
+/// Formats the line, its reason, and a preview of its content, cut off if it is long.
+impl Display for SkippedLine {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (line, path) = (self.line, self.path.display());
+        if self.content.is_empty() {
+            return write!(f, "line {line} of {path}: {}", self.reason);
+        }
+        let content = String::from_utf8_lossy(&self.content);
+        let preview = content.chars().take(Self::PREVIEW_CHARS).collect::<String>();
+        let ellipsis = match content.chars().nth(Self::PREVIEW_CHARS) {
+            Some(_) => "...",
+            None => "",
+        };
+        write!(f, "line {line} of {path}: {}: {:?}{ellipsis}", self.reason, preview)
+    }
+}
+
+/// Why a line was skipped, or what looks wrong with it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SkipReason {
+    /// The line ends in a backslash, continuing a multi-line command, but nothing follows it.
+    MultilineFragment,
+    /// The line has no command, only a header or nothing at all. Skipped unless `--keep-empty`
+    /// keeps a header.
+    EmptyCommand,
+    /// The record is longer than `--max-line-bytes`, and was skipped.
+    Oversized,
+    /// The line is not valid in the encoding of the history, and the invalid bytes were replaced.
+    InvalidEncoding,
+    /// The timestamp does not fit into 64 bits, and the record was skipped, or is before 1970 or
+    /// in the future.
+    BadTimestamp,
+    /// The last record is cut off, and was skipped.
+    Incomplete,
+    /// The command starts with a space, and `--ignore-space` skipped it.
+    SpacePrefixed,
+    /// The line does not fit the format of the history, and was skipped.
+    Unrecognized,
+    /// The command has control characters other than newlines and tabs, which are escaped or,
+    /// with `--skip-invalid`, make it skipped.
+    ControlCharacters,
+}
+
+impl SkipReason {
+    /// Whether `--strict` fails on the line, rather than it being skipped as the history or the
+    /// options ask, like an empty command or, with `--ignore-space`, a command starting with a
+    /// space.
+    pub fn is_anomaly(self) -> bool {
+        !matches!(self, Self::EmptyCommand | Self::SpacePrefixed)
+    }
+}
+
+impl Display for SkipReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::MultilineFragment => write!(f, "multi-line fragment"),
+            Self::EmptyCommand => write!(f, "empty command"),
+            Self::Oversized => write!(f, "oversized"),
+            Self::InvalidEncoding => write!(f, "invalid encoding"),
+            Self::BadTimestamp => write!(f, "bad timestamp"),
+            Self::Incomplete => write!(f, "incomplete"),
+            Self::SpacePrefixed => write!(f, "starts with a space"),
+            Self::Unrecognized => write!(f, "unrecognized"),
+            Self::ControlCharacters => write!(f, "control characters"),
+        }
+    }
+}
+
+impl From<&ParseErrorKind> for SkipReason {
+    fn from(kind: &ParseErrorKind) -> Self {
+        match kind {
+            ParseErrorKind::TimestampOverflow(_) => Self::BadTimestamp,
+            ParseErrorKind::TooLong { .. } => Self::Oversized,
+            ParseErrorKind::Incomplete => Self::Incomplete,
+            ParseErrorKind::Unrecognized(_) => Self::Unrecognized,
+        }
+    }
+}
+
+/// The failures with an exit status of their own, for scripts to tell them apart, but for the
+/// entries `--diff` leaves to append, which exit with 1 as `diff` does. Any other error exits
+/// with 1 too, and invalid options with 2, like a [`Failure::Usage`].
+///
+/// ```
+/// use anyhow::{anyhow, Context};
+/// use zsh_history_to_fish::{Failure, FailureContext};
+///
+/// let error = Err::<(), _>(anyhow!("is a directory"))
+///     .fail_as(Failure::Destination)
+///     .context("failed to write ~/.local/share/fish/fish_history")
+///     .unwrap_err();
+/// assert_eq!(Failure::of(&error), Some(Failure::Destination));
+/// assert_eq!(Failure::of(&error).unwrap().exit_code(), 5);
+/// // the message stays the same
+/// let message = "failed to write ~/.local/share/fish/fish_history: is a directory";
+/// assert_eq!(format!("{error:#}"), message);
+/// assert_eq!(Failure::of(&anyhow!("oops")), None);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Failure {
+    /// The options, from the command line, the configuration file, or the environment, do not
+    /// work together.
+    Usage,
+    /// A history, or another file to read, does not exist or cannot be read.
+    Input,
+    /// With `--strict` or `--strict-utf8`, lines could not be cleanly converted.
+    Strict,
+    /// The destination could not be locked or written.
+    Destination,
+    /// With `--fail-if-empty`, no entries are left to write.
+    Empty,
+    /// With `--diff`, entries are left to append, which is not an error, but for scripts to tell,
+    /// the way `diff` exits with 1 when the files differ.
+    Changes,
+    /// `check` found errors in the fish history, or warnings with `--warnings-as-errors`.
+    Check,
+    /// With `--run-merge`, the running fish sessions could not merge the history written.
+    Merge,
+}
+
+/// The exit statuses, as `--help` lists them.
+pub const EXIT_CODES: &str = "\
+Exit status:
+  0  success
+  1  entries left to append, with --diff, or any other error
+  2  invalid options, from the command line, the configuration file, or the environment
+  3  a history, or another file to read, does not exist or cannot be read
+  4  lines which could not be cleanly converted, with --strict or --strict-utf8
+  5  the destination could not be locked or written
+  6  no entries left to write, with --fail-if-empty
+  7  errors found by check, or warnings with --warnings-as-errors
+  8  fish failed to merge the history written, with --run-merge";
+
+impl Failure {
+    /// The exit status of the failure, as [`EXIT_CODES`] lists them.
+    pub fn exit_code(self) -> u8 {
+        match self {
+            Self::Usage => 2,
+            Self::Input => 3,
+            Self::Strict => 4,
+            Self::Destination => 5,
+            Self::Empty => 6,
+            Self::Changes => 1,
+            Self::Check => 7,
+            Self::Merge => 8,
+        }
+    }
+
+    /// The failure `error` was marked as with [`FailureContext::fail_as`], the innermost, most
+    /// specific one if it was marked several times, or `None` if it was not.
+    pub fn of(error: &anyhow::Error) -> Option<Self> {
+        error
+            .chain()
+            .filter_map(|cause| cause.downcast_ref::<Failed>())
+            .map(|failed| failed.failure)
+            .last()
+    }
+}
+
+/// Marking the error of a [`Result`] as a [`Failure`], keeping its message.
+pub trait FailureContext<T> {
+    /// Mark the error as `failure`.
+    fn fail_as(self, failure: Failure) -> anyhow::Result<T>;
+}
+
+impl<T, E: Into<anyhow::Error>> FailureContext<T> for Result<T, E> {
+    fn fail_as(self, failure: Failure) -> anyhow::Result<T> {
+        self.map_err(|error| match error.into() {
+            // marked already, as something more specific
+            error if error.is::<Failed>() => error,
+            error => anyhow::Error::new(Failed { failure, error }),
+        })
+    }
+}
+
+// An error marked as a failure, in the chain of causes, showing as the error itself.
+#[derive(Debug)]
+struct Failed {
+    failure: Failure,
+    error: anyhow::Error,
+}
+
+impl Display for Failed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.error)
+    }
+}
+
+impl std::error::Error for Failed {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.error.chain().nth(1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::anyhow;
+
+    use super::*;
+
+    const FAILURES: [Failure; 8] = [
+        Failure::Usage,
+        Failure::Input,
+        Failure::Strict,
+        Failure::Destination,
+        Failure::Empty,
+        Failure::Changes,
+        Failure::Check,
+        Failure::Merge,
+    ];
+
+    #[test]
+    fn exit_codes_are_listed() {
+        let listed = EXIT_CODES
+            .lines()
+            .skip(1)
+            .map(|line| line.split_whitespace().next().unwrap().parse::<u8>().unwrap())
+            .collect::<Vec<_>>();
+        let mut codes = FAILURES.map(Failure::exit_code).to_vec();
+        codes.extend([0, 1]);
+        codes.sort();
+        codes.dedup();
+        assert_eq!(listed, codes, "each status needs a line of its own in EXIT_CODES");
+    }
+
+    #[test]
+    fn innermost_failure_wins() {
+        let error = Err::<(), _>(anyhow!("locked"))
+            .fail_as(Failure::Destination)
+            .fail_as(Failure::Input)
+            .unwrap_err();
+        assert_eq!(Failure::of(&error), Some(Failure::Destination));
+    }
+}
@@ -0,0 +1,150 @@
+use std::{
+    ffi::OsString,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Result};
+
+/// Expand a path like a shell would, for paths which reach us literally from a systemd unit or a
+/// terminal profile: a leading `~` or `~user`, and `$VAR` or `${VAR}` anywhere. A `$` not followed
+/// by a variable name is kept as is, and so are paths which are not valid UTF-8.
+///
+/// Environment variables are looked up through `env`, like [`fish_history_path`], and the home
+/// directories of other users through `home_of`, e.g. [`user_home`].
+///
+/// [`fish_history_path`]: crate::fish_history_path
+pub fn expand_path(
+    path: &Path,
+    env: impl Fn(&str) -> Option<OsString>,
+    home_of: impl Fn(&str) -> Option<PathBuf>,
+) -> Result<PathBuf> {
+    let Some(path_str) = path.to_str() else {
+        return Ok(path.to_path_buf());
+    };
+
+    let mut expanded = OsString::new();
+    let mut rest = path_str;
+    if let Some(tilde) = rest.strip_prefix('~') {
+        let end = tilde.find(['/', '\\']).unwrap_or(tilde.len());
+        let (user, after) = tilde.split_at(end);
+        let home = match user {
+            "" => match env("HOME").filter(|home| !home.is_empty()) {
+                Some(home) => PathBuf::from(home),
+                None => bail!("cannot expand ~ in {path_str}: HOME is not set"),
+            },
+            user => match home_of(user) {
+                Some(home) => home,
+                None => bail!("cannot expand ~{user} in {path_str}: no such user"),
+            },
+        };
+        expanded.push(home);
+        rest = after;
+    }
+
+    while let Some(start) = rest.find('$') {
+        expanded.push(&rest[..start]);
+        let after = &rest[start + 1..];
+        let (name, tail) = match after.strip_prefix('{') {
+            Some(braced) => match braced.find('}') {
+                Some(end) => (&braced[..end], &braced[end + 1..]),
+                None => bail!("unterminated ${{ in {path_str}"),
+            },
+            None => {
+                let end = after
+                    .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                    .unwrap_or(after.len());
+                after.split_at(end)
+            }
+        };
+        if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+            expanded.push("$");
+            rest = after;
+            continue;
+        }
+        match env(name) {
+            Some(value) => expanded.push(value),
+            None => bail!("cannot expand {path_str}: ${name} is not set"),
+        }
+        rest = tail;
+    }
+    expanded.push(rest);
+
+    Ok(PathBuf::from(expanded))
+}
+
+/// The home directory of `user` according to `/etc/passwd`.
+pub fn user_home(user: &str) -> Option<PathBuf> {
+    let passwd = std::fs::read_to_string("/etc/passwd").ok()?;
+    passwd.lines().find_map(|line| {
+        let fields = line.split(':').collect::<Vec<_>>();
+        match fields[..] {
+            [name, _, _, _, _, home, ..] if name == user => Some(PathBuf::from(home)),
+            _ => None,
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Expand `path` with only `HOME` and `DIR` set, and only `alice` as another user.
+    fn expand(path: &str) -> Result<PathBuf> {
+        let env = |key: &str| match key {
+            "HOME" => Some(OsString::from("/home/me")),
+            "DIR" => Some(OsString::from("/data")),
+            _ => None,
+        };
+        let home_of = |user: &str| (user == "alice").then(|| PathBuf::from("/home/alice"));
+        expand_path(Path::new(path), env, home_of)
+    }
+
+    #[test]
+    fn tilde_is_the_home() {
+        assert_eq!(expand("~").unwrap(), Path::new("/home/me"));
+        assert_eq!(expand("~/.zsh_history").unwrap(), Path::new("/home/me/.zsh_history"));
+        assert_eq!(expand("~alice/.zsh_history").unwrap(), Path::new("/home/alice/.zsh_history"));
+        // only at the start
+        assert_eq!(expand("a/~/b").unwrap(), Path::new("a/~/b"));
+    }
+
+    #[test]
+    fn unknown_users_are_errors() {
+        let error = expand("~bob/.zsh_history").unwrap_err().to_string();
+        assert_eq!(error, "cannot expand ~bob in ~bob/.zsh_history: no such user");
+    }
+
+    #[test]
+    fn variables_are_expanded() {
+        assert_eq!(expand("$DIR/hist").unwrap(), Path::new("/data/hist"));
+        assert_eq!(expand("${DIR}hist").unwrap(), Path::new("/datahist"));
+        assert_eq!(expand("~/$DIR").unwrap(), Path::new("/home/me/data"));
+    }
+
+    #[test]
+    fn undefined_variables_are_named() {
+        let error = expand("$HISTDIR/hist").unwrap_err().to_string();
+        assert_eq!(error, "cannot expand $HISTDIR/hist: $HISTDIR is not set");
+        let error = expand("${HIST DIR}").unwrap_err().to_string();
+        assert!(error.ends_with("$HIST DIR is not set"), "{error}");
+        let error = expand("${DIR").unwrap_err().to_string();
+        assert_eq!(error, "unterminated ${ in ${DIR");
+    }
+
+    #[test]
+    fn other_dollars_are_kept() {
+        assert_eq!(expand("cost$").unwrap(), Path::new("cost$"));
+        assert_eq!(expand("$1 $-x").unwrap(), Path::new("$1 $-x"));
+        assert_eq!(expand("a$/b").unwrap(), Path::new("a$/b"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn non_utf8_paths_are_kept() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let path = Path::new(std::ffi::OsStr::from_bytes(b"~/$DIR\xff"));
+        let expanded = expand_path(path, |_| None, |_| None).unwrap();
+        assert_eq!(expanded, path);
+    }
+}
@@ -0,0 +1,209 @@
+//! Reading the history listings `fc -l` and `history` print.
+
+use std::{str::FromStr, sync::LazyLock};
+
+use chrono::{FixedOffset, Local, NaiveDateTime, TimeZone};
+use regex::Regex;
+
+use crate::{bash::ParsedEntry, error::ParseErrorKind, history_converter::Entry};
+
+/// A fixed offset from UTC, like `+09:00`, `-0500`, or `UTC`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UtcOffset(pub FixedOffset);
+
+impl FromStr for UtcOffset {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        static OFFSET: LazyLock<Regex> =
+            LazyLock::new(|| Regex::new(r"^([+-])([0-9]{2}):?([0-9]{2})?$").unwrap());
+
+        if s.eq_ignore_ascii_case("utc") || s == "Z" {
+            return Ok(Self(FixedOffset::east_opt(0).unwrap()));
+        }
+        let invalid = || format!("invalid UTC offset: {s}, expected e.g. +09:00 or UTC");
+        let caps = OFFSET.captures(s).ok_or_else(invalid)?;
+        let minutes = caps.get(3).map_or(0, |m| m.as_str().parse::<i32>().unwrap());
+        let secs = (caps[2].parse::<i32>().unwrap() * 60 + minutes) * 60;
+        let secs = if &caps[1] == "-" { -secs } else { secs };
+        FixedOffset::east_opt(secs).map(Self).ok_or_else(invalid)
+    }
+}
+
+/// Parse a listing like `fc -li` prints, returning every entry with the line it starts on.
+///
+/// Each entry is an optional index, as `fc -l` prints unless `-n` is given, an optional
+/// `YYYY-MM-DD HH:MM` timestamp, as `fc -li` prints, with optional seconds, and the command. The
+/// further lines of a multi-line command are indented, and the indentation they share is removed,
+/// though no further than the column the command started at. The timestamps are taken to be in
+/// `tz`, or in the local time zone if `None`.
+///
+/// Whether the listing has indices is told by its first line. If it has, a line with neither an
+/// index nor indentation is reported, rather than taken for a part of the command before it.
+pub fn parse_fc_listing(text: &str, tz: Option<UtcOffset>) -> Vec<ParsedEntry> {
+    static INDEXED: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^\s*[0-9]+\*?\s+").unwrap());
+    static TIMESTAMP: LazyLock<Regex> = LazyLock::new(|| {
+        Regex::new(r"^([0-9]{4}-[0-9]{2}-[0-9]{2} [0-9]{2}:[0-9]{2}(?::[0-9]{2})?)\s+").unwrap()
+    });
+
+    let indexed = text
+        .lines()
+        .find(|line| !line.trim().is_empty())
+        .is_some_and(|line| INDEXED.is_match(line));
+
+    let mut entries: Vec<ParsedEntry> = Vec::new();
+    // the column the current command starts at, if it may continue, and its further lines
+    let mut column = None;
+    let mut continued = Vec::new();
+    for (line_number, line) in (1..).zip(text.lines()) {
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let index = INDEXED.find(line).filter(|_| indexed);
+        let indented = line.starts_with(char::is_whitespace);
+        if index.is_none() && (indented || indexed) {
+            match column {
+                Some(_) if indented => continued.push(line),
+                _ => {
+                    continue_command(&mut entries, column.take(), &mut continued);
+                    let kind = ParseErrorKind::Unrecognized(line.to_string());
+                    entries.push((line_number, Err(kind)));
+                }
+            }
+            continue;
+        }
+        continue_command(&mut entries, column.take(), &mut continued);
+
+        let start = index.map_or(0, |index| index.end());
+        let (when, start) = match TIMESTAMP.captures(&line[start..]) {
+            Some(caps) => match local_timestamp(&caps[1], tz) {
+                Some(when) => (when, start + caps[0].len()),
+                None => {
+                    let kind = ParseErrorKind::Unrecognized(line.to_string());
+                    entries.push((line_number, Err(kind)));
+                    continue;
+                }
+            },
+            None => (0, start),
+        };
+        let entry = Entry {
+            cmd: line[start..].to_string(),
+            when,
+            ..Default::default()
+        };
+        entries.push((line_number, Ok(entry)));
+        column = Some(start);
+    }
+    continue_command(&mut entries, column, &mut continued);
+
+    entries
+}
+
+// Add the `continued` lines to the last of the `entries`, whose command started at `column`.
+fn continue_command(entries: &mut [ParsedEntry], column: Option<usize>, continued: &mut Vec<&str>) {
+    let lines = std::mem::take(continued);
+    let (Some(column), Some((_, Ok(entry)))) = (column, entries.last_mut()) else {
+        return;
+    };
+    let indent = lines.iter().map(|line| line.len() - line.trim_start().len()).min();
+    let indent = match indent {
+        Some(indent) if column > 0 => indent.min(column),
+        Some(indent) => indent,
+        None => return,
+    };
+    for line in lines {
+        entry.cmd.push('\n');
+        entry.cmd.push_str(&line[indent..]);
+    }
+}
+
+// The Unix time of the `YYYY-MM-DD HH:MM[:SS]` in `tz`, or the local time zone. A local time
+// which occurs twice, when the clocks go back, is taken to be the earlier one.
+pub(crate) fn local_timestamp(datetime: &str, tz: Option<UtcOffset>) -> Option<i64> {
+    let naive = NaiveDateTime::parse_from_str(datetime, "%Y-%m-%d %H:%M:%S")
+        .or_else(|_| NaiveDateTime::parse_from_str(datetime, "%Y-%m-%d %H:%M"))
+        .ok()?;
+    let when = match tz {
+        Some(UtcOffset(offset)) => offset.from_local_datetime(&naive).earliest()?.timestamp(),
+        None => Local.from_local_datetime(&naive).earliest()?.timestamp(),
+    };
+    Some(when)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const UTC: Option<UtcOffset> = Some(UtcOffset(FixedOffset::east_opt(0).unwrap()));
+
+    // The line an entry starts on, and its time and command, or the line which could not be read.
+    type Parsed = (usize, Result<(i64, String), ParseErrorKind>);
+
+    fn parsed(text: &str) -> Vec<Parsed> {
+        let entries = parse_fc_listing(text, UTC).into_iter();
+        entries
+            .map(|(line, entry)| (line, entry.map(|entry| (entry.when, entry.cmd))))
+            .collect()
+    }
+
+    fn unrecognized(line: &str) -> Result<(i64, String), ParseErrorKind> {
+        Err(ParseErrorKind::Unrecognized(line.into()))
+    }
+
+    #[test]
+    fn indices_and_timestamps_are_removed() {
+        let text = concat!(
+            "    1  2023-11-14 22:13  ls\n",
+            "    2* 2023-11-14 22:13:20  for i in 1\n",
+            "         do echo $i\n",
+            "         done\n",
+        );
+        assert_eq!(
+            parsed(text),
+            [
+                (1, Ok((1_700_000_000 - 20, "ls".into()))),
+                (2, Ok((1_700_000_000, "for i in 1\ndo echo $i\ndone".into()))),
+            ]
+        );
+        assert_eq!(parsed("ls\npwd\n"), [(1, Ok((0, "ls".into()))), (2, Ok((0, "pwd".into())))]);
+    }
+
+    #[test]
+    fn unrecognized_lines_are_reported() {
+        // neither an index nor indentation, in a listing with indices
+        let text = "    1  ls\nstray output\n    2  pwd\n";
+        assert_eq!(
+            parsed(text),
+            [
+                (1, Ok((0, "ls".into()))),
+                (2, unrecognized("stray output")),
+                (3, Ok((0, "pwd".into())))
+            ]
+        );
+
+        // indentation with no command before it to continue
+        let text = "  continued\nls\n";
+        assert_eq!(parsed(text), [(1, unrecognized("  continued")), (2, Ok((0, "ls".into())))]);
+
+        // a timestamp which is no time
+        let text = "    1  2023-13-45 22:13  ls\n    2  pwd\n";
+        assert_eq!(
+            parsed(text),
+            [(1, unrecognized("    1  2023-13-45 22:13  ls")), (2, Ok((0, "pwd".into())))]
+        );
+    }
+
+    #[test]
+    fn offsets_are_parsed() {
+        let offset = |s: &str| {
+            s.parse::<UtcOffset>()
+                .map(|UtcOffset(offset)| offset.local_minus_utc())
+        };
+        assert_eq!(offset("+09:00"), Ok(9 * 3600));
+        assert_eq!(offset("-0530"), Ok(-(5 * 3600 + 30 * 60)));
+        assert_eq!(offset("UTC"), Ok(0));
+        assert!(offset("9").unwrap_err().contains("expected e.g. +09:00"));
+    }
+}
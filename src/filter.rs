@@ -0,0 +1,483 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::LazyLock,
+};
+
+use chrono::{DateTime, Local, Months, TimeDelta};
+use clap::ValueEnum;
+use regex::Regex;
+
+use crate::{
+    fc::local_timestamp, history_converter::Entry, strip::is_assignment, zoxide::commands,
+};
+
+/// Keep only the newest `limit` entries by `when`, where an entry later in `entries` counts as
+/// newer than an earlier one with the same timestamp. The kept entries stay in their original
+/// order. Returns the kept entries and the number of entries which were dropped.
+pub fn limit(entries: Vec<Entry>, limit: usize) -> (Vec<Entry>, usize) {
+    if entries.len() <= limit {
+        return (entries, 0);
+    }
+
+    let mut newest = (0..entries.len()).collect::<Vec<_>>();
+    newest.sort_by_key(|&i| std::cmp::Reverse((entries[i].when, i)));
+    let mut keep = vec![false; entries.len()];
+    newest.into_iter().take(limit).for_each(|i| keep[i] = true);
+
+    let dropped = entries.len() - limit;
+    let entries = entries
+        .into_iter()
+        .zip(keep)
+        .filter_map(|(entry, keep)| keep.then_some(entry));
+    (entries.collect(), dropped)
+}
+
+/// The order to write the entries in.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Sort {
+    /// Oldest first, with the entries without a timestamp before all others, and entries sharing
+    /// a timestamp in the order they were read in.
+    When,
+    /// The order the entries were read in, after merging several histories by timestamp.
+    #[default]
+    #[value(alias = "none")]
+    Input,
+}
+
+/// Sort the entries as `sort` says.
+pub fn sort_entries(mut entries: Vec<Entry>, sort: Sort) -> Vec<Entry> {
+    if sort == Sort::When {
+        // a stable sort, so entries sharing a timestamp keep their order
+        entries.sort_by_key(|entry| entry.when);
+    }
+    entries
+}
+
+/// Keep only the first `n` entries. Returns the kept entries and the number of entries which were
+/// dropped.
+pub fn head(mut entries: Vec<Entry>, n: usize) -> (Vec<Entry>, usize) {
+    let dropped = entries.len().saturating_sub(n);
+    entries.truncate(n);
+    (entries, dropped)
+}
+
+/// Keep only the last `n` entries. Returns the kept entries and the number of entries which were
+/// dropped.
+pub fn tail(mut entries: Vec<Entry>, n: usize) -> (Vec<Entry>, usize) {
+    let dropped = entries.len().saturating_sub(n);
+    entries.drain(..dropped);
+    (entries, dropped)
+}
+
+/// Keep only the entries with a `when` within `since` and `until`, both inclusive. Entries
+/// without a timestamp count as being from 1970, so `since` drops them, unless `include_undated`
+/// keeps them regardless. Returns the kept entries and the number of entries which were dropped.
+pub fn between(
+    mut entries: Vec<Entry>,
+    since: Option<i64>,
+    until: Option<i64>,
+    include_undated: bool,
+) -> (Vec<Entry>, usize) {
+    let before = entries.len();
+    entries.retain(|entry| {
+        (include_undated && entry.when == 0)
+            || since.is_none_or(|since| entry.when >= since)
+                && until.is_none_or(|until| entry.when <= until)
+    });
+    let dropped = before - entries.len();
+    (entries, dropped)
+}
+
+/// Keep only the entries whose command matches any of `patterns`, anywhere in the command, which
+/// may span several lines. Returns the kept entries and the number of entries which were dropped.
+pub fn matching(mut entries: Vec<Entry>, patterns: &[Regex]) -> (Vec<Entry>, usize) {
+    let before = entries.len();
+    entries.retain(|entry| patterns.iter().any(|pattern| pattern.is_match(&entry.cmd)));
+    let dropped = before - entries.len();
+    (entries, dropped)
+}
+
+/// Keep only the entries whose command runs one of `names`, as its [`first_word`]. Returns the
+/// kept entries and the number of entries which were dropped.
+pub fn running(mut entries: Vec<Entry>, names: &[String]) -> (Vec<Entry>, usize) {
+    let names = names.iter().map(String::as_str).collect::<HashSet<_>>();
+    let before = entries.len();
+    entries.retain(|entry| first_word(&entry.cmd).is_some_and(|word| names.contains(&*word)));
+    let dropped = before - entries.len();
+    (entries, dropped)
+}
+
+/// The program `cmd` runs: its first word, after any variable assignments, with the quotes and
+/// backslashes the shell removes removed, so the first word of `"my prog" arg` is `my prog`.
+pub fn first_word(cmd: &str) -> Option<String> {
+    let words = commands(cmd).into_iter().next()?;
+    words.into_iter().find(|word| !is_assignment(word))
+}
+
+/// The distinct [`first_word`]s of the commands, with how many commands have each, most first and
+/// by name for the same count.
+pub fn command_counts(entries: &[Entry]) -> Vec<(String, usize)> {
+    let mut counts = HashMap::<String, usize>::new();
+    for word in entries.iter().filter_map(|entry| first_word(&entry.cmd)) {
+        *counts.entry(word).or_default() += 1;
+    }
+    let mut counts = counts.into_iter().collect::<Vec<_>>();
+    counts.sort_by(|(a, m), (b, n)| n.cmp(m).then_with(|| a.cmp(b)));
+    counts
+}
+
+/// Drop the entries whose command matches any of `patterns`, the opposite of [`matching`].
+/// Returns the kept entries and the number of entries which were dropped.
+pub fn excluding(mut entries: Vec<Entry>, patterns: &[Regex]) -> (Vec<Entry>, usize) {
+    let before = entries.len();
+    entries.retain(|entry| !patterns.iter().any(|pattern| pattern.is_match(&entry.cmd)));
+    let dropped = before - entries.len();
+    (entries, dropped)
+}
+
+/// The commands `--skip-common` skips by default, which are typed all the time without arguments.
+pub const COMMON_COMMANDS: &[&str] = &["ls", "ll", "la", "cd", "clear", "exit", "pwd", "history"];
+
+/// Drop the entries whose command, without the whitespace around it, is shorter than `min_length`
+/// characters. Returns the kept entries and the number of entries which were dropped.
+pub fn skip_short(mut entries: Vec<Entry>, min_length: usize) -> (Vec<Entry>, usize) {
+    let before = entries.len();
+    entries.retain(|entry| entry.cmd.trim().chars().count() >= min_length);
+    let dropped = before - entries.len();
+    (entries, dropped)
+}
+
+/// Drop the entries whose command, without the whitespace around it, is exactly one of `common`,
+/// like [`COMMON_COMMANDS`]. Returns the kept entries and the number of entries which were
+/// dropped.
+pub fn skip_common(mut entries: Vec<Entry>, common: &[&str]) -> (Vec<Entry>, usize) {
+    let common = common.iter().copied().collect::<HashSet<_>>();
+    let before = entries.len();
+    entries.retain(|entry| !common.contains(entry.cmd.trim()));
+    let dropped = before - entries.len();
+    (entries, dropped)
+}
+
+/// Drop the entries whose command spans several lines, for tools which keep one command per line.
+/// Returns the kept entries and the number of entries which were dropped.
+pub fn skip_multiline(mut entries: Vec<Entry>) -> (Vec<Entry>, usize) {
+    let before = entries.len();
+    entries.retain(|entry| !entry.cmd.contains('\n'));
+    let dropped = before - entries.len();
+    (entries, dropped)
+}
+
+/// Parse a point in time for `--since` and `--until`: a Unix time, an RFC 3339 timestamp like
+/// `2024-05-01T12:00:00+09:00`, a `YYYY-MM-DD` date with an optional `HH:MM[:SS]` time in the local
+/// time zone, or a time ago like `90d`, `6months`, or `2y`.
+pub fn parse_time(s: &str) -> Result<i64, String> {
+    if let Ok(when) = s.parse() {
+        return Ok(when);
+    }
+    if let Ok(datetime) = DateTime::parse_from_rfc3339(s) {
+        return Ok(datetime.timestamp());
+    }
+    if let Some(when) = ago(s) {
+        return when;
+    }
+    let datetime = match s.len() {
+        10 => format!("{s} 00:00"),
+        _ => s.replacen('T', " ", 1),
+    };
+    local_timestamp(&datetime, None).ok_or_else(|| {
+        format!(
+            "invalid time: {s}, expected a Unix time, YYYY-MM-DD [HH:MM[:SS]], an RFC 3339 \
+             timestamp, or a time ago like 90d"
+        )
+    })
+}
+
+// The Unix time of a time ago like `90d`, or `None` if `s` is not one. Months and years go back
+// by the calendar, so `1y` is the same day a year ago.
+fn ago(s: &str) -> Option<Result<i64, String>> {
+    static AGO: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^([0-9]+) ?([a-z]+)$").unwrap());
+
+    let caps = AGO.captures(s)?;
+    let n = caps[1].parse::<u32>();
+    let now = Local::now();
+    let when = match (&caps[2], n) {
+        (_, Err(_)) => None,
+        ("s" | "sec" | "secs" | "second" | "seconds", Ok(n)) => {
+            now.checked_sub_signed(TimeDelta::seconds(n.into()))
+        }
+        ("min" | "mins" | "minute" | "minutes", Ok(n)) => {
+            now.checked_sub_signed(TimeDelta::minutes(n.into()))
+        }
+        ("h" | "hour" | "hours", Ok(n)) => now.checked_sub_signed(TimeDelta::hours(n.into())),
+        ("d" | "day" | "days", Ok(n)) => now.checked_sub_signed(TimeDelta::days(n.into())),
+        ("w" | "week" | "weeks", Ok(n)) => now.checked_sub_signed(TimeDelta::weeks(n.into())),
+        ("mo" | "month" | "months", Ok(n)) => now.checked_sub_months(Months::new(n)),
+        ("y" | "year" | "years", Ok(n)) => {
+            n.checked_mul(12).and_then(|n| now.checked_sub_months(Months::new(n)))
+        }
+        (unit, _) => {
+            return Some(Err(format!(
+                "invalid unit of time: {unit} in {s}, expected s, min, h, d, w, mo, or y"
+            )))
+        }
+    };
+    Some(
+        when.map(|when| when.timestamp())
+            .ok_or_else(|| format!("invalid time: {s}, too long ago")),
+    )
+}
+
+/// Strip NUL bytes from the commands and escape the other control characters, except newlines
+/// and tabs, the way fish does, so the history file stays readable and recalling a command types
+/// the same characters. With `skip`, entries with control characters are dropped instead.
+/// Returns the remaining entries and the number of entries which were changed or dropped.
+pub fn sanitize(mut entries: Vec<Entry>, skip: bool) -> (Vec<Entry>, usize) {
+    let mut affected = 0;
+    entries.retain_mut(|entry| {
+        if !entry.cmd.chars().any(is_control) {
+            return true;
+        }
+
+        affected += 1;
+        entry.cmd = escape_controls(&entry.cmd);
+        !skip
+    });
+    (entries, affected)
+}
+
+// Whether `c` is a control character other than a newline or tab.
+pub(crate) fn is_control(c: char) -> bool {
+    matches!(c, '\0'..='\x1f') && !matches!(c, '\n' | '\t')
+}
+
+pub(crate) fn escape_controls(cmd: &str) -> String {
+    let mut escaped = String::with_capacity(cmd.len());
+    for c in cmd.chars() {
+        match c {
+            '\0' => {}
+            '\x07' => escaped.push_str(r"\a"),
+            '\x08' => escaped.push_str(r"\b"),
+            '\x0b' => escaped.push_str(r"\v"),
+            '\x0c' => escaped.push_str(r"\f"),
+            '\r' => escaped.push_str(r"\r"),
+            '\x1b' => escaped.push_str(r"\e"),
+            c if is_control(c) => escaped.push_str(&format!(r"\x{:02x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+    use clap::ValueEnum;
+
+    use super::*;
+
+    fn entries(whens: &[i64]) -> Vec<Entry> {
+        let entry = |(i, &when)| Entry {
+            cmd: format!("echo {i}"),
+            when,
+            ..Default::default()
+        };
+        whens.iter().enumerate().map(entry).collect()
+    }
+
+    fn whens(entries: &[Entry]) -> Vec<i64> {
+        entries.iter().map(|entry| entry.when).collect()
+    }
+
+    #[test]
+    fn head_and_tail() {
+        let (kept, dropped) = head(entries(&[1, 2, 3, 4]), 3);
+        assert_eq!((whens(&kept), dropped), (vec![1, 2, 3], 1));
+        let (kept, dropped) = tail(entries(&[1, 2, 3, 4]), 3);
+        assert_eq!((whens(&kept), dropped), (vec![2, 3, 4], 1));
+        let (kept, dropped) = tail(entries(&[1, 2]), 5);
+        assert_eq!((whens(&kept), dropped), (vec![1, 2], 0));
+    }
+
+    #[test]
+    fn tail_counts_after_since_and_until() {
+        // the CLI filters with `--since` and `--until` first, then keeps the window
+        let (kept, _) = between(entries(&[1, 2, 3, 4, 5, 6]), Some(2), Some(5), false);
+        let (kept, dropped) = tail(kept, 2);
+        assert_eq!((whens(&kept), dropped), (vec![4, 5], 2));
+        let (kept, _) = between(entries(&[1, 2, 3, 4, 5, 6]), Some(2), Some(5), false);
+        let (kept, _) = head(kept, 2);
+        assert_eq!(whens(&kept), [2, 3]);
+    }
+
+    #[test]
+    fn between_keeps_undated_only_when_asked() {
+        let (kept, dropped) = between(entries(&[0, 5, 10]), Some(5), None, false);
+        assert_eq!((whens(&kept), dropped), (vec![5, 10], 1));
+        let (kept, _) = between(entries(&[0, 5, 10]), Some(6), None, true);
+        assert_eq!(whens(&kept), [0, 10]);
+    }
+
+    fn commands(cmds: &[&str]) -> Vec<Entry> {
+        let entry = |cmd: &&str| Entry { cmd: cmd.to_string(), ..Default::default() };
+        cmds.iter().map(entry).collect()
+    }
+
+    #[test]
+    fn nuls_are_stripped() {
+        let (kept, affected) = sanitize(commands(&["echo a\0b", "ls"]), false);
+        assert_eq!(kept[0].cmd, "echo ab");
+        assert_eq!((kept.len(), affected), (2, 1));
+    }
+
+    #[test]
+    fn escapes_are_escaped_like_fish() {
+        let (kept, affected) = sanitize(commands(&["printf '\x1b[31mred\x1b[0m'\r\x01"]), false);
+        assert_eq!(kept[0].cmd, r"printf '\e[31mred\e[0m'\r\x01");
+        assert_eq!(affected, 1);
+    }
+
+    #[test]
+    fn newlines_and_tabs_are_left_alone() {
+        let (kept, affected) = sanitize(commands(&["for i in 1\n\techo $i\nend"]), false);
+        assert_eq!(kept[0].cmd, "for i in 1\n\techo $i\nend");
+        assert_eq!(affected, 0);
+    }
+
+    #[test]
+    fn skip_drops_entries_with_controls() {
+        let (kept, affected) = sanitize(commands(&["echo \0", "ls", "\x1b[A", "pwd"]), true);
+        assert_eq!(kept.iter().map(|entry| entry.cmd.as_str()).collect::<Vec<_>>(), ["ls", "pwd"]);
+        assert_eq!(affected, 2);
+    }
+
+    #[test]
+    fn sorting_keeps_the_order_within_a_second() {
+        let sorted = sort_entries(entries(&[7, 7, 3, 7, 7, 3, 7, 7, 7, 7, 7, 7]), Sort::When);
+        let cmds = sorted.iter().map(|entry| entry.cmd.as_str()).collect::<Vec<_>>();
+        let expected = ["2", "5", "0", "1", "3", "4", "6", "7", "8", "9", "10", "11"];
+        assert_eq!(cmds, expected.map(|i| format!("echo {i}")));
+    }
+
+    #[test]
+    fn times_parse_in_every_form() {
+        assert_eq!(parse_time("1700000000"), Ok(1_700_000_000));
+        assert_eq!(parse_time("2024-05-01T12:00:00+09:00"), Ok(1_714_532_400));
+        assert_eq!(parse_time("2024-05-01T03:00:00Z"), Ok(1_714_532_400));
+        let midnight = Local.with_ymd_and_hms(2024, 5, 1, 0, 0, 0).unwrap().timestamp();
+        assert_eq!(parse_time("2024-05-01"), Ok(midnight));
+        assert_eq!(parse_time("2024-05-01 00:30"), Ok(midnight + 30 * 60));
+        assert_eq!(parse_time("2024-05-01T00:00:05"), Ok(midnight + 5));
+    }
+
+    #[test]
+    fn times_ago_count_back_from_now() {
+        let now = Local::now();
+        let close = |s: &str, expected: DateTime<Local>| {
+            let when = parse_time(s).unwrap();
+            assert!((when - expected.timestamp()).abs() <= 5, "{s}: {when}");
+        };
+        close("90d", now - TimeDelta::days(90));
+        close("90 days", now - TimeDelta::days(90));
+        close("2w", now - TimeDelta::weeks(2));
+        close("6months", now.checked_sub_months(Months::new(6)).unwrap());
+        close("2y", now.checked_sub_months(Months::new(24)).unwrap());
+        close("1h", now - TimeDelta::hours(1));
+    }
+
+    #[test]
+    fn bad_times_say_what_is_expected() {
+        let error = parse_time("3 fortnights").unwrap_err();
+        assert!(error.starts_with("invalid unit of time: fortnights"), "{error}");
+        let error = parse_time("yesterday").unwrap_err();
+        assert!(error.contains("expected a Unix time"), "{error}");
+        assert!(parse_time("2024-13-01").is_err());
+        assert!(parse_time("99999999y").unwrap_err().contains("too long ago"));
+    }
+
+    #[test]
+    fn between_is_inclusive() {
+        let (kept, dropped) = between(entries(&[1, 5, 6, 10, 11]), Some(5), Some(10), false);
+        assert_eq!((whens(&kept), dropped), (vec![5, 6, 10], 2));
+        let (kept, _) = between(entries(&[0, 5, 11]), None, Some(10), false);
+        assert_eq!(whens(&kept), [0, 5]);
+    }
+
+    #[test]
+    fn undated_entries_sort_first() {
+        let sorted = sort_entries(entries(&[5, 0, 3, 0]), Sort::When);
+        assert_eq!(whens(&sorted), [0, 0, 3, 5]);
+        assert_eq!(sorted[0].cmd, "echo 1");
+    }
+
+    #[test]
+    fn input_keeps_the_order() {
+        let sorted = sort_entries(entries(&[5, 0, 3]), Sort::Input);
+        assert_eq!(whens(&sorted), [5, 0, 3]);
+        assert_eq!(Sort::from_str("none", false), Ok(Sort::Input));
+    }
+
+    fn patterns(patterns: &[&str]) -> Vec<Regex> {
+        patterns.iter().map(|pattern| Regex::new(pattern).unwrap()).collect()
+    }
+
+    #[test]
+    fn excluding_drops_any_match() {
+        let entries = commands(&["ls", "ls -la", "clear", "cd ..", "make"]);
+        let (kept, dropped) = excluding(entries, &patterns(&["^ls$", "^clear$", r"^cd \.\.$"]));
+        assert_eq!(
+            kept.iter().map(|entry| entry.cmd.as_str()).collect::<Vec<_>>(),
+            ["ls -la", "make"]
+        );
+        assert_eq!(dropped, 3);
+        let (kept, dropped) = excluding(commands(&["ls"]), &[]);
+        assert_eq!((kept.len(), dropped), (1, 0));
+    }
+
+    #[test]
+    fn exclude_wins_over_match() {
+        // an entry must match an include and no exclude
+        let entries = commands(&["git status", "git push --force", "ls"]);
+        let (kept, unmatched) = matching(entries, &patterns(&["^git "]));
+        let (kept, excluded) = excluding(kept, &patterns(&["--force"]));
+        assert_eq!(kept.iter().map(|entry| entry.cmd.as_str()).collect::<Vec<_>>(), ["git status"]);
+        assert_eq!((unmatched, excluded), (1, 1));
+    }
+
+    #[test]
+    fn patterns_match_across_lines() {
+        let (kept, _) =
+            excluding(commands(&["cat <<EOF\nsecret\nEOF", "ls"]), &patterns(&["secret"]));
+        assert_eq!(kept.len(), 1);
+    }
+
+    // The commands of `entries`.
+    fn cmds(entries: &[Entry]) -> Vec<&str> {
+        entries.iter().map(|entry| entry.cmd.as_str()).collect()
+    }
+
+    #[test]
+    fn short_commands_are_trimmed_first() {
+        let entries = commands(&["ls", "  ls  ", "cd ..", "make", "git st"]);
+        let (kept, dropped) = skip_short(entries, 5);
+        assert_eq!((cmds(&kept), dropped), (vec!["cd ..", "git st"], 3));
+        // characters are counted, not bytes
+        let (kept, _) = skip_short(commands(&["échø"]), 4);
+        assert_eq!(kept.len(), 1);
+        let (_, dropped) = skip_short(commands(&["ls"]), 0);
+        assert_eq!(dropped, 0);
+    }
+
+    #[test]
+    fn only_exactly_common_commands_are_skipped() {
+        let entries = commands(&["cd", "cd ..", " ls ", "ls -la /etc", "clear", "lsblk", "exit 1"]);
+        let (kept, dropped) = skip_common(entries, COMMON_COMMANDS);
+        assert_eq!((cmds(&kept), dropped), (vec!["cd ..", "ls -la /etc", "lsblk", "exit 1"], 3));
+    }
+
+    #[test]
+    fn common_commands_can_be_replaced() {
+        let entries = commands(&["ls", "cd", "gs", "k get pods"]);
+        let (kept, dropped) = skip_common(entries, &["gs", "k get pods"]);
+        assert_eq!((cmds(&kept), dropped), (vec!["ls", "cd"], 2));
+    }
+}
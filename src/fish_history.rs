@@ -0,0 +1,408 @@
+use std::{
+    ffi::OsString,
+    path::{Path, PathBuf},
+    process::ExitStatus,
+};
+
+use anyhow::{anyhow, bail, Context, Result};
+use tokio::{
+    fs::File,
+    io::{AsyncBufRead, AsyncBufReadExt, BufReader},
+    process::Command,
+};
+
+use crate::{format::Format, history_converter::Entry};
+
+/// The session fish uses when `$fish_history` is not set.
+pub const DEFAULT_SESSION_NAME: &str = "fish";
+
+/// Resolve the location of the fish history file the same way fish does: the data directory is
+/// `$XDG_DATA_HOME/fish`, falling back to `~/.local/share/fish`, and the file name is
+/// `<session>_history`. The session is the given one, falling back to `$fish_history` and then to
+/// [`DEFAULT_SESSION_NAME`].
+///
+/// Environment variables are looked up through `env` so the resolution can be exercised without
+/// touching the real environment, e.g. `fish_history_path(None, |key| std::env::var_os(key))`.
+pub fn fish_history_path(
+    session: Option<&str>,
+    env: impl Fn(&str) -> Option<OsString>,
+) -> Result<PathBuf> {
+    // fish ignores a relative XDG_DATA_HOME, as the XDG base directory spec requires.
+    let data_home = match env("XDG_DATA_HOME").map(PathBuf::from) {
+        Some(dir) if dir.is_absolute() => dir,
+        _ => match env("HOME").filter(|home| !home.is_empty()) {
+            Some(home) => PathBuf::from(home).join(".local").join("share"),
+            None => bail!(
+                "cannot locate the fish data directory: neither XDG_DATA_HOME nor HOME is set"
+            ),
+        },
+    };
+
+    let session = match session {
+        Some(session) => session.to_string(),
+        None => match env("fish_history") {
+            Some(session) if session.is_empty() => {
+                bail!("fish history is disabled for this session ($fish_history is empty)")
+            }
+            Some(session) => session.into_string().map_err(|session| {
+                anyhow!("invalid session name: {}", session.to_string_lossy())
+            })?,
+            None => DEFAULT_SESSION_NAME.to_string(),
+        },
+    };
+    validate_session_name(&session)?;
+
+    Ok(data_home.join("fish").join(format!("{session}_history")))
+}
+
+/// Check that the given history session name is one fish accepts, i.e. a valid variable name made
+/// of letters, digits, and underscores. Anything else, e.g. a path separator, makes fish
+/// ignore the name.
+pub fn validate_session_name(session: &str) -> Result<()> {
+    if session.is_empty() {
+        bail!("invalid session name: the name is empty");
+    }
+    if let Some(c) = session.chars().find(|c| !c.is_alphanumeric() && *c != '_') {
+        bail!("invalid session name: {session:?} contains {c:?}; only letters, digits, and underscores are allowed");
+    }
+
+    Ok(())
+}
+
+/// Create the directory which holds the given fish history file if it does not exist yet. Like
+/// fish, the directory is only accessible by the current user.
+pub async fn create_history_dir(path: &Path) -> Result<()> {
+    let Some(dir) = path.parent() else {
+        return Ok(());
+    };
+
+    let mut builder = tokio::fs::DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    builder.mode(0o700);
+    builder
+        .create(dir)
+        .await
+        .with_context(|| format!("failed to create {}", dir.display()))
+}
+
+/// The arguments with which fish is invoked to merge its history file into the running sessions.
+pub const HISTORY_MERGE_ARGS: [&str; 2] = ["-c", "builtin history merge"];
+
+/// Let fish merge its history file by running `fish -c 'builtin history merge'` with the given
+/// fish binary. The output of fish is forwarded as is.
+pub async fn run_history_merge(fish_bin: &Path) -> Result<ExitStatus> {
+    match Command::new(fish_bin).args(HISTORY_MERGE_ARGS).status().await {
+        Ok(status) => Ok(status),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            bail!("fish is not installed or not in PATH: {}", fish_bin.display())
+        }
+        Err(e) => Err(e).with_context(|| format!("failed to run {}", fish_bin.display())),
+    }
+}
+
+/// Read the entries of an existing fish history file. A missing file has no entries.
+pub async fn read_fish_history(path: &Path) -> Result<Vec<Entry>> {
+    match File::open(path).await {
+        Ok(file) => parse_fish_history(BufReader::new(file))
+            .await
+            .with_context(|| format!("failed to read {}", path.display())),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e).with_context(|| format!("failed to read {}", path.display())),
+    }
+}
+
+/// Parse fish history records from the given reader. A record is a `- cmd: <command>` line
+/// followed by optional `  when: <timestamp>` and `  paths:` lines, the latter followed by
+/// `    - <path>` lines.
+///
+/// Like fish, the parser is lenient: lines it does not understand are skipped, and a final line
+/// without a trailing newline is treated as a record fish is still writing and ignored.
+pub async fn parse_fish_history<R>(mut reader: R) -> Result<Vec<Entry>>
+where
+    R: AsyncBufRead + Unpin,
+{
+    let mut buf = Vec::new();
+    let mut entries = Vec::<Entry>::new();
+    let mut in_record = false;
+    let mut in_paths = false;
+
+    loop {
+        buf.clear();
+        if reader.read_until(b'\n', &mut buf).await? == 0 {
+            break; // EOF
+        }
+        let Some(line) = buf.strip_suffix(b"\n") else {
+            break; // truncated last line
+        };
+        let line = String::from_utf8_lossy(line);
+
+        if let Some(cmd) = line.strip_prefix("- cmd: ") {
+            entries.push(Entry { cmd: unescape(cmd), ..Default::default() });
+            (in_record, in_paths) = (true, false);
+            continue;
+        }
+
+        let entry = match entries.last_mut() {
+            Some(entry) if in_record => entry,
+            _ => continue,
+        };
+
+        if let Some(when) = line.strip_prefix("  when: ") {
+            entry.when = when.trim().parse().unwrap_or(0);
+            in_paths = false;
+        } else if line
+            .strip_prefix("  paths:")
+            .is_some_and(|rest| rest.trim().is_empty())
+        {
+            in_paths = true;
+        } else if let Some(path) = line.strip_prefix("    - ").filter(|_| in_paths) {
+            entry.paths.push(unescape(path));
+        } else if !line.starts_with(' ') {
+            // anything else at the top level ends the record
+            (in_record, in_paths) = (false, false);
+        }
+    }
+
+    Ok(entries)
+}
+
+/// An entry which did not survive a round trip through the fish history format.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mismatch {
+    /// The index of the entry.
+    pub index: usize,
+    /// The entry as converted.
+    pub expected: Option<Entry>,
+    /// The entry as read back from the fish history format.
+    pub actual: Option<Entry>,
+}
+
+/// Write the entries in the fish history format, parse them back, and return every entry which
+/// does not come back identical. An empty result proves that the conversion is lossless.
+pub async fn verify_round_trip(entries: &[Entry]) -> Result<Vec<Mismatch>> {
+    let mut written = Vec::new();
+    Format::Fish.write(&mut written, entries).await?;
+    let read = parse_fish_history(written.as_slice()).await?;
+
+    // Only compare what the fish history format can carry.
+    let storable = |entry: &Entry| Entry { duration: None, ..entry.clone() };
+
+    let len = entries.len().max(read.len());
+    let mismatches = (0..len).filter_map(|index| {
+        let (expected, actual) = (entries.get(index).map(storable), read.get(index).cloned());
+        (expected != actual).then_some(Mismatch { index, expected, actual })
+    });
+
+    Ok(mismatches.collect())
+}
+
+/// Escape the given command or path for the fish history file: a backslash is written as `\\`
+/// and a newline as `\n`, which keeps every record field on a single line. These are the only
+/// rules fish itself applies when writing its history (`escape_yaml_fish_2_0`), so anything which
+/// merely looks like a field, such as a joined `when: 12`, stays part of the command.
+pub fn escape(s: &str) -> String {
+    s.replace('\\', r"\\").replace('\n', r"\n")
+}
+
+/// The inverse of [`escape`]. Like fish, a backslash followed by anything but another backslash or
+/// an `n` is kept as is.
+pub fn unescape(s: &str) -> String {
+    let mut unescaped = String::with_capacity(s.len());
+
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('\\') => unescaped.push('\\'),
+            Some('n') => unescaped.push('\n'),
+            Some(other) => {
+                unescaped.push('\\');
+                unescaped.push(other);
+            }
+            None => unescaped.push('\\'),
+        }
+    }
+
+    unescaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Look up the variables in `vars` only.
+    fn env<'a>(vars: &'a [(&str, &str)]) -> impl Fn(&str) -> Option<OsString> + 'a {
+        |key| {
+            let (_, value) = vars.iter().find(|(var, _)| *var == key)?;
+            Some(OsString::from(value))
+        }
+    }
+
+    #[test]
+    fn history_is_in_the_data_home() {
+        let path = fish_history_path(None, env(&[("XDG_DATA_HOME", "/data"), ("HOME", "/home")]));
+        assert_eq!(path.unwrap(), Path::new("/data/fish/fish_history"));
+        let path = fish_history_path(None, env(&[("HOME", "/home")]));
+        assert_eq!(path.unwrap(), Path::new("/home/.local/share/fish/fish_history"));
+    }
+
+    #[test]
+    fn relative_data_home_is_ignored() {
+        let path = fish_history_path(None, env(&[("XDG_DATA_HOME", "data"), ("HOME", "/home")]));
+        assert_eq!(path.unwrap(), Path::new("/home/.local/share/fish/fish_history"));
+        let error = fish_history_path(None, env(&[("XDG_DATA_HOME", "data")])).unwrap_err();
+        assert!(error.to_string().starts_with("cannot locate"), "{error}");
+        assert!(fish_history_path(None, env(&[("HOME", "")])).is_err());
+    }
+
+    #[test]
+    fn session_names_the_file() {
+        let home = ("HOME", "/home");
+        let path = fish_history_path(Some("work"), env(&[home, ("fish_history", "other")]));
+        assert_eq!(path.unwrap(), Path::new("/home/.local/share/fish/work_history"));
+        let path = fish_history_path(None, env(&[home, ("fish_history", "other")]));
+        assert_eq!(path.unwrap(), Path::new("/home/.local/share/fish/other_history"));
+        let path = fish_history_path(None, env(&[home]));
+        assert_eq!(path.unwrap(), Path::new("/home/.local/share/fish/fish_history"));
+    }
+
+    #[test]
+    fn empty_fish_history_disables_it() {
+        let error = fish_history_path(None, env(&[("HOME", "/home"), ("fish_history", "")]));
+        assert!(error.unwrap_err().to_string().contains("disabled"));
+        // unless a session is given
+        let path = fish_history_path(Some("work"), env(&[("HOME", "/home"), ("fish_history", "")]));
+        assert!(path.is_ok());
+    }
+
+    #[test]
+    fn weird_session_names_are_refused() {
+        for session in ["", "../evil", "a/b", "a b", "work-2", "a.b", "x\0"] {
+            let error = validate_session_name(session).unwrap_err().to_string();
+            assert!(error.starts_with("invalid session name"), "{session:?}: {error}");
+        }
+        for session in ["work", "fish", "work_2", "Ünïcode", "_"] {
+            assert!(validate_session_name(session).is_ok(), "{session:?}");
+        }
+        let error = validate_session_name("a/b").unwrap_err().to_string();
+        assert!(error.contains("\"a/b\" contains '/'"), "{error}");
+        let path = fish_history_path(None, env(&[("HOME", "/home"), ("fish_history", "a/b")]));
+        assert!(path.is_err());
+    }
+
+    fn entry(cmd: &str, when: i64) -> Entry {
+        Entry { cmd: cmd.to_string(), when, ..Default::default() }
+    }
+
+    #[tokio::test]
+    async fn tricky_commands_round_trip() {
+        let entries = [
+            entry(r#"echo "it's" 'a "quote"'"#, 1),
+            entry(r"grep foo \*.rs", 2),
+            entry(r"printf '\n' \\n", 3),
+            entry("echo 日本 😀", 4),
+            entry("cat <<EOF\n  when: 12\n- cmd: rm -rf ~\nEOF", 5),
+            entry("trailing \\", 0),
+            Entry {
+                paths: vec![r"C:\new".to_string(), "a\nb".to_string()],
+                ..entry("ls", 6)
+            },
+        ];
+        assert_eq!(verify_round_trip(&entries).await.unwrap(), []);
+    }
+
+    // Records as fish 3 writes them, for the commands in `GOLDEN_COMMANDS`.
+    const GOLDEN: &str = concat!(
+        "- cmd: grep foo \\\\*.rs\n  when: 1700000000\n",
+        "- cmd: printf '\\\\n'\n  when: 1700000001\n",
+        "- cmd: for i in 1 2\\n  echo $i\\nend\n  when: 1700000002\n",
+        "- cmd: echo a\\n  when: 12\n  when: 1700000003\n",
+        "- cmd: ls ~/src\n  when: 1700000004\n  paths:\n    - ~/src\n",
+    );
+
+    const GOLDEN_COMMANDS: &[&str] = &[
+        r"grep foo \*.rs",
+        r"printf '\n'",
+        "for i in 1 2\n  echo $i\nend",
+        "echo a\n  when: 12",
+        "ls ~/src",
+    ];
+
+    fn golden_entries() -> Vec<Entry> {
+        let entries = GOLDEN_COMMANDS.iter().zip(1_700_000_000..);
+        let mut entries = entries.map(|(cmd, when)| entry(cmd, when)).collect::<Vec<_>>();
+        entries[4].paths = vec!["~/src".to_string()];
+        entries
+    }
+
+    #[test]
+    fn escapes_backslashes_and_newlines_only() {
+        assert_eq!(escape(r"grep foo \*.rs"), r"grep foo \\*.rs");
+        assert_eq!(escape("a\nb\\n"), r"a\nb\\n");
+        assert_eq!(escape("tab\tquote' \"x\" when: 1"), "tab\tquote' \"x\" when: 1");
+        assert_eq!(unescape(r"grep foo \\*.rs"), r"grep foo \*.rs");
+        assert_eq!(unescape(r"a\nb\\n"), "a\nb\\n");
+    }
+
+    #[test]
+    fn unescape_keeps_other_backslashes() {
+        // written by older versions, or by hand
+        assert_eq!(unescape(r"grep \* \t"), r"grep \* \t");
+        assert_eq!(unescape("trailing \\"), "trailing \\");
+    }
+
+    #[tokio::test]
+    async fn writes_what_fish_writes() {
+        let mut written = Vec::new();
+        Format::Fish.write(&mut written, &golden_entries()).await.unwrap();
+        assert_eq!(String::from_utf8(written).unwrap(), GOLDEN);
+    }
+
+    #[tokio::test]
+    async fn reads_what_fish_writes() {
+        let entries = parse_fish_history(GOLDEN.as_bytes()).await.unwrap();
+        assert_eq!(entries, golden_entries());
+    }
+
+    #[tokio::test]
+    async fn parsing_is_lenient() {
+        let history = concat!(
+            "# not a record\n",
+            "- cmd: ls\n  when: soon\n  unknown: field\n",
+            "garbage\n",
+            "    - not/a/path\n",
+            "- cmd: pwd\n  when: 1700000000\n",
+            "- cmd: still being writ",
+        );
+        let entries = parse_fish_history(history.as_bytes()).await.unwrap();
+        assert_eq!(entries, [entry("ls", 0), entry("pwd", 1_700_000_000)]);
+    }
+
+    #[tokio::test]
+    async fn duration_is_not_compared() {
+        // the fish history has no place for it
+        let entries = [Entry { duration: Some(3), ..entry("sleep 3", 1) }];
+        assert_eq!(verify_round_trip(&entries).await.unwrap(), []);
+    }
+
+    #[tokio::test]
+    async fn history_dir_is_created_private() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("share/fish/fish_history");
+        create_history_dir(&path).await.unwrap();
+        let fish = dir.path().join("share/fish");
+        assert!(fish.is_dir());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&fish).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o700);
+        }
+        // an existing directory is fine
+        create_history_dir(&path).await.unwrap();
+    }
+}
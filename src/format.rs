@@ -0,0 +1,293 @@
+use std::fmt::Display;
+
+use clap::ValueEnum;
+use tokio::io::AsyncWrite;
+
+use crate::{
+    abbr::AbbrOptions,
+    history_converter::Entry,
+    output::{
+        write_entries, Abbr, Bash, Builtin, Delimited, Fish, FishScript, Json, Jsonl, Nushell,
+        Plain, Plain0, Pretty, Zoxide, Zsh,
+    },
+};
+
+/// The format in which converted entries are written.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    /// The fish history file format.
+    #[default]
+    Fish,
+    /// A fish script which adds the entries with `builtin history append` when sourced.
+    FishScript,
+    /// A JSON array of objects with the `cmd`, `when`, `duration`, and `paths` of each entry, and
+    /// with `--annotate` the `line` and `raw` record it was read from.
+    Json,
+    /// The objects of `json`, one per line.
+    Jsonl,
+    /// Comma-separated values as RFC 4180 describes them, with the `--columns`.
+    Csv,
+    /// Tab-separated values, quoted like `csv`.
+    Tsv,
+    /// nushell's plain text `history.txt`, or its SQLite `history.sqlite3` if the `--output`
+    /// ends in `.sqlite3` or `.db`, or already is an SQLite database.
+    Nushell,
+    /// The commands alone, one per line, with the multi-line ones written as `--multiline` says.
+    Plain,
+    /// The commands alone, each ended by a NUL byte rather than a newline, for `fzf --read0` and
+    /// `xargs -0`. Commands with a NUL byte of their own are skipped.
+    Plain0,
+    /// A line per entry to read in a terminal, the time dimmed before the command, with the
+    /// lines of a multi-line command under it, cut off at the width of the terminal unless
+    /// `--full` is given. Colored as `--color` says.
+    Pretty,
+    /// A zsh history file with `EXTENDED_HISTORY`, to go back from fish with `--from fish`.
+    Zsh,
+    /// A bash history file, with a `#<when>` comment before each command as bash writes with
+    /// `HISTTIMEFORMAT` set, unless `--no-timestamps` is given.
+    Bash,
+    /// `abbr -a` lines to source in fish, for the commands typed most often, see `--top`.
+    Abbr,
+    /// The directories changed into with `cd`, `pushd`, or `AUTO_CD`, with their visit count and
+    /// last visit, as the `path|rank|time` lines of z's data file `zoxide import --from z` reads.
+    Zoxide,
+    /// An SQLite database with the entries in a table of their own, to query with any SQLite
+    /// client, written to the `--output`.
+    Sqlite,
+    /// Rows of the `commands` table of McFly, added to the database the `--output` names.
+    Mcfly,
+    /// Rows of the `history` table of atuin, written into the database `--atuin-db` names.
+    Atuin,
+}
+
+/// A field of an entry, as a column of the `csv` and `tsv` formats.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Column {
+    When,
+    Cmd,
+    Duration,
+    Paths,
+    /// The line the entry was read from.
+    Line,
+    /// The record of a zsh history the entry was parsed from.
+    Raw,
+}
+
+/// How to write the `csv` and `tsv` formats.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Table {
+    /// The columns to write, in order.
+    pub columns: Vec<Column>,
+    /// Whether to start with a row of the column names.
+    pub header: bool,
+}
+
+impl Default for Table {
+    fn default() -> Self {
+        Self {
+            columns: vec![Column::When, Column::Cmd],
+            header: true,
+        }
+    }
+}
+
+/// How the `plain` format writes a command spanning several lines.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Multiline {
+    /// Write its newlines as `\n`, and its backslashes as `\\`, like the fish history does.
+    #[default]
+    Escape,
+    /// Join its lines with `; `.
+    Join,
+    /// Leave it out.
+    Skip,
+}
+
+/// How to write the `plain` format.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PlainOptions {
+    /// How to write the commands spanning several lines.
+    pub multiline: Multiline,
+    /// Whether to start each line with the `when` of the entry, in ISO 8601 in the local time
+    /// zone, and a tab.
+    pub with_time: bool,
+}
+
+/// When to color the `pretty` format.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ColorWhen {
+    /// When writing to a terminal, unless `NO_COLOR` is set.
+    #[default]
+    Auto,
+    /// Even when writing to a file or a pipe.
+    Always,
+    /// Not even when writing to a terminal.
+    Never,
+}
+
+/// How to write the `pretty` format.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PrettyOptions {
+    /// Whether to color the time and the markers of the lines of multi-line commands.
+    pub color: bool,
+    /// The width in columns to cut the lines off at, with an ellipsis, or `None` to write them
+    /// in full.
+    pub width: Option<usize>,
+    /// Whether to show the line and the record each entry was read from, dimmed, under it.
+    pub annotate: bool,
+}
+
+/// How to write the `json` and `jsonl` formats.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct JsonOptions {
+    /// Whether to add the `line` and the `raw` record each entry was read from to its object.
+    pub annotate: bool,
+}
+
+/// How to write the `bash` format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BashOptions {
+    /// Whether to write the `#<when>` comments. Without them there is no telling where a
+    /// multi-line command ends, so bash would read each of its lines as a command of its own,
+    /// and writing one fails instead.
+    pub timestamps: bool,
+}
+
+impl Default for BashOptions {
+    fn default() -> Self {
+        Self { timestamps: true }
+    }
+}
+
+/// How to write the formats which take options.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Options {
+    /// The options of `csv` and `tsv`.
+    pub table: Table,
+    /// The options of `abbr`.
+    pub abbr: AbbrOptions,
+    /// The options of `plain`.
+    pub plain: PlainOptions,
+    /// The options of `pretty`.
+    pub pretty: PrettyOptions,
+    /// The options of `bash`.
+    pub bash: BashOptions,
+    /// The options of `json` and `jsonl`.
+    pub json: JsonOptions,
+}
+
+impl Column {
+    // The field of `entry` in this column. The paths are separated by newlines, like the lines of
+    // a multi-line command.
+    pub(crate) fn field(&self, entry: &Entry) -> String {
+        match self {
+            Self::When => entry.when.to_string(),
+            Self::Cmd => entry.cmd.clone(),
+            Self::Duration => entry.duration.map(|d| d.to_string()).unwrap_or_default(),
+            Self::Paths => entry.paths.join("\n"),
+            Self::Line => entry.origin.as_ref().map(|o| o.line.to_string()).unwrap_or_default(),
+            Self::Raw => entry.origin.as_ref().and_then(|o| o.raw.clone()).unwrap_or_default(),
+        }
+    }
+}
+
+/// The format of the history to convert.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum InputFormat {
+    /// Tell the format from the first few KiB of the history.
+    Auto,
+    /// A zsh history file, with or without EXTENDED_HISTORY.
+    #[default]
+    Zsh,
+    /// A bash history file, with or without `HISTTIMEFORMAT` timestamps.
+    Bash,
+    /// A fish history file, e.g. to clean one up with `--dedup-by`.
+    Fish,
+    /// The listing `fc -l` or `history` prints, preferably `fc -li` for the timestamps.
+    Fc,
+    /// PowerShell's PSReadLine history, `ConsoleHost_history.txt`.
+    Psreadline,
+    /// A xonsh JSON history session file, or the directory holding them, which are merged.
+    Xonsh,
+    /// The SQLite database of zsh-histdb, `~/.histdb/zsh-history.db`.
+    Histdb,
+    /// The SQLite database of atuin, `~/.local/share/atuin/history.db`.
+    Atuin,
+    /// nushell's history, either the plain text `history.txt` or the SQLite `history.sqlite3`.
+    Nushell,
+}
+
+impl Display for Format {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.to_possible_value().unwrap().get_name())
+    }
+}
+
+impl Display for InputFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.to_possible_value().unwrap().get_name())
+    }
+}
+
+impl Format {
+    /// Whether this format has room for the annotations of `--annotate`.
+    pub fn annotates(&self) -> bool {
+        matches!(self, Self::Json | Self::Jsonl | Self::Csv | Self::Tsv | Self::Pretty)
+    }
+
+    /// Write the entries to the given writer in this format.
+    pub async fn write<W>(&self, writer: &mut W, entries: &[Entry]) -> std::io::Result<()>
+    where
+        W: AsyncWrite + Unpin + Send,
+    {
+        self.write_with(writer, entries, &Options::default()).await
+    }
+
+    /// Write the entries to the given writer in this format, with the `options` of the formats
+    /// which take any.
+    pub async fn write_with<W>(
+        &self,
+        writer: &mut W,
+        entries: &[Entry],
+        options: &Options,
+    ) -> std::io::Result<()>
+    where
+        W: AsyncWrite + Unpin + Send,
+    {
+        let Some(mut output) = self.output(options) else {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                format!("the {self} format is written into a database, not a stream"),
+            ));
+        };
+        write_entries(&mut output, writer, entries).await
+    }
+
+    /// The implementation of this format, with the `options` of the formats which take any, or
+    /// `None` for the formats written into a database.
+    pub fn output(&self, options: &Options) -> Option<Builtin> {
+        Some(match self {
+            Self::Atuin | Self::Sqlite | Self::Mcfly => return None,
+            Self::Fish => Builtin::Fish(Fish),
+            Self::FishScript => Builtin::FishScript(FishScript::default()),
+            Self::Json => Builtin::Json(Json::new(options.json)),
+            Self::Jsonl => Builtin::Jsonl(Jsonl::new(options.json)),
+            Self::Csv => Builtin::Delimited(Delimited::csv(options.table.clone())),
+            Self::Tsv => Builtin::Delimited(Delimited::tsv(options.table.clone())),
+            Self::Plain => Builtin::Plain(Plain::new(options.plain)),
+            Self::Plain0 => Builtin::Plain0(Plain0::default()),
+            Self::Pretty => Builtin::Pretty(Pretty::new(options.pretty)),
+            Self::Nushell => Builtin::Nushell(Nushell),
+            Self::Zsh => Builtin::Zsh(Zsh),
+            Self::Bash => Builtin::Bash(Bash::new(options.bash)),
+            Self::Abbr => Builtin::Abbr(Abbr::new(options.abbr)),
+            Self::Zoxide => Builtin::Zoxide(Zoxide::default()),
+        })
+    }
+}
+
+/// Quote the given string for fish. Within single quotes fish only treats `\'` and `\\` as escape
+/// sequences, so everything else, including newlines, is kept verbatim.
+pub fn quote(s: &str) -> String {
+    format!("'{}'", s.replace('\\', r"\\").replace('\'', r"\'"))
+}
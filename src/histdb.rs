@@ -0,0 +1,149 @@
+//! Reading the SQLite database of zsh-histdb.
+
+use std::path::Path;
+
+use anyhow::Result;
+
+use crate::bash::ParsedEntry;
+
+/// Read the history in a zsh-histdb database, `~/.histdb/zsh-history.db`, returning every entry
+/// with its row id in place of a line.
+///
+/// Each row of the `history` table, joined with the command in `commands`, becomes an entry with
+/// its `start_time` as `when`. Only rows started within `since` and `until`, both inclusive, are
+/// read, which the query itself filters for. The database is opened read-only.
+pub fn read_histdb(
+    path: &Path,
+    since: Option<i64>,
+    until: Option<i64>,
+) -> Result<Vec<ParsedEntry>> {
+    query(path, since, until)
+}
+
+#[cfg(feature = "sqlite")]
+fn query(path: &Path, since: Option<i64>, until: Option<i64>) -> Result<Vec<ParsedEntry>> {
+    use rusqlite::params;
+
+    use crate::{history_converter::Entry, sqlite};
+
+    let db = sqlite::open(path)?;
+    let mut statement = db.prepare(
+        "SELECT history.id, commands.argv, history.start_time, history.duration
+         FROM history JOIN commands ON history.command_id = commands.id
+         WHERE (?1 IS NULL OR history.start_time >= ?1)
+           AND (?2 IS NULL OR history.start_time <= ?2)
+         ORDER BY history.start_time, history.id",
+    )?;
+    let rows = statement.query_map(params![since, until], |row| {
+        let entry = Entry {
+            cmd: row.get::<_, Option<String>>(1)?.unwrap_or_default(),
+            when: row.get::<_, Option<i64>>(2)?.unwrap_or_default(),
+            duration: row.get(3)?,
+            ..Default::default()
+        };
+        Ok((row.get::<_, i64>(0)? as usize, Ok(entry)))
+    })?;
+
+    let mut entries = Vec::new();
+    for row in rows {
+        let (id, entry) = row?;
+        if entry.as_ref().is_ok_and(|entry| !entry.cmd.trim().is_empty()) {
+            entries.push((id, entry));
+        }
+    }
+    Ok(entries)
+}
+
+#[cfg(not(feature = "sqlite"))]
+fn query(_: &Path, _: Option<i64>, _: Option<i64>) -> Result<Vec<ParsedEntry>> {
+    Err(crate::sqlite::unsupported())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A database as zsh-histdb creates it, with a few commands, one of them run twice and one
+    // blank.
+    #[cfg(feature = "sqlite")]
+    fn fixture(dir: &Path) -> std::path::PathBuf {
+        let path = dir.join("zsh-history.db");
+        let db = rusqlite::Connection::open(&path).unwrap();
+        db.execute_batch(
+            "CREATE TABLE commands (id INTEGER PRIMARY KEY AUTOINCREMENT, argv TEXT,
+                 UNIQUE(argv) ON CONFLICT IGNORE);
+             CREATE TABLE places (id INTEGER PRIMARY KEY AUTOINCREMENT, host TEXT, dir TEXT,
+                 UNIQUE(host, dir) ON CONFLICT IGNORE);
+             CREATE TABLE history (id INTEGER PRIMARY KEY AUTOINCREMENT, session INT,
+                 command_id INT REFERENCES commands (id), place_id INT REFERENCES places (id),
+                 exit_status INT, start_time INT, duration INT);
+             INSERT INTO commands (argv) VALUES ('ls'), ('make build'), ('  '), ('echo a\nb');
+             INSERT INTO places (host, dir) VALUES ('host', '/home/me');
+             INSERT INTO history (session, command_id, place_id, exit_status, start_time, duration)
+             VALUES (1, 1, 1, 0, 300, 0), (1, 2, 1, 2, 100, 42), (1, 3, 1, 0, 150, 0),
+                    (1, 4, 1, 0, 200, NULL), (2, 1, 1, 0, 400, 1);",
+        )
+        .unwrap();
+        path
+    }
+
+    // The row ids, timestamps, commands, and durations read.
+    #[cfg(feature = "sqlite")]
+    fn rows(entries: Vec<ParsedEntry>) -> Vec<(usize, i64, String, Option<i64>)> {
+        let rows = entries.into_iter().map(|(id, entry)| (id, entry.unwrap()));
+        rows.map(|(id, entry)| (id, entry.when, entry.cmd, entry.duration))
+            .collect()
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn a_row_is_an_entry() {
+        let dir = tempfile::tempdir().unwrap();
+        let entries = read_histdb(&fixture(dir.path()), None, None).unwrap();
+        assert_eq!(
+            rows(entries),
+            [
+                (2, 100, "make build".into(), Some(42)),
+                (4, 200, "echo a\nb".into(), None),
+                (1, 300, "ls".into(), Some(0)),
+                (5, 400, "ls".into(), Some(1)),
+            ]
+        );
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn since_and_until_are_inclusive() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = fixture(dir.path());
+        let entries = read_histdb(&path, Some(200), Some(300)).unwrap();
+        let whens = rows(entries)
+            .into_iter()
+            .map(|(_, when, ..)| when)
+            .collect::<Vec<_>>();
+        assert_eq!(whens, [200, 300]);
+        let entries = read_histdb(&path, Some(401), None).unwrap();
+        assert!(entries.is_empty());
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn the_database_is_left_alone() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = fixture(dir.path());
+        let before = std::fs::read(&path).unwrap();
+        read_histdb(&path, None, None).unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), before);
+        // and is not created if it is not there
+        let missing = dir.path().join("missing.db");
+        assert!(read_histdb(&missing, None, None).is_err());
+        assert!(!missing.exists());
+    }
+
+    #[cfg(not(feature = "sqlite"))]
+    #[test]
+    fn needs_the_sqlite_feature() {
+        let error = read_histdb(Path::new("zsh-history.db"), None, None).unwrap_err();
+        assert!(error.to_string().contains("`sqlite` feature"), "{error}");
+    }
+}
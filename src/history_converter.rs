@@ -1,25 +1,186 @@
-use std::{fmt::Display, ops::Deref, path::PathBuf, sync::LazyLock};
+use std::{
+    fmt::Display,
+    io::Cursor,
+    ops::Deref,
+    path::{Path, PathBuf},
+    pin::pin,
+    sync::{Arc, LazyLock, Mutex},
+    time::{SystemTime, UNIX_EPOCH},
+};
 
-use anyhow::{bail, Result};
-use clap::Parser;
-use regex::Regex;
+use anstream::{AutoStream, ColorChoice};
+use anyhow::{anyhow, bail, Context, Result};
+use async_stream::try_stream;
+use clap::{ArgAction, ArgGroup, Args, Parser, Subcommand};
+use clap_complete::Shell;
+use encoding_rs::{DecoderResult, Encoding};
+use futures_util::{Stream, StreamExt, TryStreamExt};
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use terminal_size::{terminal_size_of, Width};
 use tokio::{
     fs::File,
-    io::{AsyncBufReadExt, BufReader},
+    io::{self, AsyncBufRead, AsyncBufReadExt, AsyncRead, AsyncReadExt, BufReader},
 };
 
-/// A zsh history entry
-#[derive(Debug)]
+use crate::{
+    abbr::AbbrOptions,
+    aliases::{parse_aliases, Aliases, ExpandAliases},
+    atuin::read_atuin,
+    bash::{parse_bash_history, ParsedEntry},
+    compression::{decompress, Compression},
+    detect::{detect_format, Confidence, Detection},
+    encoding::InputEncoding,
+    error::{
+        Failure, FailureContext, ParseError, ParseErrorKind, SkipReason, SkippedLine, EXIT_CODES,
+    },
+    expand::{expand_path, user_home},
+    fc::{parse_fc_listing, UtcOffset},
+    filter::{is_control, parse_time, Sort, COMMON_COMMANDS},
+    fish_history::{create_history_dir, escape, fish_history_path, parse_fish_history},
+    format::{
+        BashOptions, ColorWhen, Column, Format, InputFormat, JsonOptions, Multiline, Options,
+        PlainOptions, PrettyOptions, Table,
+    },
+    histdb::read_histdb,
+    merge::{merge_histories, DedupBy, DupPolicy, Keep, Normalization},
+    normalize::{Normalize, NormalizeMode},
+    nushell::{parse_nushell_history, read_nushell},
+    progress::{CountingReader, Progress},
+    psreadline::parse_psreadline_history,
+    redact::{Redact, Redaction},
+    rotated::find_rotated_histories,
+    sqlite::{database_format, is_database},
+    strip::{StripOptions, StripPrefixes},
+    timestamps::{bump_duplicates, default_when, interpolate, DefaultWhen},
+    transform::{ListFilter, Transform},
+    verbosity::{info, set_verbosity, verbosity, Verbosity},
+    writer::{BackupPolicy, WriteMode, DEFAULT_LOCK_TIMEOUT},
+    xonsh::{find_xonsh_sessions, parse_xonsh_history},
+    zsh,
+};
+
+/// Editors on Windows tend to put this at the start of the file.
+const UTF8_BOM: &[u8] = b"\xef\xbb\xbf";
+
+/// The extended history header, `: <when>:<duration>;`, followed by the command. `\d` would match
+/// any Unicode digit.
+static HEADER: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?s)^: ([0-9]+):([0-9]+);(.*)$").unwrap());
+
+/// The length of the longest record converted by default. Longer ones are most likely garbage
+/// and would only eat memory.
+pub const DEFAULT_MAX_LINE_BYTES: usize = 1024 * 1024;
+
+/// How far in the future a timestamp may be before it is considered invalid, to allow for clocks
+/// of machines sharing a history being slightly off.
+const FUTURE_SKEW_SECS: i64 = 60 * 60;
+
+// What `convert` counts while reading, to report afterwards.
+#[derive(Default)]
+struct Tally {
+    // the lines read, or the rows of a database, and the bytes, unless it is a file
+    lines: usize,
+    bytes: u64,
+    // the entries read before any transform, and the lines with invalid bytes replaced
+    parsed: usize,
+    lossy: usize,
+    space_prefixed: usize,
+    // the entries given a timestamp as they were yielded, without one in the history
+    untimestamped: usize,
+    // the lines of the records with an invalid timestamp
+    out_of_range: Vec<usize>,
+    errors: Vec<ParseError>,
+    // the lines skipped or looking wrong, with `--verbose`
+    skips: Vec<SkippedLine>,
+}
+
+/// A zsh history entry. It serializes to an object with all of its fields, as the `json` and
+/// `jsonl` formats write it.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct Entry {
     /// The command executed.
     pub cmd: String,
     /// The time the command was executed. Set to 0 if the time information is not available.
     pub when: i64,
+    /// How many seconds the command took, if known. The fish history format has no place for it.
+    pub duration: Option<i64>,
+    /// The paths fish remembers for the command, used by its autosuggestions. zsh does not record
+    /// these, so they are only set for entries read from a fish history.
+    pub paths: Vec<String>,
+    /// Where the entry was read from, if it was read by a [`Converter`]. Only `--format sqlite`
+    /// writes it, and entries are compared without it.
+    #[serde(skip)]
+    pub origin: Option<Origin>,
+}
+
+/// What a [`Converter`] read, summed up over the histories, see [`Converter::report`].
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConversionReport {
+    /// The histories read, in order, with how many entries each had.
+    pub inputs: Vec<InputReport>,
+    /// The size of the histories on disk, or of what was read from stdin, in bytes.
+    pub bytes: u64,
+    /// The lines read, or the rows of a database.
+    pub lines: usize,
+    /// The entries read, before the transforms dropped any.
+    pub entries: usize,
+    /// The records which could not be converted and were skipped.
+    pub skipped: usize,
+    /// The entries skipped for starting with a space, with `--ignore-space`.
+    pub space_prefixed: usize,
+    /// The lines with bytes invalid in the encoding of the history, which were replaced.
+    pub lossy: usize,
+    /// Every line skipped or looking wrong, in the order of the histories and their lines. Only
+    /// gathered with `--verbose`, `--strict`, or `--report`.
+    #[serde(skip)]
+    pub skipped_lines: Vec<SkippedLine>,
+}
+
+/// A history a [`Converter`] read, as its [`ConversionReport`] lists it.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InputReport {
+    /// The path of the history, `-` for stdin or a history in memory.
+    pub path: PathBuf,
+    /// The entries read from it, before the transforms dropped any.
+    pub entries: usize,
+}
+
+/// Where in a history an entry was read from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Origin {
+    /// The path of the history, `-` for stdin or a history in memory.
+    pub path: Arc<Path>,
+    /// The line the entry starts on, or its row id in a database.
+    pub line: usize,
+    /// The record of a zsh history the entry was parsed from, with invalid bytes replaced and
+    /// each further line after a backslash, as zsh writes them. Only kept with `--annotate`,
+    /// `--annotate-file`, or the `raw` column.
+    pub raw: Option<String>,
 }
 
+/// Entries are equal if they are the same command run at the same time, wherever they were read
+/// from.
+impl PartialEq for Entry {
+    fn eq(&self, other: &Self) -> bool {
+        (&self.cmd, self.when, self.duration, &self.paths)
+            == (&other.cmd, other.when, other.duration, &other.paths)
+    }
+}
+
+impl Eq for Entry {}
+
+/// Formats the entry as a fish history record.
 impl Display for Entry {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "- cmd: {}\n  when: {}", self.cmd, self.when)
+        write!(f, "- cmd: {}\n  when: {}", escape(&self.cmd), self.when)?;
+        if !self.paths.is_empty() {
+            write!(f, "\n  paths:")?;
+            for path in &self.paths {
+                write!(f, "\n    - {}", escape(path))?;
+            }
+        }
+        Ok(())
     }
 }
 
@@ -50,108 +211,2919 @@ where
     }
 }
 
-/// The uninitialized state of the converter.
-#[derive(Debug, Parser)]
+// What `--help` and the manual page say the tool does, before the options.
+const LONG_ABOUT: &str = "\
+Convert a zsh history into a fish history, or into one of the other formats of --format, like JSON,
+CSV, a fish script, or the history of another shell.
+
+The zsh history is $HISTFILE, ~/.zsh_history, or the histories given, which are merged by
+timestamp. The entries are written to stdout, to --output, or with --into-fish and the merge
+command into the fish history itself, locking it while writing. Options not given on the command
+line are taken from the Z2F_ environment variables, then from the configuration file.";
+
+/// The command line: a command, or the arguments of `convert` without one, as before there were
+/// commands.
+#[derive(Debug, Clone, Parser)]
 #[clap(about, version)]
+#[command(long_about = LONG_ABOUT, after_long_help = EXIT_CODES)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
+pub struct Cli {
+    /// Run a command, `convert` if none is given.
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// The arguments of `convert`, when no command is given.
+    #[command(flatten)]
+    pub convert: Uninitialized,
+}
+
+impl Cli {
+    /// The command to run, `convert` with the arguments given without one if none is given.
+    pub fn command(self) -> Command {
+        self.command
+            .unwrap_or_else(|| Command::Convert(Box::new(self.convert)))
+    }
+}
+
+/// The uninitialized state of the converter, and the arguments of `convert`, whose groups the
+/// other commands reading a history share.
+#[derive(Debug, Default, Clone, Parser)]
+#[command(group(ArgGroup::new("destination").args(["output", "into_fish"])))]
 pub struct Uninitialized {
-    /// The path to the zsh history file.
-    #[arg()]
-    pub zsh_history: PathBuf,
+    /// Reading the histories.
+    #[command(flatten)]
+    pub input: InputArgs,
+
+    /// Choosing and rewriting the entries.
+    #[command(flatten)]
+    pub filters: FilterArgs,
+
+    /// The formats.
+    #[command(flatten)]
+    pub formats: FormatArgs,
+
+    /// Writing the entries.
+    #[command(flatten)]
+    pub write: WriteArgs,
+
+    /// Writing into a fish history.
+    #[command(flatten)]
+    pub fish: FishArgs,
+
+    /// Reporting on the run.
+    #[command(flatten)]
+    pub log: LogArgs,
+
+    /// Append the converted history to the fish history file of the current user.
+    #[arg(long, conflicts_with = "output")]
+    pub into_fish: bool,
+
+    /// Only append the entries which are not already in the destination fish history.
+    #[arg(long, requires = "destination", conflicts_with = "overwrite")]
+    pub merge: bool,
+
+    /// Print the distinct commands run, the first words `--command` compares, with how often each
+    /// was run, most often first, instead of converting.
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "destination", "atuin_db", "diff", "verify", "count", "fail_if_empty", "interactive"
+        ]
+    )]
+    pub list_commands: bool,
 }
 impl State for Uninitialized {}
 
-/// The initialized state of the converter.
-#[derive(Debug)]
-pub struct Initialized {
-    file: File,
+/// The options reading the histories, shared by the commands reading one.
+#[derive(Debug, Default, Clone, Args)]
+pub struct InputArgs {
+    /// The paths to the zsh history files, or directories like a backup of a home directory to
+    /// find one in, or `-` to read one from stdin. Several histories are
+    /// merged by timestamp, with duplicates collapsed by `--dedup-by` and `--dup-policy`, and
+    /// entries sharing a timestamp ordered as the files are given. Defaults to `$HISTFILE`,
+    /// `$ZDOTDIR/.zsh_history`, `~/.zsh_history`, or `~/.histfile`, whichever exists first.
+    pub zsh_history: Vec<PathBuf>,
+
+    /// Read the zsh history from stdin.
+    #[arg(long, conflicts_with = "zsh_history")]
+    pub stdin: bool,
+
+    /// Also convert the rotated copies of the zsh history file next to it, like `.zsh_history.1`,
+    /// `.zsh_history.old`, or `.zsh_history-20240131`, collapsing duplicates across them.
+    #[arg(long, conflicts_with = "stdin")]
+    pub include_rotated: bool,
+
+    /// Convert the largest history file found in a directory given as the zsh history, instead of
+    /// erroring if there are several.
+    #[arg(long)]
+    pub pick_largest: bool,
+
+    /// Take the paths given, like the zsh history, `--output`, `--backup-dir`, and `--report`,
+    /// literally, instead of expanding a leading `~` and `$VAR` or `${VAR}` in them.
+    #[arg(long)]
+    pub no_expand: bool,
+
+    /// The shell, or other program, which wrote the history. Everything said about the zsh history
+    /// file applies to the others too.
+    #[arg(long, value_enum, default_value_t)]
+    pub from: InputFormat,
+
+    /// The UTC offset the timestamps of `--from fc` are in, like `+09:00` or `UTC`. Defaults to
+    /// the local time zone.
+    #[arg(long, value_name = "OFFSET")]
+    pub tz: Option<UtcOffset>,
+
+    /// The encoding of the zsh history file, like `latin1`, `shift_jis`, or `euc-jp`, or `auto`
+    /// to guess it from the contents.
+    #[arg(long, value_name = "LABEL", default_value_t)]
+    pub encoding: InputEncoding,
+
+    /// Report every line which is not valid in the encoding of the zsh history file, with the
+    /// offending bytes, and fail after reading the whole file.
+    #[arg(long)]
+    pub strict_utf8: bool,
+
+    /// With `--strict-utf8`, replace the invalid bytes and carry on instead of failing.
+    #[arg(long, requires = "strict_utf8")]
+    pub lossy: bool,
+
+    /// Convert an incomplete last entry, which zsh may still be writing, instead of skipping it.
+    #[arg(long)]
+    pub include_partial: bool,
+
+    /// Clamp timestamps before 1970 or in the future to between 0 and now, instead of only
+    /// reporting them.
+    #[arg(long, conflicts_with = "strict")]
+    pub clamp_timestamps: bool,
+
+    /// Fail on every line which cannot be cleanly converted, like records which cannot be, invalid
+    /// bytes, timestamps before 1970 or in the future, a cut off last entry, or control
+    /// characters, listing them all once the histories are read, and write nothing.
+    #[arg(long)]
+    pub strict: bool,
+
+    /// The timestamp to give entries without one.
+    #[arg(long, value_enum, value_name = "STRATEGY", default_value_t)]
+    pub default_when: DefaultWhen,
+
+    /// Give entries without a timestamp one between those of the entries around them. Any left
+    /// without one, when no entry has a timestamp, get the `--default-when` one.
+    #[arg(long)]
+    pub interpolate_timestamps: bool,
+
+    /// Move entries sharing a second with the entry before them a second later each, so fish keeps
+    /// them in order, without crossing into the next later timestamp.
+    #[arg(long)]
+    pub bump_duplicate_timestamps: bool,
+
+    /// Skip records, including all lines of a multi-line command, longer than the given number of
+    /// bytes. Defaults to 1 MiB.
+    #[arg(long, value_name = "BYTES")]
+    pub max_line_bytes: Option<usize>,
+
+    /// Stop reading the history after N entries, 0 for no limit, counting those parsed, before
+    /// `--redact` and the like drop any. Unlike `--limit`, this keeps the oldest entries, so
+    /// combined with it the newest of those first N are kept.
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    pub max_entries: usize,
+
+    /// Skip commands starting with a space, like zsh's HIST_IGNORE_SPACE option.
+    #[arg(long)]
+    pub ignore_space: bool,
+
+    /// Keep timestamped entries with an empty command instead of skipping them.
+    #[arg(long)]
+    pub keep_empty: bool,
+
+    /// Skip entries with NUL bytes or other control characters instead of stripping NULs and
+    /// escaping the rest.
+    #[arg(long)]
+    pub skip_invalid: bool,
+
+    /// What makes two entries the same when merging into the destination or several histories
+    /// with `--dup-policy keep-both`. The other policies collapse every occurrence of a command.
+    #[arg(long, value_enum, default_value_t)]
+    pub dedup_by: DedupBy,
+
+    /// Which entry survives when merging duplicates. Unless both are kept, the destination is
+    /// rewritten sorted by timestamp if any of its entries changed.
+    #[arg(long, value_enum, default_value_t)]
+    pub dup_policy: DupPolicy,
 }
-impl State for Initialized {}
 
-impl Converter<Uninitialized> {
-    /// Create a new converter from the given path.
-    pub async fn new(path: &PathBuf) -> Result<Converter<Initialized>> {
-        Ok(Converter {
-            state: Initialized { file: File::open(path).await? },
-        })
-    }
+/// The options choosing the entries kept and rewriting their commands, shared by the commands
+/// reading a history.
+#[derive(Debug, Default, Clone, Args)]
+pub struct FilterArgs {
+    /// Expand the zsh aliases defined in FILE, as `alias` lines or what `alias` prints, in the
+    /// first word of the commands, for the shell they are converted for, which does not know them.
+    #[arg(long, value_name = "FILE")]
+    pub expand_aliases: Option<PathBuf>,
 
-    /// Parse the command line arguments, check if the zsh history file exists, and return a new
-    /// converter.
-    pub async fn from_args() -> Result<Converter<Initialized>> {
-        let Uninitialized { zsh_history } = Uninitialized::parse();
-        if !zsh_history.exists() {
-            bail!("zsh history file does not exist: {}", zsh_history.display());
-        }
-        Self::new(&zsh_history).await
-    }
+    /// Also expand the global aliases, defined with `alias -g`, in any word of the commands, with
+    /// `--expand-aliases`.
+    #[arg(long, requires = "expand_aliases")]
+    pub expand_global_aliases: bool,
+
+    /// The aliases of `--expand-aliases`, which [`Converter::with_args`] reads.
+    #[arg(skip)]
+    pub aliases: Aliases,
+
+    /// Strip WORD, like `sudo`, and its options off the front of the commands, if a command
+    /// follows them, along with the wrappers `time`, `nice`, `command`, and `env`. A prefix of
+    /// several words, like `poetry run`, is stripped as a whole. May be given several times.
+    #[arg(long, value_name = "WORD")]
+    pub strip_prefix: Vec<String>,
+
+    /// Strip variable assignments, like `FOO=bar`, off the front of the commands, along with the
+    /// wrappers `--strip-prefix` strips.
+    #[arg(long)]
+    pub strip_env_assignments: bool,
+
+    /// Remove trailing whitespace and bare `;`s from the commands, and with `aggressive` dangling
+    /// `&&` and `||` too, before looking for duplicates. Nothing quoted is touched, and neither are
+    /// commands with a heredoc.
+    #[arg(
+        long,
+        value_enum,
+        value_name = "HOW",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "basic"
+    )]
+    pub normalize: Option<NormalizeMode>,
+
+    /// Skip the entries whose command looks like it contains a password, a token, or a key, or
+    /// mask them with `***`. Known variable and option names, services' tokens, private keys, and
+    /// long random-looking tokens are detected.
+    #[arg(
+        long,
+        value_enum,
+        value_name = "HOW",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "skip"
+    )]
+    pub redact: Option<Redaction>,
+
+    /// Also take what REGEX matches for a credential, or its group named `secret` if it has one,
+    /// with `--redact`, which it implies. May be given several times.
+    #[arg(long, value_name = "REGEX", value_parser = Regex::new)]
+    pub redact_pattern: Vec<Regex>,
+
+    /// Skip commands which span several lines, rather than writing their newlines as `<\n>` with
+    /// `--format nushell`, or as the other formats do.
+    #[arg(long)]
+    pub skip_multiline: bool,
+
+    /// Skip commands shorter than N characters, not counting the whitespace around them.
+    #[arg(long, value_name = "N")]
+    pub min_length: Option<usize>,
+
+    /// Skip the commands which are typed all the time without arguments, like `ls`, `cd`, and
+    /// `clear`. A command only counts if it is exactly one of them, so `cd ..` is kept.
+    #[arg(long)]
+    pub skip_common: bool,
+
+    /// Skip the commands in FILE, one per line, with `--skip-common`, which it implies, instead of
+    /// the built-in ones. Empty lines and lines starting with `#` are skipped.
+    #[arg(long, value_name = "FILE")]
+    pub skip_common_list: Option<PathBuf>,
+
+    /// The commands of `--skip-common-list`, which [`Converter::with_args`] reads.
+    #[arg(skip)]
+    pub common: Vec<String>,
+
+    /// Keep only the first N entries, in the order of the history, after `--since` and `--until`
+    /// and before sorting and the other filters.
+    #[arg(long, value_name = "N", conflicts_with = "tail")]
+    pub head: Option<usize>,
+
+    /// Keep only the last N entries, in the order of the history, after `--since` and `--until`
+    /// and before sorting and the other filters.
+    #[arg(long, value_name = "N")]
+    pub tail: Option<usize>,
+
+    /// Keep only the entries from this time on: a Unix time, a `YYYY-MM-DD [HH:MM[:SS]]` in the
+    /// local time zone, an RFC 3339 timestamp, or a time ago like `90d`, `6months`, or `2y`.
+    #[arg(long, value_name = "TIME", value_parser = parse_time)]
+    pub since: Option<i64>,
+
+    /// Keep only the entries up to this time, inclusive, given like `--since`.
+    #[arg(long, value_name = "TIME", value_parser = parse_time)]
+    pub until: Option<i64>,
+
+    /// Keep only the entries whose command matches REGEX, in the syntax of the `regex` crate. If
+    /// given several times, the entries matching any of them are kept.
+    #[arg(long = "match", value_name = "REGEX", value_parser = Regex::new)]
+    pub matches: Vec<Regex>,
+
+    /// Keep only the entries whose command runs NAME, as its first word after any variable
+    /// assignments, and after the wrappers `--strip-prefix` strips. May be given several times.
+    #[arg(long = "command", value_name = "NAME")]
+    pub commands: Vec<String>,
+
+    /// Drop the entries whose command matches REGEX, even if they match `--match`. May be given
+    /// several times.
+    #[arg(long, value_name = "REGEX", value_parser = Regex::new)]
+    pub exclude: Vec<Regex>,
+
+    /// Drop the entries whose command matches any of the regexes in FILE, one per line, like
+    /// `--exclude`. Empty lines and lines starting with `#` are skipped. May be given several
+    /// times.
+    #[arg(long, value_name = "FILE")]
+    pub exclude_file: Vec<PathBuf>,
+
+    /// Keep only the entries whose command starts with any of the prefixes in FILE, one per line,
+    /// or matches any of its regexes, given as `re:<regex>`. Empty lines and lines starting with
+    /// `#` are skipped. Applies together with `--match`, so an entry has to pass both.
+    #[arg(long, value_name = "FILE")]
+    pub allowlist: Option<PathBuf>,
+
+    /// Drop the entries whose command starts with any of the prefixes in FILE, or matches any of
+    /// its regexes, given like in `--allowlist`. Applies together with `--exclude`, and drops
+    /// entries even if `--match` or `--allowlist` keep them.
+    #[arg(long, value_name = "FILE")]
+    pub blocklist: Option<PathBuf>,
+
+    /// The patterns of `--allowlist`, which [`Converter::with_args`] reads.
+    #[arg(skip)]
+    pub allowed: Vec<Regex>,
+
+    /// The patterns of `--blocklist`, which [`Converter::with_args`] reads.
+    #[arg(skip)]
+    pub blocked: Vec<Regex>,
+
+    /// Match `--match` and `--exclude` regardless of case, as `(?i)` does.
+    #[arg(long)]
+    pub ignore_case: bool,
+
+    /// Keep the entries without a timestamp, which `--since` and `--until` would otherwise judge
+    /// to be from 1970.
+    #[arg(long)]
+    pub include_undated: bool,
+
+    /// Collapse the entries with the same command into one, dropping the duplicates even without
+    /// `--merge`.
+    #[arg(long)]
+    pub unique: bool,
+
+    /// Which occurrence of a command `--unique` keeps, the one with the oldest or the newest
+    /// timestamp. It stays where that occurrence was.
+    #[arg(long, value_enum, default_value_t, requires = "unique")]
+    pub keep: Keep,
+
+    /// Compare commands for duplicates with their runs of whitespace, newlines included, collapsed
+    /// into single spaces and their ends trimmed, with `--unique` and when merging. The entry kept
+    /// keeps its command as it was.
+    #[arg(long)]
+    pub normalize_dedup: bool,
+
+    /// Compare commands for duplicates regardless of case, like `--normalize-dedup`.
+    #[arg(long)]
+    pub dedup_case_insensitive: bool,
 }
 
-impl Converter<Initialized> {
-    /// Convert the zsh history file to fish history.
-    pub async fn convert(&self) -> Result<Vec<Entry>> {
-        let mut buf = Vec::new();
-        let mut entries = Vec::new();
+/// The options of the formats `convert` writes in.
+#[derive(Debug, Default, Clone, Args)]
+pub struct FormatArgs {
+    /// The format to write the converted history in.
+    #[arg(long, visible_alias = "to", value_enum, default_value_t)]
+    pub format: Format,
+
+    /// The columns to write with `--format csv` or `tsv`, separated by commas.
+    #[arg(long, value_enum, value_delimiter = ',', default_value = "when,cmd")]
+    pub columns: Vec<Column>,
+
+    /// The atuin database to write into with `--format atuin`, usually
+    /// `~/.local/share/atuin/history.db`. It is created if it does not exist. Entries already
+    /// written by an earlier run are skipped. Tested with atuin 18.4.
+    #[arg(long, value_name = "PATH", conflicts_with = "count")]
+    pub atuin_db: Option<PathBuf>,
+
+    /// How to write commands spanning several lines with `--format plain`.
+    #[arg(long, value_enum, default_value_t)]
+    pub multiline: Multiline,
+
+    /// Start each line with the time of the command with `--format plain`.
+    #[arg(long)]
+    pub with_time: bool,
+
+    /// When to color `--format pretty`.
+    #[arg(long, value_enum, value_name = "WHEN", default_value_t)]
+    pub color: ColorWhen,
+
+    /// Write the commands in full with `--format pretty`, instead of cutting them off at the
+    /// width of the terminal.
+    #[arg(long)]
+    pub full: bool,
+
+    /// Leave out the `#<when>` comments with `--format bash`. Without them bash reads each line
+    /// as a command, so a command spanning several lines fails the conversion, unless
+    /// `--skip-multiline` drops it.
+    #[arg(long)]
+    pub no_timestamps: bool,
+
+    /// Suggest at most N abbreviations with `--format abbr`.
+    #[arg(long, value_name = "N", default_value_t = AbbrOptions::default().top)]
+    pub top: usize,
+
+    /// Suggest abbreviations only for commands typed at least N times with `--format abbr`.
+    #[arg(long, value_name = "N", default_value_t = AbbrOptions::default().min_count)]
+    pub min_count: usize,
+
+    /// Suggest abbreviations only for commands at least N characters long with `--format abbr`.
+    #[arg(long, value_name = "N", default_value_t = AbbrOptions::default().min_length)]
+    pub abbr_min_length: usize,
+
+    /// Replace the `entries` table of an existing database with `--format sqlite`, rather than
+    /// adding to it.
+    #[arg(long, requires = "output")]
+    pub replace: bool,
+
+    /// Leave out the row of column names with `--format csv` or `tsv`.
+    #[arg(long)]
+    pub no_header: bool,
+
+    /// Write the line of the zsh history each entry was read from, and the record on it, beside
+    /// the entry: as the `line` and `raw` fields with `--format json` or `jsonl`, as the last
+    /// columns with `csv` and `tsv`, and dimmed under the command with `pretty`. The other formats
+    /// have no room for them, so they need `--annotate-file`.
+    #[arg(long)]
+    pub annotate: bool,
+}
+
+/// The options writing the entries, shared by the commands writing a history.
+#[derive(Debug, Default, Clone, Args)]
+pub struct WriteArgs {
+    /// Write the converted history to the given file instead of stdout.
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+
+    /// Write the entries written, with the line and the record each was read from, to PATH as
+    /// JSON lines, whatever the format.
+    #[arg(long, value_name = "PATH")]
+    pub annotate_file: Option<PathBuf>,
+
+    /// Append to the output file if it already exists.
+    #[arg(long, requires = "output", conflicts_with = "overwrite")]
+    pub append: bool,
+
+    /// Replace the output file if it already exists.
+    #[arg(long, visible_alias = "force", requires = "output")]
+    pub overwrite: bool,
+
+    /// The order to write the entries in, once `--head` and `--tail` picked them by where they
+    /// are in the history, and before `--limit` picks some of them.
+    #[arg(long, value_enum, default_value_t)]
+    pub sort: Sort,
+
+    /// Write the entries in reverse, after sorting, filtering, and `--limit`, so `--sort when
+    /// --reverse` writes the newest first. The entries are all read before writing any, so
+    /// `--max-entries` bounds the memory this takes, like it does for sorting.
+    #[arg(long)]
+    pub reverse: bool,
+
+    /// Keep only the newest N entries. When merging, the limit applies to the merged history,
+    /// dropping the oldest entries of the destination if necessary.
+    #[arg(long, value_name = "N")]
+    pub limit: Option<usize>,
+
+    /// Back up the destination file, as `<name>.bak.<unix-ts>`, before modifying it.
+    #[arg(long)]
+    pub backup: bool,
+
+    /// Put the backup into the given directory instead of next to the destination file, creating
+    /// it if needed. Implies `--backup`.
+    #[arg(long, value_name = "DIR")]
+    pub backup_dir: Option<PathBuf>,
+
+    /// Check that the converted entries read back identically from the fish history format before
+    /// writing them, and fail if any does not.
+    #[arg(long)]
+    pub verify: bool,
 
-        // [`try_clone`] shares the underlying file handle with the original file, so the cost of
-        // cloning is minimal, I believe.
-        let mut file = BufReader::new(self.file.try_clone().await?);
+    /// Print only how many entries would be written, after all the filtering, instead of
+    /// converting. With `--merge`, how many the destination does not have yet.
+    #[arg(long, conflicts_with_all = ["verify", "interactive", "dry_run"])]
+    pub count: bool,
 
-        loop {
-            buf.clear();
-            let bytes_read = file.read_until(b'\n', &mut buf).await?;
+    /// Fail with exit status 6 if no entries are left to write, saying whether none were parsed,
+    /// or how many each filter dropped.
+    #[arg(long)]
+    pub fail_if_empty: bool,
 
-            if bytes_read == 0 {
-                break; // EOF
+    /// Show the entries newest first on the terminal, to pick the ones to write, before writing
+    /// them. Aborting writes nothing.
+    #[arg(long)]
+    pub interactive: bool,
+
+    /// Go through everything up to writing, then print what would be written where, and what
+    /// backed up, without writing anything, taking locks, or running fish.
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+/// The options writing into a fish history, shared by `convert` and `merge`.
+#[derive(Debug, Default, Clone, Args)]
+pub struct FishArgs {
+    /// The fish history session to write into with `--into-fish`, like `set fish_history <NAME>`
+    /// in fish. Defaults to `$fish_history`, or `fish` if that is not set.
+    #[arg(long, value_name = "NAME")]
+    pub session_name: Option<String>,
+
+    /// How many seconds to wait for the lock on the destination file.
+    #[arg(long, value_name = "SECS", default_value_t = DEFAULT_LOCK_TIMEOUT.as_secs())]
+    pub lock_timeout: u64,
+
+    /// Run `fish -c 'builtin history merge'` after writing, so running fish sessions pick up the
+    /// imported entries.
+    #[arg(long)]
+    pub run_merge: bool,
+
+    /// The fish binary to run with `--run-merge`.
+    #[arg(long, value_name = "PATH", default_value = "fish", requires = "run_merge")]
+    pub fish_bin: PathBuf,
+
+    /// Preview the entries which would be appended to the destination as a diff, without
+    /// writing anything. Exits with 1 if there are entries to append, 0 otherwise.
+    #[arg(long, conflicts_with_all = ["count", "interactive"])]
+    pub diff: bool,
+
+    /// How many added lines `--diff` shows before summarizing the rest.
+    #[arg(long, value_name = "LINES", default_value_t = 100, requires = "diff")]
+    pub diff_limit: usize,
+}
+
+/// The options reporting on a run, shared by the commands reading a history.
+#[derive(Debug, Default, Clone, Args)]
+pub struct LogArgs {
+    /// Show no progress while reading a large history, which is otherwise shown on stderr, as a
+    /// bar if it is a terminal.
+    #[arg(long)]
+    pub no_progress: bool,
+
+    /// Report every line skipped, or converted despite looking wrong, on stderr, with its line
+    /// number, why, and the start of it. Given twice, also dump the bytes of the line in hex.
+    #[arg(short, long, action = ArgAction::Count)]
+    pub verbose: u8,
+
+    /// Report nothing on stderr but errors, no warnings, summaries, or progress, for scripts.
+    #[arg(short, long, conflicts_with = "verbose")]
+    pub quiet: bool,
+
+    /// Read the default options from PATH instead of
+    /// `~/.config/zsh-history-to-fish/config.toml`, whose keys are the long names of the
+    /// options, or from `$Z2F_CONFIG`. Those given on the command line, or by the `Z2F_`
+    /// environment variables, win.
+    #[arg(long, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+
+    /// Print the options in effect, from the command line, the configuration file, or the
+    /// defaults, as a configuration file, and exit.
+    #[arg(long)]
+    pub print_config: bool,
+
+    /// Write what the run did as JSON to PATH, or to stderr with `-`, at its end, even if it
+    /// failed: the histories read, the entries each filter dropped, the duplicates removed, the
+    /// entries written and where, and the lines skipped.
+    #[arg(long, value_name = "PATH")]
+    pub report: Option<PathBuf>,
+
+    /// Print how long each phase of the run took on stderr at its end, reading, sorting,
+    /// filtering, dropping duplicates, and writing, with the megabytes and entries read per
+    /// second. With `--report`, the report has them too.
+    #[arg(long)]
+    pub timing: bool,
+}
+
+impl Uninitialized {
+    /// Whether a zsh history is read from stdin, with `--stdin` or `-` as a path.
+    pub fn reads_stdin(&self) -> bool {
+        self.input.stdin || self.input.zsh_history.iter().any(|path| path == Path::new("-"))
+    }
+
+    /// The file to write the converted history to, or `None` to write to stdout. With
+    /// `--into-fish`, the directory holding the fish history is created if necessary, unless with
+    /// `--dry-run`.
+    pub async fn destination(&self) -> Result<Option<PathBuf>> {
+        if !self.into_fish {
+            return Ok(self.write.output.clone());
+        }
+
+        let path =
+            fish_history_path(self.fish.session_name.as_deref(), |key| std::env::var_os(key))?;
+        if !self.write.dry_run {
+            create_history_dir(&path).await?;
+        }
+        Ok(Some(path))
+    }
+
+    /// The options for `--format csv` and `tsv` selected by `--columns`, `--no-header`, and
+    /// `--annotate`.
+    pub fn table(&self) -> Table {
+        // the default columns, if `self` was not parsed from the command line
+        let mut columns = match self.formats.columns.is_empty() {
+            true => Table::default().columns,
+            false => self.formats.columns.clone(),
+        };
+        if self.formats.annotate {
+            for column in [Column::Line, Column::Raw] {
+                if !columns.contains(&column) {
+                    columns.push(column);
+                }
             }
+        }
+        Table { columns, header: !self.formats.no_header }
+    }
+
+    /// The options for `--format plain` selected by `--multiline` and `--with-time`.
+    pub fn plain(&self) -> PlainOptions {
+        PlainOptions {
+            multiline: self.formats.multiline,
+            with_time: self.formats.with_time,
+        }
+    }
 
-            if let Some(entry) = Self::parse_zsh_history_line(&buf) {
-                entries.push(entry)
+    /// The options for `--format pretty` selected by `--color`, `--full`, and `--annotate`. Colors
+    /// are used by default only when writing to stdout, a terminal, and `NO_COLOR` is not set.
+    /// Lines are cut off at the width of the terminal, or at 80 columns if stdout is none.
+    pub fn pretty(&self) -> PrettyOptions {
+        let stdout = std::io::stdout();
+        let color = match self.formats.color {
+            ColorWhen::Always => true,
+            ColorWhen::Never => false,
+            ColorWhen::Auto => {
+                self.write.output.is_none() && AutoStream::choice(&stdout) != ColorChoice::Never
             }
+        };
+        let width = terminal_size_of(&stdout).map_or(80, |(Width(width), _)| width.into());
+        PrettyOptions {
+            color,
+            width: (!self.formats.full).then_some(width),
+            annotate: self.formats.annotate,
         }
+    }
 
-        Ok(entries)
+    /// The options for `--format json` and `jsonl` selected by `--annotate`.
+    pub fn json(&self) -> JsonOptions {
+        JsonOptions { annotate: self.formats.annotate }
     }
 
-    // zsh history format is typically: ": timestamp:0;command", or simply "command"
-    fn parse_zsh_history_line(bytes: &[u8]) -> Option<Entry> {
-        static RE: LazyLock<Regex> =
-            LazyLock::new(|| Regex::new(r"^: (\d+):(?:0;)?(.+)$").unwrap());
+    /// The options of all the formats, as selected by the options of each.
+    pub fn options(&self) -> Options {
+        Options {
+            table: self.table(),
+            abbr: self.abbr(),
+            plain: self.plain(),
+            pretty: self.pretty(),
+            bash: self.bash(),
+            json: self.json(),
+        }
+    }
 
-        let line = Self::decode(bytes);
-        let line = line.trim();
+    /// How `--normalize-dedup` and `--dedup-case-insensitive` normalize commands for comparing.
+    pub fn normalization(&self) -> Normalization {
+        Normalization {
+            whitespace: self.filters.normalize_dedup,
+            case_insensitive: self.filters.dedup_case_insensitive,
+        }
+    }
 
-        // Skip multi-line history
-        if line.ends_with(r#"\"#) {
-            return None;
+    /// What `--redact` does, if `--redact` or `--redact-pattern` is given.
+    pub fn redaction(&self) -> Option<Redaction> {
+        match self.filters.redact_pattern.is_empty() {
+            true => self.filters.redact,
+            false => Some(self.filters.redact.unwrap_or_default()),
         }
+    }
+
+    /// The `--match` regexes, made case-insensitive by `--ignore-case`.
+    pub fn patterns(&self) -> Vec<Regex> {
+        self.case(&self.filters.matches)
+    }
+
+    /// The `--exclude` regexes, and those of the `--exclude-file`s once the converter read them,
+    /// made case-insensitive by `--ignore-case`.
+    pub fn exclusions(&self) -> Vec<Regex> {
+        self.case(&self.filters.exclude)
+    }
 
-        if let Some(caps) = RE.captures(line) {
-            if let Ok(when) = caps[1].parse::<i64>() {
-                return Some(Entry { cmd: caps[2].to_string(), when });
+    // The `patterns`, made case-insensitive by `--ignore-case`.
+    fn case(&self, patterns: &[Regex]) -> Vec<Regex> {
+        patterns
+            .iter()
+            .map(|pattern| match self.filters.ignore_case {
+                // it compiled before, so it compiles again
+                true => RegexBuilder::new(pattern.as_str())
+                    .case_insensitive(true)
+                    .build()
+                    .unwrap(),
+                false => pattern.clone(),
+            })
+            .collect()
+    }
+
+    /// What `--strip-prefix` and `--strip-env-assignments` strip, if either is given.
+    pub fn strip(&self) -> Option<StripOptions> {
+        (!self.filters.strip_prefix.is_empty() || self.filters.strip_env_assignments).then(|| {
+            StripOptions {
+                prefixes: self.filters.strip_prefix.clone(),
+                env_assignments: self.filters.strip_env_assignments,
             }
+        })
+    }
+
+    /// The verbosity `--quiet` or `--verbose` asks for, if either is given.
+    pub fn verbosity(&self) -> Option<Verbosity> {
+        (self.log.quiet || self.log.verbose > 0)
+            .then(|| Verbosity::from_flags(self.log.quiet, self.log.verbose))
+    }
+
+    /// The options for `--format bash` selected by `--no-timestamps`.
+    pub fn bash(&self) -> BashOptions {
+        BashOptions { timestamps: !self.formats.no_timestamps }
+    }
+
+    /// The options for `--format abbr` selected by `--top`, `--min-count`, and
+    /// `--abbr-min-length`.
+    pub fn abbr(&self) -> AbbrOptions {
+        // the defaults, if `self` was not parsed from the command line
+        if (self.formats.top, self.formats.min_count, self.formats.abbr_min_length) == (0, 0, 0) {
+            return AbbrOptions::default();
+        }
+        AbbrOptions {
+            top: self.formats.top,
+            min_count: self.formats.min_count,
+            min_length: self.formats.abbr_min_length,
         }
+    }
 
-        // If no match, treat the whole line as a command
-        Some(Entry { cmd: line.to_string(), when: 0 })
+    /// The commands `--skip-common` skips, those of `--skip-common-list` once the converter read
+    /// it, or else [`COMMON_COMMANDS`].
+    pub fn common_commands(&self) -> Vec<&str> {
+        match self.filters.skip_common_list {
+            Some(_) => self.filters.common.iter().map(String::as_str).collect(),
+            None => COMMON_COMMANDS.to_vec(),
+        }
     }
 
-    // zsh treats non-ASCII characters strangely. See also: https://syossan.hateblo.jp/entry/2017/10/09/181928
-    fn decode(bytes: &[u8]) -> String {
-        let mut buf = Vec::new();
+    /// The backup policy selected by `--backup` and `--backup-dir`.
+    pub fn backup_policy(&self) -> BackupPolicy {
+        match (&self.write.backup_dir, self.write.backup) {
+            (Some(dir), _) => BackupPolicy::Dir(dir.clone()),
+            (None, true) => BackupPolicy::SameDir,
+            (None, false) => BackupPolicy::None,
+        }
+    }
 
-        let mut marked = false;
-        bytes.iter().for_each(|byte| match byte {
-            0x83 => {
-                marked = true;
-            }
-            b if marked => {
-                buf.push(b ^ 0b0010_0000);
-                marked = false;
-            }
-            b => buf.push(*b),
-        });
+    /// The write mode selected by `--append`, `--overwrite`, `--into-fish`, and `--merge`.
+    pub fn write_mode(&self) -> WriteMode {
+        match (self.write.append || self.into_fish || self.merge, self.write.overwrite) {
+            (true, _) => WriteMode::Append,
+            (_, true) => WriteMode::Overwrite,
+            _ => WriteMode::Create,
+        }
+    }
+}
+
+/// The commands.
+#[derive(Debug, Clone, Subcommand)]
+pub enum Command {
+    /// Convert a zsh history, which running without a command does too.
+    Convert(Box<Uninitialized>),
+    /// Merge a zsh history into the fish history, collapsing the duplicates, like
+    /// `convert --merge --into-fish`, or into `--output`.
+    Merge(Box<MergeArgs>),
+    /// Convert a fish history into a zsh history, like `convert --from fish --to zsh`.
+    FishToZsh(Box<FishToZshArgs>),
+    /// Print how many entries a history has, over which time, and its most run commands.
+    Stats(Box<StatsArgs>),
+    /// Check an existing fish history file for problems.
+    Check(CheckArgs),
+    /// Print the completion script for a shell, or install the one for fish.
+    Completions(CompletionsArgs),
+    /// Print the manual page, or write one for each command into a directory.
+    Man(ManArgs),
+}
+
+impl Command {
+    /// The options reading the history, and those reporting on the run, for the commands reading
+    /// a history.
+    pub fn history_args(&self) -> Option<(&InputArgs, &LogArgs)> {
+        match self {
+            Self::Convert(args) => Some((&args.input, &args.log)),
+            Self::Merge(args) => Some((&args.input, &args.log)),
+            Self::FishToZsh(args) => Some((&args.input, &args.log)),
+            Self::Stats(args) => Some((&args.input, &args.log)),
+            Self::Check(_) | Self::Completions(_) | Self::Man(_) => None,
+        }
+    }
+}
+
+/// The arguments of the `merge` command, those of `convert` but for the formats, as it writes a
+/// fish history.
+#[derive(Debug, Clone, Args)]
+pub struct MergeArgs {
+    /// Reading the histories.
+    #[command(flatten)]
+    pub input: InputArgs,
+
+    /// Choosing and rewriting the entries.
+    #[command(flatten)]
+    pub filters: FilterArgs,
+
+    /// Writing the entries.
+    #[command(flatten)]
+    pub write: WriteArgs,
+
+    /// Writing into the fish history.
+    #[command(flatten)]
+    pub fish: FishArgs,
+
+    /// Reporting on the run.
+    #[command(flatten)]
+    pub log: LogArgs,
+}
+
+/// The arguments of the `fish-to-zsh` command, those of `convert` but for the formats and writing
+/// into a fish history, as it writes a zsh history.
+#[derive(Debug, Clone, Args)]
+pub struct FishToZshArgs {
+    /// Reading the fish history.
+    #[command(flatten)]
+    pub input: InputArgs,
+
+    /// Choosing and rewriting the entries.
+    #[command(flatten)]
+    pub filters: FilterArgs,
+
+    /// Writing the zsh history.
+    #[command(flatten)]
+    pub write: WriteArgs,
+
+    /// Reporting on the run.
+    #[command(flatten)]
+    pub log: LogArgs,
+}
+
+/// The arguments of the `stats` command, those of `convert` reading the history and choosing the
+/// entries, as it writes none.
+#[derive(Debug, Clone, Args)]
+pub struct StatsArgs {
+    /// Reading the histories.
+    #[command(flatten)]
+    pub input: InputArgs,
+
+    /// Choosing and rewriting the entries.
+    #[command(flatten)]
+    pub filters: FilterArgs,
+
+    /// Reporting on the run.
+    #[command(flatten)]
+    pub log: LogArgs,
+
+    /// Print the statistics as a JSON object, for other tools to read.
+    #[arg(long)]
+    pub json: bool,
+}
+
+/// The arguments of the `check` command.
+#[derive(Debug, Clone, Args)]
+pub struct CheckArgs {
+    /// The path to the fish history file.
+    pub path: PathBuf,
+
+    /// Treat warnings as errors for the exit status.
+    #[arg(long)]
+    pub warnings_as_errors: bool,
+}
+
+/// The arguments of the `completions` command.
+#[derive(Debug, Clone, Args)]
+pub struct CompletionsArgs {
+    /// The shell to print the completion script for.
+    #[arg(value_enum, required_unless_present = "install")]
+    pub shell: Option<Shell>,
+
+    /// Write the fish completions into `~/.config/fish/completions` instead, where fish loads
+    /// them from.
+    #[arg(long)]
+    pub install: bool,
+
+    /// Replace the fish completions installed before with `--install`.
+    #[arg(long, requires = "install")]
+    pub force: bool,
+}
+
+/// The arguments of the `man` command.
+#[derive(Debug, Clone, Args)]
+pub struct ManArgs {
+    /// Write the manual page of the command line, and one of each command, like
+    /// `zsh-history-to-fish-convert.1`, into DIR instead, creating it.
+    #[arg(long, value_name = "DIR")]
+    pub out_dir: Option<PathBuf>,
+}
+
+/// Where a zsh history is read from.
+enum Input {
+    // opened anew by each conversion, so each reads it from the start
+    File(File),
+    Memory(Arc<[u8]>),
+    // any other reader, like stdin, taken by the first conversion, as it cannot be read twice
+    Reader(Mutex<Option<Box<dyn AsyncRead + Send + Unpin>>>),
+}
+
+impl std::fmt::Debug for Input {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::File(file) => f.debug_tuple("File").field(file).finish(),
+            Self::Memory(bytes) => f.debug_tuple("Memory").field(&bytes.len()).finish(),
+            Self::Reader(_) => f.debug_tuple("Reader").finish_non_exhaustive(),
+        }
+    }
+}
+
+impl Input {
+    fn reader(reader: impl AsyncRead + Send + Unpin + 'static) -> Self {
+        Self::Reader(Mutex::new(Some(Box::new(reader))))
+    }
+}
+
+/// A zsh history to convert.
+#[derive(Debug)]
+struct Source {
+    path: Arc<Path>,
+    input: Input,
+    // a rotated copy, which is only warned about if it cannot be converted
+    optional: bool,
+}
+
+/// What converting a history yields: its entries as they are read, then the records skipped.
+enum Converted {
+    Entry(Entry),
+    Errors(Vec<ParseError>),
+}
+
+/// A history opened to convert.
+enum Opened {
+    // read and parsed as a whole, like a database
+    Parsed(Vec<Entry>, Vec<ParseError>),
+    Zsh(ZshHistory),
+}
+
+/// A zsh history to read a line at a time, past the byte order mark.
+struct ZshHistory {
+    file: Box<dyn AsyncBufRead + Send + Unpin>,
+    encoding: &'static Encoding,
+    offset: usize,
+    progress: Option<Progress>,
+    compression: Option<Compression>,
+}
+
+/// The initialized state of the converter.
+pub struct Initialized {
+    sources: Vec<Source>,
+    /// The arguments the converter was initialized with.
+    pub args: Uninitialized,
+    // what `--expand-aliases`, `--strip-prefix`, `--normalize`, `--redact`, `--allowlist`, and
+    // `--blocklist` do, then the transforms added
+    transforms: Vec<Box<dyn Transform>>,
+    // what the conversions read
+    report: Mutex<ConversionReport>,
+}
+impl State for Initialized {}
+
+impl std::fmt::Debug for Initialized {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Initialized")
+            .field("sources", &self.sources)
+            .field("args", &self.args)
+            .field("transforms", &self.transforms.len())
+            .field("report", &self.report)
+            .finish()
+    }
+}
+
+impl Initialized {
+    fn new(sources: Vec<Source>, args: Uninitialized) -> Self {
+        if let Some(verbosity) = args.verbosity() {
+            set_verbosity(verbosity);
+        }
+        let mut transforms = Vec::<Box<dyn Transform>>::new();
+        if args.filters.expand_aliases.is_some() {
+            let (aliases, global) =
+                (args.filters.aliases.clone(), args.filters.expand_global_aliases);
+            transforms.push(Box::new(ExpandAliases::new(aliases, global)));
+        }
+        if let Some(options) = args.strip() {
+            transforms.push(Box::new(StripPrefixes::new(options)));
+        }
+        if let Some(mode) = args.filters.normalize {
+            transforms.push(Box::new(Normalize::new(mode)));
+        }
+        if let Some(redaction) = args.redaction() {
+            transforms.push(Box::new(Redact::new(redaction, args.filters.redact_pattern.clone())));
+        }
+        if args.filters.allowlist.is_some() {
+            transforms.push(Box::new(ListFilter::allowlist(args.filters.allowed.clone())));
+        }
+        if args.filters.blocklist.is_some() {
+            transforms.push(Box::new(ListFilter::blocklist(args.filters.blocked.clone())));
+        }
+        Self {
+            sources,
+            args,
+            transforms,
+            report: Mutex::default(),
+        }
+    }
+}
+
+impl Converter<Uninitialized> {
+    /// Create a new converter from the given path.
+    pub async fn new(path: impl AsRef<Path>) -> Result<Converter<Initialized>> {
+        let input = InputArgs {
+            zsh_history: vec![path.as_ref().to_path_buf()],
+            ..Default::default()
+        };
+        Self::with_args(Uninitialized { input, ..Default::default() }).await
+    }
+
+    /// Create a new converter for a zsh history read from `reader`, which is read into memory
+    /// right away, so it may borrow. To read it while converting instead, see
+    /// [`with_args_and_reader`](Self::with_args_and_reader).
+    pub async fn from_reader<R>(mut reader: R) -> Result<Converter<Initialized>>
+    where
+        R: AsyncRead + Unpin,
+    {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        Self::with_args_and_bytes(Uninitialized::default(), bytes)
+    }
+
+    /// Create a new converter for a zsh history in memory.
+    pub fn from_bytes(bytes: &[u8]) -> Converter<Initialized> {
+        Self::with_args_and_bytes(Uninitialized::default(), bytes).unwrap()
+    }
+
+    /// Create a new converter for a zsh history in memory.
+    ///
+    /// ```
+    /// use zsh_history_to_fish::Converter;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// let converter = Converter::from_text(": 1700000000:0;ls -la\necho hi\n");
+    /// let entries = converter.convert().await?;
+    /// assert_eq!((entries[0].cmd.as_str(), entries[0].when), ("ls -la", 1700000000));
+    /// assert_eq!(entries[1].cmd, "echo hi");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_text(history: &str) -> Converter<Initialized> {
+        Self::from_bytes(history.as_bytes())
+    }
+
+    /// Check the given arguments, and return a new converter for the zsh history in `bytes`,
+    /// instead of the paths in the arguments.
+    pub fn with_args_and_bytes(
+        args: Uninitialized,
+        bytes: impl Into<Arc<[u8]>>,
+    ) -> Result<Converter<Initialized>> {
+        check_args(&args).fail_as(Failure::Usage)?;
+        let source = Source {
+            path: Path::new("-").into(),
+            input: Input::Memory(bytes.into()),
+            optional: false,
+        };
+        Ok(Converter { state: Initialized::new(vec![source], args) })
+    }
+
+    /// Check the given arguments, and return a new converter for the zsh history read from
+    /// `reader` while converting, instead of the paths in the arguments, like a socket or a
+    /// decoder of your own. A compressed history is decompressed like a file is. Since a reader
+    /// cannot be rewound, only the first conversion reads it, and the others fail.
+    ///
+    /// ```
+    /// use std::io::Cursor;
+    ///
+    /// use zsh_history_to_fish::{Converter, Uninitialized};
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// let history = Cursor::new(b": 1700000000:0;ls -la\necho hi\n");
+    /// let converter = Converter::with_args_and_reader(Uninitialized::default(), history)?;
+    /// let entries = converter.convert().await?;
+    /// assert_eq!((entries[0].cmd.as_str(), entries[1].cmd.as_str()), ("ls -la", "echo hi"));
+    /// assert!(converter.convert().await.is_err());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_args_and_reader(
+        args: Uninitialized,
+        reader: impl AsyncRead + Send + Unpin + 'static,
+    ) -> Result<Converter<Initialized>> {
+        check_args(&args).fail_as(Failure::Usage)?;
+        let source = Source {
+            path: Path::new("-").into(),
+            input: Input::reader(reader),
+            optional: false,
+        };
+        Ok(Converter { state: Initialized::new(vec![source], args) })
+    }
+
+    /// Check the given arguments, check if the zsh history file exists, and return a new
+    /// converter. Reading from stdin needs no file. Without a path, the usual locations of the zsh
+    /// history are tried, see [`zsh::history_path`].
+    pub async fn with_args(mut args: Uninitialized) -> Result<Converter<Initialized>> {
+        check_args(&args).fail_as(Failure::Usage)?;
+        let sources = open_sources(&mut args).await.fail_as(Failure::Input)?;
+        Ok(Converter { state: Initialized::new(sources, args) })
+    }
+
+    /// Check the arguments of `merge`, and return a new converter merging into the fish history,
+    /// or into `--output`.
+    pub async fn with_merge_args(args: MergeArgs) -> Result<Converter<Initialized>> {
+        let MergeArgs { input, filters, write, fish, log } = args;
+        let into_fish = write.output.is_none();
+        let args = Uninitialized {
+            input,
+            filters,
+            write,
+            fish,
+            log,
+            into_fish,
+            merge: true,
+            ..Default::default()
+        };
+        Self::with_args(args).await
+    }
+
+    /// Check the arguments of `fish-to-zsh`, and return a new converter reading a fish history and
+    /// writing a zsh history.
+    pub async fn with_fish_to_zsh_args(args: FishToZshArgs) -> Result<Converter<Initialized>> {
+        let FishToZshArgs { mut input, filters, write, log } = args;
+        if !matches!(input.from, InputFormat::Zsh | InputFormat::Fish) {
+            return Err(anyhow!(
+                "fish-to-zsh always converts from fish to zsh, use convert for other formats"
+            ))
+            .fail_as(Failure::Usage);
+        }
+        input.from = InputFormat::Fish;
+        let formats = FormatArgs { format: Format::Zsh, ..Default::default() };
+        let args = Uninitialized {
+            input,
+            filters,
+            formats,
+            write,
+            log,
+            ..Default::default()
+        };
+        Self::with_args(args).await
+    }
+
+    /// Check the arguments of `stats`, and return a new converter reading the history to tell
+    /// about.
+    pub async fn with_stats_args(args: StatsArgs) -> Result<Converter<Initialized>> {
+        let StatsArgs { input, filters, log, .. } = args;
+        Self::with_args(Uninitialized { input, filters, log, ..Default::default() }).await
+    }
+
+    /// Parse the command line arguments, with the configuration file, check if the zsh history
+    /// file exists, and return a new converter for `convert`, `merge`, `fish-to-zsh`, or `stats`.
+    pub async fn from_args() -> Result<Converter<Initialized>> {
+        match Cli::parse_with_config()?.command() {
+            Command::Convert(args) => Self::with_args(*args).await,
+            Command::Merge(args) => Self::with_merge_args(*args).await,
+            Command::FishToZsh(args) => Self::with_fish_to_zsh_args(*args).await,
+            Command::Stats(args) => Self::with_stats_args(*args).await,
+            Command::Check(_) => Err(anyhow!("check reads a fish history, it converts nothing"))
+                .fail_as(Failure::Usage),
+            Command::Completions(_) => {
+                Err(anyhow!("completions reads no history, it converts nothing"))
+                    .fail_as(Failure::Usage)
+            }
+            Command::Man(_) => {
+                Err(anyhow!("man reads no history, it converts nothing")).fail_as(Failure::Usage)
+            }
+        }
+    }
+}
+
+impl Converter<Initialized> {
+    /// Run `transform` on every entry as it is read, before the timestamps are filled in and
+    /// several histories are merged, to rewrite or drop it. Transforms run in the order they are
+    /// added, after those of `--expand-aliases`, `--strip-prefix`, `--normalize`, `--redact`,
+    /// `--allowlist`, and `--blocklist`, and what they did is reported after converting.
+    pub fn with_transform(mut self, transform: impl Transform + 'static) -> Self {
+        self.state.transforms.push(Box::new(transform));
+        self
+    }
+
+    /// Rewrite every entry with `f`, or drop it if `f` returns `None`, like
+    /// [`with_transform`](Self::with_transform) does.
+    ///
+    /// ```
+    /// use zsh_history_to_fish::Converter;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// let converter = Converter::from_text("ssh old-host\nls\n").map_entries(|mut entry| {
+    ///     entry.cmd = entry.cmd.replace("old-host", "new-host");
+    ///     (entry.cmd != "ls").then_some(entry)
+    /// });
+    /// let entries = converter.convert().await?;
+    /// assert_eq!(entries.len(), 1);
+    /// assert_eq!(entries[0].cmd, "ssh new-host");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn map_entries<F>(self, f: F) -> Self
+    where
+        F: Fn(Entry) -> Option<Entry> + Send + Sync + 'static,
+    {
+        self.with_transform(f)
+    }
+
+    /// What the conversions so far read, like the lines which had to be skipped.
+    pub fn report(&self) -> ConversionReport {
+        self.report.lock().unwrap().clone()
+    }
+
+    /// Convert the zsh history file to fish history. Records which cannot be converted are
+    /// reported on stderr and skipped. With `--strict`, every line which cannot be cleanly
+    /// converted is reported instead, and fails the conversion.
+    pub async fn convert(&self) -> Result<Vec<Entry>> {
+        self.stream().try_collect().await
+    }
+
+    /// The entries [`convert`](Self::convert) returns, as they are read, so a huge history needs
+    /// not fit into memory. A multi-line command is yielded once all of its lines are read, and
+    /// an error ends the stream after it is yielded.
+    ///
+    /// The entries are held back until the whole history is read if what is done at the end
+    /// needs all of them: with `--strict` and `--strict-utf8`, which fail before yielding any,
+    /// with several histories, which are merged, and with `--tail`, `--interpolate-timestamps`,
+    /// `--bump-duplicate-timestamps`, or `--default-when spread`.
+    ///
+    /// ```
+    /// use futures_util::TryStreamExt;
+    /// use zsh_history_to_fish::Converter;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// let converter = Converter::from_text(": 1700000000:0;echo a\\\necho b\nls\n");
+    /// let mut entries = std::pin::pin!(converter.stream());
+    /// assert_eq!(entries.try_next().await?.unwrap().cmd, "echo a\necho b");
+    /// assert_eq!(entries.try_next().await?.unwrap().cmd, "ls");
+    /// assert!(entries.try_next().await?.is_none());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn stream(&self) -> impl Stream<Item = Result<Entry>> + '_ {
+        try_stream! {
+            let seen = self.report.lock().unwrap().skipped_lines.len();
+            let mut converted = pin!(self.converted());
+            // `--strict` fails before yielding any entry
+            let mut held = Vec::new();
+            while let Some(converted) = converted.try_next().await? {
+                match converted {
+                    Converted::Entry(entry) if self.args.input.strict => held.push(entry),
+                    Converted::Entry(entry) => yield entry,
+                    Converted::Errors(errors) if !self.args.input.strict => {
+                        errors.iter().for_each(|error| info!("skipped the record at {error}"));
+                    }
+                    Converted::Errors(_) => {}
+                }
+            }
+            if self.args.input.strict {
+                self.check_strict(seen)?;
+                for entry in held {
+                    yield entry;
+                }
+            }
+        }
+    }
+
+    /// Convert the zsh history file to fish history, also returning the records which could not
+    /// be converted and were skipped. Several histories are merged, see
+    /// [`merge_histories`](crate::merge_histories).
+    pub async fn convert_with_errors(&self) -> Result<(Vec<Entry>, Vec<ParseError>)> {
+        collect(self.converted()).await
+    }
+
+    // Fail if any of the lines skipped since the first `seen` could not be cleanly converted, as
+    // `--strict` does, listing them.
+    fn check_strict(&self, seen: usize) -> Result<()> {
+        let report = self.report.lock().unwrap();
+        let anomalies = report.skipped_lines[seen..]
+            .iter()
+            .filter(|skip| skip.reason.is_anomaly())
+            .collect::<Vec<_>>();
+        if !anomalies.is_empty() {
+            self.print_skipped(anomalies.iter().copied());
+            return Err(anyhow!(
+                "{} lines could not be cleanly converted, wrote nothing",
+                anomalies.len()
+            ))
+            .fail_as(Failure::Strict);
+        }
+        Ok(())
+    }
+
+    // The entries of the histories as they are read, then the records which were skipped.
+    // Several histories are merged once all of them are read.
+    fn converted(&self) -> impl Stream<Item = Result<Converted>> + '_ {
+        try_stream! {
+            match &self.sources[..] {
+                [source] => {
+                    let mut converted = pin!(self.convert_source(source));
+                    while let Some(converted) = converted.next().await {
+                        yield converted.fail_as(Failure::Input)?;
+                    }
+                }
+                _ => {
+                    let (entries, errors) = self.convert_sources().await.fail_as(Failure::Input)?;
+                    for entry in entries {
+                        yield Converted::Entry(entry);
+                    }
+                    yield Converted::Errors(errors);
+                }
+            }
+            for summary in self.transforms.iter().filter_map(|transform| transform.summary()) {
+                info!("{summary}");
+            }
+        }
+    }
+
+    // Convert several histories and merge them.
+    async fn convert_sources(&self) -> Result<(Vec<Entry>, Vec<ParseError>)> {
+        let (mut histories, mut errors) = (Vec::new(), Vec::new());
+        let mut paths = Vec::new();
+        for source in &self.sources {
+            match collect(self.convert_source(source)).await {
+                Ok((entries, skipped)) => {
+                    histories.push(entries);
+                    errors.extend(skipped);
+                    paths.push(&source.path);
+                }
+                Err(e) if source.optional => info!("skipping {}: {e:#}", source.path.display()),
+                Err(e) => {
+                    return Err(e.context(format!("failed to convert {}", source.path.display())))
+                }
+            }
+        }
+
+        let read = histories.iter().map(Vec::len).collect::<Vec<_>>();
+        let (entries, contributed) = merge_histories(
+            histories,
+            self.args.input.dedup_by,
+            self.args.input.dup_policy,
+            self.args.normalization(),
+        );
+        for ((path, read), contributed) in paths.iter().zip(read).zip(contributed) {
+            info!("{}: {read} entries read, {contributed} contributed", path.display());
+        }
+        Ok((entries, errors))
+    }
+
+    // Convert a single history, yielding its entries as they are read, unless what is done once
+    // it is read needs all of them, then the records which were skipped.
+    fn convert_source<'a>(
+        &'a self,
+        source: &'a Source,
+    ) -> impl Stream<Item = Result<Converted>> + 'a {
+        try_stream! {
+            let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as i64;
+            match self.open_source(source, now).await? {
+                Opened::Parsed(entries, errors) => {
+                    for entry in entries {
+                        yield Converted::Entry(entry);
+                    }
+                    yield Converted::Errors(errors);
+                }
+                Opened::Zsh(history) => {
+                    let mut converted = pin!(self.convert_zsh(source, history, now));
+                    while let Some(converted) = converted.next().await {
+                        yield converted?;
+                    }
+                }
+            }
+        }
+    }
+
+    // Open a single history to read. A zsh one is left to read a line at a time, the others are
+    // read and parsed as a whole.
+    async fn open_source(&self, source: &Source, now: i64) -> Result<Opened> {
+        let mut offset = 0;
+
+        // A handle of its own, rather than a clone of the one opened, which would share its
+        // position with every other conversion.
+        let mut reader: Box<dyn AsyncRead + Send + Unpin> = match &source.input {
+            Input::File(_) => Box::new(
+                File::open(&source.path)
+                    .await
+                    .with_context(|| format!("failed to open {}", source.path.display()))?,
+            ),
+            Input::Memory(bytes) => Box::new(Cursor::new(bytes.clone())),
+            Input::Reader(reader) => reader.lock().unwrap().take().ok_or_else(|| {
+                anyhow!(
+                    "{} was already read, a reader is only converted once",
+                    source.path.display()
+                )
+            })?,
+        };
+        let hidden = self.args.log.no_progress || verbosity() == Verbosity::Quiet;
+        let progress = match (&source.input, hidden) {
+            (Input::File(file), false) => {
+                let name = source.path.display().to_string();
+                Progress::new(name, Some(file.metadata().await?.len()))
+            }
+            _ => None,
+        };
+        if let Some(progress) = &progress {
+            reader = Box::new(CountingReader::new(reader, progress.counter()));
+        }
+        let (mut reader, compression) = decompress(BufReader::new(reader))
+            .await
+            .with_context(|| format!("failed to read {}", source.path.display()))?;
+        let read_error = || read_error(source, compression);
+
+        let from = match self.args.input.from {
+            InputFormat::Auto => {
+                let sample = reader.fill_buf().await.with_context(read_error)?;
+                let Detection { mut format, confidence } = detect_format(sample);
+                if format == InputFormat::Histdb && matches!(source.input, Input::File(_)) {
+                    format = database_format(&source.path)?;
+                }
+                let path = source.path.display();
+                match confidence {
+                    Confidence::High => info!("{path}: detected the {format} format"),
+                    Confidence::Low => info!(
+                        "{path}: could not tell the format for sure, assuming {format}, pass \
+                         --from to override"
+                    ),
+                }
+                format
+            }
+            from => from,
+        };
+        let database = match from {
+            InputFormat::Histdb | InputFormat::Atuin => true,
+            InputFormat::Nushell => is_database(reader.fill_buf().await.with_context(read_error)?),
+            _ => false,
+        };
+        if database {
+            if compression.is_some() || !matches!(source.input, Input::File(_)) {
+                bail!("{} must be an uncompressed database file", source.path.display());
+            }
+            let (path, since, until) = match self.args.filters.include_undated {
+                // the rows without a timestamp are then filtered out with the others
+                true => (source.path.clone(), None, None),
+                false => (source.path.clone(), self.args.filters.since, self.args.filters.until),
+            };
+            let parsed = tokio::task::spawn_blocking(move || match from {
+                InputFormat::Atuin => read_atuin(&path, since, until),
+                InputFormat::Nushell => read_nushell(&path, since, until),
+                _ => read_histdb(&path, since, until),
+            })
+            .await?
+            .with_context(read_error)?;
+            let tally = Tally { lines: parsed.len(), ..Default::default() };
+            let (entries, errors) = self.convert_parsed(source, parsed, tally, now).await?;
+            return Ok(Opened::Parsed(entries, errors));
+        }
+        let (encoding, mut file): (_, Box<dyn AsyncBufRead + Send + Unpin>) =
+            match self.args.input.encoding {
+                InputEncoding::Fixed(encoding) => (encoding, Box::new(reader)),
+                // guessing needs the whole file, and stdin cannot be rewound, so keep it in memory
+                auto => {
+                    let mut contents = Vec::new();
+                    reader.read_to_end(&mut contents).await.with_context(read_error)?;
+                    (auto.resolve(&zsh::unmetafy(&contents)), Box::new(Cursor::new(contents)))
+                }
+            };
+        if file.fill_buf().await.with_context(read_error)?.starts_with(UTF8_BOM) {
+            file.consume(UTF8_BOM.len());
+            offset += UTF8_BOM.len();
+        }
+
+        if from != InputFormat::Zsh {
+            let mut bytes = Vec::new();
+            file.read_to_end(&mut bytes).await.with_context(read_error)?;
+            let (text, lossy) = encoding.decode_without_bom_handling(&bytes);
+            let tally = Tally {
+                lines: text.lines().count(),
+                bytes: (offset + bytes.len()) as u64,
+                lossy: match lossy {
+                    true => text.lines().filter(|line| line.contains('\u{FFFD}')).count(),
+                    false => 0,
+                },
+                ..Default::default()
+            };
+            if lossy && self.args.input.strict_utf8 && !self.args.input.lossy {
+                return Err(anyhow!(
+                    "{} is not valid {}, rerun with --lossy to replace the invalid bytes",
+                    source.path.display(),
+                    encoding.name()
+                ))
+                .fail_as(Failure::Strict);
+            }
+            let parsed = match from {
+                InputFormat::Auto | InputFormat::Zsh | InputFormat::Histdb | InputFormat::Atuin => {
+                    unreachable!()
+                }
+                InputFormat::Bash => parse_bash_history(&text),
+                InputFormat::Fish => {
+                    let entries = parse_fish_history(text.as_bytes()).await?;
+                    (1..).zip(entries.into_iter().map(Ok)).collect()
+                }
+                InputFormat::Fc => parse_fc_listing(&text, self.args.input.tz),
+                InputFormat::Psreadline => parse_psreadline_history(&text),
+                InputFormat::Nushell => parse_nushell_history(&text),
+                InputFormat::Xonsh => {
+                    parse_xonsh_history(&text).context("not a xonsh history session")?
+                }
+            };
+            let (entries, errors) = self.convert_parsed(source, parsed, tally, now).await?;
+            return Ok(Opened::Parsed(entries, errors));
+        }
+
+        Ok(Opened::Zsh(ZshHistory { file, encoding, offset, progress, compression }))
+    }
+
+    // Convert a zsh history, a line at a time, yielding each entry once its record is complete.
+    fn convert_zsh<'a>(
+        &'a self,
+        source: &'a Source,
+        history: ZshHistory,
+        now: i64,
+    ) -> impl Stream<Item = Result<Converted>> + 'a {
+        try_stream! {
+            let ZshHistory { mut file, encoding, mut offset, mut progress, compression } = history;
+            let read_error = || read_error(source, compression);
+            let mut buf = Vec::new();
+            let mut entries = Vec::new();
+            let mut record = String::new();
+            let (mut line_number, mut record_line, mut undecodable) = (0, 0, 0);
+            let (mut record_offset, mut skipping) = (0, false);
+            let max = self.args.input.max_line_bytes.unwrap_or(DEFAULT_MAX_LINE_BYTES);
+            let (head, tail) = match self.reads_whole_window() {
+                true => (self.args.filters.head, self.args.filters.tail),
+                false => (None, None),
+            };
+            let mut tally = Tally::default();
+            // whether the entries are yielded as they are read, rather than held back to the end
+            let streams = self.streams_entries() && tail.is_none();
+            let mtime = match self.args.input.default_when {
+                DefaultWhen::Mtime if streams => self.mtime(source).await?,
+                _ => 0,
+            };
+            // the entries read so far, whether yielded, held back, or out of the `--tail` window
+            let mut read = 0;
+
+            loop {
+                buf.clear();
+                let (bytes_read, oversized) =
+                    read_line(&mut file, &mut buf, max).await.with_context(read_error)?;
+
+                if bytes_read == 0 {
+                    break; // EOF
+                }
+                line_number += 1;
+                let line_offset = offset;
+                offset += bytes_read;
+                // only the last line read can lack a newline
+                let partial = !buf.ends_with(b"\n");
+
+                // The history may have picked up CRLF line endings on the way, even on some lines
+                // only.
+                let raw = buf.strip_suffix(b"\n").unwrap_or(&buf);
+                let raw = raw.strip_suffix(b"\r").unwrap_or(raw);
+                if record.is_empty() {
+                    (record_line, record_offset) = (line_number, line_offset);
+                }
+
+                // Drop the whole record if it gets too long, including the lines of a multi-line
+                // command which are still to come.
+                if oversized || skipping || record.len() + raw.len() > max {
+                    if !skipping {
+                        let content = [record.as_bytes(), raw].concat();
+                        self.note(&mut tally, source, record_line, SkipReason::Oversized, &content);
+                        let kind = ParseErrorKind::TooLong { max, offset: record_offset };
+                        tally.errors.push(ParseError {
+                            path: source.path.to_path_buf(),
+                            line: record_line,
+                            kind,
+                        });
+                    }
+                    skipping = raw.ends_with(b"\\");
+                    record.clear();
+                    continue;
+                }
+
+                let (line, lossy) = Self::decode(raw, encoding);
+                let line = line.as_str();
+                tally.lossy += usize::from(lossy);
+                if lossy {
+                    self.note(&mut tally, source, line_number, SkipReason::InvalidEncoding, raw);
+                }
+
+                // zsh may be in the middle of appending to the file
+                if partial && !self.args.input.include_partial {
+                    let pending = format!("{record}{line}");
+                    if lossy || raw.ends_with(&[zsh::META]) || looks_torn(&pending) {
+                        let content = pending.as_bytes();
+                        self.note(&mut tally, source, record_line, SkipReason::Incomplete, content);
+                        let kind = ParseErrorKind::Incomplete;
+                        tally.errors.push(ParseError {
+                            path: source.path.to_path_buf(),
+                            line: record_line,
+                            kind,
+                        });
+                        record.clear();
+                        break;
+                    }
+                }
+
+                if lossy && self.args.input.strict_utf8 {
+                    undecodable += 1;
+                    let bytes = zsh::unmetafy(raw);
+                    for sequence in invalid_sequences(&bytes, encoding) {
+                        let hex = sequence.iter().map(|b| format!("{b:02x}")).collect::<Vec<_>>();
+                        let name = encoding.name();
+                        let message = format!(
+                            "line {line_number}: invalid {name} sequence: {}",
+                            hex.join(" ")
+                        );
+                        // warnings once `--lossy` replaces them, and without it the details of
+                        // the error `--strict-utf8` fails with, which are always reported
+                        let report = || match self.args.input.lossy {
+                            true => info!("{message}"),
+                            false => eprintln!("{message}"),
+                        };
+                        match &progress {
+                            Some(progress) => progress.suspend(report),
+                            None => report(),
+                        }
+                    }
+                }
+
+                // zsh writes each newline of a multi-line command as a backslash at the end of the
+                // line, so keep accumulating until the command is complete.
+                if let Some(line) = line.strip_suffix('\\') {
+                    record.push_str(line);
+                    record.push('\n');
+                    continue;
+                }
+                record.push_str(line);
+
+                let record = std::mem::take(&mut record);
+                let before = entries.len();
+                self.finish_record(source, &record, record_line, now, &mut entries, &mut tally);
+                read += entries.len() - before;
+                if let Some(progress) = &mut progress {
+                    progress.tick(tally.parsed);
+                }
+                if streams {
+                    for mut entry in entries.drain(..) {
+                        let strategy = self.args.input.default_when;
+                        tally.untimestamped +=
+                            default_when(std::slice::from_mut(&mut entry), strategy, mtime);
+                        yield Converted::Entry(entry);
+                    }
+                }
+
+                // only the entries `--head` and `--tail` keep are needed
+                if head.is_some_and(|n| read >= n) {
+                    break;
+                }
+                if let Some(n) = tail.filter(|&n| entries.len() >= n.max(1) * 2) {
+                    entries.drain(..entries.len() - n);
+                }
+
+                // stop early, so a runaway history cannot eat all memory
+                if self.reached_max_entries(&tally) {
+                    if file.fill_buf().await?.is_empty() {
+                        break; // nothing was left anyway
+                    }
+                    if let Some(progress) = &mut progress {
+                        progress.finish();
+                    }
+                    info!(
+                        "stopped reading after {} entries, the --max-entries limit, and {offset} \
+                         bytes of the history",
+                        tally.parsed
+                    );
+                    break;
+                }
+            }
+
+            // a continuation on the very last line
+            if record.ends_with('\n') {
+                let reason = SkipReason::MultilineFragment;
+                self.note(&mut tally, source, record_line, reason, record.as_bytes());
+            }
+            self.finish_record(source, &record, record_line, now, &mut entries, &mut tally);
+            if let Some(progress) = &mut progress {
+                progress.finish();
+            }
+            (tally.lines, tally.bytes) = (line_number, offset as u64);
+
+            self.fill_timestamps(source, &mut entries, &tally, now).await?;
+
+            if undecodable > 0 {
+                let summary = format!(
+                    "{undecodable} of {line_number} lines are not valid {}",
+                    encoding.name()
+                );
+                if !self.args.input.lossy {
+                    Err(anyhow!("{summary}, rerun with --lossy to replace the invalid bytes"))
+                        .fail_as(Failure::Strict)?;
+                }
+                info!("{summary}, replaced the invalid bytes");
+            }
+
+            for entry in entries {
+                yield Converted::Entry(entry);
+            }
+            yield Converted::Errors(tally.errors);
+        }
+    }
+
+    // Whether `--head` and `--tail` may be applied while reading the history, as no other history
+    // is merged with it, and nothing drops entries before them.
+    fn reads_whole_window(&self) -> bool {
+        self.sources.len() == 1
+            && self.args.filters.since.is_none()
+            && self.args.filters.until.is_none()
+    }
+
+    // Whether `--max-entries` entries were parsed, counted before the transforms drop any, so
+    // every kind of history stops at the same point.
+    fn reached_max_entries(&self, tally: &Tally) -> bool {
+        self.args.input.max_entries != 0 && tally.parsed >= self.args.input.max_entries
+    }
+
+    // Whether the entries of a zsh history may be yielded as they are read, as nothing done once
+    // it is read needs all of them.
+    fn streams_entries(&self) -> bool {
+        self.sources.len() == 1
+            && !self.args.input.strict
+            && !self.args.input.strict_utf8
+            && !self.args.input.interpolate_timestamps
+            && !self.args.input.bump_duplicate_timestamps
+            && self.args.input.default_when != DefaultWhen::Spread
+    }
+
+    // When the history was last modified, in seconds since the epoch.
+    async fn mtime(&self, source: &Source) -> Result<i64> {
+        let modified = match &source.input {
+            Input::File(file) => file.metadata().await?.modified()?,
+            // what was piped in is as new as it gets
+            Input::Memory(_) | Input::Reader(_) => SystemTime::now(),
+        };
+        Ok(modified.duration_since(UNIX_EPOCH)?.as_secs() as i64)
+    }
+
+    // Collect the entries parsed from a history which is not a zsh one, the way zsh records are.
+    async fn convert_parsed(
+        &self,
+        source: &Source,
+        parsed: Vec<ParsedEntry>,
+        mut tally: Tally,
+        now: i64,
+    ) -> Result<(Vec<Entry>, Vec<ParseError>)> {
+        let mut entries = Vec::new();
+        for (line, entry) in parsed {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(kind) => {
+                    self.note(&mut tally, source, line, SkipReason::from(&kind), b"");
+                    let path = source.path.to_path_buf();
+                    tally.errors.push(ParseError { path, line, kind });
+                    continue;
+                }
+            };
+            if self.args.input.ignore_space && entry.cmd.starts_with(' ') {
+                let content = entry.cmd.as_bytes();
+                self.note(&mut tally, source, line, SkipReason::SpacePrefixed, content);
+                tally.space_prefixed += 1;
+                continue;
+            }
+            if self.reached_max_entries(&tally) {
+                info!("stopped reading after {} entries, the --max-entries limit", tally.parsed);
+                break;
+            }
+            self.push_entry(source, entry, line, None, now, &mut entries, &mut tally);
+        }
+
+        self.fill_timestamps(source, &mut entries, &tally, now).await?;
+        Ok((entries, tally.errors))
+    }
+
+    // Fill in the timestamps the history lacks, and report what was done, along with the entries
+    // skipped for starting with a space and those with a timestamp out of range. What was read is
+    // added to the report.
+    async fn fill_timestamps(
+        &self,
+        source: &Source,
+        entries: &mut [Entry],
+        tally: &Tally,
+        now: i64,
+    ) -> Result<()> {
+        if self.args.input.interpolate_timestamps {
+            match interpolate(entries) {
+                0 => {}
+                n => info!("interpolated the timestamps of {n} entries without one"),
+            }
+        }
+        let strategy = self.args.input.default_when;
+        let mtime = match strategy {
+            DefaultWhen::Zero => 0,
+            _ => self.mtime(source).await?,
+        };
+        match (tally.untimestamped + default_when(entries, strategy, mtime), strategy) {
+            (0, _) => {}
+            (n, DefaultWhen::Zero) => info!("{n} entries have no timestamp, left them at 0"),
+            (n, DefaultWhen::Mtime) => {
+                info!("{n} entries have no timestamp, gave them the history's mtime {mtime}")
+            }
+            (n, DefaultWhen::Spread) => {
+                info!("{n} entries have no timestamp, spread them out up to the mtime {mtime}")
+            }
+        }
+
+        if self.args.input.bump_duplicate_timestamps {
+            match bump_duplicates(entries) {
+                0 => {}
+                n => info!("bumped the timestamps of {n} entries sharing a second"),
+            }
+        }
+
+        if tally.space_prefixed > 0 {
+            info!("skipped {} entries starting with a space", tally.space_prefixed);
+        }
+
+        if !tally.out_of_range.is_empty() {
+            let lines = tally.out_of_range.iter().map(usize::to_string).collect::<Vec<_>>();
+            let summary = format!(
+                "{} entries have a timestamp before 1970 or in the future, on lines {}",
+                lines.len(),
+                lines.join(", ")
+            );
+            match (self.args.input.strict, self.args.input.clamp_timestamps) {
+                // listed with the other lines which cannot be cleanly converted
+                (true, _) => {}
+                (_, true) => info!("{summary}, clamped them to [0, {now}]"),
+                _ => info!("{summary}, pass --clamp-timestamps to clamp them"),
+            }
+        }
+
+        let mut skips = tally.skips.clone();
+        // stable, so what is wrong with the same line stays in the order it was found
+        skips.sort_by_key(|skip| skip.line);
+        // `--strict` lists them once all histories are read
+        if verbosity() >= Verbosity::Verbose && !self.args.input.strict {
+            self.print_skipped(&skips);
+        }
+
+        let bytes = match &source.input {
+            Input::File(file) => file.metadata().await?.len(),
+            Input::Memory(_) | Input::Reader(_) => tally.bytes,
+        };
+        let mut report = self.report.lock().unwrap();
+        let path = source.path.to_path_buf();
+        report.inputs.push(InputReport { path, entries: tally.parsed });
+        report.bytes += bytes;
+        report.lines += tally.lines;
+        report.entries += tally.parsed;
+        report.skipped += tally.errors.len();
+        report.space_prefixed += tally.space_prefixed;
+        report.lossy += tally.lossy;
+        report.skipped_lines.extend(skips);
+        Ok(())
+    }
+
+    // Parse a complete record, which started at `line`, into `entries`, unless `--ignore-space`
+    // drops it like zsh's HIST_IGNORE_SPACE would have. Timestamps later than `now` plus some skew
+    // are clamped with `--clamp-timestamps`.
+    fn finish_record(
+        &self,
+        source: &Source,
+        record: &str,
+        line: usize,
+        now: i64,
+        entries: &mut Vec<Entry>,
+        tally: &mut Tally,
+    ) {
+        if self.args.input.ignore_space {
+            let cmd = HEADER
+                .captures(record)
+                .map_or(record, |caps| caps.get(3).unwrap().as_str());
+            if cmd.starts_with(' ') && !cmd.trim().is_empty() {
+                let content = record.as_bytes();
+                self.note(tally, source, line, SkipReason::SpacePrefixed, content);
+                tally.space_prefixed += 1;
+                return;
+            }
+        }
+
+        let entry = match self.parse_zsh_history_line(record) {
+            Ok(Some(entry)) => entry,
+            // a blank line is nothing worth reporting, a header without a command is
+            Ok(None) if record.trim().is_empty() => return,
+            Ok(None) => {
+                return self.note(tally, source, line, SkipReason::EmptyCommand, record.as_bytes())
+            }
+            Err(kind) => {
+                self.note(tally, source, line, SkipReason::from(&kind), record.as_bytes());
+                let path = source.path.to_path_buf();
+                return tally.errors.push(ParseError { path, line, kind });
+            }
+        };
+        self.push_entry(source, entry, line, Some(record), now, entries, tally);
+    }
+
+    // Print the lines skipped or looking wrong, with their bytes given `--verbose` twice.
+    fn print_skipped<'a>(&self, skips: impl IntoIterator<Item = &'a SkippedLine>) {
+        for skip in skips {
+            eprintln!("{skip}");
+            if verbosity() >= Verbosity::Debug {
+                eprintln!("    bytes: {}", skip.hex());
+            }
+        }
+    }
+
+    // Note with `--verbose` or `--strict` that `line` of `source`, which reads `content`, is
+    // skipped or looks wrong, for `reason`.
+    fn note(
+        &self,
+        tally: &mut Tally,
+        source: &Source,
+        line: usize,
+        reason: SkipReason,
+        content: &[u8],
+    ) {
+        if verbosity() >= Verbosity::Verbose
+            || self.args.input.strict
+            || self.args.log.report.is_some()
+        {
+            let path = source.path.to_path_buf();
+            tally.skips.push(SkippedLine::new(path, line, reason, content));
+        }
+    }
+
+    // Add an entry which started at `line` of `source`, and was parsed from `record` if it is a zsh
+    // one, checking its timestamp, unless a transform drops it.
+    #[allow(clippy::too_many_arguments)]
+    fn push_entry(
+        &self,
+        source: &Source,
+        mut entry: Entry,
+        line: usize,
+        record: Option<&str>,
+        now: i64,
+        entries: &mut Vec<Entry>,
+        tally: &mut Tally,
+    ) {
+        if !(0..=now.saturating_add(FUTURE_SKEW_SECS)).contains(&entry.when) {
+            self.note(tally, source, line, SkipReason::BadTimestamp, entry.cmd.as_bytes());
+            tally.out_of_range.push(line);
+            if self.args.input.clamp_timestamps {
+                entry.when = entry.when.clamp(0, now);
+            }
+        }
+        if entry.cmd.chars().any(is_control) {
+            let content = entry.cmd.as_bytes();
+            self.note(tally, source, line, SkipReason::ControlCharacters, content);
+        }
+        // the newlines of multi-line commands written back the way zsh does
+        let raw = record
+            .filter(|_| {
+                self.args.formats.annotate
+                    || self.args.write.annotate_file.is_some()
+                    || self.args.formats.columns.contains(&Column::Raw)
+            })
+            .map(|record| record.replace('\n', "\\\n"));
+        entry.origin = Some(Origin { path: source.path.clone(), line, raw });
+        tally.parsed += 1;
+        let entry = self
+            .transforms
+            .iter()
+            .try_fold(entry, |entry, transform| transform.apply(entry));
+        entries.extend(entry);
+    }
+
+    // zsh history format is typically: ": timestamp:duration;command", or simply "command". The
+    // command may span multiple lines. zsh only writes the header at the very start of a record,
+    // so the record is matched before trimming, and continuation lines, which are already part of
+    // the record, are never taken for a header.
+    fn parse_zsh_history_line(&self, record: &str) -> Result<Option<Entry>, ParseErrorKind> {
+        let line = record.trim();
+        if line.is_empty() {
+            return Ok(None);
+        }
+
+        if let Some(caps) = HEADER.captures(record.trim_end()) {
+            // only digits, so parsing can only fail by overflowing
+            let Ok(when) = caps[1].parse::<i64>() else {
+                return Err(ParseErrorKind::TimestampOverflow(caps[1].to_string()));
+            };
+            // zsh leaves a header without a command behind after some interrupted edits
+            let cmd = match caps[3].trim().is_empty() {
+                true if self.args.input.keep_empty => "",
+                true => return Ok(None),
+                false => &caps[3],
+            };
+            return Ok(Some(Entry {
+                cmd: cmd.to_string(),
+                when,
+                duration: caps[2].parse().ok(),
+                ..Default::default()
+            }));
+        }
+
+        // If no match, treat the whole line as a command
+        Ok(Some(Entry { cmd: line.to_string(), ..Default::default() }))
+    }
+
+    // zsh treats non-ASCII characters strangely. See also: https://syossan.hateblo.jp/entry/2017/10/09/181928
+    // Also returns whether any invalid bytes had to be replaced.
+    fn decode(bytes: &[u8], encoding: &'static Encoding) -> (String, bool) {
+        let bytes = zsh::unmetafy(bytes);
+        let (line, lossy) = encoding.decode_without_bom_handling(&bytes);
+        (line.into_owned(), lossy)
+    }
+}
+
+// Read the regexes of an `--exclude-file`, one per line, skipping empty lines and `#` comments.
+async fn read_patterns(path: &Path) -> Result<Vec<Regex>> {
+    read_pattern_file(path, Regex::new).await
+}
+
+// Read an `--allowlist` or `--blocklist`, a command prefix or a regex after `re:` per line, as
+// regexes, skipping empty lines and `#` comments.
+async fn read_prefix_list(path: &Path) -> Result<Vec<Regex>> {
+    read_pattern_file(path, |line| match line.strip_prefix("re:") {
+        Some(pattern) => Regex::new(pattern.trim_start()),
+        None => Regex::new(&format!("^{}", regex::escape(line))),
+    })
+    .await
+}
+
+// Read the lines of a file of patterns, and compile them with `compile`.
+async fn read_pattern_file(
+    path: &Path,
+    compile: impl Fn(&str) -> Result<Regex, regex::Error>,
+) -> Result<Vec<Regex>> {
+    read_list(path)
+        .await?
+        .into_iter()
+        .map(|(n, line)| {
+            compile(&line)
+                .with_context(|| format!("invalid regex on line {n} of {}", path.display()))
+        })
+        .collect()
+}
+
+// Read the lines of a list, like a file of patterns, without their surrounding whitespace, and
+// with their line numbers, skipping empty lines and `#` comments.
+async fn read_list(path: &Path) -> Result<Vec<(usize, String)>> {
+    let text = tokio::fs::read_to_string(path)
+        .await
+        .with_context(|| format!("failed to read {}", path.display()))?;
+    let lines = (1..).zip(text.lines()).map(|(n, line)| (n, line.trim()));
+    let lines = lines.filter(|(_, line)| !line.is_empty() && !line.starts_with('#'));
+    Ok(lines.map(|(n, line)| (n, line.to_string())).collect())
+}
+
+// Expand the paths of `args`, read the lists of patterns and commands it names, and open the
+// histories, finding them where they are usually kept if it names none.
+async fn open_sources(args: &mut Uninitialized) -> Result<Vec<Source>> {
+    if !args.input.no_expand {
+        let paths = args
+            .input
+            .zsh_history
+            .iter_mut()
+            .chain(&mut args.write.output)
+            .chain(&mut args.formats.atuin_db)
+            .chain(&mut args.write.backup_dir)
+            .chain(&mut args.write.annotate_file)
+            .chain(&mut args.log.report)
+            .chain(&mut args.filters.exclude_file)
+            .chain(&mut args.filters.expand_aliases)
+            .chain(&mut args.filters.allowlist)
+            .chain(&mut args.filters.blocklist)
+            .chain(&mut args.filters.skip_common_list);
+        for path in paths {
+            *path = expand_path(path, |key| std::env::var_os(key), user_home)?;
+        }
+    }
+    if args.input.stdin {
+        args.input.zsh_history = vec![PathBuf::from("-")];
+    }
+    for path in &args.filters.exclude_file {
+        args.filters.exclude.extend(read_patterns(path).await?);
+    }
+    if let Some(path) = &args.filters.expand_aliases {
+        let text = tokio::fs::read_to_string(path)
+            .await
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        args.filters.aliases = parse_aliases(&text);
+    }
+    if let Some(path) = &args.filters.allowlist {
+        args.filters.allowed = read_prefix_list(path).await?;
+    }
+    if let Some(path) = &args.filters.blocklist {
+        args.filters.blocked = read_prefix_list(path).await?;
+    }
+    if let Some(path) = &args.filters.skip_common_list {
+        args.filters.common = read_list(path).await?.into_iter().map(|(_, line)| line).collect();
+    }
+    if args.input.zsh_history.is_empty() {
+        let path = zsh::history_path(|key| std::env::var_os(key), |path| path.is_file())?;
+        args.input.zsh_history.push(path);
+    }
+
+    let mut sources = Vec::new();
+    for path in &mut args.input.zsh_history {
+        if path == Path::new("-") {
+            sources.push(Source {
+                path: path.as_path().into(),
+                input: Input::reader(io::stdin()),
+                optional: false,
+            });
+            continue;
+        }
+        if !path.exists() {
+            bail!("zsh history file does not exist: {}", path.display());
+        }
+        if path.is_dir() && args.input.from == InputFormat::Xonsh {
+            let sessions = find_xonsh_sessions(path).await?;
+            if sessions.is_empty() {
+                bail!("no xonsh history sessions found in {}", path.display());
+            }
+            for session in sessions {
+                let input = Input::File(File::open(&session).await?);
+                sources.push(Source { path: session.into(), input, optional: true });
+            }
+            continue;
+        }
+        if path.is_dir() {
+            let candidates = zsh::find_histories_in(path).await?;
+            *path = zsh::pick_history(path, candidates, args.input.pick_largest)?;
+            info!("converting {}", path.display());
+        }
+
+        if args.input.include_rotated {
+            for rotated in find_rotated_histories(path).await? {
+                match File::open(&rotated).await {
+                    Ok(file) => sources.push(Source {
+                        path: rotated.into(),
+                        input: Input::File(file),
+                        optional: true,
+                    }),
+                    Err(e) => info!("skipping {}: {e}", rotated.display()),
+                }
+            }
+        }
+        let input = Input::File(File::open(&*path).await?);
+        sources.push(Source {
+            path: path.as_path().into(),
+            input,
+            optional: false,
+        });
+    }
+
+    Ok(sources)
+}
+
+// Fail on arguments which contradict each other, and which clap cannot tell.
+fn check_args(args: &Uninitialized) -> Result<()> {
+    if args.reads_stdin() && args.input.include_rotated {
+        bail!("--include-rotated needs a zsh history file, not stdin");
+    }
+    if args.reads_stdin() && matches!(args.input.from, InputFormat::Histdb | InputFormat::Atuin) {
+        bail!("--from {} needs a database file, not stdin", args.input.from);
+    }
+    if args
+        .input
+        .zsh_history
+        .iter()
+        .filter(|path| *path == Path::new("-"))
+        .count()
+        > 1
+    {
+        bail!("stdin can only be read once");
+    }
+    // what clap cannot check, as not every command has both options
+    let destination = args.write.output.is_some() || args.into_fish;
+    let needs_destination = [
+        ("--backup", args.write.backup),
+        ("--backup-dir", args.write.backup_dir.is_some()),
+        ("--run-merge", args.fish.run_merge),
+        ("--diff", args.fish.diff),
+    ];
+    if let Some((option, _)) = needs_destination.iter().find(|(_, given)| *given && !destination) {
+        bail!("{option} needs a file to write to, --output or --into-fish");
+    }
+    if args.fish.session_name.is_some() && !args.into_fish {
+        bail!("--session-name needs --into-fish");
+    }
+    if args.write.reverse && args.merge {
+        bail!("--reverse does not work with --merge, which orders the history by timestamp");
+    }
+    if args.formats.format != Format::Fish && (args.into_fish || args.merge) {
+        bail!("--into-fish and --merge only work with --format fish");
+    }
+    if args.formats.annotate
+        && !args.formats.format.annotates()
+        && args.write.annotate_file.is_none()
+    {
+        bail!(
+            "--format {} has no room for the annotations of --annotate, write them to a file of \
+             their own with --annotate-file",
+            args.formats.format
+        );
+    }
+    if matches!(args.formats.format, Format::Sqlite | Format::Mcfly) && args.write.output.is_none()
+    {
+        bail!("--format {} needs --output, the database to write into", args.formats.format);
+    }
+    match (args.formats.format, &args.formats.atuin_db) {
+        (Format::Atuin, None) => {
+            bail!("--format atuin needs --atuin-db, the database to write into")
+        }
+        (Format::Atuin, Some(_)) if args.write.output.is_some() => {
+            bail!("--format atuin writes into --atuin-db, not --output")
+        }
+        (format, Some(_)) if format != Format::Atuin => {
+            bail!("--atuin-db only works with --format atuin")
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+// Collect what converting a history yields into its entries and the records skipped.
+async fn collect(
+    converted: impl Stream<Item = Result<Converted>>,
+) -> Result<(Vec<Entry>, Vec<ParseError>)> {
+    let (mut entries, mut errors) = (Vec::new(), Vec::new());
+    let mut converted = pin!(converted);
+    while let Some(converted) = converted.try_next().await? {
+        match converted {
+            Converted::Entry(entry) => entries.push(entry),
+            Converted::Errors(skipped) => errors.extend(skipped),
+        }
+    }
+    Ok((entries, errors))
+}
+
+// What failing to read `source` is reported as. A corrupt archive only shows while reading it.
+fn read_error(source: &Source, compression: Option<Compression>) -> String {
+    match compression {
+        Some(compression) => {
+            format!("failed to decompress {} as {compression}", source.path.display())
+        }
+        None => format!("failed to read {}", source.path.display()),
+    }
+}
+
+// Read a line like `read_until`, but stop buffering the line once it is longer than `max` bytes,
+// keeping only its first bytes, as many as a skipped line shows, and its last few, enough to tell
+// whether it continues on the next line. Returns the number of bytes read and whether the line
+// was too long.
+async fn read_line<R>(reader: &mut R, buf: &mut Vec<u8>, max: usize) -> io::Result<(usize, bool)>
+where
+    R: AsyncBufRead + Unpin,
+{
+    const HEAD: usize = SkippedLine::MAX_CONTENT;
+    const TAIL: usize = b"\\\r\n".len();
+
+    let (mut read, mut oversized) = (0, false);
+    loop {
+        let available = reader.fill_buf().await?;
+        if available.is_empty() {
+            break; // EOF
+        }
+        let (chunk, done) = match available.iter().position(|&b| b == b'\n') {
+            Some(i) => (&available[..=i], true),
+            None => (available, false),
+        };
+
+        buf.extend_from_slice(chunk);
+        // the newline does not count
+        oversized |= buf.len() - usize::from(done) > max;
+        if oversized && buf.len() > HEAD + TAIL {
+            buf.drain(HEAD..buf.len() - TAIL);
+        }
+
+        let len = chunk.len();
+        reader.consume(len);
+        read += len;
+        if done {
+            break;
+        }
+    }
+    Ok((read, oversized))
+}
+
+// Whether the record ends in a way zsh never finishes one: with only (a part of) the header, or
+// in the middle of a multi-line command.
+fn looks_torn(record: &str) -> bool {
+    static PARTIAL_HEADER: LazyLock<Regex> =
+        LazyLock::new(|| Regex::new(r"^:( [0-9]*(:[0-9]*)?;?)?$").unwrap());
+
+    record.ends_with('\\') || PARTIAL_HEADER.is_match(record.trim_end())
+}
+
+// The byte sequences in `bytes` which are not valid in `encoding`.
+fn invalid_sequences<'a>(bytes: &'a [u8], encoding: &'static Encoding) -> Vec<&'a [u8]> {
+    let mut decoder = encoding.new_decoder_without_bom_handling();
+    let mut decoded = String::with_capacity(bytes.len() * 3 + 16);
+    let mut sequences = Vec::new();
+
+    let mut rest = bytes;
+    loop {
+        decoded.clear();
+        let (result, read) = decoder.decode_to_string_without_replacement(rest, &mut decoded, true);
+        match result {
+            DecoderResult::InputEmpty => break,
+            DecoderResult::OutputFull => {}
+            DecoderResult::Malformed(len, after) => {
+                let end = read.saturating_sub(after as usize);
+                sequences.push(&rest[end.saturating_sub(len as usize)..end]);
+            }
+        }
+        rest = &rest[read..];
+    }
+    sequences
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A zsh history of `n` commands, `echo 0` to `echo n-1`, a second apart.
+    fn history(n: usize) -> String {
+        (0..n)
+            .map(|i| format!(": {}:0;echo {i}\n", 1_700_000_000 + i))
+            .collect()
+    }
+
+    fn commands(entries: &[Entry]) -> Vec<&str> {
+        entries.iter().map(|entry| entry.cmd.as_str()).collect()
+    }
+
+    #[tokio::test]
+    async fn long_lines_keep_their_ends() {
+        let line = format!("{}{}\\\r\n", "a".repeat(300), "z".repeat(300));
+        let mut buf = Vec::new();
+        let read = read_line(&mut line.as_bytes(), &mut buf, 100).await.unwrap();
+        assert_eq!(read, (line.len(), true));
+        assert_eq!(buf, [&[b'a'; 256][..], b"\\\r\n"].concat());
+
+        let mut buf = Vec::new();
+        let read = read_line(&mut &b"short\nnext\n"[..], &mut buf, 100).await.unwrap();
+        assert_eq!((read, &buf[..]), ((6, false), &b"short\n"[..]));
+    }
+
+    #[tokio::test]
+    async fn oversized_records_show_their_start() {
+        let history = format!(
+            ": 1700000000:0;echo {} \\\n{}\n: 1700000001:0;ls\n",
+            "a".repeat(100_000),
+            "b".repeat(10),
+        );
+        let args = Uninitialized {
+            input: InputArgs { max_line_bytes: Some(1000), ..Default::default() },
+            log: LogArgs {
+                report: Some("report.json".into()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let converter = Converter::with_args_and_bytes(args, history.into_bytes()).unwrap();
+        let entries = converter.convert().await.unwrap();
+        assert_eq!(commands(&entries), ["ls"]);
+        let skipped = &converter.report().skipped_lines;
+        assert_eq!(skipped.len(), 1);
+        assert_eq!(skipped[0].reason, SkipReason::Oversized);
+        let start = format!(": 1700000000:0;echo {}", "a".repeat(236));
+        assert_eq!(String::from_utf8_lossy(&skipped[0].content), start);
+    }
+
+    #[tokio::test]
+    async fn tail_stops_at_max_entries() {
+        let args = Uninitialized {
+            filters: FilterArgs { tail: Some(3), ..Default::default() },
+            input: InputArgs { max_entries: 10, ..Default::default() },
+            ..Default::default()
+        };
+        let converter = Converter::with_args_and_bytes(args, history(1000).into_bytes()).unwrap();
+        let entries = converter.convert().await.unwrap();
+        let (entries, _) = crate::tail(entries, 3);
+        assert_eq!(commands(&entries), ["echo 7", "echo 8", "echo 9"]);
+        assert_eq!(converter.report().lines, 10);
+    }
+
+    #[tokio::test]
+    async fn max_entries_counts_before_the_transforms() {
+        // the second and fourth entries are redacted away, but still count
+        let cmds = ["ls", "GITHUB_TOKEN=abc123 gh pr list", "pwd", "DB_PASSWORD=x ./run", "cd"];
+        let zsh = cmds.iter().map(|cmd| format!(": 1700000000:0;{cmd}\n"));
+        let fish = cmds.iter().map(|cmd| format!("- cmd: {cmd}\n  when: 1700000000\n"));
+        let histories = [(InputFormat::Zsh, zsh.collect()), (InputFormat::Fish, fish.collect())];
+        for (from, history) in histories {
+            let args = Uninitialized {
+                input: InputArgs { from, max_entries: 4, ..Default::default() },
+                filters: FilterArgs {
+                    redact: Some(Redaction::Skip),
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+            let history = String::into_bytes(history);
+            let converter = Converter::with_args_and_bytes(args, history).unwrap();
+            let entries = converter.convert().await.unwrap();
+            assert_eq!(commands(&entries), ["ls", "pwd"], "--from {from:?}");
+            assert_eq!(converter.report().entries, 4, "--from {from:?}");
+        }
+    }
+
+    #[tokio::test]
+    async fn head_stops_reading() {
+        let args = Uninitialized {
+            filters: FilterArgs { head: Some(2), ..Default::default() },
+            ..Default::default()
+        };
+        let converter = Converter::with_args_and_bytes(args, history(100).into_bytes()).unwrap();
+        let entries = converter.convert().await.unwrap();
+        assert_eq!(commands(&entries), ["echo 0", "echo 1"]);
+        assert_eq!(converter.report().lines, 2);
+    }
+
+    #[tokio::test]
+    async fn tail_keeps_a_bounded_window() {
+        let args = Uninitialized {
+            filters: FilterArgs { tail: Some(5), ..Default::default() },
+            ..Default::default()
+        };
+        let converter = Converter::with_args_and_bytes(args, history(1000).into_bytes()).unwrap();
+        let entries = converter.convert().await.unwrap();
+        assert!(entries.len() < 10, "kept {} entries", entries.len());
+        let (entries, _) = crate::tail(entries, 5);
+        assert_eq!(
+            commands(&entries),
+            ["echo 995", "echo 996", "echo 997", "echo 998", "echo 999"]
+        );
+    }
+
+    #[tokio::test]
+    async fn since_reads_everything_for_tail() {
+        // `--since` filters first, so `--tail` cannot be applied while reading
+        let since = Some(1_700_000_000);
+        let args = Uninitialized {
+            filters: FilterArgs { tail: Some(2), since, ..Default::default() },
+            ..Default::default()
+        };
+        let converter = Converter::with_args_and_bytes(args, history(100).into_bytes()).unwrap();
+        assert_eq!(converter.convert().await.unwrap().len(), 100);
+    }
+
+    #[tokio::test]
+    async fn tail_counts_multiline_commands_once() {
+        let history = ": 1:0;ls\n: 2:0;echo a\\\necho b\n: 3:0;pwd\\\ncd\n";
+        let args = Uninitialized {
+            filters: FilterArgs { tail: Some(2), ..Default::default() },
+            ..Default::default()
+        };
+        let converter = Converter::with_args_and_bytes(args, history.as_bytes()).unwrap();
+        let (entries, _) = crate::tail(converter.convert().await.unwrap(), 2);
+        assert_eq!(commands(&entries), ["echo a\necho b", "pwd\ncd"]);
+    }
+
+    // The commands and durations of the entries.
+    fn durations(entries: &[Entry]) -> Vec<(&str, Option<i64>)> {
+        entries
+            .iter()
+            .map(|entry| (entry.cmd.as_str(), entry.duration))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn durations_are_parsed() {
+        let history = concat!(
+            ": 1700000000:0;ls\n",
+            ": 1700000001:42;make build\n",
+            ": 1700000002:3600;sleep 3600\n",
+            ": 1700000003:7;42;echo digits and a semicolon\n",
+            ": 1700000004:0;1;2\n",
+        );
+        let entries = Converter::from_text(history).convert().await.unwrap();
+        assert_eq!(
+            durations(&entries),
+            [
+                ("ls", Some(0)),
+                ("make build", Some(42)),
+                ("sleep 3600", Some(3600)),
+                ("42;echo digits and a semicolon", Some(7)),
+                ("1;2", Some(0)),
+            ],
+        );
+        let whens = entries.iter().map(|entry| entry.when).collect::<Vec<_>>();
+        assert_eq!(whens, (1_700_000_000..1_700_000_005).collect::<Vec<_>>());
+    }
+
+    #[tokio::test]
+    async fn plain_lines_have_no_duration() {
+        let entries = Converter::from_text("ls -l\n").convert().await.unwrap();
+        assert_eq!(durations(&entries), [("ls -l", None)]);
+        // too long to be a duration, but still a header
+        let history = ": 1700000000:99999999999999999999;ls\n";
+        let entries = Converter::from_text(history).convert().await.unwrap();
+        assert_eq!(durations(&entries), [("ls", None)]);
+    }
+
+    #[tokio::test]
+    async fn only_record_starts_are_headers() {
+        let history = concat!(
+            ": 123:foo; echo hi\n",
+            ": 1700000000:0;cat <<EOF\\\n",
+            ": 1699999999:0;pasted from a log\\\n",
+            "EOF\n",
+            ": 1700000001:5;ls\n",
+        );
+        let entries = Converter::from_text(history).convert().await.unwrap();
+        let parsed = entries
+            .iter()
+            .map(|entry| (entry.cmd.as_str(), entry.when))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            parsed,
+            [
+                // the `:` builtin, with arguments which only look like a header
+                (": 123:foo; echo hi", 0),
+                ("cat <<EOF\n: 1699999999:0;pasted from a log\nEOF", 1_700_000_000),
+                ("ls", 1_700_000_001),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn headers_need_digits_on_both_sides() {
+        let history = ": 1700000000;ls\n: :0;ls\n: 17e8:0;ls\n:1700000000:0;ls\n";
+        let entries = Converter::from_text(history).convert().await.unwrap();
+        assert!(entries.iter().all(|entry| entry.when == 0));
+        assert_eq!(commands(&entries), history.lines().collect::<Vec<_>>());
+    }
+
+    fn now() -> i64 {
+        SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as i64
+    }
+
+    #[tokio::test]
+    async fn timestamps_from_the_epoch_to_now_are_valid() {
+        let now = now();
+        let history = format!(": 0:0;epoch\n: {now}:0;now\n");
+        let args = Uninitialized {
+            log: LogArgs {
+                report: Some("report.json".into()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let converter = Converter::with_args_and_bytes(args, history.into_bytes()).unwrap();
+        let entries = converter.convert().await.unwrap();
+        assert_eq!(whens(&entries), [(0, "epoch"), (now, "now")]);
+        assert!(converter.report().skipped_lines.is_empty());
+    }
+
+    #[tokio::test]
+    async fn future_timestamps_are_reported_or_clamped() {
+        let history = ": 1700000000:0;ls\n: 99999999999999:0;future\n";
+        let args = Uninitialized {
+            log: LogArgs {
+                report: Some("report.json".into()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let converter = Converter::with_args_and_bytes(args, history.as_bytes().to_vec()).unwrap();
+        let entries = converter.convert().await.unwrap();
+        assert_eq!(whens(&entries), [(1_700_000_000, "ls"), (99_999_999_999_999, "future")]);
+        let skipped = converter.report().skipped_lines;
+        assert_eq!((skipped[0].line, skipped[0].reason), (2, SkipReason::BadTimestamp));
+
+        let args = Uninitialized {
+            input: InputArgs { clamp_timestamps: true, ..Default::default() },
+            ..Default::default()
+        };
+        let converter = Converter::with_args_and_bytes(args, history.as_bytes().to_vec()).unwrap();
+        let entries = converter.convert().await.unwrap();
+        let now = now();
+        assert!((now - 60..=now).contains(&entries[1].when), "{}", entries[1].when);
+    }
+
+    #[tokio::test]
+    async fn overflowing_timestamps_are_errors() {
+        let history = format!(": 1700000000:0;ls\n: {}0:0;overflow\n", i64::MAX);
+        let (entries, errors) = Converter::from_text(&history).convert_with_errors().await.unwrap();
+        // not taken for a command without a header
+        assert_eq!(commands(&entries), ["ls"]);
+        assert_eq!(errors[0].line, 2);
+        assert!(matches!(&errors[0].kind, ParseErrorKind::TimestampOverflow(when)
+            if *when == format!("{}0", i64::MAX)));
+
+        // the largest one parses, and is in the future
+        let history = format!(": {}:0;max\n", i64::MAX);
+        let args = Uninitialized {
+            input: InputArgs { clamp_timestamps: true, ..Default::default() },
+            ..Default::default()
+        };
+        let converter = Converter::with_args_and_bytes(args, history.into_bytes()).unwrap();
+        let entries = converter.convert().await.unwrap();
+        assert!(entries[0].when <= now());
+    }
+
+    #[tokio::test]
+    async fn strict_fails_on_bad_timestamps() {
+        let history = ": 1700000000:0;ls\n: 99999999999999:0;future\n";
+        let args = Uninitialized {
+            input: InputArgs { strict: true, ..Default::default() },
+            ..Default::default()
+        };
+        let converter = Converter::with_args_and_bytes(args, history.as_bytes().to_vec()).unwrap();
+        let err = converter.convert().await.unwrap_err();
+        assert_eq!(err.to_string(), "1 lines could not be cleanly converted, wrote nothing");
+    }
+
+    #[tokio::test]
+    async fn prefix_lists_are_literal_unless_regexes() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("list");
+        let list = "# internal tooling\n\nvault \n  ./deploy.sh --token\nre: ^curl .*-H\n";
+        std::fs::write(&path, list).unwrap();
+        let patterns = read_prefix_list(&path).await.unwrap();
+        let matched = |cmd: &str| patterns.iter().any(|pattern| pattern.is_match(cmd));
+        // trimmed, so `vault ` is the prefix `vault`
+        assert!(matched("vault login") && matched("vaulted"));
+        assert!(matched("./deploy.sh --token abc") && !matched("x./deploy.sh --token"));
+        assert!(matched("curl -s -H 'X: y'") && !matched("curl -s"));
+        assert!(!matched("# internal tooling") && !matched("ls"));
+    }
+
+    #[tokio::test]
+    async fn bad_list_lines_are_named() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("list");
+        std::fs::write(&path, "vault\n# a comment\nre:(unclosed\n").unwrap();
+        let error = read_prefix_list(&path).await.unwrap_err();
+        assert_eq!(error.to_string(), format!("invalid regex on line 3 of {}", path.display()));
+    }
+
+    #[tokio::test]
+    async fn empty_commands_are_skipped() {
+        let history = ": 1700000000:0;\n: 1700000001:0;   \n: 1700000002:0;ls\n   \n";
+        let args = Uninitialized {
+            log: LogArgs {
+                report: Some("report.json".into()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let converter = Converter::with_args_and_bytes(args, history.as_bytes().to_vec()).unwrap();
+        let entries = converter.convert().await.unwrap();
+        assert_eq!(commands(&entries), ["ls"]);
+        // the headers without a command are reported, the blank line is not
+        let skipped = converter.report().skipped_lines;
+        let reasons = skipped
+            .iter()
+            .map(|skip| (skip.line, skip.reason))
+            .collect::<Vec<_>>();
+        assert_eq!(reasons, [(1, SkipReason::EmptyCommand), (2, SkipReason::EmptyCommand)]);
+    }
+
+    #[tokio::test]
+    async fn empty_commands_are_kept_when_asked() {
+        let history = ": 1700000000:0;\n: 1700000001:0;   \n: 1700000002:0;ls\n";
+        let args = Uninitialized {
+            input: InputArgs { keep_empty: true, ..Default::default() },
+            ..Default::default()
+        };
+        let converter = Converter::with_args_and_bytes(args, history.as_bytes().to_vec()).unwrap();
+        let entries = converter.convert().await.unwrap();
+        assert_eq!(commands(&entries), ["", "", "ls"]);
+        assert_eq!(entries[1].when, 1_700_000_001);
+    }
+
+    #[test]
+    fn torn_records() {
+        for torn in [":", ": ", ": 17", ": 1700000000", ": 1700000000:", ": 1700000000:0;"] {
+            assert!(looks_torn(torn), "{torn:?}");
+        }
+        assert!(looks_torn(": 1700000000:0;for i in 1\\\necho \\"));
+        assert!(!looks_torn(": 1700000000:0;ls"));
+        assert!(!looks_torn("ls"));
+    }
+
+    #[tokio::test]
+    async fn cut_off_histories_have_no_junk() {
+        let history = ": 1700000000:0;echo café\n: 1700000001:0;for i in 1\\\necho $i\\\nend\n";
+        let full = Converter::from_text(history).convert().await.unwrap();
+        // wherever zsh is while appending, only a prefix of the last command may be left
+        for cut in 0..=history.len() {
+            let converter = Converter::from_bytes(&history.as_bytes()[..cut]);
+            let (entries, _) = converter.convert_with_errors().await.unwrap();
+            for (i, entry) in entries.iter().enumerate() {
+                let whole = &full[i];
+                let ok = match i + 1 == entries.len() {
+                    true => whole.cmd.starts_with(&entry.cmd) && !entry.cmd.ends_with('\\'),
+                    false => entry.cmd == whole.cmd,
+                };
+                assert!(ok && entry.when == whole.when, "cut at {cut}: {entry:?}");
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn torn_records_are_reported_or_included() {
+        let history = b": 1700000000:0;ls\n: 1700000001:0;echo caf\xc3".to_vec();
+        let args = Uninitialized {
+            log: LogArgs {
+                report: Some("report.json".into()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let converter = Converter::with_args_and_bytes(args, history.clone()).unwrap();
+        let (entries, errors) = converter.convert_with_errors().await.unwrap();
+        assert_eq!(commands(&entries), ["ls"]);
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(errors[0].kind, ParseErrorKind::Incomplete));
+        let skipped = converter.report().skipped_lines;
+        let reasons = skipped
+            .iter()
+            .map(|skip| (skip.line, skip.reason))
+            .collect::<Vec<_>>();
+        assert_eq!(reasons, [(2, SkipReason::InvalidEncoding), (2, SkipReason::Incomplete)]);
+
+        let args = Uninitialized {
+            input: InputArgs { include_partial: true, ..Default::default() },
+            ..Default::default()
+        };
+        let converter = Converter::with_args_and_bytes(args, history).unwrap();
+        let entries = converter.convert().await.unwrap();
+        assert_eq!(commands(&entries), ["ls", "echo caf\u{fffd}"]);
+    }
+
+    #[tokio::test]
+    async fn unmetafies_commands() {
+        let mut history = b": 1700000000:0;echo ".to_vec();
+        history.extend(zsh::metafy("日本 😀".as_bytes()));
+        history.push(b'\n');
+        let entries = Converter::from_bytes(&history).convert().await.unwrap();
+        assert_eq!(commands(&entries), ["echo 日本 😀"]);
+    }
+
+    // The timestamps and commands of the entries.
+    fn whens(entries: &[Entry]) -> Vec<(i64, &str)> {
+        entries.iter().map(|entry| (entry.when, entry.cmd.as_str())).collect()
+    }
+
+    #[tokio::test]
+    async fn nested_continuations_join_into_one_entry() {
+        let history = concat!(
+            ": 1700000000:0;for d in a b\\\n",
+            "do\\\n",
+            "  for f in $d/*\\\n",
+            "  do echo $f\\\n",
+            "  done\\\n",
+            "done\n",
+            ": 1700000001:0;ls\n",
+        );
+        let entries = Converter::from_text(history).convert().await.unwrap();
+        let cmd = "for d in a b\ndo\n  for f in $d/*\n  do echo $f\n  done\ndone";
+        // the time of the first line, and no entry for the last one on its own
+        assert_eq!(whens(&entries), [(1_700_000_000, cmd), (1_700_000_001, "ls")]);
+
+        let mut fish = Vec::new();
+        crate::output::write_entries(&mut crate::output::Fish, &mut fish, &entries[..1])
+            .await
+            .unwrap();
+        let fish = String::from_utf8(fish).unwrap();
+        assert!(fish.starts_with("- cmd: for d in a b\\ndo\\n  for f in"), "{fish}");
+    }
+
+    #[tokio::test]
+    async fn continuation_on_the_last_line() {
+        let history = ": 1700000000:0;ls\n: 1700000001:0;echo a\\\necho b\\\n";
+        let args = Uninitialized {
+            log: LogArgs {
+                report: Some("report.json".into()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let converter = Converter::with_args_and_bytes(args, history.as_bytes()).unwrap();
+        let entries = converter.convert().await.unwrap();
+        // kept as far as it goes, and reported
+        let expected = [(1_700_000_000, "ls"), (1_700_000_001, "echo a\necho b")];
+        assert_eq!(whens(&entries), expected);
+        let skipped = converter.report().skipped_lines;
+        assert_eq!((skipped[0].line, skipped[0].reason), (2, SkipReason::MultilineFragment));
+    }
+
+    #[tokio::test]
+    async fn crlf_line_endings_are_stripped() {
+        let history = ": 1700000000:0;echo hi\r\n: 1700000001:0;ls\r\n";
+        let entries = Converter::from_text(history).convert().await.unwrap();
+        assert_eq!(whens(&entries), [(1_700_000_000, "echo hi"), (1_700_000_001, "ls")]);
+
+        // on some lines only, and after the backslash of a continuation
+        let history = concat!(
+            ": 1700000000:0;echo hi\n",
+            ": 1700000001:0;for i in 1\\\r\n",
+            "do echo $i\\\n",
+            "done\r\n",
+            ": 1700000002:0;ls\n",
+        );
+        let entries = Converter::from_text(history).convert().await.unwrap();
+        assert_eq!(
+            whens(&entries),
+            [
+                (1_700_000_000, "echo hi"),
+                (1_700_000_001, "for i in 1\ndo echo $i\ndone"),
+                (1_700_000_002, "ls"),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn a_leading_bom_is_skipped() {
+        let history = "\u{feff}: 1700000000:0;echo hi\r\n: 1700000001:0;ls\n";
+        let entries = Converter::from_text(history).convert().await.unwrap();
+        assert_eq!(whens(&entries), [(1_700_000_000, "echo hi"), (1_700_000_001, "ls")]);
+        // only at the very start of the file
+        let entries = Converter::from_text("ls\n\u{feff}pwd\n").convert().await.unwrap();
+        assert_eq!(commands(&entries), ["ls", "\u{feff}pwd"]);
+    }
+
+    #[tokio::test]
+    async fn a_huge_line_is_read_in_constant_memory() {
+        const HUGE: u64 = 10 * 1024 * 1024;
+        let mut reader =
+            tokio::io::BufReader::new(tokio::io::repeat(b'x').take(HUGE).chain(&b"\nls\n"[..]));
+        let mut buf = Vec::new();
+        let read = read_line(&mut reader, &mut buf, 1000).await.unwrap();
+        assert_eq!(read, (HUGE as usize + 1, true));
+        // bounded by the limit and the reader's buffer, not by the length of the line
+        assert!(buf.capacity() < 64 * 1024, "{}", buf.capacity());
+        // and reading goes on after the newline
+        buf.clear();
+        let read = read_line(&mut reader, &mut buf, 1000).await.unwrap();
+        assert_eq!((read, &buf[..]), ((3, false), &b"ls\n"[..]));
+    }
+
+    #[tokio::test]
+    async fn huge_records_are_skipped_with_their_offset() {
+        let reader = b": 1700000000:0;pwd\n"
+            .chain(tokio::io::repeat(b'x').take(10 * 1024 * 1024))
+            .chain(&b"\n: 1700000001:0;ls\n"[..]);
+        let converter = Converter::with_args_and_reader(Uninitialized::default(), reader).unwrap();
+        let (entries, errors) = converter.convert_with_errors().await.unwrap();
+        assert_eq!(commands(&entries), ["pwd", "ls"]);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].line, 2);
+        assert_eq!(
+            errors[0].kind.to_string(),
+            "record at byte offset 19 is longer than 1048576 bytes"
+        );
+    }
+
+    #[tokio::test]
+    async fn the_limit_applies_to_whole_records() {
+        // every line is short, the command they make up is not
+        let lines = (0..20).map(|_| "x".repeat(90)).collect::<Vec<_>>();
+        let history = format!(": 1700000000:0;{}\n: 1700000001:0;ls\n", lines.join("\\\n"));
+        let args = Uninitialized {
+            input: InputArgs { max_line_bytes: Some(1000), ..Default::default() },
+            ..Default::default()
+        };
+        let converter = Converter::with_args_and_bytes(args, history.into_bytes()).unwrap();
+        let (entries, errors) = converter.convert_with_errors().await.unwrap();
+        assert_eq!(commands(&entries), ["ls"]);
+        assert_eq!((errors.len(), errors[0].line), (1, 1));
 
-        // assuming we now have a valid UTF-8 string
-        String::from_utf8_lossy(&buf).into_owned()
+        let args = Uninitialized {
+            input: InputArgs { max_line_bytes: Some(1000), ..Default::default() },
+            ..Default::default()
+        };
+        let history = format!(": 1700000000:0;{}\n", "x".repeat(985));
+        let converter = Converter::with_args_and_bytes(args, history.into_bytes()).unwrap();
+        assert_eq!(converter.convert().await.unwrap().len(), 1);
     }
 }
@@ -0,0 +1,247 @@
+//! The list `--interactive` shows on the terminal to pick the entries to write from, drawn on
+//! stderr, so the entries written to stdout stay clean.
+
+use anyhow::Result;
+
+use crate::history_converter::Entry;
+
+/// Show `entries` on the terminal, newest first, to pick the ones to write, and return them, in
+/// their order, or `None` if the user aborted. Blocks until the user is done.
+///
+/// The keys are the arrows, Page Up, and Page Down to move, Shift with the arrows to pick or
+/// leave out a range, Space to toggle an entry, `a` to pick all entries shown, `n` none of them,
+/// `/` to filter them as you type, Enter to write the picked ones, and `q`, Escape, or Ctrl-C to abort.
+#[cfg(feature = "interactive")]
+pub fn review(entries: &[Entry]) -> Result<Option<Vec<Entry>>> {
+    use std::io::IsTerminal;
+
+    use anyhow::bail;
+    use crossterm::{cursor, execute, terminal};
+
+    if !std::io::stderr().is_terminal() {
+        bail!("--interactive needs stderr to be a terminal");
+    }
+
+    let mut stderr = std::io::stderr();
+    terminal::enable_raw_mode()?;
+    execute!(stderr, terminal::EnterAlternateScreen, cursor::Hide)?;
+    let reviewed = run(entries, &mut stderr);
+    // restore the terminal, whatever happened
+    let restored = execute!(stderr, cursor::Show, terminal::LeaveAlternateScreen);
+    terminal::disable_raw_mode()?;
+    restored?;
+    reviewed
+}
+
+#[cfg(feature = "interactive")]
+fn run(entries: &[Entry], out: &mut impl std::io::Write) -> Result<Option<Vec<Entry>>> {
+    use crossterm::{
+        event::{self, Event, KeyEventKind},
+        terminal,
+    };
+
+    use crate::select::Selection;
+
+    let mut selection = Selection::new(entries);
+    let (mut offset, mut filtering) = (0, false);
+    loop {
+        let (width, height) = terminal::size()?;
+        // the header takes a line
+        let rows = usize::from(height).saturating_sub(1).max(1);
+        let cursor = selection.cursor();
+        offset = offset.clamp(cursor.saturating_sub(rows - 1), cursor);
+        draw(out, &selection, offset, rows, width.into(), filtering)?;
+
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind == KeyEventKind::Release {
+            continue;
+        }
+        if let Some(reviewed) = press(&mut selection, &mut filtering, key, rows) {
+            return Ok(reviewed);
+        }
+    }
+}
+
+// Act on `key` pressed, with `rows` entries shown at once, and `filtering` while typing the
+// filter. Returns what `review` returns once the user is done, and `None` until then.
+#[cfg(feature = "interactive")]
+fn press(
+    selection: &mut crate::select::Selection,
+    filtering: &mut bool,
+    key: crossterm::event::KeyEvent,
+    rows: usize,
+) -> Option<Option<Vec<Entry>>> {
+    use crossterm::event::{KeyCode, KeyModifiers};
+
+    let shift = key.modifiers.contains(KeyModifiers::SHIFT);
+    match key.code {
+        // before typing the filter takes it as a `c`
+        KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => return Some(None),
+        KeyCode::Up if shift => selection.extend_up(),
+        KeyCode::Down if shift => selection.extend_down(),
+        KeyCode::Up => selection.up(1),
+        KeyCode::Down => selection.down(1),
+        KeyCode::PageUp => selection.up(rows),
+        KeyCode::PageDown => selection.down(rows),
+        KeyCode::Home => selection.up(usize::MAX),
+        KeyCode::End => selection.down(usize::MAX),
+        // Enter and Escape end typing the filter, keeping it
+        KeyCode::Enter | KeyCode::Esc if *filtering => *filtering = false,
+        KeyCode::Backspace if *filtering => {
+            let mut filter = selection.filter().to_string();
+            filter.pop();
+            selection.set_filter(&filter);
+        }
+        KeyCode::Char(c) if *filtering => {
+            selection.set_filter(&format!("{}{c}", selection.filter()));
+        }
+        KeyCode::Char(' ') => selection.toggle(),
+        KeyCode::Char('a') => selection.pick_all(),
+        KeyCode::Char('n') => selection.pick_none(),
+        KeyCode::Char('/') => *filtering = true,
+        KeyCode::Char('q') | KeyCode::Esc => return Some(None),
+        KeyCode::Enter => return Some(Some(selection.picked())),
+        _ => {}
+    }
+    None
+}
+
+// Draw the header and the `rows` entries shown from `offset` on, `width` columns wide.
+#[cfg(feature = "interactive")]
+fn draw(
+    out: &mut impl std::io::Write,
+    selection: &crate::select::Selection,
+    offset: usize,
+    rows: usize,
+    width: usize,
+    filtering: bool,
+) -> Result<()> {
+    use chrono::{Local, TimeZone};
+    use crossterm::{
+        cursor::MoveTo,
+        queue,
+        style::{Attribute, Print, SetAttribute},
+        terminal::{Clear, ClearType},
+    };
+
+    use crate::{filter::escape_controls, output::truncate_to_width};
+
+    let picked = selection.picked_count();
+    let header = match (filtering, selection.filter()) {
+        (true, filter) => format!("/{filter}"),
+        (false, "") => {
+            format!("{picked} picked: space toggle, a all, n none, / filter, enter write, q abort")
+        }
+        (false, filter) => format!("{picked} picked, of those containing {filter:?}"),
+    };
+    queue!(out, Clear(ClearType::All), MoveTo(0, 0), SetAttribute(Attribute::Bold))?;
+    queue!(out, Print(truncate_to_width(&header, width)), SetAttribute(Attribute::Reset))?;
+
+    let shown = selection.shown();
+    for (row, (i, &index)) in shown.iter().enumerate().skip(offset).take(rows).enumerate() {
+        let entry = &selection.entries()[index];
+        let mark = match selection.is_picked(index) {
+            true => "[x]",
+            false => "[ ]",
+        };
+        let time = match Local.timestamp_opt(entry.when, 0).single() {
+            Some(time) if entry.when != 0 => time.format("%Y-%m-%d %H:%M").to_string(),
+            _ => format!("{:16}", "-"),
+        };
+        // the first line of a multi-line command, marked as such
+        let cmd = escape_controls(&entry.cmd.replace('\t', " "));
+        let cmd = match cmd.split_once('\n') {
+            Some((first, _)) => format!("{first} ↩"),
+            None => cmd,
+        };
+        let line = format!("{mark} {time}  {cmd}");
+        queue!(out, MoveTo(0, (row + 1) as u16))?;
+        if i == selection.cursor() {
+            queue!(out, SetAttribute(Attribute::Reverse))?;
+        }
+        queue!(out, Print(truncate_to_width(&line, width)), SetAttribute(Attribute::Reset))?;
+    }
+    if shown.is_empty() {
+        queue!(out, MoveTo(0, 1), Print(truncate_to_width("no entries shown", width)))?;
+    }
+    out.flush()?;
+    Ok(())
+}
+
+/// Fail, as this build cannot show the list of `--interactive`.
+#[cfg(not(feature = "interactive"))]
+pub fn review(_: &[Entry]) -> Result<Option<Vec<Entry>>> {
+    Err(anyhow::anyhow!(
+        "this build cannot show --interactive; rebuild with the `interactive` feature"
+    ))
+}
+
+#[cfg(all(test, feature = "interactive"))]
+mod tests {
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+    use super::*;
+    use crate::select::Selection;
+
+    fn entries() -> Vec<Entry> {
+        ["ls", "git push", "git pull"]
+            .iter()
+            .zip(1..)
+            .map(|(cmd, when)| Entry { cmd: cmd.to_string(), when, ..Default::default() })
+            .collect()
+    }
+
+    // Press each of `keys` until one ends the run, returning the commands it wrote.
+    fn press_all(entries: &[Entry], keys: &[KeyEvent]) -> Option<Option<Vec<String>>> {
+        let mut selection = Selection::new(entries);
+        let mut filtering = false;
+        let reviewed = keys
+            .iter()
+            .find_map(|&key| press(&mut selection, &mut filtering, key, 10));
+        reviewed.map(|picked| Some(picked?.into_iter().map(|entry| entry.cmd).collect()))
+    }
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    #[test]
+    fn enter_writes_the_picked_entries() {
+        let entries = entries();
+        let keys = [key(KeyCode::Char(' ')), key(KeyCode::Enter)];
+        // the newest, under the cursor, was left out
+        assert_eq!(press_all(&entries, &keys), Some(Some(vec!["ls".into(), "git push".into()])));
+    }
+
+    #[test]
+    fn aborting_writes_nothing() {
+        let entries = entries();
+        for abort in [key(KeyCode::Char('q')), key(KeyCode::Esc)] {
+            assert_eq!(press_all(&entries, &[key(KeyCode::Char(' ')), abort]), Some(None));
+        }
+        let ctrl_c = KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL);
+        assert_eq!(press_all(&entries, &[ctrl_c]), Some(None));
+        // even while typing the filter, which Escape only ends
+        let keys = [key(KeyCode::Char('/')), key(KeyCode::Char('g')), ctrl_c];
+        assert_eq!(press_all(&entries, &keys), Some(None));
+        let keys = [key(KeyCode::Char('/')), key(KeyCode::Esc)];
+        assert_eq!(press_all(&entries, &keys), None);
+    }
+
+    #[test]
+    fn typing_the_filter_takes_every_character() {
+        let entries = entries();
+        let mut selection = Selection::new(&entries);
+        let mut filtering = false;
+        for c in "/gita".chars() {
+            assert!(press(&mut selection, &mut filtering, key(KeyCode::Char(c)), 10).is_none());
+        }
+        assert_eq!((selection.filter(), selection.picked_count()), ("gita", 3));
+        press(&mut selection, &mut filtering, key(KeyCode::Backspace), 10);
+        press(&mut selection, &mut filtering, key(KeyCode::Enter), 10);
+        assert!(!filtering);
+        assert_eq!(selection.shown(), [2, 1]);
+    }
+}
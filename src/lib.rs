@@ -1,3 +1,104 @@
+mod abbr;
+mod aliases;
+mod atuin;
+mod bash;
+mod check;
+mod completions;
+mod compression;
+mod config;
+mod detect;
+mod diff;
+mod encoding;
+mod error;
+mod expand;
+mod fc;
+mod filter;
+mod fish_history;
+mod format;
+mod histdb;
 mod history_converter;
+mod interactive;
+mod man;
+mod mcfly;
+mod merge;
+mod normalize;
+mod nushell;
+pub mod output;
+mod progress;
+mod psreadline;
+mod redact;
+mod report;
+mod rotated;
+mod select;
+mod sqlite;
+mod stats;
+mod strip;
+mod timestamps;
+mod transform;
+mod verbosity;
+mod writer;
+mod xonsh;
+mod zoxide;
+pub mod zsh;
 
-pub use history_converter::Converter;
+pub use abbr::{suggest_abbreviations, AbbrOptions, Abbreviation};
+pub use aliases::{expand_aliases, parse_aliases, Aliases, ExpandAliases};
+pub use atuin::{read_atuin, write_atuin, ATUIN_VERSION};
+pub use bash::{parse_bash_history, ParsedEntry};
+pub use check::{check_fish_history, Issue, Severity};
+pub use completions::{fish_completions_path, install_fish_completions, write_completions};
+pub use compression::{decompress, Compression};
+pub use config::{config_args, config_path, env_config, ConfigArgs};
+pub use detect::{detect_format, Confidence, Detection};
+pub use diff::render_diff;
+pub use encoding::InputEncoding;
+pub use error::{
+    Failure, FailureContext, ParseError, ParseErrorKind, SkipReason, SkippedLine, EXIT_CODES,
+};
+pub use expand::{expand_path, user_home};
+pub use fc::{parse_fc_listing, UtcOffset};
+pub use filter::{
+    between, command_counts, excluding, first_word, head, limit, matching, parse_time, running,
+    sanitize, skip_common, skip_multiline, skip_short, sort_entries, tail, Sort, COMMON_COMMANDS,
+};
+pub use fish_history::{
+    create_history_dir, escape, fish_history_path, parse_fish_history, read_fish_history,
+    run_history_merge, unescape, validate_session_name, verify_round_trip, Mismatch,
+    DEFAULT_SESSION_NAME, HISTORY_MERGE_ARGS,
+};
+pub use format::{
+    quote, BashOptions, ColorWhen, Column, Format, InputFormat, JsonOptions, Multiline, Options,
+    PlainOptions, PrettyOptions, Table,
+};
+pub use histdb::read_histdb;
+pub use history_converter::{
+    CheckArgs, Cli, Command, CompletionsArgs, ConversionReport, Converter, Entry, FilterArgs,
+    FishArgs, FishToZshArgs, FormatArgs, Initialized, InputArgs, InputReport, LogArgs, ManArgs,
+    MergeArgs, Origin, StatsArgs, Uninitialized, WriteArgs, DEFAULT_MAX_LINE_BYTES,
+};
+pub use interactive::review;
+pub use man::{write_man, write_man_pages};
+pub use mcfly::write_mcfly;
+pub use merge::{
+    merge, merge_histories, normalize_command, skip_existing, unique, DedupBy, DupPolicy, Keep,
+    Merged, Normalization,
+};
+pub use normalize::{normalize_end, Normalize, NormalizeMode};
+pub use nushell::{parse_nushell_history, read_nushell, write_nushell};
+pub use output::{write_entries, OutputFormat};
+pub use psreadline::parse_psreadline_history;
+pub use redact::{find_secrets, redact, Redact, Redaction, MASK};
+pub use report::{
+    DroppedReport, Phase, PhaseTiming, RunReport, RunStatus, Timing, Warning, REPORT_VERSION,
+};
+pub use rotated::{find_rotated_histories, rotated_histories};
+pub use select::Selection;
+pub use sqlite::{database_format, is_database, write_sqlite};
+pub use stats::{stats, Stats, TOP_COMMANDS};
+pub use strip::{strip_prefixes, StripOptions, StripPrefixes};
+pub use timestamps::{bump_duplicates, default_when, interpolate, DefaultWhen};
+pub use transform::{ListFilter, Transform};
+pub use verbosity::{set_verbosity, verbosity, Verbosity};
+pub use writer::{Action, BackupPolicy, Plan, WriteMode, Writer, DEFAULT_LOCK_TIMEOUT};
+pub use xonsh::{find_xonsh_sessions, parse_xonsh_history};
+pub use zoxide::{directory_visits, Visit};
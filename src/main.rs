@@ -1,14 +1,930 @@
-use anyhow::Result;
-use zsh_history_to_fish::Converter;
+use std::{
+    path::Path,
+    pin::pin,
+    process::ExitCode,
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, bail, Context, Result};
+use clap_complete::Shell;
+use futures_util::TryStreamExt;
+use regex::Regex;
+use tokio::{
+    fs::File,
+    io::{AsyncWriteExt, BufReader, BufWriter},
+};
+use zsh_history_to_fish::{
+    between, check_fish_history, command_counts, excluding, expand_path, fish_completions_path,
+    head, info, install_fish_completions, limit, matching, merge, output::Builtin,
+    read_fish_history, render_diff, review, run_history_merge, running, sanitize, skip_common,
+    skip_multiline, skip_short, sort_entries, stats, tail, unique, user_home, verify_round_trip,
+    write_atuin, write_completions, write_man, write_man_pages, CheckArgs, Cli, Command,
+    CompletionsArgs, Converter, DroppedReport, Entry, Failure, FailureContext, Format, Initialized,
+    JsonOptions, Keep, ManArgs, Merged, Mismatch, Normalization, Options, OutputFormat, Phase,
+    RunReport, Severity, Sort, Timing, Uninitialized, WriteMode, Writer, HISTORY_MERGE_ARGS,
+    TOP_COMMANDS,
+};
 
 #[tokio::main]
-async fn main() -> Result<()> {
-    Converter::from_args()
-        .await?
-        .convert()
-        .await?
+async fn main() -> ExitCode {
+    match run().await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            let failure = Failure::of(&error);
+            match failure {
+                // no error, what the diff shows
+                Some(Failure::Changes) => info!("{error:#}"),
+                _ => eprintln!("Error: {error:?}"),
+            }
+            ExitCode::from(failure.map_or(1, Failure::exit_code))
+        }
+    }
+}
+
+// Run the command, printing how long each phase took with `--timing`, and writing the report of
+// the run with `--report`, whether it fails or not.
+async fn run() -> Result<()> {
+    let command = Cli::parse_with_config()?.command();
+    let mut report = RunReport::new();
+    let args = command.history_args();
+    let timing = args.is_some_and(|(_, log)| log.timing);
+    // expanded like the paths the conversion expands, which it only does once it runs
+    let path = match args.and_then(|(input, log)| Some((log.report.as_ref()?, input.no_expand))) {
+        Some((path, false)) => {
+            let path = expand_path(path, |key| std::env::var_os(key), user_home);
+            Some(path.fail_as(Failure::Usage)?)
+        }
+        Some((path, true)) => Some(path.clone()),
+        None => None,
+    };
+    if path.is_none() && !timing {
+        return run_command(command, &mut report).await;
+    }
+    if timing {
+        report.timing = Some(Timing::default());
+    }
+
+    let started = Instant::now();
+    let result = run_command(command, &mut report).await;
+    let took = started.elapsed();
+    report.duration_secs = took.as_secs_f64();
+    if let Some(timing) = &mut report.timing {
+        timing.finish(took, report.read.bytes, report.read.entries);
+        eprint!("{timing}");
+    }
+    if let Err(error) = &result {
+        report.fail(error);
+    }
+    let Some(path) = path else {
+        return result;
+    };
+    let written = report.write_to(&path).await;
+    result.and(written)
+}
+
+async fn run_command(command: Command, report: &mut RunReport) -> Result<()> {
+    match command {
+        Command::Convert(args) => convert(Converter::with_args(*args).await?, report).await,
+        Command::Merge(args) => convert(Converter::with_merge_args(*args).await?, report).await,
+        Command::FishToZsh(args) => {
+            convert(Converter::with_fish_to_zsh_args(*args).await?, report).await
+        }
+        Command::Stats(args) => {
+            let json = args.json;
+            let converter = Converter::with_stats_args(*args).await?;
+            let entries = filtered(&converter, report).await?;
+            let stats = stats(&entries, converter.report(), TOP_COMMANDS);
+            match json {
+                true => println!("{}", serde_json::to_string_pretty(&stats)?),
+                false => print!("{stats}"),
+            }
+            Ok(())
+        }
+        Command::Check(args) => check(&args).await,
+        Command::Completions(args) => completions(&args).await,
+        Command::Man(args) => man(&args).await,
+    }
+}
+
+// The entries `converter` converts, sorted and filtered as its arguments say, noting in `report`
+// what was read, and how many entries each filter dropped.
+async fn filtered(
+    converter: &Converter<Initialized>,
+    report: &mut RunReport,
+) -> Result<Vec<Entry>> {
+    let args = &converter.args;
+    let started = report.start();
+    let converted = converter.convert().await;
+    report.end(Phase::Reading, started);
+    report.set_read(converter.report());
+    let converted = converted?;
+    let (space_prefixed, entries) = (report.read.space_prefixed, report.read.entries);
+    report.note("--ignore-space", space_prefixed);
+    report.note("while reading", entries.saturating_sub(converted.len()));
+    // `--head` and `--tail` pick entries by where they are in the history, so before sorting
+    let started = report.start();
+    let mut entries = converted;
+    if args.filters.since.is_some() || args.filters.until.is_some() {
+        entries = report.by("--since and --until", entries, |entries| {
+            apply_between(
+                entries,
+                args.filters.since,
+                args.filters.until,
+                args.filters.include_undated,
+            )
+        });
+    }
+    if let Some(n) = args.filters.head {
+        entries = report.by("--head", entries, |entries| apply_head(entries, n));
+    }
+    if let Some(n) = args.filters.tail {
+        entries = report.by("--tail", entries, |entries| apply_tail(entries, n));
+    }
+    report.end(Phase::Filtering, started);
+    let started = report.start();
+    entries = sort_entries(entries, args.write.sort);
+    report.end(Phase::Sorting, started);
+    let started = report.start();
+    entries = report
+        .by("--skip-invalid", entries, |entries| apply_sanitize(entries, args.input.skip_invalid));
+    if !args.filters.matches.is_empty() {
+        entries =
+            report.by("--match", entries, |entries| apply_matching(entries, &args.patterns()));
+    }
+    if !args.filters.commands.is_empty() {
+        entries = report
+            .by("--command", entries, |entries| apply_running(entries, &args.filters.commands));
+    }
+    if !args.filters.exclude.is_empty() {
+        entries =
+            report.by("--exclude", entries, |entries| apply_excluding(entries, &args.exclusions()));
+    }
+    if let Some(min_length) = args.filters.min_length {
+        entries =
+            report.by("--min-length", entries, |entries| apply_skip_short(entries, min_length));
+    }
+    if args.filters.skip_common || args.filters.skip_common_list.is_some() {
+        entries = report.by("--skip-common", entries, |entries| {
+            apply_skip_common(entries, &args.common_commands())
+        });
+    }
+    report.end(Phase::Filtering, started);
+    if args.filters.unique {
+        let started = report.start();
+        let before = entries.len();
+        entries = report.by("--unique", entries, |entries| {
+            apply_unique(entries, args.filters.keep, args.normalization())
+        });
+        report.duplicates += before - entries.len();
+        report.end(Phase::Dedup, started);
+    }
+    if args.filters.skip_multiline {
+        let started = report.start();
+        entries = report.by("--skip-multiline", entries, apply_skip_multiline);
+        report.end(Phase::Filtering, started);
+    }
+    Ok(entries)
+}
+
+// Noting in the report of the run how many entries were dropped while reading and by each
+// filter, to explain with `--fail-if-empty` why none are left to write.
+trait Dropping {
+    // The `entries` `filter` leaves, noting how many it dropped.
+    fn by(
+        &mut self,
+        filter: &str,
+        entries: Vec<Entry>,
+        apply: impl FnOnce(Vec<Entry>) -> Vec<Entry>,
+    ) -> Vec<Entry>;
+
+    // Note that `n` more entries were dropped by `what`.
+    fn note(&mut self, what: &str, n: usize);
+
+    // How many entries were dropped by what, as a list to show.
+    fn dropped_by(&self) -> String;
+
+    // Fail as `Failure::Empty`, saying why, if `count` entries are left to write and
+    // `--fail-if-empty` is given.
+    fn fail_if_empty(&self, converter: &Converter<Initialized>, count: usize) -> Result<()>;
+}
+
+impl Dropping for RunReport {
+    fn by(
+        &mut self,
+        filter: &str,
+        entries: Vec<Entry>,
+        apply: impl FnOnce(Vec<Entry>) -> Vec<Entry>,
+    ) -> Vec<Entry> {
+        let before = entries.len();
+        let entries = apply(entries);
+        self.note(filter, before - entries.len());
+        entries
+    }
+
+    fn note(&mut self, what: &str, n: usize) {
+        match self.dropped.iter_mut().find(|dropped| dropped.by == what) {
+            _ if n == 0 => {}
+            Some(dropped) => dropped.entries += n,
+            None => self.dropped.push(DroppedReport { by: what.to_string(), entries: n }),
+        }
+    }
+
+    fn dropped_by(&self) -> String {
+        let by = self.dropped.iter().map(|dropped| match dropped.by.starts_with("--") {
+            true => format!("{} by {}", dropped.entries, dropped.by),
+            false => format!("{} {}", dropped.entries, dropped.by),
+        });
+        by.collect::<Vec<_>>().join(", ")
+    }
+
+    fn fail_if_empty(&self, converter: &Converter<Initialized>, count: usize) -> Result<()> {
+        if count > 0 || !converter.args.write.fail_if_empty {
+            return Ok(());
+        }
+        let read = &self.read;
+        let why = match (read.entries + read.space_prefixed, read.skipped) {
+            (0, 0) => format!("no entries parsed from {} lines", read.lines),
+            (0, skipped) => {
+                format!("no entries parsed from {} lines, {skipped} records skipped", read.lines)
+            }
+            (parsed, _) => format!("{parsed} entries parsed, all dropped: {}", self.dropped_by()),
+        };
+        Err(anyhow!("nothing to write: {why}")).fail_as(Failure::Empty)
+    }
+}
+
+async fn convert(converter: Converter<Initialized>, report: &mut RunReport) -> Result<()> {
+    if let Some(output) = stream_output(&converter.args) {
+        return write_stream(&converter, output, report).await;
+    }
+    if counts_stream(&converter.args) {
+        let count = count_stream(&converter, report).await?;
+        println!("{count}");
+        return report.fail_if_empty(&converter, count);
+    }
+    let mut entries = filtered(&converter, report).await?;
+    if converter.args.list_commands {
+        for (word, count) in command_counts(&entries) {
+            println!("{count:>7} {word}");
+        }
+        return Ok(());
+    }
+    if converter.args.write.interactive {
+        let shown = entries.len();
+        let picked = tokio::task::spawn_blocking(move || review(&entries)).await??;
+        let Some(picked) = picked else {
+            bail!("aborted, nothing written");
+        };
+        info!("picked {} entries", picked.len());
+        report.note("left out with --interactive", shown - picked.len());
+        entries = picked;
+    }
+
+    if let Some(db) = &converter.args.formats.atuin_db {
+        entries = limit_and_reverse(&converter, report, entries, converter.args.write.limit);
+        report.fail_if_empty(&converter, entries.len())?;
+        if converter.args.write.dry_run {
+            eprintln!("would write {} entries into {}", entries.len(), db.display());
+            return Ok(());
+        }
+        report.destination = Some(db.clone());
+        let (db, count) = (db.clone(), entries.len());
+        let started = report.start();
+        let (added, entries) =
+            tokio::task::spawn_blocking(move || write_atuin(&db, &entries).map(|n| (n, entries)))
+                .await?
+                .fail_as(Failure::Destination)?;
+        if let Some(path) = &converter.args.write.annotate_file {
+            write_annotations(path, &entries).await?;
+        }
+        report.end(Phase::Writing, started);
+        info!("added {added} entries, {} were already there", count - added);
+        report.written = added;
+        return Ok(());
+    }
+
+    let Some(path) = converter.args.destination().await.fail_as(Failure::Destination)? else {
+        entries = limit_and_reverse(&converter, report, entries, converter.args.write.limit);
+        if converter.args.write.count {
+            println!("{}", entries.len());
+        }
+        report.fail_if_empty(&converter, entries.len())?;
+        if converter.args.write.count {
+            return Ok(());
+        }
+        if converter.args.write.verify {
+            let started = report.start();
+            verify(&entries).await?;
+            report.end(Phase::Verifying, started);
+        }
+        if converter.args.write.dry_run {
+            eprintln!("would write {} entries to stdout", entries.len());
+            return Ok(());
+        }
+        let mut stdout = BufWriter::new(tokio::io::stdout());
+        let started = report.start();
+        converter
+            .args
+            .formats
+            .format
+            .write_with(&mut stdout, &entries, &converter.args.options())
+            .await
+            .fail_as(Failure::Destination)?;
+        if let Some(path) = &converter.args.write.annotate_file {
+            write_annotations(path, &entries).await?;
+        }
+        report.end(Phase::Writing, started);
+        report.written = entries.len();
+        return Ok(());
+    };
+    report.destination = Some(path.clone());
+
+    let mut existing = None;
+    let mut write_mode = converter.args.write_mode();
+    if converter.args.merge {
+        let started = report.start();
+        let history = read_fish_history(&path).await.fail_as(Failure::Destination)?;
+        report.end(Phase::Reading, started);
+        let started = report.start();
+        let Merged { entries: merged, rewrite, skipped } = merge(
+            &history,
+            entries,
+            converter.args.input.dedup_by,
+            converter.args.input.dup_policy,
+            converter.args.normalization(),
+        );
+        entries = merged;
+        report.duplicates += skipped;
+        report.end(Phase::Dedup, started);
+        if rewrite {
+            write_mode = WriteMode::Overwrite;
+            info!("{skipped} duplicates collapsed, rewriting with {} entries", entries.len());
+        } else {
+            info!("{} new entries, {skipped} duplicates dropped", entries.len());
+            report.note(&format!("already in {}", path.display()), skipped);
+        }
+        existing = Some(history);
+    }
+    // the limit of a merge is kept by the merged history, below
+    let limit = converter.args.write.limit.filter(|_| !converter.args.merge);
+    entries = limit_and_reverse(&converter, report, entries, limit);
+
+    let added = additions(existing.as_deref(), write_mode, &entries).count();
+    if converter.args.write.count {
+        println!("{added}");
+    }
+    report.note(&format!("already in {}", path.display()), entries.len() - added);
+    report.fail_if_empty(&converter, added)?;
+    if converter.args.write.count {
+        return Ok(());
+    }
+    if converter.args.fish.diff {
+        let additions = additions(existing.as_deref(), write_mode, &entries)
+            .cloned()
+            .collect::<Vec<_>>();
+        print!("{}", render_diff(&path, &additions, converter.args.fish.diff_limit));
+        if !additions.is_empty() {
+            return Err(anyhow!("{} entries to append to {}", additions.len(), path.display()))
+                .fail_as(Failure::Changes);
+        }
+        return Ok(());
+    }
+
+    // Respecting the limit for the merged history means dropping entries from the destination,
+    // so it has to be rewritten as a whole.
+    if let (Some(n), Some(existing)) = (converter.args.write.limit, existing) {
+        if write_mode == WriteMode::Overwrite {
+            entries = report.by("--limit", entries, |entries| apply_limit(entries, n));
+        } else if existing.len() + entries.len() > n {
+            let merged = existing.into_iter().chain(entries).collect();
+            entries = report.by("--limit", merged, |merged| apply_limit(merged, n));
+            write_mode = WriteMode::Overwrite;
+        }
+    }
+
+    if converter.args.write.verify {
+        let started = report.start();
+        verify(&entries).await?;
+        report.end(Phase::Verifying, started);
+    }
+
+    let writer = Writer::new(write_mode)
+        .with_format(converter.args.formats.format)
+        .with_table(converter.args.table())
+        .with_abbr(converter.args.abbr())
+        .with_plain(converter.args.plain())
+        .with_pretty(converter.args.pretty())
+        .with_bash(converter.args.bash())
+        .with_json(converter.args.json())
+        .with_replace(converter.args.formats.replace)
+        .with_backup(converter.args.backup_policy())
+        .with_lock_timeout(Duration::from_secs(converter.args.fish.lock_timeout));
+    let fish_bin = &converter.args.fish.fish_bin;
+    if converter.args.write.dry_run {
+        eprintln!("{}", writer.plan(&path, &entries).await?);
+        if converter.args.fish.run_merge {
+            let [flag, command] = HISTORY_MERGE_ARGS;
+            eprintln!("would run: {} {flag} '{command}'", fish_bin.display());
+        }
+        return Ok(());
+    }
+    let started = report.start();
+    let backup = writer.write_to(&path, &entries).await.fail_as(Failure::Destination)?;
+    if let Some(path) = &converter.args.write.annotate_file {
+        write_annotations(path, &entries).await?;
+    }
+    report.end(Phase::Writing, started);
+    if let Some(backup) = backup {
+        info!("backed up {} to {}", path.display(), backup.display());
+    }
+    report.written = entries.len();
+
+    if converter.args.fish.run_merge {
+        let status = run_history_merge(fish_bin).await?;
+        if !status.success() {
+            return Err(anyhow!("{} exited with {status}", fish_bin.display()))
+                .fail_as(Failure::Merge);
+        }
+    }
+
+    Ok(())
+}
+
+// The format to write the entries `args` converts to stdout in as they are read, if it writes an
+// entry at a time, and nothing done before writing them needs all of them.
+fn stream_output(args: &Uninitialized) -> Option<Builtin> {
+    let streams = matches!(args.formats.format, Format::Fish | Format::Jsonl | Format::Plain)
+        && args.write.output.is_none()
+        && !args.into_fish
+        && !args.merge
+        && args.formats.atuin_db.is_none()
+        && args.write.annotate_file.is_none()
+        && args.write.sort == Sort::Input
+        && args.filters.since.is_none()
+        && args.filters.until.is_none()
+        && args.filters.head.is_none()
+        && args.filters.tail.is_none()
+        && args.write.limit.is_none()
+        && args.filters.matches.is_empty()
+        && args.filters.commands.is_empty()
+        && args.filters.exclude.is_empty()
+        && args.filters.min_length.is_none()
+        && !(args.filters.skip_common || args.filters.skip_common_list.is_some())
+        && !args.filters.unique
+        && !args.filters.skip_multiline
+        && !args.write.reverse
+        && !args.write.interactive
+        && !args.list_commands
+        && !args.write.count
+        && !args.write.verify
+        && !args.write.fail_if_empty
+        && !args.fish.diff
+        && !args.write.dry_run;
+    streams.then(|| args.formats.format.output(&args.options())).flatten()
+}
+
+// Write the entries `converter` converts to stdout as they are read, a chunk at a time, so the
+// memory taken stays flat however long the history is.
+async fn write_stream(
+    converter: &Converter<Initialized>,
+    mut output: Builtin,
+    report: &mut RunReport,
+) -> Result<()> {
+    const CHUNK: usize = 1024;
+
+    let args = &converter.args;
+    let mut stdout = BufWriter::new(tokio::io::stdout());
+    output.begin(&mut stdout).await.fail_as(Failure::Destination)?;
+    let mut entries = pin!(converter.stream());
+    let (mut converted, mut sanitized, mut written) = (0, 0, 0);
+    let mut chunk = Vec::with_capacity(CHUNK);
+    loop {
+        let started = report.start();
+        let entry = entries.try_next().await;
+        report.end(Phase::Reading, started);
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(error) => {
+                report.set_read(converter.report());
+                return Err(error);
+            }
+        };
+        let done = entry.is_none();
+        chunk.extend(entry);
+        if chunk.len() < CHUNK && !done {
+            continue;
+        }
+
+        converted += chunk.len();
+        let started = report.start();
+        let (kept, affected) = sanitize(std::mem::take(&mut chunk), args.input.skip_invalid);
+        sanitized += affected;
+        report.end(Phase::Filtering, started);
+        let started = report.start();
+        for entry in &kept {
+            output
+                .write_entry(&mut stdout, entry)
+                .await
+                .fail_as(Failure::Destination)?;
+        }
+        report.end(Phase::Writing, started);
+        written += kept.len();
+        if done {
+            break;
+        }
+    }
+    let started = report.start();
+    output.end(&mut stdout).await.fail_as(Failure::Destination)?;
+    stdout.flush().await.fail_as(Failure::Destination)?;
+    report.end(Phase::Writing, started);
+
+    report.set_read(converter.report());
+    let (space_prefixed, entries) = (report.read.space_prefixed, report.read.entries);
+    report.note("--ignore-space", space_prefixed);
+    report.note("while reading", entries.saturating_sub(converted));
+    match (sanitized, args.input.skip_invalid) {
+        (0, _) => {}
+        (n, true) => info!("skipped {n} entries with control characters"),
+        (n, false) => info!("escaped control characters in {n} entries"),
+    }
+    report.note("--skip-invalid", converted - written);
+    report.written = written;
+    Ok(())
+}
+
+// Whether `args` counts the entries it converts as they are read, with `--count`, as nothing done
+// before counting them needs all of them: no sorting, `--tail`, `--unique`, or merging.
+fn counts_stream(args: &Uninitialized) -> bool {
+    args.write.count
+        && args.write.sort == Sort::Input
+        && args.filters.tail.is_none()
+        && !args.filters.unique
+        && !args.merge
+}
+
+// Count the entries `converter` converts as they are read, filtering a chunk at a time the way
+// `filtered` filters them all, so the memory taken stays flat however long the history is.
+async fn count_stream(converter: &Converter<Initialized>, report: &mut RunReport) -> Result<usize> {
+    const CHUNK: usize = 1024;
+
+    let args = &converter.args;
+    let filters = &args.filters;
+    let (patterns, exclusions) = (args.patterns(), args.exclusions());
+    let common = args.common_commands();
+    let mut entries = pin!(converter.stream());
+    let (mut converted, mut sanitized, mut counted) = (0, 0, 0);
+    let mut left = filters.head;
+    let mut chunk = Vec::with_capacity(CHUNK);
+    loop {
+        let started = report.start();
+        let entry = entries.try_next().await;
+        report.end(Phase::Reading, started);
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(error) => {
+                report.set_read(converter.report());
+                return Err(error);
+            }
+        };
+        let done = entry.is_none();
+        chunk.extend(entry);
+        if chunk.len() < CHUNK && !done {
+            continue;
+        }
+
+        converted += chunk.len();
+        let started = report.start();
+        let mut kept = std::mem::take(&mut chunk);
+        if filters.since.is_some() || filters.until.is_some() {
+            kept = report.by("--since and --until", kept, |kept| {
+                between(kept, filters.since, filters.until, filters.include_undated).0
+            });
+        }
+        if let Some(n) = &mut left {
+            kept = report.by("--head", kept, |kept| head(kept, *n).0);
+            *n -= kept.len();
+        }
+        kept = report.by("--skip-invalid", kept, |kept| {
+            let (kept, affected) = sanitize(kept, args.input.skip_invalid);
+            sanitized += affected;
+            kept
+        });
+        if !filters.matches.is_empty() {
+            kept = report.by("--match", kept, |kept| matching(kept, &patterns).0);
+        }
+        if !filters.commands.is_empty() {
+            kept = report.by("--command", kept, |kept| running(kept, &filters.commands).0);
+        }
+        if !filters.exclude.is_empty() {
+            kept = report.by("--exclude", kept, |kept| excluding(kept, &exclusions).0);
+        }
+        if let Some(min_length) = filters.min_length {
+            kept = report.by("--min-length", kept, |kept| skip_short(kept, min_length).0);
+        }
+        if filters.skip_common || filters.skip_common_list.is_some() {
+            kept = report.by("--skip-common", kept, |kept| skip_common(kept, &common).0);
+        }
+        if filters.skip_multiline {
+            kept = report.by("--skip-multiline", kept, |kept| skip_multiline(kept).0);
+        }
+        report.end(Phase::Filtering, started);
+        counted += kept.len();
+        if done {
+            break;
+        }
+    }
+
+    report.set_read(converter.report());
+    let (space_prefixed, entries) = (report.read.space_prefixed, report.read.entries);
+    report.note("--ignore-space", space_prefixed);
+    report.note("while reading", entries.saturating_sub(converted));
+    if let Some(n) = args.write.limit {
+        report.note("--limit", counted.saturating_sub(n));
+        counted = counted.min(n);
+    }
+    // those skipped are among the dropped
+    if sanitized > 0 && !args.input.skip_invalid {
+        info!("escaped control characters in {sanitized} entries");
+    }
+    if !report.dropped.is_empty() {
+        info!("counted {counted} entries as they were read, dropped {}", report.dropped_by());
+    }
+    Ok(counted)
+}
+
+// The `entries` added to the destination, of those written with `write_mode`, where rewriting
+// its `existing` entries adds those it does not have yet.
+fn additions<'a>(
+    existing: Option<&'a [Entry]>,
+    write_mode: WriteMode,
+    entries: &'a [Entry],
+) -> impl Iterator<Item = &'a Entry> {
+    entries.iter().filter(move |entry| match (existing, write_mode) {
+        (Some(existing), WriteMode::Overwrite) => !existing.contains(entry),
+        _ => true,
+    })
+}
+
+async fn check(args: &CheckArgs) -> Result<()> {
+    let file = File::open(&args.path)
+        .await
+        .with_context(|| format!("failed to open {}", args.path.display()))
+        .fail_as(Failure::Input)?;
+    let issues = check_fish_history(BufReader::new(file))
+        .await
+        .fail_as(Failure::Input)?;
+    issues
         .iter()
-        .for_each(|entry| println!("{entry}"));
+        .for_each(|issue| println!("{}: {issue}", args.path.display()));
+
+    let count = |severity| issues.iter().filter(|issue| issue.severity == severity).count();
+    let (errors, warnings) = (count(Severity::Error), count(Severity::Warning));
+    eprintln!("{errors} errors, {warnings} warnings");
+
+    if errors > 0 || (args.warnings_as_errors && warnings > 0) {
+        return Err(anyhow!("{} is not a valid fish history", args.path.display()))
+            .fail_as(Failure::Check);
+    }
+    Ok(())
+}
+
+async fn completions(args: &CompletionsArgs) -> Result<()> {
+    if !args.install {
+        let shell = args
+            .shell
+            .context("a shell is needed without --install")
+            .fail_as(Failure::Usage)?;
+        write_completions(shell, &mut std::io::stdout());
+        return Ok(());
+    }
+    if args.shell.is_some_and(|shell| shell != Shell::Fish) {
+        return Err(anyhow!("--install only installs the fish completions"))
+            .fail_as(Failure::Usage);
+    }
+    let path = fish_completions_path(|key| std::env::var_os(key)).fail_as(Failure::Destination)?;
+    install_fish_completions(&path, args.force)
+        .await
+        .fail_as(Failure::Destination)?;
+    info!("installed the fish completions into {}", path.display());
+    Ok(())
+}
+
+async fn man(args: &ManArgs) -> Result<()> {
+    let Some(dir) = &args.out_dir else {
+        return write_man(&mut std::io::stdout())
+            .context("failed to write the manual page")
+            .fail_as(Failure::Destination);
+    };
+    for path in write_man_pages(dir).await.fail_as(Failure::Destination)? {
+        info!("wrote {}", path.display());
+    }
+    Ok(())
+}
 
+// Write the entries with the line and the record each was read from to `path`, as the JSON lines
+// of `--annotate-file`.
+async fn write_annotations(path: &Path, entries: &[Entry]) -> Result<()> {
+    let file = File::create(path)
+        .await
+        .with_context(|| format!("failed to create {}", path.display()))
+        .fail_as(Failure::Destination)?;
+    let options = Options {
+        json: JsonOptions { annotate: true },
+        ..Default::default()
+    };
+    Format::Jsonl
+        .write_with(&mut BufWriter::new(file), entries, &options)
+        .await
+        .with_context(|| format!("failed to write the annotations to {}", path.display()))
+        .fail_as(Failure::Destination)
+}
+
+async fn verify(entries: &[Entry]) -> Result<()> {
+    let mismatches = verify_round_trip(entries).await?;
+    for Mismatch { index, expected, actual } in &mismatches {
+        let cmd = expected.as_ref().or(actual.as_ref()).map_or("", |entry| &entry.cmd);
+        eprintln!("entry {index} does not round-trip: {cmd:?}");
+        eprintln!("  converted: {expected:?}");
+        eprintln!("  read back: {actual:?}");
+    }
+
+    if !mismatches.is_empty() {
+        bail!(
+            "{} of {} entries do not round-trip, nothing written",
+            mismatches.len(),
+            entries.len()
+        );
+    }
     Ok(())
 }
+
+fn apply_sanitize(entries: Vec<Entry>, skip: bool) -> Vec<Entry> {
+    let (entries, affected) = sanitize(entries, skip);
+    match (affected, skip) {
+        (0, _) => {}
+        (n, true) => info!("skipped {n} entries with control characters"),
+        (n, false) => info!("escaped control characters in {n} entries"),
+    }
+    entries
+}
+
+fn apply_between(
+    entries: Vec<Entry>,
+    since: Option<i64>,
+    until: Option<i64>,
+    include_undated: bool,
+) -> Vec<Entry> {
+    let undated = |entries: &[Entry]| entries.iter().filter(|entry| entry.when == 0).count();
+    let before = undated(&entries);
+    let (entries, dropped) = between(entries, since, until, include_undated);
+    match (dropped, before - undated(&entries)) {
+        (0, _) => {}
+        (n, 0) => info!("dropped {n} entries outside of --since and --until"),
+        (n, undated) => info!(
+            "dropped {n} entries outside of --since and --until, {undated} of them without a \
+             timestamp, which --include-undated keeps"
+        ),
+    }
+    entries
+}
+
+fn apply_head(entries: Vec<Entry>, n: usize) -> Vec<Entry> {
+    // the converter may have stopped reading after them, so how many were dropped is unknown
+    let (entries, _) = head(entries, n);
+    info!("kept the first {} entries with --head", entries.len());
+    entries
+}
+
+fn apply_tail(entries: Vec<Entry>, n: usize) -> Vec<Entry> {
+    // the converter may have dropped older ones while reading
+    let (entries, _) = tail(entries, n);
+    info!("kept the last {} entries with --tail", entries.len());
+    entries
+}
+
+fn apply_matching(entries: Vec<Entry>, patterns: &[Regex]) -> Vec<Entry> {
+    let total = entries.len();
+    let (entries, _) = matching(entries, patterns);
+    info!("{} of {total} entries matched --match", entries.len());
+    entries
+}
+
+fn apply_running(entries: Vec<Entry>, names: &[String]) -> Vec<Entry> {
+    let total = entries.len();
+    let (entries, _) = running(entries, names);
+    info!("{} of {total} entries run a --command", entries.len());
+    entries
+}
+
+fn apply_excluding(entries: Vec<Entry>, patterns: &[Regex]) -> Vec<Entry> {
+    let (entries, dropped) = excluding(entries, patterns);
+    if dropped > 0 {
+        info!("excluded {dropped} entries matching --exclude");
+    }
+    entries
+}
+
+fn apply_skip_short(entries: Vec<Entry>, min_length: usize) -> Vec<Entry> {
+    let (entries, dropped) = skip_short(entries, min_length);
+    if dropped > 0 {
+        info!("skipped {dropped} commands shorter than --min-length");
+    }
+    entries
+}
+
+fn apply_skip_common(entries: Vec<Entry>, common: &[&str]) -> Vec<Entry> {
+    let (entries, dropped) = skip_common(entries, common);
+    if dropped > 0 {
+        info!("skipped {dropped} common commands");
+    }
+    entries
+}
+
+fn apply_unique(entries: Vec<Entry>, keep: Keep, normalization: Normalization) -> Vec<Entry> {
+    let (entries, dropped) = unique(entries, keep, normalization);
+    if dropped > 0 {
+        info!("dropped {dropped} duplicates with --unique");
+    }
+    entries
+}
+
+fn apply_skip_multiline(entries: Vec<Entry>) -> Vec<Entry> {
+    let (entries, dropped) = skip_multiline(entries);
+    if dropped > 0 {
+        info!("skipped {dropped} multi-line commands");
+    }
+    entries
+}
+
+// Keep the newest `limit` entries, then put them in reverse with `--reverse`, right before writing
+// them.
+fn limit_and_reverse(
+    converter: &Converter<Initialized>,
+    report: &mut RunReport,
+    mut entries: Vec<Entry>,
+    limit: Option<usize>,
+) -> Vec<Entry> {
+    if let Some(n) = limit {
+        let started = report.start();
+        entries = report.by("--limit", entries, |entries| apply_limit(entries, n));
+        report.end(Phase::Filtering, started);
+    }
+    if converter.args.write.reverse {
+        entries.reverse();
+    }
+    entries
+}
+
+fn apply_limit(entries: Vec<Entry>, n: usize) -> Vec<Entry> {
+    let (entries, dropped) = limit(entries, n);
+    if dropped > 0 {
+        info!("dropped {dropped} oldest entries to stay within the limit of {n}");
+    }
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use zsh_history_to_fish::{FilterArgs, FormatArgs, WriteArgs};
+
+    use super::*;
+
+    #[test]
+    fn counting_streams_without_buffering() {
+        let count = |write: WriteArgs, filters: FilterArgs, merge| {
+            let write = WriteArgs { count: true, ..write };
+            counts_stream(&Uninitialized { write, filters, merge, ..Default::default() })
+        };
+        assert!(count(WriteArgs::default(), FilterArgs::default(), false));
+        let filters = FilterArgs {
+            head: Some(5),
+            exclude: vec![Regex::new("^ls").unwrap()],
+            ..Default::default()
+        };
+        assert!(count(WriteArgs { limit: Some(3), ..Default::default() }, filters, false));
+
+        let sorted = WriteArgs { sort: Sort::When, ..Default::default() };
+        assert!(!count(sorted, FilterArgs::default(), false));
+        let tail = FilterArgs { tail: Some(5), ..Default::default() };
+        assert!(!count(WriteArgs::default(), tail, false));
+        let unique = FilterArgs { unique: true, ..Default::default() };
+        assert!(!count(WriteArgs::default(), unique, false));
+        assert!(!count(WriteArgs::default(), FilterArgs::default(), true));
+    }
+
+    #[test]
+    fn reverse_reads_everything_first() {
+        assert!(stream_output(&Uninitialized::default()).is_some());
+        let args = Uninitialized {
+            formats: FormatArgs { format: Format::Jsonl, ..Default::default() },
+            ..Default::default()
+        };
+        assert!(stream_output(&args).is_some());
+        let args = Uninitialized {
+            formats: FormatArgs { format: Format::Jsonl, ..Default::default() },
+            write: WriteArgs { reverse: true, ..Default::default() },
+            ..Default::default()
+        };
+        assert!(stream_output(&args).is_none());
+    }
+}
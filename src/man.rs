@@ -0,0 +1,252 @@
+//! The manual pages the `man` command prints or writes, rendered from the command line itself, so
+//! they cannot drift from it.
+
+use std::{
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use clap::CommandFactory;
+use clap_mangen::{
+    roff::{bold, roman, Roff},
+    Man,
+};
+
+use crate::{
+    config::{env_var, option_name, COMMAND_LINE_ONLY},
+    error::EXIT_CODES,
+    history_converter::Cli,
+};
+
+/// Write the manual page of the command line as roff, for `man -l -` or to install as
+/// `zsh-history-to-fish.1`: every option with its values and its default, the commands, the
+/// `Z2F_` environment variables and the others read, the files, and the exit statuses.
+///
+/// ```
+/// use zsh_history_to_fish::write_man;
+///
+/// let mut page = Vec::new();
+/// write_man(&mut page).unwrap();
+/// let page = String::from_utf8(page).unwrap();
+/// for section in ["NAME", "SYNOPSIS", "OPTIONS", "SUBCOMMANDS", "ENVIRONMENT", "FILES"] {
+///     assert!(page.contains(&format!("\n.SH {section}\n")), "no {section} section");
+/// }
+/// assert!(page.contains("\n.SH \"EXIT STATUS\"\n"));
+/// assert!(page.contains("\\fBZ2F_INTO_FISH\\fR"));
+/// assert!(page.contains("fish\\-script"));
+/// assert!(page.contains("[default: fish]"));
+/// ```
+pub fn write_man(out: &mut impl Write) -> io::Result<()> {
+    render(command(), out)
+}
+
+/// Write the manual page of the command line, and one of each command, like
+/// `zsh-history-to-fish-convert.1`, into `dir`, creating it, and return the paths written.
+pub async fn write_man_pages(dir: &Path) -> Result<Vec<PathBuf>> {
+    tokio::fs::create_dir_all(dir)
+        .await
+        .with_context(|| format!("failed to create {}", dir.display()))?;
+    let command = command();
+    let commands = command
+        .get_subcommands()
+        .filter(|command| !command.is_hide_set())
+        .cloned();
+    let mut paths = Vec::new();
+    for command in std::iter::once(command.clone()).chain(commands) {
+        let path = dir.join(Man::new(command.clone()).get_filename());
+        let mut page = Vec::new();
+        render(command, &mut page)?;
+        tokio::fs::write(&path, page)
+            .await
+            .with_context(|| format!("failed to write {}", path.display()))?;
+        paths.push(path);
+    }
+    Ok(paths)
+}
+
+// The command line, built so the commands have their full names, like
+// `zsh-history-to-fish-convert`, without the `help` command, which has no page.
+fn command() -> clap::Command {
+    let mut command = <Cli as CommandFactory>::command().disable_help_subcommand(true);
+    command.build();
+    command
+}
+
+// Render the page of `command`, with the environment and the files of the commands reading a
+// history, which are those taking `--config`.
+fn render(command: clap::Command, out: &mut dyn Write) -> io::Result<()> {
+    let reads_history = command.get_arguments().any(|arg| arg.get_id() == "config");
+    let has_options = command.get_arguments().any(|arg| !arg.is_hide_set());
+    let has_commands = command.get_subcommands().any(|command| !command.is_hide_set());
+    let has_version = command.get_version().is_some();
+    let mut roff = Roff::new();
+    if reads_history {
+        environment(&command, &mut roff);
+        files(&mut roff);
+    }
+    exit_status(&mut roff);
+
+    // the commands have no version of their own
+    let source = format!("zsh-history-to-fish {}", env!("CARGO_PKG_VERSION"));
+    let man = Man::new(command).source(source);
+    man.render_title(out)?;
+    man.render_name_section(out)?;
+    man.render_synopsis_section(out)?;
+    man.render_description_section(out)?;
+    if has_options {
+        man.render_options_section(out)?;
+    }
+    if has_commands {
+        man.render_subcommands_section(out)?;
+    }
+    roff.to_writer(out)?;
+    if has_version {
+        man.render_version_section(out)?;
+    }
+    Ok(())
+}
+
+// The ENVIRONMENT section: the `Z2F_` variable of each option of `command`, then the others read.
+fn environment(command: &clap::Command, roff: &mut Roff) {
+    roff.control("SH", ["ENVIRONMENT"]);
+    roff.text([roman(
+        "Each option can also be given by a Z2F_ variable named after its long name, which the \
+         command line wins over, and which wins over the configuration file. A flag takes 1, \
+         true, or yes to be given, and 0, false, or no not to, and --verbose how many times it \
+         is given.",
+    )]);
+    for arg in command.get_arguments() {
+        let key = option_name(arg);
+        if arg.is_hide_set() || COMMAND_LINE_ONLY.contains(&key.as_str()) {
+            continue;
+        }
+        let option = match arg.get_long() {
+            Some(long) => format!("--{long}"),
+            None => format!("the {}", arg.get_id().as_str().to_uppercase()),
+        };
+        roff.control("TP", []);
+        roff.text([bold(env_var(&key))]);
+        roff.text([roman(format!("Gives {option}."))]);
+    }
+    let others = [
+        ("Z2F_CONFIG", "The configuration file, unless --config is given."),
+        ("HISTFILE", "The zsh history to convert, unless one is given."),
+        ("ZDOTDIR", "Where to look for .zsh_history, unless a history is given."),
+        ("HOME", "Where to look for the zsh history, the configuration, and the fish history."),
+        ("XDG_CONFIG_HOME", "Where the configuration file is, instead of ~/.config."),
+        ("XDG_DATA_HOME", "Where the fish history is, instead of ~/.local/share."),
+        ("fish_history", "The fish history session to write into, unless --session-name says."),
+        ("NO_COLOR", "Not to color --format pretty, unless --color always is given."),
+    ];
+    for (var, help) in others {
+        roff.control("TP", []);
+        roff.text([bold(var)]);
+        roff.text([roman(help)]);
+    }
+}
+
+// The FILES section.
+fn files(roff: &mut Roff) {
+    roff.control("SH", ["FILES"]);
+    let files = [
+        (
+            "~/.config/zsh-history-to-fish/config.toml",
+            "The configuration file, with an option per key, named after its long name.",
+        ),
+        ("~/.zsh_history", "The zsh history converted, unless one is given."),
+        ("~/.local/share/fish/fish_history", "The fish history written with --into-fish or merge."),
+    ];
+    for (path, help) in files {
+        roff.control("TP", []);
+        roff.text([bold(path)]);
+        roff.text([roman(help)]);
+    }
+}
+
+// The EXIT STATUS section, from the statuses `--help` lists.
+fn exit_status(roff: &mut Roff) {
+    roff.control("SH", ["EXIT STATUS"]);
+    for line in EXIT_CODES.lines().skip(1) {
+        let Some((code, meaning)) = line.trim().split_once(' ') else {
+            continue;
+        };
+        roff.control("TP", []);
+        roff.text([bold(code)]);
+        roff.text([roman(meaning.trim())]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The sections of `page`, in order.
+    fn sections(page: &[u8]) -> Vec<String> {
+        let page = String::from_utf8(page.to_vec()).unwrap();
+        let sections = page.lines().filter_map(|line| line.strip_prefix(".SH "));
+        sections
+            .map(|section| section.trim_matches('"').to_string())
+            .collect()
+    }
+
+    #[test]
+    fn main_page_has_every_section() {
+        let mut page = Vec::new();
+        write_man(&mut page).unwrap();
+        assert_eq!(
+            sections(&page),
+            [
+                "NAME",
+                "SYNOPSIS",
+                "DESCRIPTION",
+                "OPTIONS",
+                "SUBCOMMANDS",
+                "ENVIRONMENT",
+                "FILES",
+                "EXIT STATUS",
+                "VERSION",
+            ],
+        );
+    }
+
+    #[test]
+    fn every_exit_status_is_listed() {
+        let mut page = Vec::new();
+        write_man(&mut page).unwrap();
+        let page = String::from_utf8(page).unwrap();
+        let (_, statuses) = page.split_once(".SH \"EXIT STATUS\"").unwrap();
+        for code in 0..=8 {
+            assert!(statuses.contains(&format!("\n\\fB{code}\\fR\n")), "no exit status {code}");
+        }
+    }
+
+    #[tokio::test]
+    async fn each_command_has_a_page() {
+        let dir = tempfile::tempdir().unwrap();
+        let paths = write_man_pages(&dir.path().join("man1")).await.unwrap();
+        let names = paths
+            .iter()
+            .map(|path| path.file_name().unwrap().to_str().unwrap())
+            .collect::<Vec<_>>();
+        assert!(names.contains(&"zsh-history-to-fish.1"));
+        assert!(!names.contains(&"zsh-history-to-fish-help.1"));
+
+        for path in &paths {
+            let page = std::fs::read(path).unwrap();
+            let sections = sections(&page);
+            // only the commands reading a history have its environment and files
+            let name = path.file_stem().unwrap().to_str().unwrap();
+            let reads_history = !name.ends_with("-check")
+                && !name.ends_with("-completions")
+                && !name.ends_with("-man");
+            for section in ["NAME", "SYNOPSIS", "EXIT STATUS"] {
+                assert!(sections.iter().any(|s| s == section), "no {section} in {name}");
+            }
+            for section in ["ENVIRONMENT", "FILES"] {
+                let has = sections.iter().any(|s| s == section);
+                assert_eq!(has, reads_history, "{section} in {name}");
+            }
+        }
+    }
+}
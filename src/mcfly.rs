@@ -0,0 +1,140 @@
+//! Writing the SQLite database of McFly.
+
+use std::path::Path;
+
+use anyhow::Result;
+
+use crate::history_converter::Entry;
+
+/// The columns of McFly's `commands` table, as of the McFly release [`write_mcfly`] was tested
+/// with.
+#[cfg(feature = "sqlite")]
+const COLUMNS: [&str; 9] =
+    ["id", "cmd", "cmd_tpl", "session_id", "when_run", "exit_code", "selected", "dir", "old_dir"];
+
+/// Add the entries to the `commands` table of the McFly database at `path`, usually
+/// `~/.local/share/mcfly/history.db`, returning how many rows were added.
+///
+/// The database has to exist already, as McFly creates it on its first run, and its `commands`
+/// table to have exactly the columns McFly 0.9 gives it, so that a database of another version is
+/// refused rather than written into wrongly. Entries with the command and time of a row already
+/// there are skipped. The columns McFly ranks by get neutral values: the exit code is 0, the
+/// command never selected, and the directory empty; the command is its own template, and all rows
+/// share one session.
+pub fn write_mcfly(path: &Path, entries: &[Entry]) -> Result<usize> {
+    insert(path, entries)
+}
+
+#[cfg(feature = "sqlite")]
+fn insert(path: &Path, entries: &[Entry]) -> Result<usize> {
+    use anyhow::bail;
+    use rusqlite::{params, Connection};
+
+    let mut db = Connection::open(path)?;
+    let mut columns = db
+        .prepare("SELECT name FROM pragma_table_info('commands')")?
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    if columns.is_empty() {
+        bail!("no McFly `commands` table; McFly creates its database when it first runs");
+    }
+    columns.sort();
+    let mut expected = COLUMNS.to_vec();
+    expected.sort();
+    if columns != expected {
+        bail!(
+            "unexpected McFly schema, with the columns {} rather than {}; is it a newer McFly?",
+            columns.join(", "),
+            COLUMNS.join(", ")
+        );
+    }
+
+    let transaction = db.transaction()?;
+    let mut added = 0;
+    {
+        let mut statement = transaction.prepare(
+            "INSERT INTO commands (cmd, cmd_tpl, session_id, when_run, exit_code, selected, dir)
+             SELECT ?1, ?1, 'zsh-history-to-fish', ?2, 0, 0, ''
+             WHERE NOT EXISTS (SELECT 1 FROM commands WHERE cmd = ?1 AND when_run = ?2)",
+        )?;
+        for entry in entries {
+            added += statement.execute(params![entry.cmd, entry.when])?;
+        }
+    }
+    transaction.commit()?;
+
+    Ok(added)
+}
+
+#[cfg(not(feature = "sqlite"))]
+fn insert(_: &Path, _: &[Entry]) -> Result<usize> {
+    Err(crate::sqlite::unsupported())
+}
+
+#[cfg(all(test, feature = "sqlite"))]
+mod tests {
+    use rusqlite::Connection;
+
+    use super::*;
+
+    // A database with a `commands` table of the given columns, besides `id`.
+    fn fixture(dir: &Path, columns: &[&str]) -> std::path::PathBuf {
+        let path = dir.join("history.db");
+        let columns = columns.join(", ");
+        let db = Connection::open(&path).unwrap();
+        db.execute_batch(&format!(
+            "CREATE TABLE commands (id INTEGER PRIMARY KEY AUTOINCREMENT, {columns});"
+        ))
+        .unwrap();
+        path
+    }
+
+    fn entries() -> [Entry; 2] {
+        let entry = |cmd: &str, when| Entry { cmd: cmd.into(), when, ..Default::default() };
+        [entry("ls", 100), entry("pwd", 200)]
+    }
+
+    #[test]
+    fn written_entries_are_added_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = fixture(dir.path(), &COLUMNS[1..]);
+        assert_eq!(write_mcfly(&path, &entries()).unwrap(), 2);
+        assert_eq!(write_mcfly(&path, &entries()).unwrap(), 0);
+
+        let db = Connection::open(&path).unwrap();
+        let rows = db
+            .prepare("SELECT cmd, when_run, cmd_tpl FROM commands ORDER BY id")
+            .unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+            .unwrap()
+            .collect::<rusqlite::Result<Vec<(String, i64, String)>>>()
+            .unwrap();
+        assert_eq!(rows, [("ls".into(), 100, "ls".into()), ("pwd".into(), 200, "pwd".into())]);
+    }
+
+    #[test]
+    fn schema_drift_is_refused() {
+        for missing in ["cmd", "when_run"] {
+            let dir = tempfile::tempdir().unwrap();
+            let columns = COLUMNS[1..].iter().copied().filter(|&column| column != missing);
+            let path = fixture(dir.path(), &columns.collect::<Vec<_>>());
+            let error = write_mcfly(&path, &entries()).unwrap_err().to_string();
+            assert!(error.starts_with("unexpected McFly schema"), "{error}");
+            assert!(error.contains("newer McFly"), "{error}");
+        }
+
+        // a column McFly does not have
+        let dir = tempfile::tempdir().unwrap();
+        let path = fixture(dir.path(), &[&COLUMNS[1..], &["rank"]].concat());
+        let error = write_mcfly(&path, &entries()).unwrap_err().to_string();
+        assert!(error.contains("rank"), "{error}");
+    }
+
+    #[test]
+    fn a_missing_table_is_refused() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("history.db");
+        let error = write_mcfly(&path, &entries()).unwrap_err().to_string();
+        assert!(error.contains("no McFly `commands` table"), "{error}");
+    }
+}
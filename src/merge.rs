@@ -0,0 +1,463 @@
+use std::collections::{HashMap, HashSet};
+
+use clap::ValueEnum;
+
+use crate::history_converter::Entry;
+
+/// What makes two entries the same when merging into an existing fish history with
+/// [`DupPolicy::KeepBoth`]. The other policies collapse every occurrence of a command, whenever
+/// it was executed.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum DedupBy {
+    /// Entries with the same command are duplicates, regardless of when they were executed.
+    Cmd,
+    /// Entries are duplicates only if both the command and the timestamp match.
+    #[default]
+    #[value(name = "cmd+when")]
+    CmdWhen,
+}
+
+/// Which entry survives when the same command is both in the converted and the existing history.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum DupPolicy {
+    /// Collapse duplicates into one entry with the newest timestamp.
+    #[default]
+    KeepNewest,
+    /// Collapse duplicates into one entry with the oldest timestamp.
+    KeepOldest,
+    /// Keep the existing history as is and append every entry not already present.
+    KeepBoth,
+}
+
+impl DupPolicy {
+    // What makes two entries the same with this policy: collapsing duplicates into the newest or
+    // the oldest needs all the occurrences of a command, whenever they were executed.
+    fn dedup_by(self, by: DedupBy) -> DedupBy {
+        match self {
+            Self::KeepBoth => by,
+            Self::KeepNewest | Self::KeepOldest => DedupBy::Cmd,
+        }
+    }
+}
+
+/// How commands are normalized before comparing them for duplicates. The entries themselves keep
+/// their commands as they are.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Normalization {
+    /// Collapse runs of whitespace into single spaces and trim the ends, see
+    /// [`normalize_command`].
+    pub whitespace: bool,
+    /// Compare regardless of case.
+    pub case_insensitive: bool,
+}
+
+impl Normalization {
+    // What `cmd` is compared as.
+    fn apply(&self, cmd: &str) -> String {
+        let cmd = match self.whitespace {
+            true => normalize_command(cmd),
+            false => cmd.to_string(),
+        };
+        match self.case_insensitive {
+            true => cmd.to_lowercase(),
+            false => cmd,
+        }
+    }
+}
+
+/// Collapse every run of whitespace in `cmd`, including tabs, non-breaking spaces, and the
+/// newlines of a multi-line command, into a single space, and trim the ends, so `git  status `
+/// becomes `git status`.
+pub fn normalize_command(cmd: &str) -> String {
+    cmd.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+// What identifies an entry as the same as another with `by`, its command normalized.
+fn key(entry: &Entry, by: DedupBy, normalization: Normalization) -> (String, i64) {
+    let cmd = normalization.apply(&entry.cmd);
+    match by {
+        DedupBy::Cmd => (cmd, 0),
+        DedupBy::CmdWhen => (cmd, entry.when),
+    }
+}
+
+/// Which occurrence of a command survives `--unique`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Keep {
+    /// The occurrence with the oldest timestamp.
+    First,
+    /// The occurrence with the newest timestamp, which fish ranks by recency.
+    #[default]
+    Last,
+}
+
+/// The result of merging converted entries into an existing history.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Merged {
+    /// The entries to write.
+    pub entries: Vec<Entry>,
+    /// Whether `entries` is the whole merged history, which has to replace the existing one, or
+    /// only the entries to append to it.
+    pub rewrite: bool,
+    /// How many entries were dropped as duplicates.
+    pub skipped: usize,
+}
+
+/// Merge the converted entries into the existing history. With [`DupPolicy::KeepBoth`] this is
+/// [`skip_existing`] with `by`. Otherwise the occurrences of a command across and within both
+/// histories are collapsed into one entry carrying the newest or oldest timestamp, and the result
+/// is sorted by timestamp.
+/// Commands are compared as `normalization` normalizes them.
+/// Entries sharing a timestamp, as zsh only records whole seconds, keep the order in which the
+/// occurrence they carry the timestamp of appears in `existing` followed by `entries`. The
+/// existing history only has to be rewritten if that changed any of its entries.
+pub fn merge(
+    existing: &[Entry],
+    entries: Vec<Entry>,
+    by: DedupBy,
+    policy: DupPolicy,
+    normalization: Normalization,
+) -> Merged {
+    if policy == DupPolicy::KeepBoth {
+        let (entries, skipped) = skip_existing(entries, existing, by, normalization);
+        return Merged { entries, rewrite: false, skipped };
+    }
+
+    let total = existing.len() + entries.len();
+    // each entry goes with the sequence number of the occurrence it carries the timestamp of
+    let mut merged = Vec::<(usize, Entry)>::with_capacity(total);
+    let mut seen = HashMap::new();
+    let by = policy.dedup_by(by);
+    for (seq, entry) in existing.iter().cloned().chain(entries).enumerate() {
+        let key = key(&entry, by, normalization);
+        let Some(&i) = seen.get(&key) else {
+            seen.insert(key, merged.len());
+            merged.push((seq, entry));
+            continue;
+        };
+
+        let (kept_seq, kept) = &mut merged[i];
+        let replace = match policy {
+            DupPolicy::KeepNewest => entry.when > kept.when,
+            _ => entry.when < kept.when,
+        };
+        if replace {
+            kept.when = entry.when;
+            *kept_seq = seq;
+        }
+        if kept.paths.is_empty() {
+            kept.paths = entry.paths;
+        }
+    }
+    merged.sort_by_key(|(seq, entry)| (entry.when, *seq));
+    let merged = merged.into_iter().map(|(_, entry)| entry).collect::<Vec<_>>();
+
+    let skipped = total - merged.len();
+    match merged.strip_prefix(existing) {
+        Some(appended) => Merged {
+            entries: appended.to_vec(),
+            rewrite: false,
+            skipped,
+        },
+        None => Merged { entries: merged, rewrite: true, skipped },
+    }
+}
+
+/// Collapse the entries with the same command into one, the occurrence with the oldest or the
+/// newest timestamp, or, among those sharing it, the first or the last of them. The surviving
+/// entries stay in the order of their occurrences in `entries`. Commands are compared as
+/// `normalization` normalizes them. Returns the remaining entries and the number of entries which
+/// were dropped.
+pub fn unique(
+    entries: Vec<Entry>,
+    keep: Keep,
+    normalization: Normalization,
+) -> (Vec<Entry>, usize) {
+    let total = entries.len();
+    // each entry goes with the sequence number of the occurrence which survives
+    let mut kept = Vec::<(usize, Entry)>::new();
+    let mut seen = HashMap::new();
+    for (seq, entry) in entries.into_iter().enumerate() {
+        let key = normalization.apply(&entry.cmd);
+        let Some(&i) = seen.get(&key) else {
+            seen.insert(key, kept.len());
+            kept.push((seq, entry));
+            continue;
+        };
+
+        let (kept_seq, kept) = &mut kept[i];
+        let replace = match keep {
+            Keep::First => entry.when < kept.when,
+            Keep::Last => entry.when >= kept.when,
+        };
+        if replace {
+            let paths = std::mem::take(&mut kept.paths);
+            *kept = entry;
+            *kept_seq = seq;
+            if kept.paths.is_empty() {
+                kept.paths = paths;
+            }
+        } else if kept.paths.is_empty() {
+            kept.paths = entry.paths;
+        }
+    }
+    kept.sort_by_key(|(seq, _)| *seq);
+
+    let dropped = total - kept.len();
+    (kept.into_iter().map(|(_, entry)| entry).collect(), dropped)
+}
+
+/// Drop the entries which are already present in `existing`, comparing commands as
+/// `normalization` normalizes them. Returns the remaining entries and the number of entries which
+/// were skipped.
+pub fn skip_existing(
+    entries: Vec<Entry>,
+    existing: &[Entry],
+    by: DedupBy,
+    normalization: Normalization,
+) -> (Vec<Entry>, usize) {
+    let key = |entry: &Entry| key(entry, by, normalization);
+    let existing = existing.iter().map(key).collect::<HashSet<_>>();
+
+    let total = entries.len();
+    let entries = entries
+        .into_iter()
+        .filter(|entry| !existing.contains(&key(entry)))
+        .collect::<Vec<_>>();
+    let skipped = total - entries.len();
+
+    (entries, skipped)
+}
+
+/// Merge several converted histories into one sorted by timestamp, collapsing duplicates within
+/// and across them like [`merge`] does, or, with [`DupPolicy::KeepBoth`], only dropping entries
+/// already in an earlier history. Entries sharing a timestamp keep the order of `histories`, then
+/// their order within each. Also returns how many entries each history contributed, that is how
+/// many of its distinct entries no earlier history has.
+pub fn merge_histories(
+    histories: Vec<Vec<Entry>>,
+    by: DedupBy,
+    policy: DupPolicy,
+    normalization: Normalization,
+) -> (Vec<Entry>, Vec<usize>) {
+    let key = |entry: &Entry| key(entry, policy.dedup_by(by), normalization);
+    let mut seen = HashSet::new();
+    let contributed = histories
+        .iter()
+        .map(|history| history.iter().filter(|entry| seen.insert(key(entry))).count())
+        .collect();
+
+    let mut merged = Vec::new();
+    for history in histories {
+        match policy {
+            DupPolicy::KeepBoth => {
+                merged.extend(skip_existing(history, &merged, by, normalization).0)
+            }
+            _ => merged.extend(history),
+        }
+    }
+    let entries = match policy {
+        DupPolicy::KeepBoth => {
+            merged.sort_by_key(|entry| entry.when);
+            merged
+        }
+        _ => merge(&[], merged, by, policy, normalization).entries,
+    };
+    (entries, contributed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(cmd: &str, when: i64) -> Entry {
+        Entry { cmd: cmd.to_string(), when, ..Default::default() }
+    }
+
+    fn pairs(entries: &[Entry]) -> Vec<(&str, i64)> {
+        entries.iter().map(|entry| (entry.cmd.as_str(), entry.when)).collect()
+    }
+
+    fn merged(existing: &[Entry], entries: Vec<Entry>, policy: DupPolicy) -> Merged {
+        merge(existing, entries, DedupBy::default(), policy, Normalization::default())
+    }
+
+    #[test]
+    fn keep_newest_collapses_by_command() {
+        let existing = [entry("ls", 5)];
+        let Merged { entries, rewrite, skipped } =
+            merged(&existing, vec![entry("ls", 10)], DupPolicy::default());
+        assert_eq!(pairs(&entries), [("ls", 10)]);
+        assert!(rewrite);
+        assert_eq!(skipped, 1);
+    }
+
+    #[test]
+    fn keep_oldest_collapses_by_command() {
+        let existing = [entry("ls", 5)];
+        let Merged { entries, rewrite, skipped } =
+            merged(&existing, vec![entry("ls", 10), entry("ls", 7)], DupPolicy::KeepOldest);
+        assert!(entries.is_empty());
+        assert!(!rewrite);
+        assert_eq!(skipped, 2);
+    }
+
+    #[test]
+    fn keep_newest_moves_the_entry_to_its_timestamp() {
+        let existing = [entry("ls", 1), entry("pwd", 5)];
+        let merged = merged(&existing, vec![entry("ls", 10)], DupPolicy::KeepNewest);
+        assert_eq!(pairs(&merged.entries), [("pwd", 5), ("ls", 10)]);
+        assert!(merged.rewrite);
+    }
+
+    #[test]
+    fn keep_newest_appends_new_commands() {
+        let existing = [entry("ls", 1)];
+        let merged =
+            merged(&existing, vec![entry("ls", 1), entry("pwd", 2)], DupPolicy::KeepNewest);
+        assert_eq!(pairs(&merged.entries), [("pwd", 2)]);
+        assert!(!merged.rewrite);
+    }
+
+    #[test]
+    fn keep_both_appends_by_dedup_by() {
+        let existing = [entry("ls", 5)];
+        let entries = vec![entry("ls", 5), entry("ls", 10)];
+        let by_when = merged(&existing, entries.clone(), DupPolicy::KeepBoth);
+        assert_eq!((pairs(&by_when.entries), by_when.skipped), (vec![("ls", 10)], 1));
+        let by_cmd =
+            merge(&existing, entries, DedupBy::Cmd, DupPolicy::KeepBoth, Normalization::default());
+        assert_eq!((by_cmd.entries.len(), by_cmd.skipped), (0, 2));
+    }
+
+    #[test]
+    fn normalized_commands_are_duplicates() {
+        let normalization = Normalization { whitespace: true, case_insensitive: true };
+        let existing = [entry("git  status", 5)];
+        let merged = merge(
+            &existing,
+            vec![entry("GIT status ", 9)],
+            DedupBy::Cmd,
+            DupPolicy::KeepNewest,
+            normalization,
+        );
+        // the entry kept keeps its command
+        assert_eq!(pairs(&merged.entries), [("git  status", 9)]);
+    }
+
+    #[test]
+    fn histories_merge_across_sources() {
+        let fish = vec![entry("ls", 5), entry("cd", 6)];
+        let zsh = vec![entry("ls", 10), entry("make", 3)];
+        let (entries, contributed) = merge_histories(
+            vec![fish, zsh],
+            DedupBy::default(),
+            DupPolicy::KeepNewest,
+            Normalization::default(),
+        );
+        assert_eq!(pairs(&entries), [("make", 3), ("cd", 6), ("ls", 10)]);
+        assert_eq!(contributed, [2, 1]);
+    }
+
+    // Ten commands typed within the same second.
+    fn burst(when: i64) -> Vec<Entry> {
+        (0..10).map(|i| entry(&format!("echo {i}"), when)).collect()
+    }
+
+    #[test]
+    fn a_burst_keeps_its_order_through_merge() {
+        let existing = [entry("ls", 5), entry("echo 3", 7), entry("pwd", 9)];
+        let mut entries = burst(7);
+        entries.insert(4, entry("ls", 7));
+        let merged = merged(&existing, entries, DupPolicy::KeepNewest);
+        // `echo 3` stays where it already was, `ls` moves to where it was typed again
+        let cmds = ["echo 3", "echo 0", "echo 1", "echo 2", "ls", "echo 4", "echo 5", "echo 6"];
+        let mut expected = cmds.map(|cmd| (cmd, 7)).to_vec();
+        expected.extend([("echo 7", 7), ("echo 8", 7), ("echo 9", 7), ("pwd", 9)]);
+        assert_eq!(pairs(&merged.entries), expected);
+        assert_eq!(merged.skipped, 2);
+    }
+
+    #[test]
+    fn a_burst_keeps_its_order_across_histories() {
+        let (entries, _) = merge_histories(
+            vec![burst(7), vec![entry("make", 7), entry("cd", 6)]],
+            DedupBy::default(),
+            DupPolicy::KeepNewest,
+            Normalization::default(),
+        );
+        let cmds = entries.iter().map(|entry| entry.cmd.as_str()).collect::<Vec<_>>();
+        let mut expected = vec!["cd".to_string()];
+        expected.extend((0..10).map(|i| format!("echo {i}")));
+        expected.push("make".to_string());
+        assert_eq!(cmds, expected);
+    }
+
+    #[test]
+    fn a_burst_keeps_its_order_through_unique() {
+        let mut entries = burst(7);
+        entries.extend(burst(7).into_iter().step_by(2));
+        let (kept, dropped) = unique(entries, Keep::Last, Normalization::default());
+        // the last occurrences of the even ones follow the odd ones
+        let cmds = kept.iter().map(|entry| entry.cmd.as_str()).collect::<Vec<_>>();
+        let expected = ["1", "3", "5", "7", "9", "0", "2", "4", "6", "8"];
+        assert_eq!(cmds, expected.map(|i| format!("echo {i}")));
+        assert_eq!(dropped, 5);
+    }
+
+    #[test]
+    fn normalizing_collapses_whitespace() {
+        assert_eq!(normalize_command("git  status "), "git status");
+        assert_eq!(normalize_command("\tgit\tstatus\t"), "git status");
+        assert_eq!(normalize_command("git\u{a0}status"), "git status");
+        assert_eq!(normalize_command("for i in 1\n  echo $i\nend"), "for i in 1 echo $i end");
+        assert_eq!(normalize_command("   "), "");
+    }
+
+    #[test]
+    fn unique_compares_normalized_commands() {
+        let entries =
+            vec![entry("git  status", 1), entry("GIT status ", 2), entry("git status", 3)];
+        let (kept, dropped) = unique(entries.clone(), Keep::Last, Normalization::default());
+        assert_eq!((kept.len(), dropped), (3, 0));
+
+        let whitespace = Normalization { whitespace: true, case_insensitive: false };
+        let (kept, dropped) = unique(entries.clone(), Keep::First, whitespace);
+        assert_eq!((pairs(&kept), dropped), (vec![("git  status", 1), ("GIT status ", 2)], 1));
+
+        // the survivor keeps its own text
+        let both = Normalization { whitespace: true, case_insensitive: true };
+        let (kept, _) = unique(entries.clone(), Keep::First, both);
+        assert_eq!(pairs(&kept), [("git  status", 1)]);
+        let (kept, _) = unique(entries, Keep::Last, both);
+        assert_eq!(pairs(&kept), [("git status", 3)]);
+    }
+
+    #[test]
+    fn merges_compare_the_same_way() {
+        let normalization = Normalization { whitespace: true, case_insensitive: false };
+        let (entries, _) = merge_histories(
+            vec![vec![entry("git  status", 5)], vec![entry("git status\t", 9)]],
+            DedupBy::Cmd,
+            DupPolicy::KeepNewest,
+            normalization,
+        );
+        assert_eq!(pairs(&entries), [("git  status", 9)]);
+        let (entries, skipped) = skip_existing(
+            vec![entry("ls  -la", 1)],
+            &[entry("ls -la", 1)],
+            DedupBy::default(),
+            normalization,
+        );
+        assert_eq!((entries.len(), skipped), (0, 1));
+    }
+
+    #[test]
+    fn unique_keeps_first_or_last() {
+        let entries = vec![entry("ls", 1), entry("pwd", 2), entry("ls", 3)];
+        let (kept, dropped) = unique(entries.clone(), Keep::Last, Normalization::default());
+        assert_eq!((pairs(&kept), dropped), (vec![("pwd", 2), ("ls", 3)], 1));
+        let (kept, _) = unique(entries, Keep::First, Normalization::default());
+        assert_eq!(pairs(&kept), [("ls", 1), ("pwd", 2)]);
+    }
+}
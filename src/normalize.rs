@@ -0,0 +1,190 @@
+//! Tidying up the ends of commands, so the variants sloppy typing left behind collapse into one.
+
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use clap::ValueEnum;
+
+use crate::{history_converter::Entry, transform::Transform};
+
+/// What `--normalize` removes from the end of a command.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum NormalizeMode {
+    /// Trailing whitespace and bare `;`s.
+    #[default]
+    Basic,
+    /// Also `&&` and `||` left dangling with nothing after them.
+    Aggressive,
+}
+
+/// Remove trailing whitespace and a trailing bare `;` from `cmd`, and with
+/// [`NormalizeMode::Aggressive`] a dangling `&&` or `||`, as often as they repeat, so
+/// `make build ;` becomes `make build`. Nothing quoted or escaped is touched, so neither is a
+/// command ending within quotes, nor one with a heredoc, whose end is the end of its body. The
+/// `;;` of a `case` and a command which would end up empty are left alone too.
+pub fn normalize_end(cmd: &str, mode: NormalizeMode) -> &str {
+    let Some(protected) = protected_end(cmd) else {
+        return cmd;
+    };
+
+    let mut end = cmd.len();
+    loop {
+        end = protected + cmd[protected..end].trim_end().len();
+        let rest = &cmd[protected..end];
+        let dangling = match mode {
+            NormalizeMode::Aggressive if rest.ends_with("&&") || rest.ends_with("||") => 2,
+            _ if rest.ends_with(';') && !rest.ends_with(";;") => 1,
+            _ => break,
+        };
+        end -= dangling;
+    }
+
+    match cmd[..end].trim().is_empty() {
+        true => cmd,
+        false => &cmd[..end],
+    }
+}
+
+// The end of the last quoted or escaped character in `cmd`, which normalizing must not reach into,
+// or `None` if `cmd` ends within quotes or after a backslash, or has a heredoc.
+fn protected_end(cmd: &str) -> Option<usize> {
+    let mut protected = 0;
+    let mut chars = cmd.char_indices().peekable();
+    let mut previous = None;
+    while let Some((_, c)) = chars.next() {
+        match c {
+            '\\' => {
+                let (i, c) = chars.next()?;
+                protected = i + c.len_utf8();
+            }
+            '\'' | '"' => {
+                // backslashes escape within double quotes, and within `$'...'`
+                let escapes = c == '"' || previous == Some('$');
+                loop {
+                    match chars.next()? {
+                        (_, '\\') if escapes => {
+                            chars.next()?;
+                        }
+                        (i, quote) if quote == c => {
+                            protected = i + 1;
+                            break;
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            // a heredoc, unlike a here-string `<<<`
+            '<' if chars.next_if(|&(_, c)| c == '<').is_some()
+                && chars.next_if(|&(_, c)| c == '<').is_none() =>
+            {
+                return None;
+            }
+            _ => {}
+        }
+        previous = Some(c);
+    }
+    Some(protected)
+}
+
+/// `--normalize` as a [`Transform`], tidying up the end of every command with [`normalize_end`].
+#[derive(Debug, Clone)]
+pub struct Normalize {
+    mode: NormalizeMode,
+    modified: Arc<AtomicUsize>,
+}
+
+impl Normalize {
+    /// Normalize as `mode` says.
+    pub fn new(mode: NormalizeMode) -> Self {
+        Self { mode, modified: Arc::default() }
+    }
+
+    /// How many commands were modified so far.
+    pub fn modified(&self) -> usize {
+        self.modified.load(Ordering::Relaxed)
+    }
+}
+
+impl Transform for Normalize {
+    fn apply(&self, mut entry: Entry) -> Option<Entry> {
+        let len = normalize_end(&entry.cmd, self.mode).len();
+        if len < entry.cmd.len() {
+            entry.cmd.truncate(len);
+            self.modified.fetch_add(1, Ordering::Relaxed);
+        }
+        Some(entry)
+    }
+
+    fn summary(&self) -> Option<String> {
+        match self.modified() {
+            0 => None,
+            n => Some(format!("normalized the end of {n} commands")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn basic(cmd: &str) -> &str {
+        normalize_end(cmd, NormalizeMode::Basic)
+    }
+
+    fn aggressive(cmd: &str) -> &str {
+        normalize_end(cmd, NormalizeMode::Aggressive)
+    }
+
+    #[test]
+    fn trailing_whitespace_and_semicolons() {
+        assert_eq!(basic("git push   "), "git push");
+        assert_eq!(basic("make build ;"), "make build");
+        assert_eq!(basic("make build; ; \t"), "make build");
+        assert_eq!(basic("ls; pwd"), "ls; pwd");
+    }
+
+    #[test]
+    fn dangling_operators_only_when_aggressive() {
+        assert_eq!(basic("cargo test &&"), "cargo test &&");
+        assert_eq!(aggressive("cargo test &&"), "cargo test");
+        assert_eq!(aggressive("make || ;"), "make");
+        assert_eq!(aggressive("a && b"), "a && b");
+        // a lone `&` runs in the background, and is kept
+        assert_eq!(aggressive("sleep 10 &"), "sleep 10 &");
+    }
+
+    #[test]
+    fn quotes_and_escapes_are_left_alone() {
+        assert_eq!(basic("echo 'a ;'"), "echo 'a ;'");
+        assert_eq!(basic("echo \"a ;\" ;"), "echo \"a ;\"");
+        assert_eq!(basic("echo $'\\' ;' ;"), "echo $'\\' ;'");
+        assert_eq!(basic(r"find . -exec rm {} \;"), r"find . -exec rm {} \;");
+        // ending within quotes, which a later line would have closed
+        assert_eq!(basic("echo 'a ;"), "echo 'a ;");
+    }
+
+    #[test]
+    fn heredocs_are_left_alone() {
+        let cmd = "cat <<EOF\nx ;\nEOF ";
+        assert_eq!(basic(cmd), cmd);
+        assert_eq!(basic("cat <<< 'x' ;"), "cat <<< 'x'");
+    }
+
+    #[test]
+    fn case_and_empty_commands_are_left_alone() {
+        assert_eq!(basic("case $x in a) ls;;"), "case $x in a) ls;;");
+        assert_eq!(basic(";"), ";");
+        assert_eq!(aggressive(" && "), " && ");
+    }
+
+    #[test]
+    fn modified_commands_are_counted() {
+        let normalize = Normalize::new(NormalizeMode::Basic);
+        let entry = |cmd: &str| Entry { cmd: cmd.to_string(), ..Default::default() };
+        assert_eq!(normalize.apply(entry("ls ;")).unwrap().cmd, "ls");
+        assert_eq!(normalize.apply(entry("ls")).unwrap().cmd, "ls");
+        assert_eq!(normalize.summary(), Some("normalized the end of 1 commands".into()));
+    }
+}
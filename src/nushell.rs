@@ -0,0 +1,135 @@
+//! Reading and writing nushell's history, in either of the formats it can keep it in.
+
+use std::path::Path;
+
+use anyhow::Result;
+
+use crate::{bash::ParsedEntry, history_converter::Entry};
+
+/// How nushell's plain text history, `history.txt`, writes a newline in a command.
+pub(crate) const NEWLINE_ESCAPE: &str = "<\\n>";
+
+/// Parse nushell's plain text history, `history.txt`, returning every entry with the line it is
+/// on. Each line is a command, with its newlines written as `<\n>`. There are no timestamps, so
+/// every entry has a `when` of 0. Empty lines are skipped.
+pub fn parse_nushell_history(text: &str) -> Vec<ParsedEntry> {
+    (1..)
+        .zip(text.lines())
+        .map(|(line_number, line)| (line_number, line.strip_suffix('\r').unwrap_or(line)))
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(line_number, line)| {
+            let entry = Entry {
+                cmd: line.replace(NEWLINE_ESCAPE, "\n"),
+                ..Default::default()
+            };
+            (line_number, Ok(entry))
+        })
+        .collect()
+}
+
+/// Read nushell's SQLite history, `history.sqlite3`, returning every entry with its row id in
+/// place of a line.
+///
+/// Each row of the `history` table becomes an entry, with its `start_timestamp` and
+/// `duration_ms`, which nushell keeps in milliseconds, in seconds. Blank commands are skipped.
+/// Only rows started within `since` and `until`, both inclusive, are read, which the query itself
+/// filters for. The database is opened read-only.
+pub fn read_nushell(
+    path: &Path,
+    since: Option<i64>,
+    until: Option<i64>,
+) -> Result<Vec<ParsedEntry>> {
+    query(path, since, until)
+}
+
+/// Write the entries into nushell's SQLite history, `history.sqlite3`, creating the `history`
+/// table if there is none, and returning how many rows were added.
+///
+/// The `when` and `duration` of each entry become its `start_timestamp` and `duration_ms`, with
+/// a `when` of 0 as no `start_timestamp` at all. The exit status is left out, as zsh does not
+/// record it. The rows are inserted in a single transaction.
+pub fn write_nushell(path: &Path, entries: &[Entry]) -> Result<usize> {
+    insert(path, entries)
+}
+
+#[cfg(feature = "sqlite")]
+const MILLIS: i64 = 1000;
+
+#[cfg(feature = "sqlite")]
+fn query(path: &Path, since: Option<i64>, until: Option<i64>) -> Result<Vec<ParsedEntry>> {
+    use rusqlite::params;
+
+    use crate::sqlite;
+
+    let db = sqlite::open(path)?;
+    let mut statement = db.prepare(
+        "SELECT id, command_line, start_timestamp, duration_ms FROM history
+         WHERE trim(command_line) != ''
+           AND (?1 IS NULL OR start_timestamp >= ?1 * 1000)
+           AND (?2 IS NULL OR start_timestamp < (?2 + 1) * 1000)
+         ORDER BY start_timestamp, id",
+    )?;
+    let rows = statement.query_map(params![since, until], |row| {
+        let duration = row.get::<_, Option<i64>>(3)?.filter(|&duration| duration >= 0);
+        let entry = Entry {
+            cmd: row.get(1)?,
+            when: row.get::<_, Option<i64>>(2)?.unwrap_or_default().div_euclid(MILLIS),
+            duration: duration.map(|duration| (duration + MILLIS / 2) / MILLIS),
+            ..Default::default()
+        };
+        Ok((row.get::<_, i64>(0)? as usize, Ok(entry)))
+    })?;
+
+    Ok(rows.collect::<rusqlite::Result<_>>()?)
+}
+
+#[cfg(feature = "sqlite")]
+fn insert(path: &Path, entries: &[Entry]) -> Result<usize> {
+    use rusqlite::{params, Connection};
+
+    // the table reedline, nushell's line editor, creates
+    const SCHEMA: &str = "
+        CREATE TABLE IF NOT EXISTS history (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            command_line TEXT NOT NULL,
+            start_timestamp INTEGER,
+            session_id INTEGER,
+            hostname TEXT,
+            cwd TEXT,
+            duration_ms INTEGER,
+            exit_status INTEGER,
+            more_info TEXT
+        ) STRICT;
+        CREATE INDEX IF NOT EXISTS idx_history_time ON history(start_timestamp);
+        CREATE INDEX IF NOT EXISTS idx_history_cwd ON history(cwd);
+        CREATE INDEX IF NOT EXISTS idx_history_exit_status ON history(exit_status);
+        CREATE INDEX IF NOT EXISTS idx_history_cmd ON history(command_line);";
+
+    let mut db = Connection::open(path)?;
+    db.execute_batch(SCHEMA)?;
+    let transaction = db.transaction()?;
+    let mut added = 0;
+    {
+        let mut statement = transaction.prepare(
+            "INSERT INTO history (command_line, start_timestamp, duration_ms) VALUES (?1, ?2, ?3)",
+        )?;
+        for entry in entries {
+            let when = (entry.when != 0).then_some(entry.when * MILLIS);
+            let duration = entry.duration.map(|duration| duration * MILLIS);
+            added += statement.execute(params![entry.cmd, when, duration])?;
+        }
+    }
+    transaction.commit()?;
+
+    Ok(added)
+}
+
+#[cfg(not(feature = "sqlite"))]
+fn query(_: &Path, _: Option<i64>, _: Option<i64>) -> Result<Vec<ParsedEntry>> {
+    Err(crate::sqlite::unsupported())
+}
+
+#[cfg(not(feature = "sqlite"))]
+fn insert(_: &Path, _: &[Entry]) -> Result<usize> {
+    Err(crate::sqlite::unsupported())
+}
@@ -0,0 +1,962 @@
+//! The formats converted entries are written in, as implementations of [`OutputFormat`].
+//!
+//! Each built-in [`Format`](crate::Format) which is written to a stream has one here, and
+//! [`Builtin`] picks among them. A format of your own only needs to implement [`OutputFormat`] to
+//! be written with [`write_entries`] or [`Writer::write_as`](crate::Writer::write_as).
+
+use std::{borrow::Cow, future::Future, io, path::PathBuf};
+
+use anstream::adapter::strip_str;
+use anstyle::Style;
+use chrono::{Local, SecondsFormat, TimeZone};
+use clap::ValueEnum;
+use serde::Serialize;
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+use crate::{
+    abbr::{suggest_abbreviations, AbbrOptions},
+    filter::escape_controls,
+    fish_history::escape,
+    format::{quote, BashOptions, JsonOptions, Multiline, PlainOptions, PrettyOptions, Table},
+    history_converter::Entry,
+    nushell::NEWLINE_ESCAPE,
+    verbosity::info,
+    zoxide::{directory_visits, Visit},
+    zsh::metafy,
+};
+
+/// fish script invocations are kept below this many bytes, so no single command line gets
+/// unreasonably long.
+const SCRIPT_CHUNK_BYTES: usize = 64 * 1024;
+
+/// A format to write entries in, an entry at a time, so that a format does not need to hold the
+/// whole history unless it has to look at all of it first.
+///
+/// [`write_entries`] calls `begin` once, then `write_entry` for every entry, then `end` once.
+/// `begin` and `end` do nothing unless implemented.
+pub trait OutputFormat: Send {
+    /// Write what comes before the entries, like a header.
+    fn begin<W>(&mut self, writer: &mut W) -> impl Future<Output = io::Result<()>> + Send
+    where
+        W: AsyncWrite + Unpin + Send,
+    {
+        let _ = writer;
+        async { Ok(()) }
+    }
+
+    /// Write a single entry.
+    fn write_entry<W>(
+        &mut self,
+        writer: &mut W,
+        entry: &Entry,
+    ) -> impl Future<Output = io::Result<()>> + Send
+    where
+        W: AsyncWrite + Unpin + Send;
+
+    /// Write what comes after the entries, like the end of a JSON array.
+    fn end<W>(&mut self, writer: &mut W) -> impl Future<Output = io::Result<()>> + Send
+    where
+        W: AsyncWrite + Unpin + Send,
+    {
+        let _ = writer;
+        async { Ok(()) }
+    }
+}
+
+/// Write the entries to the given writer in `format`, and flush it.
+pub async fn write_entries<F, W>(
+    format: &mut F,
+    writer: &mut W,
+    entries: &[Entry],
+) -> io::Result<()>
+where
+    F: OutputFormat,
+    W: AsyncWrite + Unpin + Send,
+{
+    format.begin(writer).await?;
+    for entry in entries {
+        format.write_entry(writer, entry).await?;
+    }
+    format.end(writer).await?;
+    writer.flush().await
+}
+
+/// The fish history file format.
+#[derive(Debug, Default, Clone)]
+pub struct Fish;
+
+impl OutputFormat for Fish {
+    async fn write_entry<W>(&mut self, writer: &mut W, entry: &Entry) -> io::Result<()>
+    where
+        W: AsyncWrite + Unpin + Send,
+    {
+        writer.write_all(format!("{entry}\n").as_bytes()).await
+    }
+}
+
+/// A fish script which adds the entries with `builtin history append` when sourced. Since
+/// `history append` does not take a timestamp, fish records the time the script is sourced.
+#[derive(Debug, Default, Clone)]
+pub struct FishScript {
+    // the quoted commands of the next `history append`
+    chunk: String,
+}
+
+impl FishScript {
+    async fn flush_chunk<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: AsyncWrite + Unpin + Send,
+    {
+        let line = format!("builtin history append --{}\n", self.chunk);
+        self.chunk.clear();
+        writer.write_all(line.as_bytes()).await
+    }
+}
+
+impl OutputFormat for FishScript {
+    async fn begin<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: AsyncWrite + Unpin + Send,
+    {
+        let header = concat!(
+            "#!/usr/bin/env fish\n",
+            "# Generated by zsh-history-to-fish. Review it, then run `source <this file>` in fish.\n",
+            // in the syntax fish shares with sh, so sh stops before the lines only fish parses
+            "test -n \"$FISH_VERSION\" || echo 'this script must be sourced by fish' >&2\n",
+            "test -n \"$FISH_VERSION\" || return 1 2>/dev/null\n",
+            "test -n \"$FISH_VERSION\" || exit 1\n",
+        );
+        writer.write_all(header.as_bytes()).await
+    }
+
+    async fn write_entry<W>(&mut self, writer: &mut W, entry: &Entry) -> io::Result<()>
+    where
+        W: AsyncWrite + Unpin + Send,
+    {
+        let quoted = quote(&entry.cmd);
+        if !self.chunk.is_empty() && self.chunk.len() + quoted.len() > SCRIPT_CHUNK_BYTES {
+            self.flush_chunk(writer).await?;
+        }
+        self.chunk.push(' ');
+        self.chunk.push_str(&quoted);
+        Ok(())
+    }
+
+    async fn end<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: AsyncWrite + Unpin + Send,
+    {
+        match self.chunk.is_empty() {
+            true => Ok(()),
+            false => self.flush_chunk(writer).await,
+        }
+    }
+}
+
+/// The object of an entry in the `json` and `jsonl` formats, with where it was read from if
+/// annotated.
+#[derive(Serialize)]
+struct Object<'a> {
+    #[serde(flatten)]
+    entry: &'a Entry,
+    #[serde(flatten)]
+    annotation: Option<Annotation<'a>>,
+}
+
+#[derive(Serialize)]
+struct Annotation<'a> {
+    line: Option<usize>,
+    raw: Option<&'a str>,
+}
+
+// The JSON object of `entry`, annotated as `options` say.
+fn to_json(entry: &Entry, options: JsonOptions) -> serde_json::Result<Vec<u8>> {
+    let annotation = options.annotate.then(|| Annotation {
+        line: entry.origin.as_ref().map(|origin| origin.line),
+        raw: entry.origin.as_ref().and_then(|origin| origin.raw.as_deref()),
+    });
+    serde_json::to_vec(&Object { entry, annotation })
+}
+
+/// A JSON array of the entries, one per line.
+#[derive(Debug, Default, Clone)]
+pub struct Json {
+    options: JsonOptions,
+    written: usize,
+}
+
+impl Json {
+    /// The entries written with the given options.
+    pub fn new(options: JsonOptions) -> Self {
+        Self { options, written: 0 }
+    }
+}
+
+impl OutputFormat for Json {
+    async fn begin<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: AsyncWrite + Unpin + Send,
+    {
+        writer.write_all(b"[").await
+    }
+
+    async fn write_entry<W>(&mut self, writer: &mut W, entry: &Entry) -> io::Result<()>
+    where
+        W: AsyncWrite + Unpin + Send,
+    {
+        let separator = if self.written == 0 { "\n  " } else { ",\n  " };
+        self.written += 1;
+        writer.write_all(separator.as_bytes()).await?;
+        writer.write_all(&to_json(entry, self.options)?).await
+    }
+
+    async fn end<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: AsyncWrite + Unpin + Send,
+    {
+        let end = if self.written == 0 { "]\n" } else { "\n]\n" };
+        writer.write_all(end.as_bytes()).await
+    }
+}
+
+/// A JSON object per line.
+#[derive(Debug, Default, Clone)]
+pub struct Jsonl {
+    options: JsonOptions,
+}
+
+impl Jsonl {
+    /// The entries written with the given options.
+    pub fn new(options: JsonOptions) -> Self {
+        Self { options }
+    }
+}
+
+impl OutputFormat for Jsonl {
+    async fn write_entry<W>(&mut self, writer: &mut W, entry: &Entry) -> io::Result<()>
+    where
+        W: AsyncWrite + Unpin + Send,
+    {
+        let mut line = to_json(entry, self.options)?;
+        line.push(b'\n');
+        writer.write_all(&line).await
+    }
+}
+
+/// Delimiter-separated values, quoted as RFC 4180 describes, with the columns of a [`Table`].
+#[derive(Debug, Clone)]
+pub struct Delimited {
+    delimiter: u8,
+    table: Table,
+}
+
+impl Delimited {
+    /// Comma-separated values.
+    pub fn csv(table: Table) -> Self {
+        Self { delimiter: b',', table }
+    }
+
+    /// Tab-separated values.
+    pub fn tsv(table: Table) -> Self {
+        Self { delimiter: b'\t', table }
+    }
+
+    // A record of `fields`, quoted where necessary.
+    fn record(&self, fields: impl Iterator<Item = String>) -> io::Result<Vec<u8>> {
+        let mut csv = csv::WriterBuilder::new()
+            .delimiter(self.delimiter)
+            .from_writer(Vec::new());
+        csv.write_record(fields)?;
+        csv.into_inner().map_err(|e| e.into_error())
+    }
+}
+
+impl OutputFormat for Delimited {
+    async fn begin<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: AsyncWrite + Unpin + Send,
+    {
+        if !self.table.header {
+            return Ok(());
+        }
+        let names = self
+            .table
+            .columns
+            .iter()
+            .map(|column| column.to_possible_value().unwrap().get_name().to_string());
+        writer.write_all(&self.record(names)?).await
+    }
+
+    async fn write_entry<W>(&mut self, writer: &mut W, entry: &Entry) -> io::Result<()>
+    where
+        W: AsyncWrite + Unpin + Send,
+    {
+        let fields = self.table.columns.iter().map(|column| column.field(entry));
+        writer.write_all(&self.record(fields)?).await
+    }
+}
+
+/// The commands alone, one per line.
+#[derive(Debug, Default, Clone)]
+pub struct Plain {
+    options: PlainOptions,
+}
+
+impl Plain {
+    /// The commands written with the given options.
+    pub fn new(options: PlainOptions) -> Self {
+        Self { options }
+    }
+
+    // The line of `entry`, unless it is left out.
+    fn line(&self, entry: &Entry) -> Option<String> {
+        let cmd = match self.options.multiline {
+            Multiline::Escape => escape(&entry.cmd),
+            Multiline::Join => entry
+                .cmd
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty())
+                .collect::<Vec<_>>()
+                .join("; "),
+            Multiline::Skip if entry.cmd.contains('\n') => return None,
+            Multiline::Skip => entry.cmd.clone(),
+        };
+        if !self.options.with_time {
+            return Some(cmd);
+        }
+        let time = Local.timestamp_opt(entry.when, 0).single()?;
+        Some(format!("{}\t{cmd}", time.to_rfc3339_opts(SecondsFormat::Secs, false)))
+    }
+}
+
+impl OutputFormat for Plain {
+    async fn write_entry<W>(&mut self, writer: &mut W, entry: &Entry) -> io::Result<()>
+    where
+        W: AsyncWrite + Unpin + Send,
+    {
+        match self.line(entry) {
+            Some(line) => writer.write_all(format!("{line}\n").as_bytes()).await,
+            None => Ok(()),
+        }
+    }
+}
+
+/// The commands alone, each ended by a NUL byte. Commands with a NUL byte of their own are
+/// skipped, as it would split them in two, and the number of them is reported at the end.
+#[derive(Debug, Default, Clone)]
+pub struct Plain0 {
+    skipped: usize,
+}
+
+impl Plain0 {
+    // What is reported at the end about the commands skipped.
+    fn warning(&self) -> Option<String> {
+        match self.skipped {
+            0 => None,
+            n => Some(format!("skipped {n} entries with a NUL byte in the command")),
+        }
+    }
+}
+
+impl OutputFormat for Plain0 {
+    async fn write_entry<W>(&mut self, writer: &mut W, entry: &Entry) -> io::Result<()>
+    where
+        W: AsyncWrite + Unpin + Send,
+    {
+        if entry.cmd.contains('\0') {
+            self.skipped += 1;
+            return Ok(());
+        }
+        writer.write_all(entry.cmd.as_bytes()).await?;
+        writer.write_all(b"\0").await
+    }
+
+    async fn end<W>(&mut self, _: &mut W) -> io::Result<()>
+    where
+        W: AsyncWrite + Unpin + Send,
+    {
+        if let Some(warning) = self.warning() {
+            info!("{warning}");
+        }
+        Ok(())
+    }
+}
+
+// How wide the time of an entry is in the `pretty` format, and what each further line of a
+// multi-line command starts with.
+const TIME_WIDTH: usize = "1970-01-01T00:00:00+00:00".len();
+const CONTINUATION: &str = "↩";
+
+/// Each entry on a line to read in a terminal, the time, dimmed, before the command, and each
+/// further line of a multi-line command under it after a `↩`. Escape sequences in the commands
+/// are stripped and the other control characters escaped, so they cannot garble the terminal.
+#[derive(Debug, Default, Clone)]
+pub struct Pretty {
+    options: PrettyOptions,
+}
+
+impl Pretty {
+    /// The entries written with the given options.
+    pub fn new(options: PrettyOptions) -> Self {
+        Self { options }
+    }
+
+    // `s` dimmed, if colored.
+    fn dim(&self, s: &str) -> String {
+        let dim = Style::new().dimmed();
+        match self.options.color {
+            true => format!("{dim}{s}{dim:#}"),
+            false => s.to_string(),
+        }
+    }
+
+    // `line` fit into what the width leaves after `indent` columns.
+    fn fit<'a>(&self, line: &'a str, indent: usize) -> Cow<'a, str> {
+        match self.options.width {
+            Some(width) => truncate_to_width(line, width.saturating_sub(indent)),
+            None => Cow::Borrowed(line),
+        }
+    }
+}
+
+impl OutputFormat for Pretty {
+    async fn write_entry<W>(&mut self, writer: &mut W, entry: &Entry) -> io::Result<()>
+    where
+        W: AsyncWrite + Unpin + Send,
+    {
+        let time = match entry.when {
+            0 => None,
+            when => Local.timestamp_opt(when, 0).single(),
+        };
+        let time =
+            time.map_or("-".to_string(), |time| time.to_rfc3339_opts(SecondsFormat::Secs, false));
+        let cmd = strip_str(&entry.cmd).to_string().replace('\t', "    ");
+
+        let mut out = String::new();
+        for (i, line) in cmd.split('\n').enumerate() {
+            let line = escape_controls(line);
+            let indent = match i {
+                0 => {
+                    out.push_str(&self.dim(&format!("{time:<TIME_WIDTH$}")));
+                    TIME_WIDTH + 1
+                }
+                _ => {
+                    out.push_str(&" ".repeat(TIME_WIDTH + 1));
+                    out.push_str(&self.dim(CONTINUATION));
+                    TIME_WIDTH + CONTINUATION.width() + 2
+                }
+            };
+            out.push(' ');
+            out.push_str(&self.fit(&line, indent));
+            out.push('\n');
+        }
+        if let Some(origin) = entry.origin.as_ref().filter(|_| self.options.annotate) {
+            // the line it was read from, and its record under the command
+            let raw = origin.raw.as_deref().unwrap_or_default();
+            for (i, raw) in raw.split('\n').enumerate() {
+                let line = match i {
+                    0 => format!("{}: {}", origin.line, escape_controls(raw)),
+                    _ => format!(
+                        "{:width$}  {}",
+                        "",
+                        escape_controls(raw),
+                        width = digits(origin.line)
+                    ),
+                };
+                out.push_str(&" ".repeat(TIME_WIDTH + 1));
+                out.push_str(&self.dim(&self.fit(&line, TIME_WIDTH + 1)));
+                out.push('\n');
+            }
+        }
+        writer.write_all(out.as_bytes()).await
+    }
+}
+
+// The number of decimal digits of `n`.
+fn digits(n: usize) -> usize {
+    n.checked_ilog10().map_or(1, |log| log as usize + 1)
+}
+
+/// Cut `line` off to fit into `width` columns of a terminal, ending it with `…` if anything had
+/// to go. Wide characters, like most CJK ones, take two columns.
+///
+/// ```
+/// use zsh_history_to_fish::output::truncate_to_width;
+///
+/// assert_eq!(truncate_to_width("git status", 10), "git status");
+/// assert_eq!(truncate_to_width("git status", 6), "git s…");
+/// assert_eq!(truncate_to_width("echo 日本語", 9), "echo 日…");
+/// assert_eq!(truncate_to_width("git status", 1), "…");
+/// assert_eq!(truncate_to_width("git status", 0), "");
+/// ```
+pub fn truncate_to_width(line: &str, width: usize) -> Cow<'_, str> {
+    if line.width() <= width {
+        return Cow::Borrowed(line);
+    }
+    if width == 0 {
+        return Cow::Borrowed("");
+    }
+    let (mut used, mut end) = (0, 0);
+    for (i, c) in line.char_indices() {
+        let c_width = c.width().unwrap_or(0);
+        // leaving a column for the ellipsis
+        if used + c_width >= width {
+            break;
+        }
+        (used, end) = (used + c_width, i + c.len_utf8());
+    }
+    Cow::Owned(format!("{}…", &line[..end]))
+}
+
+/// A zsh history file with `EXTENDED_HISTORY` records, `: <when>:<duration>;<command>`, metafied
+/// like zsh writes it. The newlines of a command are written as `\` and a newline, which zsh reads
+/// back as a single command. Entries without a timestamp are written as the command alone.
+#[derive(Debug, Default, Clone)]
+pub struct Zsh;
+
+impl OutputFormat for Zsh {
+    async fn write_entry<W>(&mut self, writer: &mut W, entry: &Entry) -> io::Result<()>
+    where
+        W: AsyncWrite + Unpin + Send,
+    {
+        let cmd = entry.cmd.replace('\n', "\\\n");
+        let record = match entry.when {
+            0 => cmd,
+            when => format!(": {when}:{};{cmd}", entry.duration.unwrap_or(0)),
+        };
+        let mut line = metafy(record.as_bytes());
+        line.push(b'\n');
+        writer.write_all(&line).await
+    }
+}
+
+/// A bash history file. With timestamps, each command follows a `#<when>` comment and keeps its
+/// newlines, which bash reads back as a single command no matter how `lithist` is set. An entry
+/// without a timestamp follows no comment, unless one was written before, after which bash would
+/// take the entry for a part of the command before it, so it follows `#0` instead. bash only
+/// joins the lines of a command if the history starts with a comment, so the entries of a history
+/// starting without a timestamp are held back until a command spanning several lines shows, and
+/// then all follow comments. Without timestamps, bash reads each line as a command of its own, so
+/// a command spanning several lines fails to be written.
+#[derive(Debug, Default, Clone)]
+pub struct Bash {
+    options: BashOptions,
+    // whether a timestamp was written yet
+    timestamped: bool,
+    // the entries held back since the first, which had no timestamp
+    held: Vec<Entry>,
+}
+
+impl Bash {
+    /// A bash history written with the given options.
+    pub fn new(options: BashOptions) -> Self {
+        Self { options, timestamped: false, held: Vec::new() }
+    }
+
+    // Write `entry`, after a comment if `timestamped`, or if it has a timestamp and one may be
+    // written.
+    async fn write_record<W>(
+        &mut self,
+        writer: &mut W,
+        entry: &Entry,
+        timestamped: bool,
+    ) -> io::Result<()>
+    where
+        W: AsyncWrite + Unpin + Send,
+    {
+        if timestamped || (self.options.timestamps && (entry.when != 0 || self.timestamped)) {
+            writer.write_all(format!("#{}\n", entry.when).as_bytes()).await?;
+            self.timestamped = true;
+        }
+        writer.write_all(format!("{}\n", entry.cmd).as_bytes()).await
+    }
+}
+
+impl OutputFormat for Bash {
+    async fn write_entry<W>(&mut self, writer: &mut W, entry: &Entry) -> io::Result<()>
+    where
+        W: AsyncWrite + Unpin + Send,
+    {
+        let multiline = entry.cmd.contains('\n');
+        if !self.options.timestamps && multiline {
+            let error = format!(
+                "{:?} spans several lines, which bash reads back as a command each without the \
+                 `#<when>` comments; drop such commands with --skip-multiline",
+                entry.cmd,
+            );
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, error));
+        }
+        let starts_undated = !self.held.is_empty() || (entry.when == 0 && !self.timestamped);
+        if !self.options.timestamps || !starts_undated {
+            return self.write_record(writer, entry, false).await;
+        }
+        if !multiline {
+            self.held.push(entry.clone());
+            return Ok(());
+        }
+        for held in std::mem::take(&mut self.held) {
+            self.write_record(writer, &held, true).await?;
+        }
+        self.write_record(writer, entry, true).await
+    }
+
+    async fn end<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: AsyncWrite + Unpin + Send,
+    {
+        // no command spans several lines, so the entries without a timestamp need no comment
+        for held in std::mem::take(&mut self.held) {
+            self.write_record(writer, &held, false).await?;
+        }
+        Ok(())
+    }
+}
+
+/// nushell's plain text history, with the newlines of a command written as `<\n>`.
+#[derive(Debug, Default, Clone)]
+pub struct Nushell;
+
+impl OutputFormat for Nushell {
+    async fn write_entry<W>(&mut self, writer: &mut W, entry: &Entry) -> io::Result<()>
+    where
+        W: AsyncWrite + Unpin + Send,
+    {
+        let line = entry.cmd.replace('\n', NEWLINE_ESCAPE);
+        writer.write_all(format!("{line}\n").as_bytes()).await
+    }
+}
+
+/// `abbr -a` lines for the commands typed most often. They are only known once all entries are
+/// seen, so the entries are kept until the end.
+#[derive(Debug, Default, Clone)]
+pub struct Abbr {
+    options: AbbrOptions,
+    entries: Vec<Entry>,
+}
+
+impl Abbr {
+    /// Abbreviations suggested with the given options.
+    pub fn new(options: AbbrOptions) -> Self {
+        Self { options, entries: Vec::new() }
+    }
+}
+
+impl OutputFormat for Abbr {
+    async fn write_entry<W>(&mut self, _: &mut W, entry: &Entry) -> io::Result<()>
+    where
+        W: AsyncWrite + Unpin + Send,
+    {
+        self.entries.push(entry.clone());
+        Ok(())
+    }
+
+    async fn end<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: AsyncWrite + Unpin + Send,
+    {
+        for abbr in suggest_abbreviations(&self.entries, &self.options) {
+            let line = format!("abbr -a {} {}\n", abbr.name, quote(&abbr.expansion));
+            writer.write_all(line.as_bytes()).await?;
+        }
+        Ok(())
+    }
+}
+
+/// The directories changed into, as the lines of z's data file. Like [`Abbr`], the entries are
+/// kept until the end. A leading `~` is expanded with `$HOME`.
+#[derive(Debug, Default, Clone)]
+pub struct Zoxide {
+    entries: Vec<Entry>,
+}
+
+impl OutputFormat for Zoxide {
+    async fn write_entry<W>(&mut self, _: &mut W, entry: &Entry) -> io::Result<()>
+    where
+        W: AsyncWrite + Unpin + Send,
+    {
+        self.entries.push(entry.clone());
+        Ok(())
+    }
+
+    async fn end<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: AsyncWrite + Unpin + Send,
+    {
+        let home = std::env::var_os("HOME").map(PathBuf::from);
+        for Visit { path, count, last } in directory_visits(&self.entries, home.as_deref()) {
+            let line = format!("{}|{count}|{last}\n", path.display());
+            writer.write_all(line.as_bytes()).await?;
+        }
+        Ok(())
+    }
+}
+
+/// One of the built-in formats, as [`Format::output`](crate::Format::output) picks it.
+#[derive(Debug, Clone)]
+pub enum Builtin {
+    Fish(Fish),
+    FishScript(FishScript),
+    Json(Json),
+    Jsonl(Jsonl),
+    Delimited(Delimited),
+    Plain(Plain),
+    Plain0(Plain0),
+    Pretty(Pretty),
+    Nushell(Nushell),
+    Zsh(Zsh),
+    Bash(Bash),
+    Abbr(Abbr),
+    Zoxide(Zoxide),
+}
+
+// Call the same method of whichever format `self` is.
+macro_rules! dispatch {
+    ($self:ident, $format:ident => $call:expr) => {
+        match $self {
+            Builtin::Fish($format) => $call,
+            Builtin::FishScript($format) => $call,
+            Builtin::Json($format) => $call,
+            Builtin::Jsonl($format) => $call,
+            Builtin::Delimited($format) => $call,
+            Builtin::Plain($format) => $call,
+            Builtin::Plain0($format) => $call,
+            Builtin::Pretty($format) => $call,
+            Builtin::Nushell($format) => $call,
+            Builtin::Zsh($format) => $call,
+            Builtin::Bash($format) => $call,
+            Builtin::Abbr($format) => $call,
+            Builtin::Zoxide($format) => $call,
+        }
+    };
+}
+
+impl OutputFormat for Builtin {
+    async fn begin<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: AsyncWrite + Unpin + Send,
+    {
+        dispatch!(self, format => format.begin(writer).await)
+    }
+
+    async fn write_entry<W>(&mut self, writer: &mut W, entry: &Entry) -> io::Result<()>
+    where
+        W: AsyncWrite + Unpin + Send,
+    {
+        dispatch!(self, format => format.write_entry(writer, entry).await)
+    }
+
+    async fn end<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: AsyncWrite + Unpin + Send,
+    {
+        dispatch!(self, format => format.end(writer).await)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format::Column;
+
+    fn entry(cmd: &str, when: i64) -> Entry {
+        Entry { cmd: cmd.to_string(), when, ..Default::default() }
+    }
+
+    async fn written(mut format: impl OutputFormat, entries: &[Entry]) -> io::Result<String> {
+        let mut out = Vec::new();
+        write_entries(&mut format, &mut out, entries).await?;
+        Ok(String::from_utf8(out).unwrap())
+    }
+
+    #[tokio::test]
+    async fn bash_keeps_newlines_after_a_timestamp() {
+        let entries = [entry("ls", 10), entry("for i in 1\ndo echo $i\ndone", 11), entry("pwd", 0)];
+        let bash = written(Bash::default(), &entries).await.unwrap();
+        assert_eq!(bash, "#10\nls\n#11\nfor i in 1\ndo echo $i\ndone\n#0\npwd\n");
+    }
+
+    #[tokio::test]
+    async fn bash_starts_with_a_comment_for_newlines() {
+        let entries = [entry("cd", 0), entry("ls", 10), entry("pwd", 0)];
+        let bash = written(Bash::default(), &entries).await.unwrap();
+        assert_eq!(bash, "cd\n#10\nls\n#0\npwd\n");
+
+        let entries = [entry("cd", 0), entry("ls", 10), entry("echo \\\nhi", 0)];
+        let bash = written(Bash::default(), &entries).await.unwrap();
+        assert_eq!(bash, "#0\ncd\n#10\nls\n#0\necho \\\nhi\n");
+    }
+
+    #[tokio::test]
+    async fn bash_without_timestamps_refuses_newlines() {
+        let bash = Bash::new(BashOptions { timestamps: false });
+        let plain = written(bash.clone(), &[entry("ls", 10), entry("pwd", 11)]).await;
+        assert_eq!(plain.unwrap(), "ls\npwd\n");
+
+        let error = written(bash, &[entry("ls", 10), entry("echo \\\nhi", 11)])
+            .await
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        assert!(error.to_string().contains("--skip-multiline"), "{error}");
+    }
+
+    #[tokio::test]
+    async fn json_carries_the_duration() {
+        let entries = [Entry { duration: Some(42), ..entry("make", 10) }, entry("ls", 11)];
+        let jsonl = written(Jsonl::default(), &entries).await.unwrap();
+        assert_eq!(
+            jsonl,
+            concat!(
+                r#"{"cmd":"make","when":10,"duration":42,"paths":[]}"#,
+                "\n",
+                r#"{"cmd":"ls","when":11,"duration":null,"paths":[]}"#,
+                "\n",
+            ),
+        );
+        // the fish history has no place for it
+        assert_eq!(written(Fish, &entries[..1]).await.unwrap(), "- cmd: make\n  when: 10\n");
+    }
+
+    // The records of a CSV or TSV, as a standard reader reads them.
+    fn read_csv(text: &str, delimiter: u8) -> Vec<Vec<String>> {
+        let mut reader = csv::ReaderBuilder::new()
+            .delimiter(delimiter)
+            .has_headers(false)
+            .from_reader(text.as_bytes());
+        let records = reader.records().map(|record| record.unwrap());
+        records
+            .map(|record| record.iter().map(String::from).collect())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn csv_quotes_what_needs_it() {
+        let entries = [entry("echo \"a\",\nb", 10), entry("ls", 11)];
+        let csv = written(Delimited::csv(Table::default()), &entries).await.unwrap();
+        assert_eq!(csv, "when,cmd\n10,\"echo \"\"a\"\",\nb\"\n11,ls\n");
+        let records = read_csv(&csv, b',');
+        assert_eq!(records, [["when", "cmd"], ["10", "echo \"a\",\nb"], ["11", "ls"]]);
+    }
+
+    #[tokio::test]
+    async fn tsv_quotes_tabs() {
+        let entries = [entry("printf 'a\tb'", 10), entry("a,b", 11)];
+        let tsv = written(Delimited::tsv(Table::default()), &entries).await.unwrap();
+        assert_eq!(tsv, "when\tcmd\n10\t\"printf 'a\tb'\"\n11\ta,b\n");
+        assert_eq!(read_csv(&tsv, b'\t')[1], ["10", "printf 'a\tb'"]);
+    }
+
+    #[tokio::test]
+    async fn columns_are_chosen_and_ordered() {
+        let table = Table {
+            columns: vec![Column::Cmd, Column::Duration, Column::When],
+            header: false,
+        };
+        let entries = [Entry { duration: Some(3), ..entry("make", 10) }, entry("ls", 11)];
+        let csv = written(Delimited::csv(table), &entries).await.unwrap();
+        assert_eq!(csv, "make,3,10\nls,,11\n");
+    }
+
+    // The plain lines of the entries with `multiline`.
+    async fn plain(multiline: Multiline, entries: &[Entry]) -> String {
+        let format = Plain::new(PlainOptions { multiline, with_time: false });
+        written(format, entries).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn plain_writes_multiline_commands_as_asked() {
+        let entries = [entry("for i in 1\n  echo $i\nend", 10), entry(r"grep \*", 11)];
+        let escaped = plain(Multiline::Escape, &entries).await;
+        assert_eq!(escaped, "for i in 1\\n  echo $i\\nend\ngrep \\\\*\n");
+        let joined = plain(Multiline::Join, &entries).await;
+        assert_eq!(joined, "for i in 1; echo $i; end\ngrep \\*\n");
+        assert_eq!(plain(Multiline::Skip, &entries).await, "grep \\*\n");
+    }
+
+    #[tokio::test]
+    async fn plain_with_time_starts_with_it() {
+        let format = Plain::new(PlainOptions { with_time: true, ..Default::default() });
+        let line = written(format, &[entry("ls", 1_700_000_000)]).await.unwrap();
+        let (time, cmd) = line.split_once('\t').unwrap();
+        assert_eq!(cmd, "ls\n");
+        let time = chrono::DateTime::parse_from_rfc3339(time).unwrap();
+        assert_eq!(time.timestamp(), 1_700_000_000);
+    }
+
+    #[tokio::test]
+    async fn plain0_skips_commands_with_a_nul_byte() {
+        let entries = [entry("ls", 10), entry("printf a\0b", 11), entry("echo a\nb", 12)];
+        let mut plain0 = Plain0::default();
+        let mut out = Vec::new();
+        write_entries(&mut plain0, &mut out, &entries).await.unwrap();
+        // newlines are kept, as the NUL bytes alone end the commands
+        assert_eq!(out, b"ls\0echo a\nb\0");
+        let warning = plain0.warning();
+        assert_eq!(warning.as_deref(), Some("skipped 1 entries with a NUL byte in the command"));
+        assert_eq!(Plain0::default().warning(), None);
+    }
+
+    #[tokio::test]
+    async fn zsh_metafies_and_continues_lines() {
+        let entries = [
+            Entry { duration: Some(2), ..entry("echo 日本", 10) },
+            entry("for i in 1\ndo echo $i\ndone", 11),
+            entry("ls", 0),
+        ];
+        let mut out = Vec::new();
+        write_entries(&mut Zsh, &mut out, &entries).await.unwrap();
+        let mut expected = b": 10:2;echo ".to_vec();
+        // `日本`, `e6 97 a5 e6 9c ac`, with 0x97 and 0x9c metafied
+        expected.extend([0xe6, 0x83, 0xb7, 0xa5, 0xe6, 0x83, 0xbc, 0xac]);
+        expected.extend(b"\n: 11:0;for i in 1\\\ndo echo $i\\\ndone\nls\n");
+        assert_eq!(out, expected);
+    }
+
+    // The entries written with `--format pretty`, uncolored, as wide as `width`.
+    async fn pretty(width: Option<usize>, entries: &[Entry]) -> String {
+        let options = PrettyOptions { color: false, width, annotate: false };
+        written(Pretty::new(options), entries).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn pretty_strips_escapes_and_expands_tabs() {
+        let out = pretty(None, &[entry("echo \x1b[31mred\x1b[0m\tdone", 0)]).await;
+        assert_eq!(out, format!("{:<TIME_WIDTH$} echo red    done\n", "-"));
+    }
+
+    #[tokio::test]
+    async fn pretty_indents_further_lines_after_a_continuation() {
+        let out = pretty(None, &[entry("for i in 1\ndo echo $i\ndone", 0)]).await;
+        let indent = " ".repeat(TIME_WIDTH + 1);
+        let expected =
+            format!("{:<TIME_WIDTH$} for i in 1\n{indent}↩ do echo $i\n{indent}↩ done\n", "-");
+        assert_eq!(out, expected);
+    }
+
+    #[tokio::test]
+    async fn pretty_cuts_lines_to_the_width() {
+        let width = TIME_WIDTH + 1 + 8;
+        let out = pretty(Some(width), &[entry("cargo build --release\ncargo test", 0)]).await;
+        let indent = " ".repeat(TIME_WIDTH + 1);
+        // further lines lose the two columns of the `↩ ` too
+        assert_eq!(out, format!("{:<TIME_WIDTH$} cargo b…\n{indent}↩ cargo…\n", "-"));
+        for line in out.lines() {
+            assert!(line.width() <= width, "{line:?}");
+        }
+    }
+
+    #[tokio::test]
+    async fn pretty_narrower_than_the_indent_leaves_the_time() {
+        let out = pretty(Some(10), &[entry("ls -la\npwd", 0)]).await;
+        let indent = " ".repeat(TIME_WIDTH + 1);
+        assert_eq!(out, format!("{:<TIME_WIDTH$} \n{indent}↩ \n", "-"));
+    }
+
+    #[tokio::test]
+    async fn pretty_in_full_leaves_lines_uncut() {
+        let cmd = format!("echo {}", "x".repeat(300));
+        let out = pretty(None, &[entry(&cmd, 0)]).await;
+        assert_eq!(out, format!("{:<TIME_WIDTH$} {cmd}\n", "-"));
+    }
+}
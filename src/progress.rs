@@ -0,0 +1,169 @@
+//! Showing how far reading a large history got, on stderr.
+
+use std::{
+    io::IsTerminal,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use tokio::io::{AsyncRead, ReadBuf};
+
+// Nothing is shown for a history read faster than this.
+const DELAY: Duration = Duration::from_secs(1);
+
+// How often the bar is redrawn, and how often a line is printed when stderr is no terminal.
+const BAR_INTERVAL: Duration = Duration::from_millis(100);
+const LINE_INTERVAL: Duration = Duration::from_secs(5);
+
+// How many records to read between looking at the clock.
+const CHECK_EVERY: usize = 256;
+
+/// A reader counting the bytes read through it, before any decompression, so the progress of a
+/// compressed history is that of the file being read.
+pub(crate) struct CountingReader<R> {
+    inner: R,
+    count: Arc<AtomicU64>,
+}
+
+impl<R> CountingReader<R> {
+    pub(crate) fn new(inner: R, count: Arc<AtomicU64>) -> Self {
+        Self { inner, count }
+    }
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for CountingReader<R> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let before = buf.filled().len();
+        let poll = Pin::new(&mut self.inner).poll_read(cx, buf);
+        let read = buf.filled().len() - before;
+        self.count.fetch_add(read as u64, Ordering::Relaxed);
+        poll
+    }
+}
+
+/// The progress of reading a history of `total` bytes, shown as a bar when stderr is a terminal,
+/// and as a line every few seconds otherwise, once reading takes longer than a second.
+pub(crate) struct Progress {
+    name: String,
+    total: u64,
+    read: Arc<AtomicU64>,
+    started: Instant,
+    shown: Option<Instant>,
+    calls: usize,
+    terminal: bool,
+    #[cfg(feature = "progress")]
+    bar: Option<indicatif::ProgressBar>,
+}
+
+impl Progress {
+    /// Track the progress of reading `name`, if `total` is known, counting what is read through
+    /// the readers made with [`counter`](Self::counter).
+    pub(crate) fn new(name: String, total: Option<u64>) -> Option<Self> {
+        Some(Self {
+            name,
+            total: total.filter(|&total| total > 0)?,
+            read: Arc::default(),
+            started: Instant::now(),
+            shown: None,
+            calls: 0,
+            terminal: cfg!(feature = "progress") && std::io::stderr().is_terminal(),
+            #[cfg(feature = "progress")]
+            bar: None,
+        })
+    }
+
+    /// The counter of the bytes read, for a [`CountingReader`].
+    pub(crate) fn counter(&self) -> Arc<AtomicU64> {
+        self.read.clone()
+    }
+
+    /// Update what is shown, now that `entries` entries were read.
+    pub(crate) fn tick(&mut self, entries: usize) {
+        self.calls += 1;
+        if !self.calls.is_multiple_of(CHECK_EVERY) {
+            return;
+        }
+        let now = Instant::now();
+        let interval = match self.terminal {
+            true => BAR_INTERVAL,
+            false => LINE_INTERVAL,
+        };
+        let due = match self.shown {
+            None => now - self.started >= DELAY,
+            Some(shown) => now - shown >= interval,
+        };
+        if due {
+            self.shown = Some(now);
+            self.show(entries, now);
+        }
+    }
+
+    /// Run `f`, which prints to stderr, without it garbling the bar.
+    pub(crate) fn suspend<T>(&self, f: impl FnOnce() -> T) -> T {
+        #[cfg(feature = "progress")]
+        if let Some(bar) = &self.bar {
+            return bar.suspend(f);
+        }
+        f()
+    }
+
+    /// Remove the bar, once reading is done.
+    pub(crate) fn finish(&mut self) {
+        #[cfg(feature = "progress")]
+        if let Some(bar) = self.bar.take() {
+            bar.finish_and_clear();
+        }
+    }
+
+    fn show(&mut self, entries: usize, now: Instant) {
+        let elapsed = (now - self.started).as_secs_f64();
+        let rate = (entries as f64 / elapsed) as u64;
+        let read = self.read.load(Ordering::Relaxed).min(self.total);
+
+        #[cfg(feature = "progress")]
+        if self.terminal {
+            let bar = self.bar.get_or_insert_with(|| {
+                let style = indicatif::ProgressStyle::with_template(
+                    "{prefix} [{bar:30}] {percent:>3}% {bytes}/{total_bytes}, {msg}, {eta} left",
+                )
+                .unwrap()
+                .progress_chars("=> ");
+                let bar = indicatif::ProgressBar::new(self.total).with_style(style);
+                bar.with_prefix(self.name.clone())
+            });
+            bar.set_position(read);
+            bar.set_message(format!("{entries} entries, {rate} entries/s"));
+            return;
+        }
+
+        let left = match read {
+            0 => String::new(),
+            read => {
+                let secs = elapsed * (self.total - read) as f64 / read as f64;
+                format!(", about {}s left", secs.round())
+            }
+        };
+        eprintln!(
+            "{}: read {} of {} MiB ({}%), {entries} entries, {rate} entries/s{left}",
+            self.name,
+            read / (1024 * 1024),
+            self.total / (1024 * 1024),
+            read * 100 / self.total,
+        );
+    }
+}
+
+impl Drop for Progress {
+    fn drop(&mut self) {
+        self.finish();
+    }
+}
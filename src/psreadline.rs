@@ -0,0 +1,93 @@
+//! Reading PowerShell's PSReadLine history files.
+
+use crate::{bash::ParsedEntry, history_converter::Entry};
+
+/// Parse a PSReadLine history file, `ConsoleHost_history.txt`, returning every entry with the line
+/// it starts on.
+///
+/// PSReadLine writes one command per line, and each line of a multi-line command but the last
+/// with a backtick at the end, which is dropped like PSReadLine does when reading the file back.
+/// There are no timestamps, so every entry has a `when` of 0. Empty lines are skipped.
+pub fn parse_psreadline_history(text: &str) -> Vec<ParsedEntry> {
+    let mut entries = Vec::new();
+    let mut cmd = String::new();
+    let mut start = 0;
+
+    for (line_number, line) in (1..).zip(text.lines()) {
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        if cmd.is_empty() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            start = line_number;
+        }
+        if let Some(line) = line.strip_suffix('`') {
+            cmd.push_str(line);
+            cmd.push('\n');
+            continue;
+        }
+        cmd.push_str(line);
+        let entry = Entry {
+            cmd: std::mem::take(&mut cmd),
+            ..Default::default()
+        };
+        entries.push((start, Ok(entry)));
+    }
+    // a continuation on the very last line
+    if !cmd.trim().is_empty() {
+        let entry = Entry {
+            cmd: cmd.trim_end().to_string(),
+            ..Default::default()
+        };
+        entries.push((start, Ok(entry)));
+    }
+
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The lines the entries start on, and their commands.
+    fn parsed(text: &str) -> Vec<(usize, String)> {
+        let entries = parse_psreadline_history(text).into_iter();
+        entries.map(|(line, entry)| (line, entry.unwrap().cmd)).collect()
+    }
+
+    #[test]
+    fn a_line_is_a_command() {
+        let parsed = parsed("Get-ChildItem\ncd C:\\Users\n");
+        assert_eq!(parsed, [(1, "Get-ChildItem".into()), (2, "cd C:\\Users".into())]);
+    }
+
+    #[test]
+    fn backticks_continue_the_command() {
+        let text = "Get-Process |`\n  Where-Object CPU |`\n  Sort-Object\nls\n";
+        let parsed = parsed(text);
+        let cmd = "Get-Process |\n  Where-Object CPU |\n  Sort-Object";
+        assert_eq!(parsed, [(1, cmd.into()), (4, "ls".into())]);
+    }
+
+    #[test]
+    fn crlf_line_endings() {
+        let parsed = parsed("dir `\r\n  -Force\r\n\r\nexit\r\n");
+        assert_eq!(parsed, [(1, "dir \n  -Force".into()), (4, "exit".into())]);
+    }
+
+    #[test]
+    fn empty_lines_are_skipped() {
+        assert_eq!(parsed("\n \nls\n\n"), [(3, "ls".into())]);
+        assert!(parsed("").is_empty());
+    }
+
+    #[test]
+    fn a_continuation_at_the_end_is_kept() {
+        assert_eq!(parsed("ls\nGet-Item `"), [(1, "ls".into()), (2, "Get-Item".into())]);
+    }
+
+    #[test]
+    fn backticks_elsewhere_are_kept() {
+        assert_eq!(parsed("echo `t`n\n"), [(1, "echo `t`n".into())]);
+    }
+}
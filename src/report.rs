@@ -0,0 +1,354 @@
+//! The JSON report `--report` writes at the end of a run, for other tools to read instead of
+//! stderr.
+
+use std::{
+    fmt::Display,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::{Failure, SkipReason, SkippedLine},
+    history_converter::ConversionReport,
+};
+
+/// The version of the fields of a [`RunReport`], raised whenever one is renamed, removed, or
+/// changes its meaning. Adding a field keeps it.
+pub const REPORT_VERSION: u32 = 1;
+
+/// What a run did, as `--report` writes it, whether it succeeded or not.
+///
+/// ```
+/// use std::path::PathBuf;
+///
+/// use zsh_history_to_fish::{DroppedReport, RunReport, REPORT_VERSION};
+///
+/// let mut report = RunReport::new();
+/// report.dropped.push(DroppedReport { by: "--exclude".to_string(), entries: 3 });
+/// report.written = 42;
+/// report.destination = Some(PathBuf::from("fish_history"));
+/// report.fail(&anyhow::anyhow!("disk full"));
+///
+/// let json = serde_json::to_string(&report).unwrap();
+/// assert!(json.starts_with(&format!(r#"{{"report_version":{REPORT_VERSION},"status":"error""#)));
+/// assert_eq!(serde_json::from_str::<RunReport>(&json).unwrap(), report);
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunReport {
+    /// The version of the fields, [`REPORT_VERSION`].
+    pub report_version: u32,
+    /// Whether the run succeeded.
+    pub status: RunStatus,
+    /// What went wrong, with its causes, if the run failed.
+    pub error: Option<String>,
+    /// The exit status of the run.
+    pub exit_code: u8,
+    /// What was read, with the histories and how many entries each had.
+    pub read: ConversionReport,
+    /// How many entries each filter dropped, in the order they ran.
+    pub dropped: Vec<DroppedReport>,
+    /// The duplicates removed, by `--unique` or when merging.
+    pub duplicates: usize,
+    /// The entries written.
+    pub written: usize,
+    /// Where the entries were written, or `None` for stdout.
+    pub destination: Option<PathBuf>,
+    /// How long the run took, in seconds.
+    pub duration_secs: f64,
+    /// The lines skipped, or converted despite looking wrong.
+    pub warnings: Vec<Warning>,
+    /// How long each phase took, with `--timing`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timing: Option<Timing>,
+}
+
+/// How long each phase of a run took, and how fast it went, as `--timing` prints it.
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use zsh_history_to_fish::{Phase, Timing};
+///
+/// let mut timing = Timing::default();
+/// timing.add(Phase::Reading, Duration::from_millis(1500));
+/// timing.add(Phase::Filtering, Duration::from_millis(250));
+/// timing.add(Phase::Reading, Duration::from_millis(500));
+/// timing.finish(Duration::from_secs(4), 20_000_000, 100_000);
+/// assert_eq!(timing.phases[0].secs, 2.0);
+/// assert_eq!((timing.bytes_per_sec, timing.entries_per_sec), (5_000_000.0, 25_000.0));
+/// assert_eq!(
+///     timing.to_string(),
+///     "reading       2.000s\n\
+///      filtering     0.250s\n\
+///      total         4.000s, 5.0 MB/s, 25000 entries/s\n",
+/// );
+/// ```
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Timing {
+    /// The phases, in the order they first ran, each with how long it took in all.
+    pub phases: Vec<PhaseTiming>,
+    /// How long the whole run took, in seconds.
+    pub total_secs: f64,
+    /// The bytes of the histories read per second of the run.
+    pub bytes_per_sec: f64,
+    /// The entries read per second of the run.
+    pub entries_per_sec: f64,
+}
+
+/// How long a phase of a run took, as [`Timing`] lists them.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PhaseTiming {
+    /// The phase.
+    pub phase: Phase,
+    /// How long it took, in seconds.
+    pub secs: f64,
+}
+
+/// A phase of a run, as [`Timing`] measures them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Phase {
+    /// Reading and parsing the histories.
+    Reading,
+    /// Sorting the entries.
+    Sorting,
+    /// Filtering the entries, but for dropping duplicates.
+    Filtering,
+    /// Dropping duplicates, with `--unique` or when merging.
+    Dedup,
+    /// Checking that the entries round-trip, with `--verify`.
+    Verifying,
+    /// Serializing and writing the entries.
+    Writing,
+}
+
+impl Display for Phase {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Reading => write!(f, "reading"),
+            Self::Sorting => write!(f, "sorting"),
+            Self::Filtering => write!(f, "filtering"),
+            Self::Dedup => write!(f, "dedup"),
+            Self::Verifying => write!(f, "verifying"),
+            Self::Writing => write!(f, "writing"),
+        }
+    }
+}
+
+impl Timing {
+    /// Add `took` to the time `phase` took.
+    pub fn add(&mut self, phase: Phase, took: Duration) {
+        match self.phases.iter_mut().find(|timing| timing.phase == phase) {
+            Some(timing) => timing.secs += took.as_secs_f64(),
+            None => self.phases.push(PhaseTiming { phase, secs: took.as_secs_f64() }),
+        }
+    }
+
+    /// Set how long the whole run took, which read `bytes` and `entries`, and how fast it went.
+    pub fn finish(&mut self, took: Duration, bytes: u64, entries: usize) {
+        self.total_secs = took.as_secs_f64();
+        // a run too short to measure is as fast as it gets
+        let per_sec = |n: f64| match self.total_secs {
+            0.0 => 0.0,
+            secs => n / secs,
+        };
+        (self.bytes_per_sec, self.entries_per_sec) =
+            (per_sec(bytes as f64), per_sec(entries as f64));
+    }
+}
+
+/// Formats the timing as `--timing` prints it, a phase a line, and the whole run with its
+/// throughput last.
+impl Display for Timing {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for PhaseTiming { phase, secs } in &self.phases {
+            writeln!(f, "{:<10} {secs:>8.3}s", phase.to_string())?;
+        }
+        writeln!(
+            f,
+            "{:<10} {:>8.3}s, {:.1} MB/s, {:.0} entries/s",
+            "total",
+            self.total_secs,
+            self.bytes_per_sec / 1e6,
+            self.entries_per_sec
+        )
+    }
+}
+
+/// Whether a run succeeded, as a [`RunReport`] tells.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RunStatus {
+    /// The run succeeded.
+    #[default]
+    Ok,
+    /// The run failed.
+    Error,
+}
+
+/// How many entries a filter dropped, as a [`RunReport`] lists them.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DroppedReport {
+    /// What dropped them, like `--exclude`, or `while reading` for the transforms.
+    pub by: String,
+    /// How many entries it dropped.
+    pub entries: usize,
+}
+
+/// A line skipped, or converted despite looking wrong, as a [`RunReport`] lists it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Warning {
+    /// The history the line is in, `-` for stdin or a history in memory.
+    pub path: PathBuf,
+    /// The 1-based line, or the line the record starts on.
+    pub line: usize,
+    /// Why the line was skipped, or what looks wrong with it.
+    pub reason: SkipReason,
+    /// The start of the line, with invalid bytes replaced.
+    pub content: String,
+}
+
+impl From<&SkippedLine> for Warning {
+    fn from(skip: &SkippedLine) -> Self {
+        Self {
+            path: skip.path.clone(),
+            line: skip.line,
+            reason: skip.reason,
+            content: String::from_utf8_lossy(&skip.content).into_owned(),
+        }
+    }
+}
+
+impl Default for RunReport {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RunReport {
+    /// The report of a run which did nothing yet.
+    pub fn new() -> Self {
+        Self {
+            report_version: REPORT_VERSION,
+            status: RunStatus::Ok,
+            error: None,
+            exit_code: 0,
+            read: ConversionReport::default(),
+            dropped: Vec::new(),
+            duplicates: 0,
+            written: 0,
+            destination: None,
+            duration_secs: 0.0,
+            warnings: Vec::new(),
+            timing: None,
+        }
+    }
+
+    /// When a phase starts, to [`end`](Self::end) it, or `None` if the run is not timed, so an
+    /// untimed run does not even look at the clock.
+    pub fn start(&self) -> Option<Instant> {
+        self.timing.as_ref().map(|_| Instant::now())
+    }
+
+    /// Add the time since `started` to the time `phase` took, if the run is timed.
+    pub fn end(&mut self, phase: Phase, started: Option<Instant>) {
+        if let (Some(timing), Some(started)) = (&mut self.timing, started) {
+            timing.add(phase, started.elapsed());
+        }
+    }
+
+    /// Take what was read from `read`, with the lines it skipped as the warnings.
+    pub fn set_read(&mut self, read: ConversionReport) {
+        self.warnings = read.skipped_lines.iter().map(Warning::from).collect();
+        self.read = read;
+    }
+
+    /// Mark the run as failed with `error`, exiting with the status of its [`Failure`].
+    pub fn fail(&mut self, error: &anyhow::Error) {
+        self.status = RunStatus::Error;
+        self.error = Some(format!("{error:#}"));
+        self.exit_code = Failure::of(error).map_or(1, Failure::exit_code);
+    }
+
+    /// Write the report as JSON to `path`, or to stderr if it is `-`.
+    pub async fn write_to(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        if path == Path::new("-") {
+            eprintln!("{json}");
+            return Ok(());
+        }
+        tokio::fs::write(path, json + "\n")
+            .await
+            .with_context(|| format!("failed to write the report to {}", path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::history_converter::InputReport;
+
+    use super::*;
+
+    fn report() -> RunReport {
+        let mut report = RunReport::new();
+        report.set_read(ConversionReport {
+            inputs: vec![InputReport { path: PathBuf::from("zsh_history"), entries: 3 }],
+            lines: 4,
+            entries: 3,
+            skipped_lines: vec![SkippedLine::new(
+                PathBuf::from("zsh_history"),
+                2,
+                SkipReason::BadTimestamp,
+                b"ls",
+            )],
+            ..Default::default()
+        });
+        report
+            .dropped
+            .push(DroppedReport { by: "--exclude".to_string(), entries: 1 });
+        report.written = 2;
+        report.destination = Some(PathBuf::from("fish_history"));
+        report.timing = Some(Timing::default());
+        report
+            .timing
+            .as_mut()
+            .unwrap()
+            .add(Phase::Reading, Duration::from_millis(5));
+        report
+    }
+
+    #[tokio::test]
+    async fn round_trips_through_the_file_written() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("report.json");
+        let mut report = report();
+        report.fail(&anyhow::anyhow!("disk full"));
+        report.write_to(&path).await.unwrap();
+
+        let json = std::fs::read_to_string(&path).unwrap();
+        let value = serde_json::from_str::<serde_json::Value>(&json).unwrap();
+        assert_eq!(value["report_version"], REPORT_VERSION);
+        assert_eq!(value["status"], "error");
+        assert_eq!(value["warnings"][0]["reason"], "bad-timestamp");
+        // the lines skipped are written as the warnings only
+        report.read.skipped_lines.clear();
+        assert_eq!(serde_json::from_str::<RunReport>(&json).unwrap(), report);
+    }
+
+    #[test]
+    fn timing_is_left_out_unless_timed() {
+        let json = serde_json::to_string(&RunReport::new()).unwrap();
+        assert!(!json.contains("timing"));
+        assert_eq!(serde_json::from_str::<RunReport>(&json).unwrap(), RunReport::new());
+    }
+
+    #[test]
+    fn warnings_come_from_the_lines_skipped() {
+        let report = report();
+        assert_eq!(report.warnings.len(), 1);
+        assert_eq!((report.warnings[0].line, report.warnings[0].content.as_str()), (2, "ls"));
+    }
+}
@@ -0,0 +1,128 @@
+use std::{
+    cmp::Reverse,
+    ffi::OsStr,
+    io,
+    path::{Path, PathBuf},
+    sync::LazyLock,
+};
+
+use regex::Regex;
+
+/// Find the rotated copies of the history file at `path` among `names`, the file names in its
+/// directory: those named like it followed by a number (`.zsh_history.1`), `.old`, `.bak`, or a
+/// date (`.zsh_history.2024-01-31`, `.zsh_history-20240131`). They are returned oldest first as far
+/// as the names tell: higher numbers and earlier dates first, then `.bak` and `.old`.
+pub fn rotated_histories<I, S>(path: &Path, names: I) -> Vec<PathBuf>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+{
+    static SUFFIX: LazyLock<Regex> = LazyLock::new(|| {
+        Regex::new(r"^[.-](?:(?<date>[0-9]{4}-?[0-9]{2}-?[0-9]{2})|(?<n>[0-9]+)|bak|old)$").unwrap()
+    });
+
+    let Some(name) = path.file_name().and_then(OsStr::to_str) else {
+        return Vec::new();
+    };
+    let dir = path.parent().unwrap_or(Path::new(""));
+
+    let mut rotated = names
+        .into_iter()
+        .filter_map(|candidate| {
+            let candidate = candidate.as_ref().to_str()?.to_string();
+            let caps = SUFFIX.captures(candidate.strip_prefix(name)?)?;
+            // numbers count up into the past, dates down
+            let age = match (caps.name("date"), caps.name("n")) {
+                (Some(date), _) => (1, date.as_str().replace('-', ""), Reverse(0)),
+                (_, Some(n)) => (0, String::new(), Reverse(n.as_str().parse().unwrap_or(u64::MAX))),
+                _ => (2, String::new(), Reverse(0)),
+            };
+            Some((age, candidate))
+        })
+        .collect::<Vec<_>>();
+    rotated.sort();
+
+    rotated.into_iter().map(|(_, name)| dir.join(name)).collect()
+}
+
+/// Find the rotated copies of the history file at `path` in its directory, see
+/// [`rotated_histories`].
+pub async fn find_rotated_histories(path: &Path) -> io::Result<Vec<PathBuf>> {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+
+    let mut names = Vec::new();
+    let mut read_dir = tokio::fs::read_dir(dir).await?;
+    while let Some(entry) = read_dir.next_entry().await? {
+        names.push(entry.file_name());
+    }
+    Ok(rotated_histories(path, names))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rotated(names: &[&str]) -> Vec<PathBuf> {
+        rotated_histories(Path::new("/home/.zsh_history"), names)
+    }
+
+    #[test]
+    fn common_suffixes_are_found() {
+        let names = [
+            ".zsh_history",
+            ".zsh_history.1",
+            ".zsh_history.old",
+            ".zsh_history.bak",
+            ".zsh_history.2024-01-31",
+            ".zsh_history-20240131",
+            ".zshrc",
+        ];
+        assert_eq!(rotated(&names).len(), 5);
+    }
+
+    #[test]
+    fn other_files_are_not() {
+        let names = [
+            ".zsh_history",
+            ".zsh_history.swp",
+            ".zsh_history.1.gz",
+            ".zsh_history1",
+            ".zsh_history.2024-1-31",
+            "zsh_history.1",
+            ".bash_history.1",
+        ];
+        assert_eq!(rotated(&names), Vec::<PathBuf>::new());
+    }
+
+    #[test]
+    fn oldest_come_first() {
+        let names = [
+            ".zsh_history.old",
+            ".zsh_history.1",
+            ".zsh_history.2024-03-01",
+            ".zsh_history.10",
+            ".zsh_history-20240101",
+            ".zsh_history.bak",
+            ".zsh_history.2",
+        ];
+        let expected = [
+            ".zsh_history.10",
+            ".zsh_history.2",
+            ".zsh_history.1",
+            ".zsh_history-20240101",
+            ".zsh_history.2024-03-01",
+            ".zsh_history.bak",
+            ".zsh_history.old",
+        ];
+        assert_eq!(rotated(&names), expected.map(|name| Path::new("/home").join(name)));
+    }
+
+    #[test]
+    fn relative_paths_stay_relative() {
+        let found = rotated_histories(Path::new("zsh_history"), ["zsh_history.1"]);
+        assert_eq!(found, [PathBuf::from("zsh_history.1")]);
+    }
+}
@@ -0,0 +1,270 @@
+//! Which entries `--interactive` writes: the state of the list it shows, apart from drawing it.
+
+use crate::history_converter::Entry;
+
+/// The entries of a history to pick from, newest first, with a cursor, and a filter narrowing
+/// down the ones shown to those containing it. All entries start out picked. Toggling, picking
+/// all, and picking none act on the entries shown.
+///
+/// ```
+/// use zsh_history_to_fish::{Entry, Selection};
+///
+/// let entry = |cmd: &str, when| Entry { cmd: cmd.to_string(), when, ..Default::default() };
+/// let entries = [entry("ls", 1), entry("git push", 3), entry("git pull", 2)];
+/// let mut selection = Selection::new(&entries);
+/// // newest first
+/// assert_eq!(selection.shown(), [1, 2, 0]);
+///
+/// selection.toggle();
+/// assert_eq!(selection.picked().len(), 2);
+///
+/// selection.set_filter("GIT");
+/// assert_eq!(selection.shown(), [1, 2]);
+/// selection.pick_none();
+/// selection.set_filter("");
+/// assert_eq!(selection.picked()[0].cmd, "ls");
+///
+/// // a range, by taking the state of the entry under the cursor along
+/// selection.pick_all();
+/// selection.extend_down();
+/// selection.toggle();
+/// selection.extend_up();
+/// assert_eq!(selection.picked().len(), 1);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Selection<'a> {
+    entries: &'a [Entry],
+    picked: Vec<bool>,
+    // the indices of the entries shown, newest first
+    shown: Vec<usize>,
+    cursor: usize,
+    filter: String,
+}
+
+impl<'a> Selection<'a> {
+    /// Pick from `entries`, all of them picked to begin with.
+    pub fn new(entries: &'a [Entry]) -> Self {
+        let mut selection = Self {
+            entries,
+            picked: vec![true; entries.len()],
+            shown: Vec::new(),
+            cursor: 0,
+            filter: String::new(),
+        };
+        selection.set_filter("");
+        selection
+    }
+
+    /// All entries, shown or not.
+    pub fn entries(&self) -> &'a [Entry] {
+        self.entries
+    }
+
+    /// The indices into the entries of those shown, newest first.
+    pub fn shown(&self) -> &[usize] {
+        &self.shown
+    }
+
+    /// Where the cursor is among the entries shown.
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    /// The filter, which the commands shown contain, ignoring case.
+    pub fn filter(&self) -> &str {
+        &self.filter
+    }
+
+    /// Whether the entry at `index` into the entries is picked.
+    pub fn is_picked(&self, index: usize) -> bool {
+        self.picked[index]
+    }
+
+    /// How many entries are picked, shown or not.
+    pub fn picked_count(&self) -> usize {
+        self.picked.iter().filter(|&&picked| picked).count()
+    }
+
+    /// Move the cursor `n` entries up, stopping at the first.
+    pub fn up(&mut self, n: usize) {
+        self.cursor = self.cursor.saturating_sub(n);
+    }
+
+    /// Move the cursor `n` entries down, stopping at the last.
+    pub fn down(&mut self, n: usize) {
+        self.cursor = (self.cursor + n).min(self.shown.len().saturating_sub(1));
+    }
+
+    /// Pick the entry under the cursor if it was not, or leave it out if it was.
+    pub fn toggle(&mut self) {
+        if let Some(&index) = self.shown.get(self.cursor) {
+            self.picked[index] = !self.picked[index];
+        }
+    }
+
+    /// Move the cursor an entry up, taking whether the entry it leaves is picked along, so a
+    /// range is picked or left out by moving over it.
+    pub fn extend_up(&mut self) {
+        self.extend(|selection| selection.up(1));
+    }
+
+    /// Move the cursor an entry down, the way [`extend_up`](Self::extend_up) moves it up.
+    pub fn extend_down(&mut self) {
+        self.extend(|selection| selection.down(1));
+    }
+
+    fn extend(&mut self, step: impl FnOnce(&mut Self)) {
+        let Some(&from) = self.shown.get(self.cursor) else {
+            return;
+        };
+        step(self);
+        let to = self.shown[self.cursor];
+        self.picked[to] = self.picked[from];
+    }
+
+    /// Pick all entries shown.
+    pub fn pick_all(&mut self) {
+        self.set_shown(true);
+    }
+
+    /// Leave out all entries shown.
+    pub fn pick_none(&mut self) {
+        self.set_shown(false);
+    }
+
+    fn set_shown(&mut self, picked: bool) {
+        for &index in &self.shown {
+            self.picked[index] = picked;
+        }
+    }
+
+    /// Show only the entries containing `filter`, ignoring case, or all of them if it is empty,
+    /// and move the cursor to the first.
+    pub fn set_filter(&mut self, filter: &str) {
+        self.filter = filter.to_string();
+        let filter = filter.to_lowercase();
+        self.shown = (0..self.entries.len())
+            .filter(|&index| self.entries[index].cmd.to_lowercase().contains(&filter))
+            .collect();
+        // newest first, and the later of the entries sharing a time first
+        self.shown
+            .sort_by_key(|&index| std::cmp::Reverse((self.entries[index].when, index)));
+        self.cursor = 0;
+    }
+
+    /// The entries picked, in their order.
+    pub fn picked(&self) -> Vec<Entry> {
+        self.entries
+            .iter()
+            .zip(&self.picked)
+            .filter(|(_, &picked)| picked)
+            .map(|(entry, _)| entry.clone())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entries(cmds: &[&str]) -> Vec<Entry> {
+        cmds.iter()
+            .zip(1..)
+            .map(|(cmd, when)| Entry { cmd: cmd.to_string(), when, ..Default::default() })
+            .collect()
+    }
+
+    fn picked(selection: &Selection) -> Vec<String> {
+        selection.picked().into_iter().map(|entry| entry.cmd).collect()
+    }
+
+    #[test]
+    fn toggling_one_entry() {
+        let entries = entries(&["ls", "pwd", "cd"]);
+        let mut selection = Selection::new(&entries);
+        assert_eq!(selection.picked_count(), 3);
+        selection.down(1);
+        selection.toggle();
+        assert_eq!(picked(&selection), ["ls", "cd"]);
+        selection.toggle();
+        assert_eq!(selection.picked_count(), 3);
+    }
+
+    #[test]
+    fn toggling_a_range() {
+        let entries = entries(&["a", "b", "c", "d"]);
+        let mut selection = Selection::new(&entries);
+        // leave out the newest, then take that along over the next two
+        selection.toggle();
+        selection.extend_down();
+        selection.extend_down();
+        assert_eq!(picked(&selection), ["a"]);
+        // and back up, picking them again
+        selection.toggle();
+        selection.extend_up();
+        selection.extend_up();
+        assert_eq!(picked(&selection), ["a", "b", "c", "d"]);
+    }
+
+    #[test]
+    fn all_and_none_act_on_the_entries_shown() {
+        let entries = entries(&["git push", "ls", "git pull"]);
+        let mut selection = Selection::new(&entries);
+        selection.pick_none();
+        assert_eq!(selection.picked_count(), 0);
+        selection.set_filter("git");
+        selection.pick_all();
+        assert_eq!(picked(&selection), ["git push", "git pull"]);
+        selection.set_filter("");
+        selection.pick_all();
+        assert_eq!(selection.picked_count(), 3);
+    }
+
+    #[test]
+    fn filtering_narrows_and_widens() {
+        let entries = entries(&["git push", "ls", "Git pull", "git log"]);
+        let mut selection = Selection::new(&entries);
+        selection.set_filter("git");
+        assert_eq!(selection.shown(), [3, 2, 0]);
+        selection.set_filter("git p");
+        assert_eq!(selection.shown(), [2, 0]);
+        selection.set_filter("g");
+        assert_eq!(selection.shown(), [3, 2, 0]);
+        selection.set_filter("");
+        assert_eq!(selection.shown(), [3, 2, 1, 0]);
+        // what is filtered out stays picked or not
+        selection.set_filter("ls");
+        selection.toggle();
+        selection.set_filter("");
+        assert_eq!(picked(&selection), ["git push", "Git pull", "git log"]);
+    }
+
+    #[test]
+    fn cursor_stays_among_the_entries_shown() {
+        let entries = entries(&["git push", "ls", "git pull", "pwd"]);
+        let mut selection = Selection::new(&entries);
+        selection.down(usize::MAX);
+        assert_eq!(selection.cursor(), 3);
+        selection.set_filter("git");
+        assert_eq!(selection.cursor(), 0);
+        selection.down(5);
+        assert_eq!(selection.cursor(), 1);
+
+        // with nothing shown, there is nothing to toggle or move over
+        selection.set_filter("nothing");
+        assert_eq!(selection.shown(), [] as [usize; 0]);
+        selection.down(1);
+        selection.toggle();
+        selection.extend_down();
+        selection.extend_up();
+        assert_eq!((selection.cursor(), selection.picked_count()), (0, 4));
+    }
+
+    #[test]
+    fn picking_none_writes_nothing() {
+        let entries = entries(&["ls", "pwd"]);
+        let mut selection = Selection::new(&entries);
+        selection.pick_none();
+        assert!(selection.picked().is_empty());
+    }
+}
@@ -0,0 +1,121 @@
+//! Reading the SQLite databases other history tools keep, and writing one of our own.
+
+use std::path::Path;
+
+use anyhow::Result;
+
+use crate::{format::InputFormat, history_converter::Entry};
+
+/// The bytes every SQLite database starts with.
+const MAGIC: &[u8] = b"SQLite format 3\0";
+
+/// Whether `bytes`, the start of a file, are those of an SQLite database.
+pub fn is_database(bytes: &[u8]) -> bool {
+    bytes.starts_with(MAGIC)
+}
+
+/// Tell which of the databases `--from` knows the SQLite database at `path` is, by its tables:
+/// zsh-histdb keeps the commands in a `commands` table, while atuin keeps them in a `history`
+/// table with a `deleted_at` column, and nushell in one with a `command_line` column.
+pub fn database_format(path: &Path) -> Result<InputFormat> {
+    tables(path)
+}
+
+#[cfg(feature = "sqlite")]
+fn tables(path: &Path) -> Result<InputFormat> {
+    let db = open(path)?;
+    let has = |sql: &str| -> Result<bool> { Ok(db.query_row(sql, [], |row| row.get(0))?) };
+
+    if has("SELECT count(*) > 0 FROM sqlite_master WHERE type = 'table' AND name = 'commands'")? {
+        return Ok(InputFormat::Histdb);
+    }
+    if has("SELECT count(*) > 0 FROM pragma_table_info('history') WHERE name = 'deleted_at'")? {
+        return Ok(InputFormat::Atuin);
+    }
+    if has("SELECT count(*) > 0 FROM pragma_table_info('history') WHERE name = 'command_line'")? {
+        return Ok(InputFormat::Nushell);
+    }
+    anyhow::bail!("{} is not a zsh-histdb, atuin, or nushell database", path.display())
+}
+
+#[cfg(not(feature = "sqlite"))]
+fn tables(_: &Path) -> Result<InputFormat> {
+    Err(unsupported())
+}
+
+/// Write the entries into the `entries` table of the SQLite database at `path`, creating the
+/// database and the table if there are none, or, with `replace`, replacing the table. Returns how
+/// many rows were added. The table is
+///
+/// ```sql
+/// CREATE TABLE entries (
+///     id INTEGER PRIMARY KEY,
+///     when_ts INTEGER, -- the `when` of the entry, a Unix time, or 0 if unknown
+///     cmd TEXT,
+///     source TEXT,     -- the history the entry was read from, `-` for stdin
+///     line INTEGER     -- the line of `source` the entry starts on, or its row id in a database
+/// );
+/// CREATE INDEX entries_when_ts ON entries(when_ts);
+/// ```
+///
+/// The rows are inserted in a single transaction, which keeps even a million of them quick.
+pub fn write_sqlite(path: &Path, entries: &[Entry], replace: bool) -> Result<usize> {
+    insert(path, entries, replace)
+}
+
+#[cfg(feature = "sqlite")]
+fn insert(path: &Path, entries: &[Entry], replace: bool) -> Result<usize> {
+    use rusqlite::{params, Connection};
+
+    const SCHEMA: &str = "
+        CREATE TABLE IF NOT EXISTS entries (
+            id INTEGER PRIMARY KEY,
+            when_ts INTEGER,
+            cmd TEXT,
+            source TEXT,
+            line INTEGER
+        );
+        CREATE INDEX IF NOT EXISTS entries_when_ts ON entries(when_ts);";
+
+    let mut db = Connection::open(path)?;
+    let transaction = db.transaction()?;
+    if replace {
+        transaction.execute("DROP TABLE IF EXISTS entries", [])?;
+    }
+    transaction.execute_batch(SCHEMA)?;
+    let mut added = 0;
+    {
+        let mut statement = transaction
+            .prepare("INSERT INTO entries (when_ts, cmd, source, line) VALUES (?1, ?2, ?3, ?4)")?;
+        for entry in entries {
+            let origin = entry.origin.as_ref();
+            let source = origin.map(|origin| origin.path.to_string_lossy());
+            let line = origin.map(|origin| origin.line as i64);
+            added += statement.execute(params![entry.when, entry.cmd, source, line])?;
+        }
+    }
+    transaction.commit()?;
+
+    Ok(added)
+}
+
+#[cfg(not(feature = "sqlite"))]
+fn insert(_: &Path, _: &[Entry], _: bool) -> Result<usize> {
+    Err(unsupported())
+}
+
+/// Open the database at `path` read-only, so it cannot be changed by accident.
+#[cfg(feature = "sqlite")]
+pub(crate) fn open(path: &Path) -> Result<rusqlite::Connection> {
+    use rusqlite::{Connection, OpenFlags};
+
+    Ok(Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)?)
+}
+
+// The error for builds without the `sqlite` feature.
+#[cfg(not(feature = "sqlite"))]
+pub(crate) fn unsupported() -> anyhow::Error {
+    anyhow::anyhow!(
+        "this build cannot read or write SQLite databases; rebuild with the `sqlite` feature"
+    )
+}
@@ -0,0 +1,94 @@
+//! What the `stats` command prints about a history.
+
+use std::{collections::HashSet, fmt::Display};
+
+use chrono::{Local, SecondsFormat, TimeZone};
+use serde::Serialize;
+
+use crate::{
+    filter::command_counts,
+    history_converter::{ConversionReport, Entry},
+};
+
+/// How many commands the `stats` command lists as run most often.
+pub const TOP_COMMANDS: usize = 10;
+
+/// Statistics about a history, which format as the `stats` command prints them, and serialize as
+/// `stats --json` writes them.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize)]
+pub struct Stats {
+    /// The number of entries.
+    pub entries: usize,
+    /// The number of distinct commands.
+    pub distinct: usize,
+    /// The number of entries without a timestamp.
+    pub undated: usize,
+    /// The number of commands spanning several lines.
+    pub multiline: usize,
+    /// The earliest timestamp, if any entry has one.
+    pub first: Option<i64>,
+    /// The latest timestamp, if any entry has one.
+    pub last: Option<i64>,
+    /// The size of the entries written as a fish history, in bytes.
+    pub fish_bytes: usize,
+    /// What was read to get the entries.
+    pub read: ConversionReport,
+    /// The programs run most often, by the first word of the commands, with how often each was.
+    pub top: Vec<(String, usize)>,
+}
+
+/// Gather the [`Stats`] of `entries`, which were read as `read` says, with the `top` programs run
+/// most often.
+pub fn stats(entries: &[Entry], read: ConversionReport, top: usize) -> Stats {
+    let dated = entries.iter().map(|entry| entry.when).filter(|&when| when != 0);
+    let mut counts = command_counts(entries);
+    counts.truncate(top);
+    Stats {
+        entries: entries.len(),
+        distinct: entries.iter().map(|entry| &entry.cmd).collect::<HashSet<_>>().len(),
+        undated: entries.iter().filter(|entry| entry.when == 0).count(),
+        multiline: entries.iter().filter(|entry| entry.cmd.contains('\n')).count(),
+        first: dated.clone().min(),
+        last: dated.max(),
+        // each entry as `Display` writes it, and a newline
+        fish_bytes: entries.iter().map(|entry| entry.to_string().len() + 1).sum(),
+        read,
+        top: counts,
+    }
+}
+
+impl Display for Stats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let read = &self.read;
+        match read.entries == self.entries {
+            true => writeln!(f, "entries: {}", self.entries)?,
+            false => writeln!(f, "entries: {}, of {} read", self.entries, read.entries)?,
+        }
+        writeln!(f, "distinct commands: {}", self.distinct)?;
+        writeln!(f, "with a timestamp: {}", self.entries - self.undated)?;
+        writeln!(f, "without a timestamp: {}", self.undated)?;
+        writeln!(f, "multi-line commands: {}", self.multiline)?;
+        let time = |when| match Local.timestamp_opt(when, 0).single() {
+            Some(time) => time.to_rfc3339_opts(SecondsFormat::Secs, false),
+            None => when.to_string(),
+        };
+        if let (Some(first), Some(last)) = (self.first, self.last) {
+            writeln!(f, "first: {}", time(first))?;
+            writeln!(f, "last: {}", time(last))?;
+        }
+        writeln!(f, "lines read: {}", read.lines)?;
+        writeln!(f, "records skipped: {}", read.skipped)?;
+        if read.space_prefixed > 0 {
+            writeln!(f, "entries starting with a space skipped: {}", read.space_prefixed)?;
+        }
+        writeln!(f, "lines with invalid bytes replaced: {}", read.lossy)?;
+        writeln!(f, "size: {} bytes, {} bytes as a fish history", read.bytes, self.fish_bytes)?;
+        if !self.top.is_empty() {
+            writeln!(f, "most run:")?;
+        }
+        for (word, count) in &self.top {
+            writeln!(f, "{count:>7} {word}")?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,336 @@
+//! Stripping the wrappers, like `sudo` or `time`, off the front of commands.
+
+use std::{
+    ops::Range,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use crate::{history_converter::Entry, transform::Transform};
+
+/// What `--strip-prefix` and `--strip-env-assignments` strip off the front of commands, besides
+/// `time`, `nice`, `command`, and `env`, which are always stripped.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct StripOptions {
+    /// The prefixes to strip, each a word, like `sudo`, or several, like `poetry run`.
+    pub prefixes: Vec<String>,
+    /// Strip variable assignments, like `FOO=bar`.
+    pub env_assignments: bool,
+}
+
+// A command which runs the command after it: its name, the options taking a value as the next
+// word, and the options with which it runs no command after it, like `sudo -l` or `command -v`.
+#[derive(Debug, Clone, Copy)]
+struct Wrapper {
+    name: &'static str,
+    with_value: &'static [&'static str],
+    not_wrapping: &'static [&'static str],
+}
+
+// The wrappers which change nothing about the command they run, and are always stripped.
+const WRAPPERS: &[Wrapper] = &[
+    Wrapper {
+        name: "time",
+        with_value: &["-f", "-o"],
+        not_wrapping: &[],
+    },
+    Wrapper {
+        name: "nice",
+        with_value: &["-n", "--adjustment"],
+        not_wrapping: &[],
+    },
+    Wrapper {
+        name: "command",
+        with_value: &[],
+        not_wrapping: &["-v", "-V"],
+    },
+    Wrapper {
+        name: "env",
+        with_value: &["-u", "-C", "--unset", "--chdir"],
+        not_wrapping: &["-S", "--split-string"],
+    },
+];
+
+// The options of the wrappers which are only stripped when asked to, by `--strip-prefix`.
+const KNOWN_PREFIXES: &[Wrapper] = &[
+    Wrapper {
+        name: "sudo",
+        with_value: &["-u", "-g", "-h", "-p", "-C", "-D", "-R", "-T", "-U", "-r", "-t", "--user"],
+        not_wrapping: &["-e", "-l", "-v", "-k", "-K", "-V", "--edit", "--list", "--validate"],
+    },
+    Wrapper {
+        name: "doas",
+        with_value: &["-u", "-C"],
+        not_wrapping: &[],
+    },
+];
+
+/// Strip `time`, `nice`, `command`, `env`, the `prefixes`, and with `env_assignments` the variable
+/// assignments off the front of `cmd`, however they are stacked. The options of the wrappers, and
+/// of `sudo` and `doas`, are stripped with them. Something is only stripped if it is followed by
+/// a command, so `sudo -i` is left alone, and so are commands quoted or after a wrapper's
+/// options it does not wrap with, like `command -v`.
+///
+/// ```
+/// use zsh_history_to_fish::{strip_prefixes, StripOptions};
+///
+/// let options = StripOptions { prefixes: vec!["sudo".to_string()], env_assignments: true };
+/// assert_eq!(strip_prefixes("sudo env FOO=1 make install", &options), "make install");
+/// assert_eq!(strip_prefixes("time nice -n 10 sudo -u bob ls", &options), "ls");
+/// assert_eq!(strip_prefixes("sudo -i", &options), "sudo -i");
+/// assert_eq!(strip_prefixes("command -v git", &options), "command -v git");
+/// ```
+pub fn strip_prefixes<'a>(cmd: &'a str, options: &StripOptions) -> &'a str {
+    let words = leading_words(cmd);
+    let (mut i, mut start) = (0, 0);
+    while let Some(next) = strip_one(&words, i, options) {
+        // only if a command follows
+        let Some((range, _)) = words.get(next) else {
+            break;
+        };
+        (i, start) = (next, range.start);
+    }
+    // what is left must be a command, not assignments or a wrapper of nothing, like the rest of
+    // `sudo env FOO=1`
+    let assignments = words[i..]
+        .iter()
+        .all(|(_, word)| word.as_deref().is_some_and(is_assignment));
+    if start > 0 && (assignments || strip_one(&words, i, options).is_some()) {
+        return cmd;
+    }
+    &cmd[start..]
+}
+
+// Where the words after the prefix at `words[i]` start, or `None` if there is none.
+fn strip_one(
+    words: &[(Range<usize>, Option<String>)],
+    i: usize,
+    options: &StripOptions,
+) -> Option<usize> {
+    let word = words.get(i)?.1.as_deref()?;
+    if options.env_assignments && is_assignment(word) {
+        return Some(i + 1);
+    }
+
+    let wrapper = WRAPPERS.iter().find(|wrapper| wrapper.name == word);
+    let wrapper = wrapper.or_else(|| {
+        let asked = options.prefixes.iter().any(|prefix| prefix == word);
+        KNOWN_PREFIXES.iter().find(|known| asked && known.name == word)
+    });
+    if let Some(wrapper) = wrapper {
+        return skip_options(words, i + 1, wrapper);
+    }
+
+    // a prefix of several words, or one with unknown options, stripped as given
+    options.prefixes.iter().find_map(|prefix| {
+        let mut next = i;
+        for part in prefix.split_whitespace() {
+            (words.get(next)?.1.as_deref()? == part).then_some(())?;
+            next += 1;
+        }
+        (next > i).then_some(next)
+    })
+}
+
+// Where the words after the options of `wrapper` starting at `words[i]` start, or `None` if it
+// is given an option with which it wraps no command.
+fn skip_options(
+    words: &[(Range<usize>, Option<String>)],
+    mut i: usize,
+    wrapper: &Wrapper,
+) -> Option<usize> {
+    while let Some((_, Some(word))) = words.get(i) {
+        if !word.starts_with('-') || word == "-" {
+            break;
+        }
+        i += 1;
+        let option = word.split_once('=').map_or(word.as_str(), |(option, _)| option);
+        if word == "--" {
+            break;
+        } else if wrapper.not_wrapping.contains(&option) {
+            return None;
+        } else if wrapper.with_value.contains(&word.as_str()) {
+            i += 1;
+        }
+    }
+    Some(i)
+}
+
+// Whether `word` assigns a variable, like `FOO=bar`.
+pub(crate) fn is_assignment(word: &str) -> bool {
+    word.split_once('=').is_some_and(|(name, _)| {
+        name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+    })
+}
+
+// The words at the front of `cmd`, up to the end of its first simple command, with the word
+// itself, or `None` if any of it is quoted or escaped, which makes it no wrapper.
+fn leading_words(cmd: &str) -> Vec<(Range<usize>, Option<String>)> {
+    let mut words = Vec::new();
+    let mut chars = cmd.char_indices().peekable();
+    loop {
+        while chars.next_if(|&(_, c)| c.is_whitespace() && c != '\n').is_some() {}
+        let Some(&(start, c)) = chars.peek() else {
+            break;
+        };
+        if matches!(c, '\n' | ';' | '&' | '|' | '(' | ')' | '<' | '>') {
+            break;
+        }
+
+        let (mut end, mut quoted) = (start, false);
+        while let Some(&(i, c)) = chars.peek() {
+            if c.is_whitespace() || matches!(c, ';' | '&' | '|' | '(' | ')' | '<' | '>') {
+                break;
+            }
+            chars.next();
+            end = i + c.len_utf8();
+            match c {
+                '\\' => {
+                    quoted = true;
+                    if let Some((i, c)) = chars.next() {
+                        end = i + c.len_utf8();
+                    }
+                }
+                '\'' | '"' => {
+                    quoted = true;
+                    for (i, next) in chars.by_ref() {
+                        end = i + next.len_utf8();
+                        if next == c {
+                            break;
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        let word = (!quoted).then(|| cmd[start..end].to_string());
+        words.push((start..end, word));
+    }
+    words
+}
+
+/// `--strip-prefix` and `--strip-env-assignments` as a [`Transform`], stripping the front of
+/// every command with [`strip_prefixes`].
+#[derive(Debug, Clone)]
+pub struct StripPrefixes {
+    options: StripOptions,
+    rewritten: Arc<AtomicUsize>,
+}
+
+impl StripPrefixes {
+    /// Strip as `options` say.
+    pub fn new(options: StripOptions) -> Self {
+        Self { options, rewritten: Arc::default() }
+    }
+
+    /// How many commands were rewritten so far.
+    pub fn rewritten(&self) -> usize {
+        self.rewritten.load(Ordering::Relaxed)
+    }
+}
+
+impl Transform for StripPrefixes {
+    fn apply(&self, mut entry: Entry) -> Option<Entry> {
+        let stripped = entry.cmd.len() - strip_prefixes(&entry.cmd, &self.options).len();
+        if stripped > 0 {
+            entry.cmd.drain(..stripped);
+            self.rewritten.fetch_add(1, Ordering::Relaxed);
+        }
+        Some(entry)
+    }
+
+    fn summary(&self) -> Option<String> {
+        match self.rewritten() {
+            0 => None,
+            n => Some(format!("stripped wrapper prefixes from {n} commands")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(prefixes: &[&str], env_assignments: bool) -> StripOptions {
+        let prefixes = prefixes.iter().map(|prefix| prefix.to_string()).collect();
+        StripOptions { prefixes, env_assignments }
+    }
+
+    fn strip<'a>(cmd: &'a str, prefixes: &[&str]) -> &'a str {
+        strip_prefixes(cmd, &options(prefixes, true))
+    }
+
+    #[test]
+    fn stacked_wrappers() {
+        assert_eq!(strip("sudo env FOO=1 make install", &["sudo"]), "make install");
+        assert_eq!(strip("FOO=1 BAR=2 time nice -n 5 cargo build", &[]), "cargo build");
+        assert_eq!(strip("sudo -u bob -- ls -la", &["sudo"]), "ls -la");
+        assert_eq!(strip("time -f %e command git status", &[]), "git status");
+    }
+
+    #[test]
+    fn built_in_wrappers_are_always_stripped() {
+        let options = options(&[], false);
+        assert_eq!(strip_prefixes("time make", &options), "make");
+        assert_eq!(strip_prefixes("nice --adjustment=5 make", &options), "make");
+        assert_eq!(strip_prefixes("sudo make", &options), "sudo make");
+        assert_eq!(strip_prefixes("FOO=1 make", &options), "FOO=1 make");
+    }
+
+    #[test]
+    fn only_with_a_command_after() {
+        assert_eq!(strip("sudo -i", &["sudo"]), "sudo -i");
+        assert_eq!(strip("sudo", &["sudo"]), "sudo");
+        assert_eq!(strip("time", &[]), "time");
+        assert_eq!(strip("FOO=1", &[]), "FOO=1");
+        assert_eq!(strip("nice -n 5", &[]), "nice -n 5");
+        // nor when only assignments or a wrapper of nothing would be left
+        assert_eq!(strip("sudo env FOO=1", &["sudo"]), "sudo env FOO=1");
+        let options = options(&["sudo"], false);
+        assert_eq!(strip_prefixes("sudo env FOO=1", &options), "sudo env FOO=1");
+        assert_eq!(strip_prefixes("sudo env FOO=1 make", &options), "FOO=1 make");
+        assert_eq!(strip("sudo time", &["sudo"]), "sudo time");
+    }
+
+    #[test]
+    fn options_without_a_command() {
+        assert_eq!(strip("sudo -l ls", &["sudo"]), "sudo -l ls");
+        assert_eq!(strip("command -v git", &[]), "command -v git");
+        assert_eq!(strip("env -S 'a b' c", &[]), "env -S 'a b' c");
+    }
+
+    #[test]
+    fn only_at_the_front() {
+        assert_eq!(strip("echo sudo ls", &["sudo"]), "echo sudo ls");
+        assert_eq!(strip("ls && sudo make", &["sudo"]), "ls && sudo make");
+        assert_eq!(strip("sudoedit /etc/hosts", &["sudo"]), "sudoedit /etc/hosts");
+        assert_eq!(strip("'sudo' ls", &["sudo"]), "'sudo' ls");
+        assert_eq!(strip("FOO='a b' make", &[]), "FOO='a b' make");
+        assert_eq!(strip("sudo ls | less", &["sudo"]), "ls | less");
+    }
+
+    #[test]
+    fn prefixes_of_several_words() {
+        assert_eq!(strip("poetry run pytest -x", &["poetry run"]), "pytest -x");
+        assert_eq!(strip("poetry install", &["poetry run"]), "poetry install");
+        assert_eq!(strip("poetry run", &["poetry run"]), "poetry run");
+    }
+
+    #[test]
+    fn assignments() {
+        assert!(is_assignment("FOO=bar") && is_assignment("_x1=") && is_assignment("a=b=c"));
+        assert!(!is_assignment("=x") && !is_assignment("1A=x") && !is_assignment("--opt=x"));
+    }
+
+    #[test]
+    fn rewritten_commands_are_counted() {
+        let strip = StripPrefixes::new(options(&["sudo"], false));
+        let entry = |cmd: &str| Entry { cmd: cmd.to_string(), ..Default::default() };
+        assert_eq!(strip.apply(entry("sudo ls")).unwrap().cmd, "ls");
+        assert_eq!(strip.apply(entry("sudo -i")).unwrap().cmd, "sudo -i");
+        assert_eq!(strip.summary(), Some("stripped wrapper prefixes from 1 commands".into()));
+    }
+}
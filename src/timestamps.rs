@@ -0,0 +1,204 @@
+use clap::ValueEnum;
+
+use crate::history_converter::Entry;
+
+/// The timestamp to give entries without one, which zsh writes without EXTENDED_HISTORY.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum DefaultWhen {
+    /// Leave them at 0, the start of 1970.
+    #[default]
+    Zero,
+    /// The modification time of the history file.
+    Mtime,
+    /// Increasing timestamps ending at the modification time of the history file, so fish keeps
+    /// them in order.
+    Spread,
+}
+
+/// Give the entries without a timestamp, that is with a `when` of 0, one according to `strategy`,
+/// where `mtime` is the modification time of the history file. Returns the number of entries
+/// without a timestamp.
+///
+/// Spread timestamps count backwards, one second apart, from `mtime` or the next entry with a
+/// timestamp, but never go below the previous entry with a timestamp, so the result never
+/// decreases.
+pub fn default_when(entries: &mut [Entry], strategy: DefaultWhen, mtime: i64) -> usize {
+    let untimestamped = entries.iter().filter(|entry| entry.when == 0).count();
+    match strategy {
+        DefaultWhen::Zero => {}
+        DefaultWhen::Mtime => entries
+            .iter_mut()
+            .filter(|entry| entry.when == 0)
+            .for_each(|entry| entry.when = mtime),
+        DefaultWhen::Spread => {
+            let floors = entries
+                .iter()
+                .scan(0, |floor, entry| {
+                    let before = *floor;
+                    *floor = (*floor).max(entry.when);
+                    Some(before)
+                })
+                .collect::<Vec<_>>();
+
+            let mut next = mtime.saturating_add(1);
+            for (entry, floor) in entries.iter_mut().zip(floors).rev() {
+                if entry.when == 0 {
+                    entry.when = (next - 1).max(floor);
+                }
+                next = entry.when;
+            }
+        }
+    }
+    untimestamped
+}
+
+/// Give the entries without a timestamp, that is with a `when` of 0, one between those of the
+/// nearest entries with a timestamp, keeping the order of `entries`. Entries before the first
+/// timestamp count backwards from it, and those after the last count onwards from it, one second
+/// apart. Returns the number of entries which got a timestamp, which is 0 if no entry has one.
+pub fn interpolate(entries: &mut [Entry]) -> usize {
+    let timestamped = (0..entries.len())
+        .filter(|&i| entries[i].when != 0)
+        .collect::<Vec<_>>();
+    let (Some(&first), Some(&last)) = (timestamped.first(), timestamped.last()) else {
+        return 0;
+    };
+
+    let mut interpolated = first + (entries.len() - 1 - last);
+    let start = entries[first].when;
+    for (i, entry) in entries[..first].iter_mut().enumerate() {
+        entry.when = start.saturating_sub((first - i) as i64);
+    }
+    let end = entries[last].when;
+    for (i, entry) in entries[last + 1..].iter_mut().enumerate() {
+        entry.when = end.saturating_add(i as i64 + 1);
+    }
+
+    for pair in timestamped.windows(2) {
+        let (before, after) = (pair[0], pair[1]);
+        // in i128, as the distance between two timestamps may not fit into an i64
+        let (from, to) = (entries[before].when as i128, entries[after].when as i128);
+        let steps = (after - before) as i128;
+        for (k, entry) in (1..).zip(&mut entries[before + 1..after]) {
+            // spread evenly, or just follow the earlier one if the later one is not later
+            entry.when = (from + (to - from).max(0) * k / steps) as i64;
+            interpolated += 1;
+        }
+    }
+    interpolated
+}
+
+/// Nudge entries sharing the timestamp of the entry before them by a second each, so fish, which
+/// orders its history by timestamp, keeps them in order. An entry is never moved up to the next
+/// later timestamp in `entries`, so a long run may still share its last timestamp. Entries
+/// without a timestamp are left alone. Returns the number of entries which were nudged.
+pub fn bump_duplicates(entries: &mut [Entry]) -> usize {
+    let mut bumped = 0;
+    let mut start = 0;
+    while start < entries.len() {
+        let when = entries[start].when;
+        let end = start + entries[start..].iter().take_while(|e| e.when == when).count();
+        if when != 0 {
+            // the last second before the next later timestamp
+            let cap = entries[end..]
+                .iter()
+                .map(|entry| entry.when)
+                .find(|&next| next > when)
+                .map_or(i64::MAX, |next| next - 1);
+            for (k, entry) in (0..).zip(&mut entries[start + 1..end]) {
+                entry.when = when.saturating_add(k + 1).min(cap);
+                bumped += usize::from(entry.when != when);
+            }
+        }
+        start = end;
+    }
+    bumped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entries(whens: &[i64]) -> Vec<Entry> {
+        let entry = |(i, &when)| Entry {
+            cmd: format!("echo {i}"),
+            when,
+            ..Default::default()
+        };
+        whens.iter().enumerate().map(entry).collect()
+    }
+
+    // The timestamps after interpolating, and how many were interpolated.
+    fn interpolated(whens: &[i64]) -> (Vec<i64>, usize) {
+        let mut entries = entries(whens);
+        let n = interpolate(&mut entries);
+        let cmds = entries.iter().map(|entry| entry.cmd.as_str()).collect::<Vec<_>>();
+        // the order is kept
+        assert_eq!(cmds, (0..whens.len()).map(|i| format!("echo {i}")).collect::<Vec<_>>());
+        (entries.iter().map(|entry| entry.when).collect(), n)
+    }
+
+    #[test]
+    fn leading_entries_count_backwards() {
+        assert_eq!(interpolated(&[0, 0, 0, 100, 200]), (vec![97, 98, 99, 100, 200], 3));
+    }
+
+    #[test]
+    fn trailing_entries_count_onwards() {
+        assert_eq!(interpolated(&[100, 200, 0, 0]), (vec![100, 200, 201, 202], 2));
+    }
+
+    #[test]
+    fn interleaved_entries_are_spread_between() {
+        assert_eq!(interpolated(&[100, 0, 0, 0, 200]), (vec![100, 125, 150, 175, 200], 3));
+        assert_eq!(
+            interpolated(&[100, 0, 101, 0, 0, 400]),
+            (vec![100, 100, 101, 200, 300, 400], 3)
+        );
+    }
+
+    #[test]
+    fn entries_follow_an_earlier_neighbour_which_is_later() {
+        assert_eq!(interpolated(&[200, 0, 0, 100]), (vec![200, 200, 200, 100], 2));
+    }
+
+    #[test]
+    fn without_any_timestamp_nothing_changes() {
+        assert_eq!(interpolated(&[0, 0]), (vec![0, 0], 0));
+        assert_eq!(interpolated(&[]), (vec![], 0));
+        assert_eq!(interpolated(&[5, 6]), (vec![5, 6], 0));
+    }
+
+    #[test]
+    fn far_apart_timestamps_do_not_overflow() {
+        let (whens, _) = interpolated(&[1, 0, i64::MAX]);
+        assert_eq!(whens[1], 1 + (i64::MAX - 1) / 2);
+    }
+
+    // The timestamps after bumping duplicates, and how many were bumped.
+    fn bumped(whens: &[i64]) -> (Vec<i64>, usize) {
+        let mut entries = entries(whens);
+        let n = bump_duplicates(&mut entries);
+        (entries.iter().map(|entry| entry.when).collect(), n)
+    }
+
+    #[test]
+    fn duplicates_are_bumped_a_second_each() {
+        assert_eq!(bumped(&[5; 10]), ((5..15).collect(), 9));
+        assert_eq!(bumped(&[5, 5, 5, 100]), (vec![5, 6, 7, 100], 2));
+    }
+
+    #[test]
+    fn bumping_stops_before_the_next_timestamp() {
+        assert_eq!(bumped(&[5, 5, 5, 5, 7]), (vec![5, 6, 6, 6, 7], 3));
+        assert_eq!(bumped(&[5, 5, 6, 6]), (vec![5, 5, 6, 7], 1));
+        // later runs are capped by what follows them, not by what came before
+        assert_eq!(bumped(&[9, 9, 3, 3]), (vec![9, 10, 3, 4], 2));
+    }
+
+    #[test]
+    fn untimestamped_entries_are_not_bumped() {
+        assert_eq!(bumped(&[0, 0, 0]), (vec![0, 0, 0], 0));
+        assert_eq!(bumped(&[i64::MAX, i64::MAX]), (vec![i64::MAX, i64::MAX], 0));
+    }
+}
@@ -0,0 +1,138 @@
+//! Rewriting or dropping entries as they are read, see [`Converter::with_transform`].
+//!
+//! [`Converter::with_transform`]: crate::Converter::with_transform
+
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use regex::Regex;
+
+use crate::history_converter::Entry;
+
+/// A rewrite of the entries a converter reads.
+pub trait Transform: Send + Sync {
+    /// Rewrite `entry`, or drop it by returning `None`.
+    fn apply(&self, entry: Entry) -> Option<Entry>;
+
+    /// What the transform did, to report once the conversion is done.
+    fn summary(&self) -> Option<String> {
+        None
+    }
+}
+
+impl<F> Transform for F
+where
+    F: Fn(Entry) -> Option<Entry> + Send + Sync,
+{
+    fn apply(&self, entry: Entry) -> Option<Entry> {
+        self(entry)
+    }
+}
+
+/// The `--allowlist` or the `--blocklist`: keeps only the entries whose command matches any of
+/// its regexes, or drops them.
+#[derive(Debug, Clone)]
+pub struct ListFilter {
+    patterns: Vec<Regex>,
+    allow: bool,
+    // how many entries were seen, and how many of those were dropped
+    seen: Arc<AtomicUsize>,
+    dropped: Arc<AtomicUsize>,
+}
+
+impl ListFilter {
+    /// Keep only the entries matching any of `patterns`.
+    pub fn allowlist(patterns: Vec<Regex>) -> Self {
+        Self::new(patterns, true)
+    }
+
+    /// Drop the entries matching any of `patterns`.
+    pub fn blocklist(patterns: Vec<Regex>) -> Self {
+        Self::new(patterns, false)
+    }
+
+    fn new(patterns: Vec<Regex>, allow: bool) -> Self {
+        Self {
+            patterns,
+            allow,
+            seen: Arc::default(),
+            dropped: Arc::default(),
+        }
+    }
+
+    /// How many entries were dropped so far.
+    pub fn dropped(&self) -> usize {
+        self.dropped.load(Ordering::Relaxed)
+    }
+}
+
+impl Transform for ListFilter {
+    fn apply(&self, entry: Entry) -> Option<Entry> {
+        self.seen.fetch_add(1, Ordering::Relaxed);
+        let matches = self.patterns.iter().any(|pattern| pattern.is_match(&entry.cmd));
+        if matches == self.allow {
+            return Some(entry);
+        }
+        self.dropped.fetch_add(1, Ordering::Relaxed);
+        None
+    }
+
+    fn summary(&self) -> Option<String> {
+        let (seen, dropped) = (self.seen.load(Ordering::Relaxed), self.dropped());
+        match self.allow {
+            true => Some(format!("{} of {seen} entries are on the --allowlist", seen - dropped)),
+            false if dropped > 0 => Some(format!("dropped {dropped} entries on the --blocklist")),
+            false => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(cmd: &str) -> Entry {
+        Entry { cmd: cmd.to_string(), ..Default::default() }
+    }
+
+    // The commands `filter` keeps.
+    fn kept<'a>(filter: &ListFilter, cmds: &[&'a str]) -> Vec<&'a str> {
+        let kept = cmds.iter().filter(|cmd| filter.apply(entry(cmd)).is_some());
+        kept.copied().collect()
+    }
+
+    fn patterns(patterns: &[&str]) -> Vec<Regex> {
+        patterns.iter().map(|pattern| Regex::new(pattern).unwrap()).collect()
+    }
+
+    #[test]
+    fn allowlist_keeps_matches_only() {
+        let filter = ListFilter::allowlist(patterns(&["^git ", "^cargo"]));
+        assert_eq!(
+            kept(&filter, &["git status", "ls", "cargo test", "gitk"]),
+            ["git status", "cargo test"]
+        );
+        assert_eq!(filter.summary().unwrap(), "2 of 4 entries are on the --allowlist");
+    }
+
+    #[test]
+    fn blocklist_drops_matches() {
+        let filter = ListFilter::blocklist(patterns(&["^vault "]));
+        assert_eq!(filter.summary(), None);
+        assert_eq!(kept(&filter, &["vault login x", "ls", "echo vault x"]), ["ls", "echo vault x"]);
+        assert_eq!(filter.dropped(), 1);
+        assert_eq!(filter.summary().unwrap(), "dropped 1 entries on the --blocklist");
+    }
+
+    #[test]
+    fn closures_are_transforms() {
+        let upper = |mut entry: Entry| {
+            entry.cmd = entry.cmd.to_uppercase();
+            Some(entry)
+        };
+        assert_eq!(upper.apply(entry("ls")).unwrap().cmd, "LS");
+        assert_eq!(upper.summary(), None);
+    }
+}
@@ -0,0 +1,62 @@
+//! How much is reported on stderr, besides the errors: nothing with `--quiet`, the warnings and
+//! summaries by default, and every line skipped with `--verbose`.
+
+use std::sync::atomic::{AtomicU8, Ordering};
+
+static VERBOSITY: AtomicU8 = AtomicU8::new(Verbosity::Normal as u8);
+
+/// How much is reported on stderr. Errors are always reported, and so is what `--dry-run` would
+/// do.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Verbosity {
+    /// Only the errors, with `--quiet`.
+    Quiet,
+    /// The warnings, like about the records skipped, the summaries of what was done, and the
+    /// progress.
+    #[default]
+    Normal,
+    /// Also every line skipped or looking wrong, with `-v`.
+    Verbose,
+    /// Also the bytes of those lines, with `-vv`.
+    Debug,
+}
+
+impl Verbosity {
+    /// The verbosity for `--quiet`, or `--verbose` given `verbose` times.
+    pub fn from_flags(quiet: bool, verbose: u8) -> Self {
+        match (quiet, verbose) {
+            (true, _) => Self::Quiet,
+            (false, 0) => Self::Normal,
+            (false, 1) => Self::Verbose,
+            (false, _) => Self::Debug,
+        }
+    }
+}
+
+/// How much the whole process reports on stderr.
+pub fn verbosity() -> Verbosity {
+    match VERBOSITY.load(Ordering::Relaxed) {
+        0 => Verbosity::Quiet,
+        1 => Verbosity::Normal,
+        2 => Verbosity::Verbose,
+        _ => Verbosity::Debug,
+    }
+}
+
+/// Set how much the whole process reports on stderr. A converter sets it when `--quiet` or
+/// `--verbose` is given.
+pub fn set_verbosity(verbosity: Verbosity) {
+    VERBOSITY.store(verbosity as u8, Ordering::Relaxed);
+}
+
+/// Print a warning or a summary on stderr, like `eprintln!`, unless the [`verbosity`] is
+/// [`Verbosity::Quiet`].
+#[macro_export]
+macro_rules! info {
+    ($($arg:tt)*) => {
+        if $crate::verbosity() >= $crate::Verbosity::Normal {
+            eprintln!($($arg)*);
+        }
+    };
+}
+pub(crate) use info;
@@ -0,0 +1,725 @@
+use std::{
+    fs::TryLockError,
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{bail, Context, Result};
+use tokio::{
+    fs::{File, OpenOptions},
+    io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt, BufWriter},
+};
+
+use crate::{
+    abbr::AbbrOptions,
+    format::{BashOptions, Format, JsonOptions, Options, PlainOptions, PrettyOptions, Table},
+    history_converter::Entry,
+    mcfly::write_mcfly,
+    nushell::write_nushell,
+    output::{write_entries, Fish, OutputFormat},
+    sqlite::{is_database, write_sqlite},
+    verbosity::info,
+};
+
+/// How to treat an output file which already exists.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum WriteMode {
+    /// Create the file, refusing to touch it if it already exists and is not empty.
+    #[default]
+    Create,
+    /// Append to the end of the file.
+    Append,
+    /// Truncate the file and write from scratch.
+    Overwrite,
+}
+
+/// Whether and where to back up an existing output file before modifying it.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub enum BackupPolicy {
+    /// Modify the file without a backup.
+    #[default]
+    None,
+    /// Copy the file next to itself, as `<name>.bak.<unix-ts>`.
+    SameDir,
+    /// Copy the file into the given directory, as `<name>.bak.<unix-ts>`, creating the directory
+    /// if it does not exist.
+    Dir(PathBuf),
+}
+
+impl BackupPolicy {
+    /// The path the given file would be backed up to, or `None` if backups are disabled.
+    pub fn backup_path(&self, path: &Path) -> Option<PathBuf> {
+        let dir = match self {
+            Self::None => return None,
+            Self::SameDir => path.parent().unwrap_or(Path::new("")),
+            Self::Dir(dir) => dir,
+        };
+
+        let mut name = path.file_name().unwrap_or(path.as_os_str()).to_os_string();
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        name.push(format!(".bak.{now}"));
+        Some(dir.join(name))
+    }
+}
+
+/// What a [`Writer`] would do to a file, as [`Writer::plan`] tells without doing it, which
+/// formats as `--dry-run` reports it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Plan {
+    /// The file which would be written, with symlinks resolved.
+    pub path: PathBuf,
+    /// What would be done to it.
+    pub action: Action,
+    /// How many entries would be written.
+    pub entries: usize,
+    /// Where the file would be backed up to first, if it would be.
+    pub backup: Option<PathBuf>,
+}
+
+/// What a [`Writer`] would do to a file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    /// Create the file, or write the empty file there is.
+    Create,
+    /// Append to the file.
+    Append,
+    /// Replace the content of the file.
+    Overwrite,
+    /// Add rows to the database the file is.
+    Insert,
+}
+
+impl std::fmt::Display for Plan {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (path, n) = (self.path.display(), self.entries);
+        match self.action {
+            Action::Create => write!(f, "would write {n} entries to {path}")?,
+            Action::Append => write!(f, "would append {n} entries to {path}")?,
+            Action::Overwrite => write!(f, "would overwrite {path} with {n} entries")?,
+            Action::Insert => write!(f, "would add {n} entries to the database {path}")?,
+        }
+        if let Some(backup) = &self.backup {
+            write!(f, ", backing it up to {} first", backup.display())?;
+        }
+        Ok(())
+    }
+}
+
+/// How long to wait for the lock on the output file by default.
+pub const DEFAULT_LOCK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Writes converted entries to a file, by default in the fish history format.
+#[derive(Debug, Clone)]
+pub struct Writer {
+    mode: WriteMode,
+    format: Format,
+    options: Options,
+    backup: BackupPolicy,
+    replace: bool,
+    lock_timeout: Duration,
+}
+
+impl Default for Writer {
+    fn default() -> Self {
+        Self {
+            mode: WriteMode::default(),
+            format: Format::default(),
+            options: Options::default(),
+            backup: BackupPolicy::default(),
+            replace: false,
+            lock_timeout: DEFAULT_LOCK_TIMEOUT,
+        }
+    }
+}
+
+impl Writer {
+    /// Create a new writer with the given write mode.
+    pub fn new(mode: WriteMode) -> Self {
+        Self { mode, ..Default::default() }
+    }
+
+    /// Wait at most the given duration for the lock on the output file.
+    pub fn with_lock_timeout(self, lock_timeout: Duration) -> Self {
+        Self { lock_timeout, ..self }
+    }
+
+    /// Write the entries in the given format.
+    pub fn with_format(self, format: Format) -> Self {
+        Self { format, ..self }
+    }
+
+    /// Write the `csv` and `tsv` formats with the given options.
+    pub fn with_table(self, table: Table) -> Self {
+        Self { options: Options { table, ..self.options }, ..self }
+    }
+
+    /// Suggest abbreviations with the given options in the `abbr` format.
+    pub fn with_abbr(self, abbr: AbbrOptions) -> Self {
+        Self { options: Options { abbr, ..self.options }, ..self }
+    }
+
+    /// Replace the table of an existing database with `Format::Sqlite`, rather than adding to it.
+    pub fn with_replace(self, replace: bool) -> Self {
+        Self { replace, ..self }
+    }
+
+    /// Write the `plain` format with the given options.
+    pub fn with_plain(self, plain: PlainOptions) -> Self {
+        Self { options: Options { plain, ..self.options }, ..self }
+    }
+
+    /// Write the `pretty` format with the given options.
+    pub fn with_pretty(self, pretty: PrettyOptions) -> Self {
+        Self {
+            options: Options { pretty, ..self.options },
+            ..self
+        }
+    }
+
+    /// Write the `bash` format with the given options.
+    pub fn with_bash(self, bash: BashOptions) -> Self {
+        Self { options: Options { bash, ..self.options }, ..self }
+    }
+
+    /// Write the `json` and `jsonl` formats with the given options.
+    pub fn with_json(self, json: JsonOptions) -> Self {
+        Self { options: Options { json, ..self.options }, ..self }
+    }
+
+    /// Back up the existing output file according to the given policy before modifying it.
+    pub fn with_backup(self, backup: BackupPolicy) -> Self {
+        Self { backup, ..self }
+    }
+
+    /// Write the entries to the given path, honoring the write mode. Returns the path of the
+    /// backup, if one was made.
+    ///
+    /// The file is never modified in place: the new content is written to a temporary file next
+    /// to it, synced to disk, and renamed over the original, so a crash or an error leaves either
+    /// the complete old or the complete new file behind. An advisory lock, the same kind fish
+    /// takes, is held on an existing file meanwhile so that a running fish does not modify it
+    /// concurrently.
+    pub async fn write_to(
+        &self,
+        path: impl AsRef<Path>,
+        entries: &[Entry],
+    ) -> Result<Option<PathBuf>> {
+        self.write(path.as_ref(), entries, None::<&mut Fish>).await
+    }
+
+    /// Write the entries to the given path like [`write_to`](Self::write_to), but in a format of
+    /// the caller's own rather than the one given with [`with_format`](Self::with_format).
+    pub async fn write_as<F>(
+        &self,
+        path: impl AsRef<Path>,
+        entries: &[Entry],
+        format: &mut F,
+    ) -> Result<Option<PathBuf>>
+    where
+        F: OutputFormat,
+    {
+        self.write(path.as_ref(), entries, Some(format)).await
+    }
+
+    /// Tell what [`write_to`](Self::write_to) would do with the entries at the given path, failing
+    /// like it would, without taking a lock or touching anything.
+    pub async fn plan(&self, path: impl AsRef<Path>, entries: &[Entry]) -> Result<Plan> {
+        let path = path.as_ref();
+        let existed = tokio::fs::try_exists(path).await?;
+        let path = match existed {
+            true => tokio::fs::canonicalize(path).await?,
+            false => path.to_path_buf(),
+        };
+        let len = match existed {
+            true => Some(
+                tokio::fs::metadata(&path)
+                    .await
+                    .with_context(|| format!("failed to inspect {}", path.display()))?
+                    .len(),
+            ),
+            false => None,
+        };
+
+        let database = self.writes_database(&path).await?;
+        let action = match (self.mode, len) {
+            (_, Some(_)) if database && self.mode != WriteMode::Overwrite => Action::Insert,
+            (WriteMode::Create, Some(len)) if len > 0 && !database => bail!(
+                "refusing to overwrite non-empty file: {} (use --append or --overwrite)",
+                path.display()
+            ),
+            (WriteMode::Append, Some(_)) => Action::Append,
+            (WriteMode::Overwrite, Some(_)) => Action::Overwrite,
+            _ => Action::Create,
+        };
+        let backup = match len {
+            Some(_) => self.backup_path(&path).await?,
+            None => None,
+        };
+        Ok(Plan { path, action, entries: entries.len(), backup })
+    }
+
+    // Write the entries to `path` in `output`, or in the format of the writer if `None`.
+    async fn write<F>(
+        &self,
+        path: &Path,
+        entries: &[Entry],
+        output: Option<&mut F>,
+    ) -> Result<Option<PathBuf>>
+    where
+        F: OutputFormat,
+    {
+        let existed = tokio::fs::try_exists(path).await?;
+
+        // Replace the file a symlink points to rather than the symlink itself.
+        let path = match existed {
+            true => tokio::fs::canonicalize(path).await?,
+            false => path.to_path_buf(),
+        };
+        let _lock = match existed {
+            true => Some(self.lock(&path).await?),
+            false => None,
+        };
+
+        let metadata = match existed {
+            true => Some(
+                tokio::fs::metadata(&path)
+                    .await
+                    .with_context(|| format!("failed to inspect {}", path.display()))?,
+            ),
+            false => None,
+        };
+
+        // Rows are added to a database rather than clobbering anything, so it needs no --append.
+        let database = output.is_none() && self.writes_database(&path).await?;
+        if self.mode == WriteMode::Create
+            && !database
+            && metadata.as_ref().is_some_and(|m| m.len() > 0)
+        {
+            bail!(
+                "refusing to overwrite non-empty file: {} (use --append or --overwrite)",
+                path.display()
+            )
+        }
+
+        let backup = match metadata {
+            Some(_) => self.backup(&path).await?,
+            None => None,
+        };
+
+        // SQLite makes the insertion atomic itself, and a running shell may have the database open,
+        // so it is not replaced by a copy.
+        if database && metadata.is_some() && self.mode != WriteMode::Overwrite {
+            let inserted = self.insert(&path, entries).await;
+            inserted.with_context(|| format!("failed to write {}", path.display()))?;
+            return Ok(backup);
+        }
+
+        let tmp = Self::tmp_path(&path);
+        let written = match database {
+            true => self.write_database_tmp(&tmp, metadata.as_ref(), entries).await,
+            false => self.write_tmp(&tmp, &path, metadata.as_ref(), entries, output).await,
+        };
+        let written = match written {
+            Ok(()) => Self::replace(&tmp, &path).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = written {
+            let _ = tokio::fs::remove_file(&tmp).await;
+            return Err(e.context(format!("failed to write {}", path.display())));
+        }
+
+        Ok(backup)
+    }
+
+    // A hidden file in the same directory, so it is on the same file system and can be renamed
+    // over the destination atomically.
+    fn tmp_path(path: &Path) -> PathBuf {
+        let mut name = std::ffi::OsString::from(".");
+        name.push(path.file_name().unwrap_or(path.as_os_str()));
+        name.push(format!(".tmp.{}", std::process::id()));
+        path.with_file_name(name)
+    }
+
+    // Write the new content of `path` to `tmp`: the old content when appending, then the entries.
+    async fn write_tmp<F>(
+        &self,
+        tmp: &Path,
+        path: &Path,
+        metadata: Option<&std::fs::Metadata>,
+        entries: &[Entry],
+        output: Option<&mut F>,
+    ) -> Result<()>
+    where
+        F: OutputFormat,
+    {
+        let file = OpenOptions::new().write(true).create_new(true).open(tmp).await?;
+        if let Some(metadata) = metadata {
+            file.set_permissions(metadata.permissions()).await?;
+        }
+
+        let mut file = BufWriter::new(file);
+        if let (WriteMode::Append, Some(metadata)) = (self.mode, metadata) {
+            let mut original = File::open(path).await?;
+            tokio::io::copy(&mut original, &mut file).await?;
+            Self::ensure_trailing_newline(&mut original, metadata.len(), &mut file).await?;
+        }
+        match output {
+            Some(output) => write_entries(output, &mut file, entries).await?,
+            None => self.format.write_with(&mut file, entries, &self.options).await?,
+        }
+
+        file.into_inner().sync_all().await?;
+        Ok(())
+    }
+
+    // Write a new database with the entries to `tmp`.
+    async fn write_database_tmp(
+        &self,
+        tmp: &Path,
+        metadata: Option<&std::fs::Metadata>,
+        entries: &[Entry],
+    ) -> Result<()> {
+        let file = OpenOptions::new().write(true).create_new(true).open(tmp).await?;
+        if let Some(metadata) = metadata {
+            file.set_permissions(metadata.permissions()).await?;
+        }
+        drop(file);
+
+        self.insert(tmp, entries).await?;
+        File::open(tmp).await?.sync_all().await?;
+        Ok(())
+    }
+
+    // Whether the entries go into an SQLite database at `path` rather than a text file.
+    async fn writes_database(&self, path: &Path) -> Result<bool> {
+        match self.format {
+            Format::Sqlite | Format::Mcfly => return Ok(true),
+            Format::Nushell => {}
+            _ => return Ok(false),
+        }
+        if path.extension().is_some_and(|ext| ext == "sqlite3" || ext == "db") {
+            return Ok(true);
+        }
+
+        let mut magic = Vec::new();
+        if let Ok(file) = File::open(path).await {
+            file.take(16).read_to_end(&mut magic).await?;
+        }
+        Ok(is_database(&magic))
+    }
+
+    // Insert the entries into the database at `path`, in a single transaction.
+    async fn insert(&self, path: &Path, entries: &[Entry]) -> Result<()> {
+        let (path, entries) = (path.to_path_buf(), entries.to_vec());
+        let (format, replace) = (self.format, self.replace);
+        tokio::task::spawn_blocking(move || match format {
+            Format::Nushell => write_nushell(&path, &entries),
+            Format::Sqlite => write_sqlite(&path, &entries, replace),
+            Format::Mcfly => write_mcfly(&path, &entries),
+            _ => unreachable!("{format:?} is not written into a database"),
+        })
+        .await??;
+        Ok(())
+    }
+
+    // Move `tmp` over `path` and sync the directory, so the rename itself survives a power loss.
+    // Should `tmp` end up on another file system after all, e.g. with a bind-mounted destination,
+    // copy it next to the destination first.
+    async fn replace(tmp: &Path, path: &Path) -> Result<()> {
+        if let Err(e) = tokio::fs::rename(tmp, path).await {
+            if e.kind() != std::io::ErrorKind::CrossesDevices {
+                return Err(e.into());
+            }
+            let mut local = Self::tmp_path(path).into_os_string();
+            local.push(".copy");
+            tokio::fs::copy(tmp, &local).await?;
+            File::open(&local).await?.sync_all().await?;
+            tokio::fs::rename(&local, path).await?;
+            tokio::fs::remove_file(tmp).await?;
+        }
+
+        #[cfg(unix)]
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            File::open(dir).await?.sync_all().await?;
+        }
+
+        Ok(())
+    }
+
+    // Take an exclusive lock on the file, creating it if necessary. The lock is released when the
+    // returned handle is dropped, whichever way `write_to` returns.
+    async fn lock(&self, path: &Path) -> Result<std::fs::File> {
+        let file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)
+            .await;
+        let file = file.with_context(|| format!("failed to open {}", path.display()))?;
+        let file = file.into_std().await;
+
+        let deadline = Instant::now() + self.lock_timeout;
+        loop {
+            match file.try_lock() {
+                Ok(()) => return Ok(file),
+                Err(TryLockError::WouldBlock) if Instant::now() < deadline => {
+                    tokio::time::sleep(Duration::from_millis(50)).await
+                }
+                Err(TryLockError::WouldBlock) => bail!(
+                    "timed out after {:?} waiting for the lock on {}; is fish writing to it?",
+                    self.lock_timeout,
+                    path.display()
+                ),
+                // a file system without locks, like some network ones
+                Err(TryLockError::Error(e)) if e.kind() == std::io::ErrorKind::Unsupported => {
+                    info!("warning: cannot lock {}, writing without a lock: {e}", path.display());
+                    return Ok(file);
+                }
+                Err(TryLockError::Error(e)) => {
+                    return Err(e).with_context(|| format!("failed to lock {}", path.display()))
+                }
+            }
+        }
+    }
+
+    // Copy the file to its backup location and make sure the copy hit the disk, so the original
+    // is never modified without a complete backup.
+    async fn backup(&self, path: &Path) -> Result<Option<PathBuf>> {
+        let Some(backup) = self.backup_path(path).await? else {
+            return Ok(None);
+        };
+
+        if let BackupPolicy::Dir(dir) = &self.backup {
+            tokio::fs::create_dir_all(dir)
+                .await
+                .with_context(|| format!("failed to create {}", dir.display()))?;
+        }
+        let context = || format!("failed to back up {} to {}", path.display(), backup.display());
+        tokio::fs::copy(path, &backup).await.with_context(context)?;
+        File::open(&backup)
+            .await
+            .with_context(context)?
+            .sync_all()
+            .await
+            .with_context(context)?;
+
+        Ok(Some(backup))
+    }
+
+    // Where to back up the file at `path` to, if at all. An earlier backup taken within the same
+    // second is never clobbered.
+    async fn backup_path(&self, path: &Path) -> Result<Option<PathBuf>> {
+        let Some(mut backup) = self.backup.backup_path(path) else {
+            return Ok(None);
+        };
+        let base = backup.clone().into_os_string();
+        let mut n = 0;
+        while tokio::fs::try_exists(&backup).await? {
+            n += 1;
+            let mut name = base.clone();
+            name.push(format!(".{n}"));
+            backup = name.into();
+        }
+        Ok(Some(backup))
+    }
+
+    // Appending right after a record without a final newline would glue the first new entry onto
+    // it, so terminate the last line first.
+    async fn ensure_trailing_newline(
+        original: &mut File,
+        len: u64,
+        file: &mut BufWriter<File>,
+    ) -> Result<()> {
+        if len == 0 {
+            return Ok(());
+        }
+
+        let mut last = [0u8; 1];
+        original.seek(std::io::SeekFrom::Start(len - 1)).await?;
+        original.read_exact(&mut last).await?;
+        if last[0] != b'\n' {
+            file.write_all(b"\n").await?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{future::Future, io};
+
+    use tokio::io::AsyncWrite;
+
+    use super::*;
+
+    fn entries(cmds: &[&str]) -> Vec<Entry> {
+        let entry = |(i, cmd): (usize, &&str)| Entry {
+            cmd: cmd.to_string(),
+            when: 1_700_000_000 + i as i64,
+            ..Default::default()
+        };
+        cmds.iter().enumerate().map(entry).collect()
+    }
+
+    // The files in `dir` but `kept`, like a temporary file left behind.
+    fn leftovers(dir: &Path, kept: &[&str]) -> Vec<String> {
+        let names = std::fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name());
+        let names = names.map(|name| name.to_string_lossy().into_owned());
+        names.filter(|name| !kept.contains(&name.as_str())).collect()
+    }
+
+    // Writes the first entry, then fails, like a full disk.
+    struct FailingFormat;
+
+    impl OutputFormat for FailingFormat {
+        fn write_entry<W>(
+            &mut self,
+            writer: &mut W,
+            entry: &Entry,
+        ) -> impl Future<Output = io::Result<()>> + Send
+        where
+            W: AsyncWrite + Unpin + Send,
+        {
+            let fails = entry.cmd != "first";
+            async move {
+                writer.write_all(b"- cmd: first\n").await?;
+                match fails {
+                    true => Err(io::Error::other("no space left on device")),
+                    false => Ok(()),
+                }
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn failed_write_leaves_the_original() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("fish_history");
+        let original = "- cmd: ls\n  when: 1\n";
+        std::fs::write(&path, original).unwrap();
+
+        for mode in [WriteMode::Append, WriteMode::Overwrite] {
+            let written = Writer::new(mode)
+                .write_as(&path, &entries(&["first", "second"]), &mut FailingFormat)
+                .await;
+            let error = format!("{:#}", written.unwrap_err());
+            assert!(error.contains("no space left on device"), "{error}");
+            assert_eq!(std::fs::read_to_string(&path).unwrap(), original);
+            assert_eq!(leftovers(dir.path(), &["fish_history"]), Vec::<String>::new());
+        }
+    }
+
+    #[tokio::test]
+    async fn failed_new_file_is_not_left_behind() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("fish_history");
+        let written = Writer::new(WriteMode::Create)
+            .write_as(&path, &entries(&["first", "second"]), &mut FailingFormat)
+            .await;
+        assert!(written.is_err());
+        assert_eq!(leftovers(dir.path(), &[]), Vec::<String>::new());
+    }
+
+    #[tokio::test]
+    async fn appends_after_a_missing_newline() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("fish_history");
+        std::fs::write(&path, "- cmd: ls\n  when: 1").unwrap();
+        Writer::new(WriteMode::Append)
+            .write_to(&path, &entries(&["pwd"]))
+            .await
+            .unwrap();
+        let written = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(written, "- cmd: ls\n  when: 1\n- cmd: pwd\n  when: 1700000000\n");
+    }
+
+    #[tokio::test]
+    async fn refuses_to_overwrite_without_a_mode() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("fish_history");
+        std::fs::write(&path, "- cmd: ls\n").unwrap();
+        let written = Writer::new(WriteMode::Create)
+            .write_to(&path, &entries(&["pwd"]))
+            .await;
+        assert!(format!("{:#}", written.unwrap_err()).contains("refusing to overwrite"));
+    }
+
+    #[tokio::test]
+    async fn backs_up_into_a_new_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("fish_history");
+        std::fs::write(&path, "- cmd: ls\n").unwrap();
+        let backups = dir.path().join("backups/fish");
+        let written = Writer::new(WriteMode::Append)
+            .with_backup(BackupPolicy::Dir(backups.clone()))
+            .write_to(&path, &entries(&["pwd"]))
+            .await
+            .unwrap();
+        let backup = written.expect("a backup");
+        assert_eq!(backup.parent(), Some(backups.as_path()));
+        assert_eq!(std::fs::read_to_string(&backup).unwrap(), "- cmd: ls\n");
+        let second = Writer::new(WriteMode::Append)
+            .with_backup(BackupPolicy::Dir(backups.clone()))
+            .write_to(&path, &entries(&["cd"]))
+            .await
+            .unwrap();
+        assert_ne!(second.as_ref(), Some(&backup));
+        assert_eq!(std::fs::read_dir(&backups).unwrap().count(), 2);
+    }
+
+    #[tokio::test]
+    async fn releases_the_lock_after_failing() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("fish_history");
+        std::fs::write(&path, "- cmd: ls\n").unwrap();
+        let written = Writer::new(WriteMode::Append)
+            .write_as(&path, &entries(&["first", "second"]), &mut FailingFormat)
+            .await;
+        assert!(written.is_err());
+        let file = std::fs::File::open(&path).unwrap();
+        assert!(file.try_lock().is_ok(), "the lock is still held");
+    }
+
+    #[tokio::test]
+    async fn times_out_while_the_lock_is_held() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("fish_history");
+        std::fs::write(&path, "- cmd: ls\n").unwrap();
+        // a second holder, like fish merging its sessions
+        let holder = std::fs::File::open(&path).unwrap();
+        holder.lock().unwrap();
+
+        let writer = Writer::new(WriteMode::Append).with_lock_timeout(Duration::from_millis(200));
+        let started = Instant::now();
+        let written = writer.write_to(&path, &entries(&["pwd"])).await;
+        let error = format!("{:#}", written.unwrap_err());
+        assert!(error.contains("timed out"), "{error}");
+        assert!(started.elapsed() >= Duration::from_millis(200));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "- cmd: ls\n");
+    }
+
+    #[tokio::test]
+    async fn waits_for_the_lock_to_be_released() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("fish_history");
+        std::fs::write(&path, "- cmd: ls\n").unwrap();
+        let holder = std::fs::File::open(&path).unwrap();
+        holder.lock().unwrap();
+        let release = tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            drop(holder);
+        });
+
+        let writer = Writer::new(WriteMode::Append).with_lock_timeout(Duration::from_secs(10));
+        writer.write_to(&path, &entries(&["pwd"])).await.unwrap();
+        release.await.unwrap();
+        assert!(std::fs::read_to_string(&path)
+            .unwrap()
+            .ends_with("- cmd: pwd\n  when: 1700000000\n"));
+    }
+}
@@ -0,0 +1,127 @@
+//! Reading xonsh's JSON history files.
+
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+use tokio::io;
+
+use crate::{bash::ParsedEntry, history_converter::Entry};
+
+// A session file, `~/.local/share/xonsh/history_json/xonsh-<id>.json`, as xonsh's file backend
+// writes it: the commands are under `data`, next to an index into the file.
+#[derive(Deserialize)]
+struct Session {
+    #[serde(default)]
+    data: Data,
+}
+
+#[derive(Default, Deserialize)]
+struct Data {
+    #[serde(default)]
+    cmds: Vec<Cmd>,
+}
+
+#[derive(Deserialize)]
+struct Cmd {
+    inp: String,
+    // when the command started and stopped, in fractional seconds
+    #[serde(default)]
+    ts: Vec<Option<f64>>,
+}
+
+/// Parse a xonsh JSON history session file, returning every command with its 1-based position in
+/// the session in place of a line.
+///
+/// The start of each command becomes its `when`, rounded down to the second, and the time to its
+/// stop its duration. The newline xonsh keeps at the end of each command is dropped.
+pub fn parse_xonsh_history(text: &str) -> serde_json::Result<Vec<ParsedEntry>> {
+    let session: Session = serde_json::from_str(text)?;
+
+    let entries = (1..)
+        .zip(session.data.cmds)
+        .filter(|(_, cmd)| !cmd.inp.trim().is_empty())
+        .map(|(i, cmd)| {
+            let (start, stop) = match cmd.ts[..] {
+                [Some(start), Some(stop), ..] => (start, Some(stop)),
+                [Some(start), ..] => (start, None),
+                _ => (0.0, None),
+            };
+            let entry = Entry {
+                cmd: cmd.inp.strip_suffix('\n').unwrap_or(&cmd.inp).to_string(),
+                when: start.floor() as i64,
+                duration: stop.map(|stop| (stop - start).round() as i64),
+                ..Default::default()
+            };
+            (i, Ok(entry))
+        })
+        .collect();
+    Ok(entries)
+}
+
+/// The session files in a xonsh history directory, `~/.local/share/xonsh/history_json`, sorted
+/// by name.
+pub async fn find_xonsh_sessions(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut sessions = Vec::new();
+    let mut read_dir = tokio::fs::read_dir(dir).await?;
+    while let Some(entry) = read_dir.next_entry().await? {
+        let path = entry.path();
+        if path.extension().is_some_and(|ext| ext == "json") && path.is_file() {
+            sessions.push(path);
+        }
+    }
+    sessions.sort();
+    Ok(sessions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{history_converter::InputArgs, Converter, InputFormat, Uninitialized};
+
+    // A session file with the commands, a time and a loose start and stop each.
+    fn session(cmds: &[(&str, f64)]) -> String {
+        let cmds = cmds.iter().map(|(inp, ts)| {
+            serde_json::json!({ "inp": format!("{inp}\n"), "ts": [ts, ts + 1.6], "rtn": 0 })
+        });
+        let data = serde_json::json!({ "cmds": cmds.collect::<Vec<_>>(), "sessionid": "a" });
+        serde_json::json!({ "locs": [69, 2, 3], "index": {}, "data": data }).to_string()
+    }
+
+    #[test]
+    fn commands_are_read_with_their_times() {
+        let text = session(&[("ls", 1_700_000_000.7), ("  ", 1_700_000_001.0), ("pwd", 1.0)]);
+        let entries = parse_xonsh_history(&text).unwrap().into_iter();
+        let entries = entries
+            .map(|(i, entry)| entry.map(|entry| (i, entry.cmd, entry.when, entry.duration)))
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        // the blank command is left out, but still counted in the positions
+        assert_eq!(
+            entries,
+            [(1, "ls".into(), 1_700_000_000, Some(2)), (3, "pwd".into(), 1, Some(2))]
+        );
+    }
+
+    #[tokio::test]
+    async fn malformed_sessions_are_skipped() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("xonsh-a.json"), session(&[("ls", 100.0)])).unwrap();
+        std::fs::write(dir.path().join("xonsh-b.json"), "{\"data\": {\"cmds\": [").unwrap();
+        std::fs::write(dir.path().join("xonsh-c.json"), session(&[("pwd", 200.0)])).unwrap();
+        assert!(parse_xonsh_history("{\"data\": {\"cmds\": [").is_err());
+
+        let input = InputArgs {
+            zsh_history: vec![dir.path().to_path_buf()],
+            from: InputFormat::Xonsh,
+            ..Default::default()
+        };
+        let converter = Converter::with_args(Uninitialized { input, ..Default::default() })
+            .await
+            .unwrap();
+        let entries = converter.convert().await.unwrap();
+        let commands = entries.iter().map(|entry| entry.cmd.as_str()).collect::<Vec<_>>();
+        assert_eq!(commands, ["ls", "pwd"]);
+        let inputs = converter.report().inputs;
+        assert_eq!(inputs.len(), 2, "{inputs:?}");
+    }
+}
@@ -0,0 +1,223 @@
+//! Collecting the directories a history shows to have been visited, for zoxide.
+
+use std::path::{Component, Path, PathBuf};
+
+use crate::{expand::user_home, history_converter::Entry};
+
+/// A directory visited with `cd`, `pushd`, or zsh's `AUTO_CD`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Visit {
+    /// The absolute path of the directory.
+    pub path: PathBuf,
+    /// How many times it was visited.
+    pub count: usize,
+    /// When it was last visited.
+    pub last: i64,
+}
+
+/// Collect the directories the commands in `entries` changed into, most visited first.
+///
+/// A command visits a directory if it is `cd` or `pushd` with one, or, like `AUTO_CD` allows, a
+/// single word which is `..`, ends in `/`, or names an existing directory. Each command of a
+/// list like `cd src && make` counts. A leading `~` or `~user` is expanded, with `home` as the
+/// home directory, and a relative path resolved against the directory changed into before, if
+/// there was one. `cd -` goes back to the directory before that. Paths which cannot be resolved,
+/// with variables, command substitutions, or globs in them, and `cd` without a directory, are
+/// skipped.
+pub fn directory_visits(entries: &[Entry], home: Option<&Path>) -> Vec<Visit> {
+    let mut visits: Vec<Visit> = Vec::new();
+    // the directory changed into last, and the one before it, for `cd -`
+    let (mut cwd, mut previous): (Option<PathBuf>, Option<PathBuf>) = (None, None);
+
+    for entry in entries {
+        for words in commands(&entry.cmd) {
+            let target = match target(&words) {
+                Some(("-", false)) => previous.clone(),
+                Some((dir, auto_cd)) => resolve(dir, cwd.as_deref(), home)
+                    .filter(|path| !auto_cd || dir.ends_with('/') || dir == ".." || path.is_dir()),
+                None => continue,
+            };
+            // a newline would end the line of the directory in the output
+            let Some(target) = target.filter(|path| !path.to_string_lossy().contains('\n')) else {
+                continue;
+            };
+
+            match visits.iter_mut().find(|visit| visit.path == target) {
+                Some(visit) => {
+                    visit.count += 1;
+                    visit.last = visit.last.max(entry.when);
+                }
+                None => visits.push(Visit { path: target.clone(), count: 1, last: entry.when }),
+            }
+            previous = cwd.replace(target);
+        }
+    }
+
+    visits.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.path.cmp(&b.path)));
+    visits
+}
+
+// The directory the words of a command change into, with any `~` still in place, and whether it
+// is a single word, which only changes into a directory if there is one.
+fn target(words: &[String]) -> Option<(&str, bool)> {
+    match words {
+        [command, rest @ ..] if command == "cd" || command == "pushd" => {
+            // the options of `cd -P` and `cd -L` do not matter here
+            let mut dirs = rest.iter().skip_while(|word| *word == "-P" || *word == "-L");
+            match (dirs.next(), dirs.next()) {
+                // `cd +1` and `cd -1` refer to the directory stack
+                (Some(dir), None)
+                    if !dir.starts_with('+') && (dir == "-" || !dir.starts_with('-')) =>
+                {
+                    Some((dir, false))
+                }
+                _ => None,
+            }
+        }
+        [word] => Some((word, true)),
+        _ => None,
+    }
+}
+
+// The absolute, normalized path of `dir`, relative to `cwd`.
+fn resolve(dir: &str, cwd: Option<&Path>, home: Option<&Path>) -> Option<PathBuf> {
+    if dir.contains(['$', '`', '*', '?', '[']) {
+        return None;
+    }
+
+    let path = match dir.strip_prefix('~') {
+        Some(rest) => {
+            let end = rest.find('/').unwrap_or(rest.len());
+            let base = match &rest[..end] {
+                "" => home?.to_path_buf(),
+                user => user_home(user)?,
+            };
+            base.join(rest[end..].trim_start_matches('/'))
+        }
+        None if dir.starts_with('/') => PathBuf::from(dir),
+        None => cwd?.join(dir),
+    };
+
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            Component::CurDir => {}
+            component => normalized.push(component),
+        }
+    }
+    Some(normalized)
+}
+
+// The words of each simple command in `cmd`, with the quotes and backslashes shells remove
+// removed. Commands are separated by newlines, `;`, `&`, and `|`, outside of quotes.
+pub(crate) fn commands(cmd: &str) -> Vec<Vec<String>> {
+    let mut commands = Vec::new();
+    let (mut words, mut word, mut in_word) = (Vec::new(), String::new(), false);
+    let mut chars = cmd.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' => {
+                in_word = true;
+                word.extend(chars.by_ref().take_while(|&c| c != '\''));
+            }
+            '"' => {
+                in_word = true;
+                while let Some(c) = chars.next() {
+                    match c {
+                        '"' => break,
+                        '\\' => word.extend(chars.next()),
+                        c => word.push(c),
+                    }
+                }
+            }
+            '\\' => {
+                in_word = true;
+                word.extend(chars.next().filter(|&c| c != '\n'));
+            }
+            c if c.is_whitespace() || matches!(c, ';' | '&' | '|') => {
+                if in_word {
+                    words.push(std::mem::take(&mut word));
+                    in_word = false;
+                }
+                if c == '\n' || matches!(c, ';' | '&' | '|') {
+                    commands.push(std::mem::take(&mut words));
+                }
+            }
+            c => {
+                in_word = true;
+                word.push(c);
+            }
+        }
+    }
+    if in_word {
+        words.push(word);
+    }
+    commands.push(words);
+
+    commands.retain(|words| !words.is_empty());
+    commands
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The visits of the commands, each a second after the one before, with `/home/u` as home.
+    fn visits(cmds: &[&str]) -> Vec<(String, usize, i64)> {
+        let entries = (1..)
+            .zip(cmds)
+            .map(|(when, cmd)| Entry { cmd: cmd.to_string(), when, ..Default::default() })
+            .collect::<Vec<_>>();
+        let visits = directory_visits(&entries, Some(Path::new("/home/u")));
+        visits
+            .into_iter()
+            .map(|visit| (visit.path.display().to_string(), visit.count, visit.last))
+            .collect()
+    }
+
+    fn visit(path: &str, count: usize, last: i64) -> (String, usize, i64) {
+        (path.to_string(), count, last)
+    }
+
+    #[test]
+    fn home_is_expanded() {
+        assert_eq!(
+            visits(&["cd ~/src", "cd ~", "pushd ~/"]),
+            [visit("/home/u", 2, 3), visit("/home/u/src", 1, 1)]
+        );
+        // with no home to expand it to, it is skipped
+        let entries = [Entry { cmd: "cd ~/src".into(), ..Default::default() }];
+        assert_eq!(directory_visits(&entries, None), []);
+    }
+
+    #[test]
+    fn relative_paths_follow_the_directory_changed_into() {
+        assert_eq!(
+            visits(&["cd app", "cd /srv", "cd app", "cd ../x/./y", "cd -", "web/"]),
+            [
+                visit("/srv/app", 2, 5),
+                visit("/srv", 1, 2),
+                visit("/srv/app/web", 1, 6),
+                visit("/srv/x/y", 1, 4),
+            ]
+        );
+    }
+
+    #[test]
+    fn visits_are_counted_with_the_last_time() {
+        assert_eq!(
+            visits(&["cd /srv && make", "cd -P /opt", "cd /srv; ls", "cd /opt", "cd /srv"]),
+            [visit("/srv", 3, 5), visit("/opt", 2, 4)]
+        );
+    }
+
+    #[test]
+    fn unresolvable_paths_are_skipped() {
+        let cmds = ["cd $HOME", "cd", "cd /a/*", "cd +1", "cd /a /b", "cd \"$(pwd)\"", "ls -l"];
+        assert_eq!(visits(&cmds), []);
+    }
+}
@@ -0,0 +1,255 @@
+//! Helpers for the way zsh stores its history file.
+
+use std::{
+    collections::HashSet,
+    ffi::OsString,
+    io,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Result};
+
+/// The byte zsh prefixes metafied bytes with.
+pub const META: u8 = 0x83;
+
+/// Undo zsh's metafication of a history line, matching zsh's own `unmetafy()`.
+///
+/// zsh stores bytes which collide with its internal tokens (NUL and 0x83 to 0xa2, which are
+/// common in UTF-8 multibyte sequences) as [`META`] followed by the byte XORed with 32, e.g. the
+/// UTF-8 encoding of `あ`, `e3 81 82`, is stored as `e3 83 a1 83 a2`. Like zsh, a [`META`] at the
+/// very end, which has no byte to unmetafy, is kept as is.
+pub fn unmetafy(bytes: &[u8]) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(bytes.len());
+
+    let mut iter = bytes.iter();
+    while let Some(&byte) = iter.next() {
+        match (byte, iter.as_slice().first()) {
+            (META, Some(&next)) => {
+                buf.push(next ^ 32);
+                iter.next();
+            }
+            _ => buf.push(byte),
+        }
+    }
+
+    buf
+}
+
+/// Metafy a history line like zsh does when writing it, the inverse of [`unmetafy`]: NUL and the
+/// bytes 0x83 to 0xa2 are written as [`META`] followed by the byte XORed with 32.
+pub fn metafy(bytes: &[u8]) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(bytes.len());
+    for &byte in bytes {
+        match byte {
+            0 | META..=0xa2 => buf.extend([META, byte ^ 32]),
+            _ => buf.push(byte),
+        }
+    }
+    buf
+}
+
+/// The places the zsh history file usually is, in the order they are tried: `$HISTFILE`,
+/// `$ZDOTDIR/.zsh_history`, `~/.zsh_history`, and `~/.histfile`, which zsh's new user setup
+/// suggests. Unset or empty variables are skipped.
+///
+/// Environment variables are looked up through `env`, like [`fish_history_path`].
+///
+/// [`fish_history_path`]: crate::fish_history_path
+pub fn history_path_candidates(env: impl Fn(&str) -> Option<OsString>) -> Vec<PathBuf> {
+    let env = |key| env(key).filter(|value| !value.is_empty()).map(PathBuf::from);
+
+    let mut candidates = Vec::new();
+    candidates.extend(env("HISTFILE"));
+    candidates.extend(env("ZDOTDIR").map(|dir| dir.join(".zsh_history")));
+    if let Some(home) = env("HOME") {
+        candidates.push(home.join(".zsh_history"));
+        candidates.push(home.join(".histfile"));
+    }
+    candidates
+}
+
+/// The first of the [`history_path_candidates`] for which `exists` holds. The error lists every
+/// path which was tried.
+pub fn history_path(
+    env: impl Fn(&str) -> Option<OsString>,
+    exists: impl Fn(&Path) -> bool,
+) -> Result<PathBuf> {
+    let candidates = history_path_candidates(env);
+    if let Some(path) = candidates.iter().find(|path| exists(path)) {
+        return Ok(path.clone());
+    }
+
+    let tried = candidates
+        .iter()
+        .map(|path| path.display().to_string())
+        .collect::<Vec<_>>();
+    match tried.is_empty() {
+        true => bail!("no zsh history file given, and neither HISTFILE, ZDOTDIR, nor HOME is set"),
+        false => bail!("no zsh history file given, and none found at {}", tried.join(", ")),
+    }
+}
+
+/// The names zsh history files usually have: zsh's own default, the one its new user setup
+/// suggests, and a common unhidden one.
+pub const HISTORY_NAMES: [&str; 3] = [".zsh_history", ".histfile", "zsh_history"];
+
+/// Find the zsh history files in `dir`, like a backup of a home directory: the files with one of
+/// the [`HISTORY_NAMES`] in `dir`, in `.config/zsh`, or in any directory right inside it, hidden
+/// ones included, where a `$ZDOTDIR` usually is. Symlinks are followed, a file reached by several
+/// paths is only listed once, and broken symlinks are skipped. Returns the paths with their sizes.
+pub async fn find_histories_in(dir: &Path) -> io::Result<Vec<(PathBuf, u64)>> {
+    let mut dirs = vec![dir.to_path_buf(), dir.join(".config").join("zsh")];
+    let mut read_dir = tokio::fs::read_dir(dir).await?;
+    while let Some(entry) = read_dir.next_entry().await? {
+        if tokio::fs::metadata(entry.path())
+            .await
+            .is_ok_and(|meta| meta.is_dir())
+        {
+            dirs.push(entry.path());
+        }
+    }
+    dirs[2..].sort();
+
+    let mut seen = HashSet::new();
+    let mut found = Vec::new();
+    for path in dirs.iter().flat_map(|dir| HISTORY_NAMES.map(|name| dir.join(name))) {
+        let Ok(meta) = tokio::fs::metadata(&path).await else {
+            continue;
+        };
+        let real = tokio::fs::canonicalize(&path).await?;
+        if meta.is_file() && seen.insert(real) {
+            found.push((path, meta.len()));
+        }
+    }
+    Ok(found)
+}
+
+/// Pick the history file to convert among the `candidates` found in `dir` with
+/// [`find_histories_in`]. Empty files only count if there is nothing else. With several left,
+/// the largest is picked if `pick_largest` is set, and otherwise the error lists them.
+pub fn pick_history(
+    dir: &Path,
+    mut candidates: Vec<(PathBuf, u64)>,
+    pick_largest: bool,
+) -> Result<PathBuf> {
+    if candidates.iter().any(|&(_, len)| len > 0) {
+        candidates.retain(|&(_, len)| len > 0);
+    }
+    if pick_largest {
+        // the first of the largest, so a tie goes to the most usual place
+        candidates.reverse();
+    }
+    match &candidates[..] {
+        [] => bail!("no zsh history file found in {}", dir.display()),
+        [(path, _)] => Ok(path.clone()),
+        _ if pick_largest => Ok(candidates.into_iter().max_by_key(|&(_, len)| len).unwrap().0),
+        _ => {
+            let found = candidates
+                .iter()
+                .map(|(path, len)| format!("{} ({len} bytes)", path.display()))
+                .collect::<Vec<_>>();
+            bail!(
+                "several zsh history files found in {}, give one or --pick-largest: {}",
+                dir.display(),
+                found.join(", ")
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn metafies_nul_and_the_token_range() {
+        assert_eq!(metafy(b"\0"), [META, 0x20]);
+        for byte in META..=0xa2 {
+            assert_eq!(metafy(&[byte]), [META, byte ^ 32], "{byte:#x}");
+        }
+        // just outside of the range
+        assert_eq!(metafy(&[0x82, 0xa3, 0x7f, 0xff]), [0x82, 0xa3, 0x7f, 0xff]);
+    }
+
+    #[test]
+    fn unmetafies_what_zsh_writes() {
+        // `あ`, `e3 81 82`, as zsh writes it
+        assert_eq!(unmetafy(&[0xe3, 0x83, 0xa1, 0x83, 0xa2]), "あ".as_bytes());
+        // `😀`, `f0 9f 98 80`, with 0x9f and 0x98 metafied
+        assert_eq!(unmetafy(&[0xf0, 0x83, 0xbf, 0x83, 0xb8, 0x80]), "😀".as_bytes());
+        // `日本`, `e6 97 a5 e6 9c ac`, with 0x97 and 0x9c metafied
+        let written = [0xe6, 0x83, 0xb7, 0xa5, 0xe6, 0x83, 0xbc, 0xac];
+        assert_eq!(unmetafy(&written), "日本".as_bytes());
+        assert_eq!(unmetafy(&[b'a', META, 0x20, b'b']), b"a\0b");
+    }
+
+    #[test]
+    fn keeps_a_trailing_meta() {
+        assert_eq!(unmetafy(&[b'l', b's', META]), [b'l', b's', META]);
+        assert_eq!(unmetafy(&[META]), [META]);
+        assert!(unmetafy(&[]).is_empty());
+    }
+
+    // Look up the variables in `vars` only.
+    fn env<'a>(vars: &'a [(&str, &str)]) -> impl Fn(&str) -> Option<OsString> + 'a {
+        |key| {
+            let (_, value) = vars.iter().find(|(var, _)| *var == key)?;
+            Some(OsString::from(value))
+        }
+    }
+
+    #[test]
+    fn candidates_are_tried_in_order() {
+        let vars = [("HISTFILE", "/h/hist"), ("ZDOTDIR", "/z"), ("HOME", "/home")];
+        let candidates = history_path_candidates(env(&vars));
+        let expected = ["/h/hist", "/z/.zsh_history", "/home/.zsh_history", "/home/.histfile"];
+        assert_eq!(candidates, expected.map(PathBuf::from));
+    }
+
+    #[test]
+    fn unset_and_empty_variables_are_skipped() {
+        let candidates = history_path_candidates(env(&[("HISTFILE", ""), ("HOME", "/home")]));
+        assert_eq!(candidates, ["/home/.zsh_history", "/home/.histfile"].map(PathBuf::from));
+        assert!(history_path_candidates(env(&[])).is_empty());
+    }
+
+    #[test]
+    fn the_first_existing_one_wins() {
+        let vars = [("HISTFILE", "/h/hist"), ("ZDOTDIR", "/z"), ("HOME", "/home")];
+        let exists = |path: &Path| path.starts_with("/home");
+        assert_eq!(history_path(env(&vars), exists).unwrap(), Path::new("/home/.zsh_history"));
+        let exists = |path: &Path| path.ends_with(".histfile") || path.starts_with("/z");
+        assert_eq!(history_path(env(&vars), exists).unwrap(), Path::new("/z/.zsh_history"));
+    }
+
+    #[test]
+    fn the_error_lists_what_was_tried() {
+        let vars = [("ZDOTDIR", "/z"), ("HOME", "/home")];
+        let error = history_path(env(&vars), |_| false).unwrap_err().to_string();
+        assert_eq!(
+            error,
+            "no zsh history file given, and none found at /z/.zsh_history, /home/.zsh_history, \
+             /home/.histfile"
+        );
+        let error = history_path(env(&[]), |_| true).unwrap_err().to_string();
+        assert!(error.contains("neither HISTFILE, ZDOTDIR, nor HOME is set"), "{error}");
+    }
+
+    #[test]
+    fn round_trips_every_byte() {
+        let bytes = (0..=255).collect::<Vec<u8>>();
+        assert_eq!(unmetafy(&metafy(&bytes)), bytes);
+        let text = "echo 日本語 😀 ünïcödé\0";
+        assert_eq!(unmetafy(&metafy(text.as_bytes())), text.as_bytes());
+        // what zsh writes never has a byte of the range other than after META
+        let metafied = metafy(&bytes);
+        let mut iter = metafied.iter();
+        while let Some(&byte) = iter.next() {
+            match byte {
+                META => assert!(iter.next().is_some()),
+                0 | 0x84..=0xa2 => panic!("{byte:#x} left unmetafied"),
+                _ => {}
+            }
+        }
+    }
+}
@@ -0,0 +1,40 @@
+//! `--expand-aliases` expands the aliases of a file in the first word of the commands, and with
+//! `--expand-global-aliases` the global ones anywhere, before `--unique` compares them.
+
+mod common;
+
+use common::{status, Scratch};
+
+const ALIASES: &str = "alias gst='git status'\nalias -g G='| grep'\n";
+
+const HISTORY: &str = ": 1:0;gst\n: 2:0;git status\n: 3:0;echo gst\n: 4:0;ps G ssh\n";
+
+fn run(args: &[&str]) -> (String, String) {
+    let scratch = Scratch::new();
+    scratch.write("zsh_history", HISTORY);
+    scratch.write("aliases.zsh", ALIASES);
+    let expand = ["--to", "plain", "--expand-aliases", "aliases.zsh"];
+    scratch.convert(&[&expand[..], args, &["zsh_history"]].concat())
+}
+
+#[test]
+fn regular_aliases() {
+    let (stdout, stderr) = run(&[]);
+    assert_eq!(stdout, "git status\ngit status\necho gst\nps G ssh\n");
+    assert!(stderr.contains("expanded aliases in 1 commands"), "{stderr}");
+}
+
+#[test]
+fn global_aliases_and_unique() {
+    let (stdout, stderr) = run(&["--expand-global-aliases", "--unique"]);
+    assert_eq!(stdout, "git status\necho gst\nps | grep ssh\n");
+    assert!(stderr.contains("expanded aliases in 2 commands"), "{stderr}");
+}
+
+#[test]
+fn global_aliases_need_a_file() {
+    let scratch = Scratch::new();
+    scratch.write("zsh_history", HISTORY);
+    let output = scratch.run(&["convert", "--expand-global-aliases", "zsh_history"]);
+    assert_eq!(status(&output), 2);
+}
@@ -0,0 +1,80 @@
+//! The bash histories written are read back by bash itself, as the commands they were, when bash
+//! is installed.
+
+mod common;
+
+use std::process::Command;
+
+use common::{status, stderr, Scratch};
+
+const HISTORY: &str = concat!(
+    "undated\n",
+    ": 1700000000:0;ls\n",
+    ": 1700000001:0;for i in 1 2; do\\\n",
+    "echo $i\\\n",
+    "done\n",
+    ": 1700000002:0;pwd\n",
+);
+
+// The history bash reads from `path` of the scratch directory, as `<when> <command>`, or `None`
+// without bash.
+fn read_by_bash(scratch: &Scratch, path: &str) -> Option<Vec<String>> {
+    let script = "HISTTIMEFORMAT='%s '; shopt -s lithist cmdhist; set -o history; history -c; \
+                  history -r \"$1\"; history";
+    let output = Command::new("bash")
+        .args(["--norc", "--noprofile", "-c", script, "bash", path])
+        .current_dir(scratch.path(""))
+        .env_clear()
+        .output()
+        .ok()?;
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    // each entry starts with its number, its further lines do not
+    let mut entries = Vec::<String>::new();
+    for line in String::from_utf8(output.stdout).unwrap().lines() {
+        let numbered = line.trim_start().split_once("  ");
+        match numbered.filter(|(number, _)| number.parse::<usize>().is_ok()) {
+            Some((_, entry)) => entries.push(entry.to_string()),
+            None => *entries.last_mut().unwrap() += &format!("\n{line}"),
+        }
+    }
+    Some(entries)
+}
+
+#[test]
+fn multiline_commands_read_back_whole() {
+    let scratch = Scratch::new();
+    scratch.write("zsh_history", HISTORY);
+    let output = scratch.run(&["convert", "--to", "bash", "-o", "bash_history", "zsh_history"]);
+    assert_eq!(status(&output), 0, "{}", stderr(&output));
+    let Some(entries) = read_by_bash(&scratch, "bash_history") else {
+        return;
+    };
+    // the undated entry follows `#0`, which bash shows as an invalid timestamp
+    assert_eq!(entries.len(), 4, "{entries:?}");
+    assert!(entries[0].ends_with("undated"), "{entries:?}");
+    assert_eq!(
+        entries[1..],
+        ["1700000000 ls", "1700000001 for i in 1 2; do\necho $i\ndone", "1700000002 pwd"],
+    );
+}
+
+#[test]
+fn without_timestamps_a_line_is_a_command() {
+    let scratch = Scratch::new();
+    scratch.write("zsh_history", HISTORY);
+    let args = ["convert", "--to", "bash", "--no-timestamps", "-o", "bash_history", "zsh_history"];
+    let output = scratch.run(&args);
+    assert_eq!(status(&output), 5);
+    assert!(stderr(&output).contains("--skip-multiline"), "{}", stderr(&output));
+    assert!(!scratch.path("bash_history").exists());
+
+    let output = scratch.run(&[&args[..], &["--skip-multiline"]].concat());
+    assert_eq!(status(&output), 0, "{}", stderr(&output));
+    let bash = std::fs::read_to_string(scratch.path("bash_history")).unwrap();
+    assert_eq!(bash, "undated\nls\npwd\n");
+    let Some(entries) = read_by_bash(&scratch, "bash_history") else {
+        return;
+    };
+    let commands = entries.iter().map(|entry| entry.split_once(' ').unwrap().1);
+    assert_eq!(commands.collect::<Vec<_>>(), ["undated", "ls", "pwd"]);
+}
@@ -0,0 +1,81 @@
+//! Running the binary in a scratch home of its own, so the configuration, the environment, and
+//! the histories of the machine running the tests stay out of it.
+
+#![allow(dead_code)]
+
+use std::{
+    fs,
+    path::PathBuf,
+    process::{Command, Output},
+};
+
+use tempfile::TempDir;
+
+/// A scratch directory, which is also `$HOME` for the binary.
+pub struct Scratch {
+    dir: TempDir,
+}
+
+impl Scratch {
+    pub fn new() -> Self {
+        Self { dir: tempfile::tempdir().unwrap() }
+    }
+
+    /// The path of `name` in the scratch directory.
+    pub fn path(&self, name: &str) -> PathBuf {
+        self.dir.path().join(name)
+    }
+
+    /// Write `contents` into `name`, and return its path.
+    pub fn write(&self, name: &str, contents: impl AsRef<[u8]>) -> PathBuf {
+        let path = self.path(name);
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    /// The binary, run in the scratch directory with nothing else of the environment than
+    /// `$PATH`.
+    pub fn command(&self) -> Command {
+        let mut command = Command::new(env!("CARGO_BIN_EXE_zsh-history-to-fish"));
+        command
+            .env_clear()
+            .env("HOME", self.dir.path())
+            .env("PATH", std::env::var_os("PATH").unwrap_or_default())
+            .current_dir(self.dir.path());
+        command
+    }
+
+    /// Run the binary with `args`.
+    pub fn run(&self, args: &[&str]) -> Output {
+        self.command().args(args).output().unwrap()
+    }
+
+    /// Run `convert` with `args`, which must succeed, and return what it printed on stdout and
+    /// on stderr.
+    pub fn convert(&self, args: &[&str]) -> (String, String) {
+        let output = self.run(&[&["convert"], args].concat());
+        assert_eq!(status(&output), 0, "{}", stderr(&output));
+        (stdout(&output), stderr(&output))
+    }
+}
+
+/// Convert `history`, written into a scratch directory of its own as `zsh_history`, with `args`,
+/// like [`Scratch::convert`].
+pub fn convert(history: impl AsRef<[u8]>, args: &[&str]) -> (String, String) {
+    let scratch = Scratch::new();
+    scratch.write("zsh_history", history);
+    scratch.convert(&[args, &["zsh_history"]].concat())
+}
+
+/// The exit status of a run, which was not killed by a signal.
+pub fn status(output: &Output) -> i32 {
+    output.status.code().expect("killed by a signal")
+}
+
+pub fn stdout(output: &Output) -> String {
+    String::from_utf8(output.stdout.clone()).unwrap()
+}
+
+pub fn stderr(output: &Output) -> String {
+    String::from_utf8(output.stderr.clone()).unwrap()
+}
@@ -0,0 +1,80 @@
+//! The completion scripts, checked by fish itself when it is installed, and against a snapshot
+//! otherwise. `UPDATE_SNAPSHOTS=1 cargo test` rewrites the snapshot once the options change.
+
+mod common;
+
+use std::{path::Path, process::Command};
+
+use clap::ValueEnum;
+use common::{status, stdout, Scratch};
+use zsh_history_to_fish::{Format, InputFormat};
+
+const SNAPSHOT: &str = "tests/snapshots/zsh-history-to-fish.fish";
+
+fn fish_script(scratch: &Scratch) -> String {
+    let output = scratch.run(&["completions", "fish"]);
+    assert_eq!(status(&output), 0);
+    stdout(&output)
+}
+
+#[test]
+fn fish_script_parses() {
+    let scratch = Scratch::new();
+    let script = fish_script(&scratch);
+    match Command::new("fish").arg("-n").arg("-c").arg(&script).status() {
+        Ok(parsed) => assert!(parsed.success(), "fish -n rejects the completions"),
+        // no fish to parse it, so compare it with what it was
+        Err(_) => {
+            let path = Path::new(env!("CARGO_MANIFEST_DIR")).join(SNAPSHOT);
+            if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+                std::fs::write(&path, &script).unwrap();
+            }
+            let snapshot = std::fs::read_to_string(&path).unwrap();
+            assert!(script == snapshot, "the completions changed, see {SNAPSHOT}");
+        }
+    }
+}
+
+#[test]
+fn values_come_from_the_parser() {
+    let scratch = Scratch::new();
+    let script = fish_script(&scratch);
+    for format in Format::value_variants()
+        .iter()
+        .filter_map(|format| format.to_possible_value())
+    {
+        assert!(script.contains(format.get_name()), "no --format {}", format.get_name());
+    }
+    for from in InputFormat::value_variants()
+        .iter()
+        .filter_map(|from| from.to_possible_value())
+    {
+        assert!(script.contains(from.get_name()), "no --from {}", from.get_name());
+    }
+}
+
+#[test]
+fn every_shell() {
+    let scratch = Scratch::new();
+    for (shell, start) in
+        [("bash", "_zsh__history__to__fish()"), ("zsh", "#compdef zsh-history-to-fish")]
+    {
+        let output = scratch.run(&["completions", shell]);
+        assert_eq!(status(&output), 0);
+        assert!(stdout(&output).contains(start), "completions {shell}");
+    }
+}
+
+#[test]
+fn install_refuses_to_clobber() {
+    let scratch = Scratch::new();
+    let installed = scratch.path(".config/fish/completions/zsh-history-to-fish.fish");
+    assert_eq!(status(&scratch.run(&["completions", "--install"])), 0);
+    assert_eq!(std::fs::read_to_string(&installed).unwrap(), fish_script(&scratch));
+
+    std::fs::write(&installed, "# edited\n").unwrap();
+    assert_ne!(status(&scratch.run(&["completions", "--install"])), 0);
+    assert_eq!(std::fs::read_to_string(&installed).unwrap(), "# edited\n");
+    assert_eq!(status(&scratch.run(&["completions", "--install", "--force"])), 0);
+    assert_eq!(std::fs::read_to_string(&installed).unwrap(), fish_script(&scratch));
+}
@@ -0,0 +1,97 @@
+//! Where an option comes from: the command line wins over the `Z2F_` environment variables, which
+//! win over the configuration file, which wins over the defaults.
+
+mod common;
+
+use common::{status, stderr, stdout, Scratch};
+
+const HISTORY: &str = ": 1700000000:0;ls\n: 1700000001:0;cd /tmp\n: 1700000002:0;ls\n";
+
+// A scratch home with `config` as its configuration file, and a history.
+fn configured(config: &str) -> Scratch {
+    let scratch = Scratch::new();
+    std::fs::create_dir_all(scratch.path(".config/zsh-history-to-fish")).unwrap();
+    scratch.write(".config/zsh-history-to-fish/config.toml", config);
+    scratch.write("zsh_history", HISTORY);
+    scratch
+}
+
+// The line of `--print-config` for `key`.
+fn printed(output: &str, key: &str) -> String {
+    let prefix = format!("{key} = ");
+    let line = output.lines().find(|line| line.starts_with(&prefix));
+    line.unwrap_or_else(|| panic!("no {key} in {output}")).to_string()
+}
+
+#[test]
+fn each_layer_wins_over_the_next() {
+    let scratch = configured("format = 'json'\nsort = 'input'\nunique = true\n");
+    let output = scratch
+        .command()
+        .args(["convert", "--print-config", "--format=plain", "zsh_history"])
+        .env("Z2F_SORT", "when")
+        .output()
+        .unwrap();
+    assert_eq!(status(&output), 0);
+    let output = stdout(&output);
+    assert_eq!(printed(&output, "format"), "format = \"plain\" # from the command line");
+    assert_eq!(printed(&output, "sort"), "sort = \"when\" # from the environment");
+    let config = scratch.path(".config/zsh-history-to-fish/config.toml");
+    let from = format!("# from {}", config.display());
+    assert_eq!(printed(&output, "unique"), format!("unique = true {from}"));
+    assert_eq!(printed(&output, "reverse"), "reverse = false # from the default");
+}
+
+#[test]
+fn layers_apply_to_the_conversion() {
+    let scratch = configured("format = 'json'\nunique = true\nexclude = ['^cd ']\n");
+    let output = scratch.run(&["convert", "--format=plain", "zsh_history"]);
+    assert_eq!(status(&output), 0);
+    assert_eq!(stdout(&output), "ls\n");
+
+    let output = scratch
+        .command()
+        .args(["convert", "--format=plain", "zsh_history"])
+        .env("Z2F_EXCLUDE", "^ls$")
+        .output()
+        .unwrap();
+    assert_eq!(stdout(&output), "cd /tmp\n");
+}
+
+#[test]
+fn conflicting_config_gives_way() {
+    // `--output` on the command line and `into-fish` in the file write to different places
+    let scratch = configured("into-fish = true\nformat = 'plain'\n");
+    let output = scratch.run(&["convert", "-o", "out", "zsh_history"]);
+    assert_eq!(status(&output), 0, "{}", stderr(&output));
+    assert_eq!(std::fs::read_to_string(scratch.path("out")).unwrap(), "ls\ncd /tmp\nls\n");
+    assert!(!scratch.path(".local/share/fish/fish_history").exists());
+}
+
+#[test]
+fn config_flag_overrides_the_default_file() {
+    let scratch = configured("format = 'json'\n");
+    scratch.write("other.toml", "format = 'plain'\nlimit = 1\n");
+    let output = scratch.run(&["convert", "--config", "other.toml", "zsh_history"]);
+    assert_eq!(status(&output), 0);
+    assert_eq!(stdout(&output), "ls\n");
+}
+
+#[test]
+fn unknown_keys_are_warned_about() {
+    let scratch = configured("colour = 'never'\nformat = 'plain'\n");
+    let output = scratch.run(&["convert", "zsh_history"]);
+    assert_eq!(status(&output), 0);
+    assert!(stderr(&output).contains("colour is no option, ignored it"));
+}
+
+#[test]
+fn command_line_only_keys_are_refused() {
+    for key in ["print-config = true", "config = 'other.toml'"] {
+        let scratch = configured(&format!("{key}\n"));
+        let output = scratch.run(&["convert", "zsh_history"]);
+        assert_eq!(status(&output), 2);
+        assert!(stderr(&output).contains("is only taken on the command line"));
+        assert_eq!(stdout(&output), "");
+    }
+}
@@ -0,0 +1,41 @@
+//! NUL bytes are stripped and other control characters escaped on the way to fish, or the
+//! entries holding them dropped with `--skip-invalid`, with the number affected on stderr.
+
+mod common;
+
+use common::{status, stderr, stdout, Scratch};
+
+// A binary paste with a NUL byte, and a prompt with ANSI colors.
+const HISTORY: &[u8] = concat!(
+    ": 1700000000:0;echo a\0b\n",
+    ": 1700000001:0;ls\n",
+    ": 1700000002:0;printf '\x1b[31mred\x1b[0m'\n",
+)
+.as_bytes();
+
+#[test]
+fn controls_are_stripped_and_escaped() {
+    let scratch = Scratch::new();
+    let history = scratch.write("zsh_history", HISTORY);
+    let output = scratch.run(&["convert", history.to_str().unwrap()]);
+    assert_eq!(status(&output), 0);
+    assert_eq!(
+        stdout(&output),
+        concat!(
+            "- cmd: echo ab\n  when: 1700000000\n",
+            "- cmd: ls\n  when: 1700000001\n",
+            "- cmd: printf '\\\\e[31mred\\\\e[0m'\n  when: 1700000002\n",
+        )
+    );
+    assert!(stderr(&output).contains("escaped control characters in 2 entries"));
+}
+
+#[test]
+fn skip_invalid_drops_them() {
+    let scratch = Scratch::new();
+    let history = scratch.write("zsh_history", HISTORY);
+    let output = scratch.run(&["convert", "--skip-invalid", history.to_str().unwrap()]);
+    assert_eq!(status(&output), 0);
+    assert_eq!(stdout(&output), "- cmd: ls\n  when: 1700000001\n");
+    assert!(stderr(&output).contains("skipped 2 entries with control characters"));
+}
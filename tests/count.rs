@@ -0,0 +1,77 @@
+//! `--count` prints how many entries would be written, or appended when merging, counting them as
+//! they are read unless sorting, `--tail`, `--unique`, or merging needs them all first.
+
+mod common;
+
+use common::{status, stderr, stdout, Scratch};
+
+// 3000 entries, more than a chunk counted at a time, every third one a `git` command, and every
+// tenth one without a timestamp
+fn history() -> String {
+    (0..3000)
+        .map(|i| match (i % 10, i % 3) {
+            (0, _) => format!("undated {i}\n"),
+            (_, 0) => format!(": {}:0;git status {i}\n", 1700000000 + i),
+            _ => format!(": {}:0;ls {}\n", 1700000000 + i, i % 7),
+        })
+        .collect()
+}
+
+fn count(scratch: &Scratch, args: &[&str]) -> usize {
+    let (stdout, _) = scratch.convert(&[&["--count"], args, &["zsh_history"]].concat());
+    stdout.trim().parse().unwrap()
+}
+
+fn written(scratch: &Scratch, args: &[&str]) -> usize {
+    let (stdout, _) = scratch.convert(&[&["--to", "jsonl"], args, &["zsh_history"]].concat());
+    stdout.lines().count()
+}
+
+#[test]
+fn counts_what_would_be_written() {
+    let scratch = Scratch::new();
+    scratch.write("zsh_history", history());
+    let filters: [&[&str]; 9] = [
+        &[],
+        &["--head", "1500"],
+        &["--match", "^git"],
+        &["--exclude", "^git", "--since", "1700001000"],
+        &["--since", "1700001000", "--include-undated", "--head", "2000"],
+        &["--limit", "700", "--exclude", "^undated"],
+        &["--sort", "when", "--head", "10"],
+        &["--tail", "1200", "--match", "ls"],
+        &["--unique", "--limit", "5"],
+    ];
+    for args in filters {
+        assert_eq!(count(&scratch, args), written(&scratch, args), "{args:?}");
+    }
+    assert_eq!(count(&scratch, &[]), 3000);
+    assert_eq!(count(&scratch, &["--match", "^git"]), 900);
+    assert_eq!(count(&scratch, &["--unique", "--match", "^ls"]), 7);
+}
+
+#[test]
+fn counts_what_merging_would_append() {
+    let scratch = Scratch::new();
+    scratch.write("zsh_history", ": 1700000000:0;ls\n: 1700000001:0;pwd\n");
+    let fish = scratch.write("fish_history", "- cmd: ls\n  when: 1700000000\n");
+    let fish = fish.to_str().unwrap();
+    assert_eq!(count(&scratch, &["--merge", "-o", fish]), 1);
+    // and nothing was written
+    assert_eq!(std::fs::read_to_string(fish).unwrap(), "- cmd: ls\n  when: 1700000000\n");
+}
+
+#[test]
+fn says_what_dropped_the_entries_counted() {
+    let scratch = Scratch::new();
+    scratch.write("zsh_history", history());
+    let args = ["convert", "--count", "--match", "^git", "zsh_history"];
+    let output = scratch.run(&args);
+    let dropped = "counted 900 entries as they were read, dropped 2100 by --match\n";
+    assert!(stderr(&output).ends_with(dropped), "{}", stderr(&output));
+
+    let args = ["convert", "--count", "--fail-if-empty", "--exclude", ".", "zsh_history"];
+    let output = scratch.run(&args);
+    assert_eq!((status(&output), stdout(&output)), (6, "0\n".to_string()));
+    assert!(stderr(&output).contains("all dropped: 3000 by --exclude"), "{}", stderr(&output));
+}
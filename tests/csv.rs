@@ -0,0 +1,27 @@
+//! `--format csv` and `tsv` write a header and the columns `--columns` selects, quoted so
+//! standard readers read the commands back whole.
+
+mod common;
+
+use common::{status, stderr, stdout, Scratch};
+
+const HISTORY: &str = ": 1700000000:3;echo \"a\",\\\nb\n: 1700000001:0;ls\n";
+
+#[test]
+fn header_and_quoting() {
+    let scratch = Scratch::new();
+    let history = scratch.write("zsh_history", HISTORY);
+    let output = scratch.run(&["convert", "--format", "csv", history.to_str().unwrap()]);
+    assert_eq!(status(&output), 0, "{}", stderr(&output));
+    assert_eq!(stdout(&output), "when,cmd\n1700000000,\"echo \"\"a\"\",\nb\"\n1700000001,ls\n");
+}
+
+#[test]
+fn columns_without_a_header() {
+    let scratch = Scratch::new();
+    let history = scratch.write("zsh_history", HISTORY);
+    let args = ["convert", "--format", "tsv", "--no-header", "--columns", "line,duration,cmd"];
+    let output = scratch.command().args(args).arg(&history).output().unwrap();
+    assert_eq!(status(&output), 0, "{}", stderr(&output));
+    assert_eq!(stdout(&output), "1\t3\t\"echo \"\"a\"\",\nb\"\n3\t0\tls\n");
+}
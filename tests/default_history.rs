@@ -0,0 +1,62 @@
+//! Without a history given, the first of `$HISTFILE`, `$ZDOTDIR/.zsh_history`, `~/.zsh_history`,
+//! and `~/.histfile` which exists is converted.
+
+mod common;
+
+use common::{status, stderr, stdout, Scratch};
+
+#[test]
+fn histfile_comes_first() {
+    let scratch = Scratch::new();
+    let histfile = scratch.write("hist", ": 1:0;from histfile\n");
+    scratch.write(".zsh_history", ": 2:0;from home\n");
+    let output = scratch
+        .command()
+        .arg("convert")
+        .env("HISTFILE", &histfile)
+        .output()
+        .unwrap();
+    assert_eq!(status(&output), 0, "{}", stderr(&output));
+    assert_eq!(stdout(&output), "- cmd: from histfile\n  when: 1\n");
+}
+
+#[test]
+fn missing_ones_are_passed_over() {
+    let scratch = Scratch::new();
+    std::fs::create_dir(scratch.path("zdotdir")).unwrap();
+    scratch.write(".histfile", ": 3:0;from histfile\n");
+    let output = scratch
+        .command()
+        .arg("convert")
+        .env("HISTFILE", scratch.path("missing"))
+        .env("ZDOTDIR", scratch.path("zdotdir"))
+        .output()
+        .unwrap();
+    assert_eq!(status(&output), 0, "{}", stderr(&output));
+    assert_eq!(stdout(&output), "- cmd: from histfile\n  when: 3\n");
+}
+
+#[test]
+fn a_given_history_wins() {
+    let scratch = Scratch::new();
+    let histfile = scratch.write("hist", ": 1:0;from histfile\n");
+    let given = scratch.write("given", ": 4:0;given\n");
+    let output = scratch
+        .command()
+        .args(["convert", given.to_str().unwrap()])
+        .env("HISTFILE", &histfile)
+        .output()
+        .unwrap();
+    assert_eq!(stdout(&output), "- cmd: given\n  when: 4\n");
+}
+
+#[test]
+fn none_found() {
+    let scratch = Scratch::new();
+    let output = scratch.run(&["convert"]);
+    assert_eq!(status(&output), 3);
+    let stderr = stderr(&output);
+    assert!(stderr.contains("none found at "), "{stderr}");
+    assert!(stderr.contains(".zsh_history, "), "{stderr}");
+    assert!(stderr.contains(".histfile"), "{stderr}");
+}
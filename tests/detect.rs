@@ -0,0 +1,38 @@
+//! `--from auto` tells the format of each history from its start, saying which it detected, or
+//! that it fell back to one it is not sure of.
+
+mod common;
+
+use common::{status, stderr, stdout, Scratch};
+
+fn detect(history: &[u8]) -> (String, String) {
+    let scratch = Scratch::new();
+    scratch.write("history", history);
+    let output = scratch.run(&["convert", "--from", "auto", "--to", "plain", "history"]);
+    assert_eq!(status(&output), 0, "{}", stderr(&output));
+    (stdout(&output), stderr(&output))
+}
+
+#[test]
+fn detected_formats_are_named() {
+    let (stdout, stderr) = detect(b"#1700000000\nls -la\n");
+    assert_eq!(stdout, "ls -la\n");
+    assert!(stderr.contains("history: detected the bash format"), "{stderr}");
+
+    let (stdout, stderr) = detect(b"- cmd: ls -la\n  when: 1700000000\n");
+    assert_eq!(stdout, "ls -la\n");
+    assert!(stderr.contains("history: detected the fish format"), "{stderr}");
+}
+
+#[test]
+fn falling_back_is_warned_about() {
+    let warning = "history: could not tell the format for sure, assuming zsh, pass --from to \
+                   override";
+    let (stdout, stderr) = detect(b"ls -la\ncd src\n");
+    assert_eq!(stdout, "ls -la\ncd src\n");
+    assert!(stderr.contains(warning), "{stderr}");
+
+    // lines of several formats are not for sure either
+    let (_, stderr) = detect(b": 1700000000:0;ls\n: 1700000001:0;pwd\n- cmd: cd\n");
+    assert!(stderr.contains(warning), "{stderr}");
+}
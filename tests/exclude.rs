@@ -0,0 +1,51 @@
+//! `--exclude` and `--exclude-file` drop the entries matching any of their patterns, after
+//! `--match` kept those matching any of its own.
+
+mod common;
+
+use common::{status, stderr, Scratch};
+
+const HISTORY: &str =
+    ": 1:0;ls\n: 2:0;git status\n: 3:0;clear\n: 4:0;git push --force\n: 5:0;cd ..\n: 6:0;make\n";
+
+fn run(scratch: &Scratch, args: &[&str]) -> (String, String) {
+    scratch.write("zsh_history", HISTORY);
+    scratch.convert(&[&["--to", "plain"], args, &["zsh_history"]].concat())
+}
+
+#[test]
+fn repeated_excludes() {
+    let (stdout, stderr) = run(&Scratch::new(), &["--exclude", "^ls$", "--exclude", "^clear$"]);
+    assert_eq!(stdout, "git status\ngit push --force\ncd ..\nmake\n");
+    assert!(stderr.contains("excluded 2 entries matching --exclude"), "{stderr}");
+}
+
+#[test]
+fn after_match() {
+    let args = ["--match", "^git ", "--match", "^ls", "--exclude=--force", "--exclude", "^ls"];
+    let (stdout, stderr) = run(&Scratch::new(), &args);
+    assert_eq!(stdout, "git status\n");
+    // `ls` matches both, and counts as excluded, once it was matched
+    assert!(stderr.contains("3 of 6 entries matched --match"), "{stderr}");
+    assert!(stderr.contains("excluded 2 entries matching --exclude"), "{stderr}");
+}
+
+#[test]
+fn exclude_file() {
+    let scratch = Scratch::new();
+    scratch
+        .write("noise", "# what the team never wants in fish\n^ls$\n\n  ^clear$  \n^cd \\.\\.$\n");
+    let (stdout, stderr) = run(&scratch, &["--exclude-file", "noise", "--exclude", "^make$"]);
+    assert_eq!(stdout, "git status\ngit push --force\n");
+    assert!(stderr.contains("excluded 4 entries matching --exclude"), "{stderr}");
+}
+
+#[test]
+fn invalid_patterns_name_their_line() {
+    let scratch = Scratch::new();
+    scratch.write("noise", "^ls$\n# fine\n(unclosed\n");
+    let history = scratch.write("zsh_history", HISTORY);
+    let output = scratch.run(&["convert", "--exclude-file", "noise", history.to_str().unwrap()]);
+    assert_ne!(status(&output), 0);
+    assert!(stderr(&output).contains("invalid regex on line 3 of "), "{}", stderr(&output));
+}
@@ -0,0 +1,99 @@
+//! Each kind of failure exits with the status `--help` lists for it.
+
+mod common;
+
+use common::{status, stderr, Scratch};
+
+const HISTORY: &str = ": 1700000000:0;ls\n: 1700000001:0;git status\n";
+
+#[test]
+fn success() {
+    let scratch = Scratch::new();
+    let history = scratch.write("zsh_history", HISTORY);
+    assert_eq!(status(&scratch.run(&["convert", history.to_str().unwrap()])), 0);
+}
+
+#[test]
+fn usage() {
+    let scratch = Scratch::new();
+    let history = scratch.write("zsh_history", HISTORY);
+    let history = history.to_str().unwrap();
+    assert_eq!(status(&scratch.run(&["convert", "--no-such-option", history])), 2);
+    // a value the option does not take
+    assert_eq!(status(&scratch.run(&["convert", "--format", "yaml", history])), 2);
+    let output = scratch
+        .command()
+        .args(["convert", history])
+        .env("Z2F_SORT", "sideways")
+        .output();
+    assert_eq!(status(&output.unwrap()), 2);
+}
+
+#[test]
+fn input() {
+    let scratch = Scratch::new();
+    let output = scratch.run(&["convert", "no_such_history"]);
+    assert_eq!(status(&output), 3);
+    assert!(stderr(&output).contains("does not exist"), "{}", stderr(&output));
+}
+
+#[test]
+fn strict() {
+    let scratch = Scratch::new();
+    let history = scratch.write("zsh_history", ": 1700000000:0;ls\n: 99999999999999:0;ls\n");
+    assert_eq!(status(&scratch.run(&["convert", "--strict", history.to_str().unwrap()])), 4);
+}
+
+#[test]
+fn destination() {
+    let scratch = Scratch::new();
+    let history = scratch.write("zsh_history", HISTORY);
+    let output = scratch.path("no_such_dir/fish_history");
+    let args = ["convert", "-o", output.to_str().unwrap(), history.to_str().unwrap()];
+    assert_eq!(status(&scratch.run(&args)), 5);
+}
+
+#[test]
+fn empty() {
+    let scratch = Scratch::new();
+    let history = scratch.write("zsh_history", HISTORY);
+    let args = ["convert", "--exclude", ".", history.to_str().unwrap()];
+    let output = scratch.run(&[&args[..], &["--fail-if-empty"]].concat());
+    assert_eq!(status(&output), 6);
+    assert!(stderr(&output).contains("all dropped: 2 by --exclude"), "{}", stderr(&output));
+    // without the flag, writing nothing succeeds
+    assert_eq!(status(&scratch.run(&args)), 0);
+}
+
+#[test]
+fn diff() {
+    let scratch = Scratch::new();
+    let history = scratch.write("zsh_history", HISTORY);
+    let (history, fish) = (history.to_str().unwrap(), scratch.path("fish_history"));
+    let diff = ["convert", "--diff", "--merge", "-o", fish.to_str().unwrap(), history];
+    assert_eq!(status(&scratch.run(&diff)), 1);
+    assert_eq!(status(&scratch.run(&["convert", "-o", fish.to_str().unwrap(), history])), 0);
+    // nothing left to append
+    assert_eq!(status(&scratch.run(&diff)), 0);
+}
+
+#[test]
+fn check() {
+    let scratch = Scratch::new();
+    let valid = scratch.write("valid", "- cmd: ls\n  when: 1700000000\n");
+    assert_eq!(status(&scratch.run(&["check", valid.to_str().unwrap()])), 0);
+    let invalid = scratch.write("invalid", "- cmd: ls\n  when: soon\n");
+    assert_eq!(status(&scratch.run(&["check", invalid.to_str().unwrap()])), 7);
+}
+
+#[test]
+fn merge() {
+    let scratch = Scratch::new();
+    let history = scratch.write("zsh_history", HISTORY);
+    let fish = scratch.path("fish_history");
+    let args = ["convert", "-o", fish.to_str().unwrap(), "--run-merge", "--fish-bin", "false"];
+    let output = scratch.run(&[&args[..], &[history.to_str().unwrap()]].concat());
+    assert_eq!(status(&output), 8);
+    // the history was written before fish failed to merge it
+    assert!(fish.exists());
+}
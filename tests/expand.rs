@@ -0,0 +1,38 @@
+//! `~` and environment variables are expanded in the paths given, unless `--no-expand`.
+
+mod common;
+
+use common::{status, stderr, stdout, Scratch};
+
+#[test]
+fn history_and_output_are_expanded() {
+    let scratch = Scratch::new();
+    scratch.write("zsh_history", ": 1:0;ls\n");
+    let output = scratch
+        .command()
+        .args(["convert", "-o", "${OUT}.fish", "~/zsh_history"])
+        .env("OUT", scratch.path("out"))
+        .output()
+        .unwrap();
+    assert_eq!(status(&output), 0, "{}", stderr(&output));
+    let written = std::fs::read_to_string(scratch.path("out.fish")).unwrap();
+    assert_eq!(written, "- cmd: ls\n  when: 1\n");
+}
+
+#[test]
+fn undefined_variables_are_named() {
+    let scratch = Scratch::new();
+    let output = scratch.run(&["convert", "$HISTDIR/zsh_history"]);
+    assert_ne!(status(&output), 0);
+    assert!(stderr(&output).contains("$HISTDIR is not set"), "{}", stderr(&output));
+}
+
+#[test]
+fn no_expand_keeps_names_literal() {
+    let scratch = Scratch::new();
+    scratch.write("~$weird", ": 1:0;ls\n");
+    let output = scratch.run(&["convert", "--no-expand", "~$weird"]);
+    assert_eq!(status(&output), 0, "{}", stderr(&output));
+    assert_eq!(stdout(&output), "- cmd: ls\n  when: 1\n");
+    assert_ne!(status(&scratch.run(&["convert", "~$weird"])), 0);
+}
@@ -0,0 +1,64 @@
+//! `--fail-if-empty` exits with 6, saying why nothing is left to write, and changes nothing
+//! without entries left out.
+
+mod common;
+
+use common::{status, stderr, stdout, Scratch};
+
+const HISTORY: &str = ": 1700000000:0;ls\n: 1700000001:0;git status\n: 1700000002:0;ls\n";
+
+// The error of a run failing with `--fail-if-empty`, after checking it exits with 6.
+fn why(scratch: &Scratch, args: &[&str]) -> String {
+    let output = scratch.run(&[args, &["--fail-if-empty"]].concat());
+    assert_eq!(status(&output), 6, "{}", stderr(&output));
+    assert_eq!(stdout(&output), "");
+    stderr(&output)
+}
+
+#[test]
+fn nothing_parsed() {
+    let scratch = Scratch::new();
+    scratch.write("zsh_history", "");
+    let why = why(&scratch, &["convert", "zsh_history"]);
+    assert!(why.contains("nothing to write: no entries parsed from 0 lines"), "{why}");
+}
+
+#[test]
+fn all_filtered() {
+    let scratch = Scratch::new();
+    scratch.write("zsh_history", HISTORY);
+    let args = ["convert", "--match", "^ls", "--exclude", "^ls$", "zsh_history"];
+    let why = why(&scratch, &args);
+    assert!(why.contains("3 entries parsed, all dropped: 1 by --match, 2 by --exclude"), "{why}");
+}
+
+#[test]
+fn all_in_the_destination() {
+    let scratch = Scratch::new();
+    scratch.write("zsh_history", HISTORY);
+    let args = ["convert", "--into-fish", "--merge", "zsh_history"];
+    assert_eq!(status(&scratch.run(&args)), 0);
+    let why = why(&scratch, &args);
+    let fish = scratch.path(".local/share/fish/fish_history");
+    let dropped = format!("3 entries parsed, all dropped: 3 already in {}", fish.display());
+    assert!(why.contains(&dropped), "{why}");
+}
+
+#[test]
+fn entries_left() {
+    let scratch = Scratch::new();
+    scratch.write("zsh_history", HISTORY);
+    let output = scratch.run(&["convert", "--format", "plain", "--fail-if-empty", "zsh_history"]);
+    assert_eq!(status(&output), 0);
+    assert_eq!(stdout(&output), "ls\ngit status\nls\n");
+}
+
+#[test]
+fn report_has_the_status() {
+    let scratch = Scratch::new();
+    scratch.write("zsh_history", HISTORY);
+    why(&scratch, &["convert", "--exclude", ".", "--report", "report.json", "zsh_history"]);
+    let report = std::fs::read(scratch.path("report.json")).unwrap();
+    let report = serde_json::from_slice::<serde_json::Value>(&report).unwrap();
+    assert_eq!(report["exit_code"], 6, "{report}");
+}
@@ -0,0 +1,66 @@
+//! `--format fish-script` refuses to go on in any shell but fish, whether run or sourced.
+
+mod common;
+
+use std::process::Command;
+
+use common::{status, stderr, stdout, Scratch};
+
+// The script of a history, from the scratch directory.
+fn script(scratch: &Scratch) -> &'static str {
+    scratch.write("zsh_history", ": 1700000000:0;echo 'it'\\''s'\n");
+    let args = ["convert", "--format", "fish-script", "-o", "history.fish", "zsh_history"];
+    let output = scratch.run(&args);
+    assert_eq!(status(&output), 0, "{}", stderr(&output));
+    "history.fish"
+}
+
+// Run `shell` with `args` in the scratch directory, unless it is not installed.
+fn shell(scratch: &Scratch, shell: &str, args: &[&str]) -> Option<(i32, String, String)> {
+    let output = Command::new(shell)
+        .args(args)
+        .current_dir(scratch.path(""))
+        .env_clear()
+        .output()
+        .ok()?;
+    Some((status(&output), stdout(&output), stderr(&output)))
+}
+
+#[test]
+fn run_by_sh() {
+    let scratch = Scratch::new();
+    let script = script(&scratch);
+    for sh in ["sh", "bash", "dash", "zsh"] {
+        let Some((status, stdout, stderr)) = shell(&scratch, sh, &[script]) else {
+            continue;
+        };
+        assert_eq!(status, 1, "{sh}: {stderr}");
+        assert_eq!(stdout, "");
+        assert_eq!(stderr, "this script must be sourced by fish\n", "{sh}");
+    }
+}
+
+#[test]
+fn sourced_by_sh() {
+    let scratch = Scratch::new();
+    let script = script(&scratch);
+    let source = format!(". ./{script}; echo \"returned $?\"");
+    for sh in ["sh", "bash", "dash", "zsh"] {
+        let Some((status, stdout, stderr)) = shell(&scratch, sh, &["-c", &source]) else {
+            continue;
+        };
+        // the shell sourcing it goes on
+        assert_eq!(status, 0, "{sh}: {stderr}");
+        assert_eq!(stdout, "returned 1\n", "{sh}");
+        assert_eq!(stderr, "this script must be sourced by fish\n", "{sh}");
+    }
+}
+
+#[test]
+fn fish_parses_it() {
+    let scratch = Scratch::new();
+    let script = script(&scratch);
+    if let Some((status, _, stderr)) = shell(&scratch, "fish", &["--no-execute", script]) {
+        assert_eq!(status, 0, "{stderr}");
+    }
+}
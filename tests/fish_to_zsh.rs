@@ -0,0 +1,61 @@
+//! `fish-to-zsh` writes a fish history back as a zsh one, which converts to the same fish
+//! history again.
+
+mod common;
+
+use common::{status, stderr, stdout, Scratch};
+use zsh_history_to_fish::zsh::metafy;
+
+// A zsh history as zsh writes it: metafied, with a command spanning several lines, and one
+// without a timestamp.
+fn zsh_history() -> Vec<u8> {
+    let history = concat!(
+        ": 1700000000:0;echo 日本語 😀\n",
+        ": 1700000001:3;for i in 1 2\\\ndo echo $i\\\ndone\n",
+        ": 1700000002:0;grep foo \\*.rs\n",
+        "undated\n",
+    );
+    metafy(history.as_bytes())
+        .split(|&b| b == b'\n')
+        .collect::<Vec<_>>()
+        .join(&b'\n')
+}
+
+#[test]
+fn zsh_to_fish_to_zsh_is_byte_identical() {
+    let scratch = Scratch::new();
+    scratch.write("zsh_history", zsh_history());
+    let output = scratch.run(&["convert", "-o", "fish_history", "zsh_history"]);
+    assert_eq!(status(&output), 0, "{}", stderr(&output));
+    // durations are lost in fish
+    let output = scratch.run(&["fish-to-zsh", "fish_history"]);
+    assert_eq!(status(&output), 0, "{}", stderr(&output));
+    let expected = zsh_history()
+        .split(|&b| b == b'\n')
+        .map(|line| match line.starts_with(b": 1700000001:3;") {
+            true => [b": 1700000001:0;", &line[15..]].concat(),
+            false => line.to_vec(),
+        })
+        .collect::<Vec<_>>()
+        .join(&b'\n');
+    assert_eq!(output.stdout, expected);
+}
+
+#[test]
+fn undated_entries_get_the_default_when() {
+    let scratch = Scratch::new();
+    scratch.write("fish_history", "- cmd: ls\n  when: 0\n- cmd: pwd\n  when: 5\n");
+    let output = scratch.run(&["fish-to-zsh", "fish_history"]);
+    assert_eq!(stdout(&output), "ls\n: 5:0;pwd\n");
+    let output = scratch.run(&["fish-to-zsh", "--default-when", "mtime", "fish_history"]);
+    let stdout = stdout(&output);
+    assert!(stdout.starts_with(": ") && stdout.ends_with(":0;ls\n: 5:0;pwd\n"), "{stdout}");
+}
+
+#[test]
+fn other_formats_are_refused() {
+    let scratch = Scratch::new();
+    scratch.write("fish_history", "- cmd: ls\n  when: 1\n");
+    let output = scratch.run(&["fish-to-zsh", "--to", "json", "fish_history"]);
+    assert_eq!(status(&output), 2);
+}
@@ -0,0 +1,81 @@
+//! `--into-fish` writes into the fish history where fish keeps it.
+
+mod common;
+
+use common::{status, stderr, Scratch};
+
+const HISTORY: &str = ": 1700000000:0;ls\n: 1700000001:0;git status\n";
+const FISH: &str = "- cmd: ls\n  when: 1700000000\n- cmd: git status\n  when: 1700000001\n";
+
+#[test]
+fn into_the_home() {
+    let scratch = Scratch::new();
+    scratch.write("zsh_history", HISTORY);
+    let output = scratch.run(&["convert", "--into-fish", "zsh_history"]);
+    assert_eq!(status(&output), 0, "{}", stderr(&output));
+    let fish = scratch.path(".local/share/fish/fish_history");
+    assert_eq!(std::fs::read_to_string(&fish).unwrap(), FISH);
+
+    // a second run appends
+    assert_eq!(status(&scratch.run(&["convert", "--into-fish", "zsh_history"])), 0);
+    assert_eq!(std::fs::read_to_string(&fish).unwrap(), FISH.repeat(2));
+}
+
+#[test]
+fn into_the_data_home() {
+    let scratch = Scratch::new();
+    scratch.write("zsh_history", HISTORY);
+    let output = scratch
+        .command()
+        .args(["convert", "--into-fish", "zsh_history"])
+        .env("XDG_DATA_HOME", scratch.path("data"))
+        .output()
+        .unwrap();
+    assert_eq!(status(&output), 0, "{}", stderr(&output));
+    let fish = scratch.path("data/fish/fish_history");
+    assert_eq!(std::fs::read_to_string(fish).unwrap(), FISH);
+    assert!(!scratch.path(".local").exists());
+}
+
+#[test]
+fn dry_run_creates_nothing() {
+    let scratch = Scratch::new();
+    scratch.write("zsh_history", HISTORY);
+    let output = scratch.run(&["convert", "--into-fish", "--dry-run", "zsh_history"]);
+    assert_eq!(status(&output), 0, "{}", stderr(&output));
+    assert!(stderr(&output).contains("fish_history"), "{}", stderr(&output));
+    assert!(!scratch.path(".local").exists());
+}
+
+#[test]
+fn into_a_session() {
+    let scratch = Scratch::new();
+    scratch.write("zsh_history", HISTORY);
+    let output = scratch.run(&["convert", "--into-fish", "--session-name", "work", "zsh_history"]);
+    assert_eq!(status(&output), 0, "{}", stderr(&output));
+    let work = scratch.path(".local/share/fish/work_history");
+    assert_eq!(std::fs::read_to_string(work).unwrap(), FISH);
+
+    // the session of the fish running it
+    let output = scratch
+        .command()
+        .args(["convert", "--into-fish", "zsh_history"])
+        .env("fish_history", "other")
+        .output()
+        .unwrap();
+    assert_eq!(status(&output), 0, "{}", stderr(&output));
+    let other = scratch.path(".local/share/fish/other_history");
+    assert_eq!(std::fs::read_to_string(other).unwrap(), FISH);
+    assert!(!scratch.path(".local/share/fish/fish_history").exists());
+}
+
+#[test]
+fn invalid_session_names() {
+    let scratch = Scratch::new();
+    scratch.write("zsh_history", HISTORY);
+    let output = scratch.run(&["convert", "--into-fish", "--session-name", "../x", "zsh_history"]);
+    assert_ne!(status(&output), 0);
+    assert!(stderr(&output).contains("invalid session name"), "{}", stderr(&output));
+    assert!(!scratch.path(".local").exists());
+    assert!(!scratch.path("x_history").exists());
+}
@@ -0,0 +1,55 @@
+//! `--limit` keeps the newest entries and `--reverse` writes them newest first, the same whether
+//! written to stdout, to a file, or merged into a fish history.
+
+mod common;
+
+use common::{status, stderr, stdout, Scratch};
+use zsh_history_to_fish::RunReport;
+
+// Four entries, the newest in the middle.
+const HISTORY: &str = concat!(
+    ": 1700000001:0;one\n",
+    ": 1700000004:0;four\n",
+    ": 1700000002:0;two\n",
+    ": 1700000003:0;three\n",
+);
+
+fn commands(fish: &str) -> Vec<&str> {
+    fish.lines().filter_map(|line| line.strip_prefix("- cmd: ")).collect()
+}
+
+#[test]
+fn to_stdout_and_to_a_file() {
+    let scratch = Scratch::new();
+    scratch.write("zsh_history", HISTORY);
+    let args = ["convert", "--limit", "2", "--reverse", "zsh_history"];
+    let output = scratch.run(&args);
+    assert_eq!(status(&output), 0, "{}", stderr(&output));
+    assert_eq!(commands(&stdout(&output)), ["three", "four"]);
+
+    let output = scratch.run(&[&args[..], &["-o", "fish_history"]].concat());
+    assert_eq!(status(&output), 0, "{}", stderr(&output));
+    let fish = std::fs::read_to_string(scratch.path("fish_history")).unwrap();
+    assert_eq!(commands(&fish), ["three", "four"]);
+}
+
+#[test]
+fn merged() {
+    let scratch = Scratch::new();
+    scratch.write("zsh_history", HISTORY);
+    scratch.write("fish_history", "- cmd: zero\n  when: 1700000000\n");
+    let args = ["convert", "--merge", "-o", "fish_history", "--limit", "3", "zsh_history"];
+    let output = scratch.run(&[&args[..], &["--report", "report.json"]].concat());
+    assert_eq!(status(&output), 0, "{}", stderr(&output));
+    // the limit is kept by the merged history, the one already there being the oldest
+    let fish = std::fs::read_to_string(scratch.path("fish_history")).unwrap();
+    assert_eq!(commands(&fish), ["two", "three", "four"]);
+    // which the report counts among the dropped
+    let report = std::fs::read_to_string(scratch.path("report.json")).unwrap();
+    let report = serde_json::from_str::<RunReport>(&report).unwrap();
+    let dropped = report
+        .dropped
+        .iter()
+        .map(|dropped| (dropped.by.as_str(), dropped.entries));
+    assert_eq!(dropped.collect::<Vec<_>>(), [("--limit", 2)]);
+}
@@ -0,0 +1,50 @@
+//! `--allowlist` and `--blocklist` apply together with `--match` and `--exclude`: an entry is
+//! kept if it passes `--match` and `--allowlist`, and neither `--exclude` nor `--blocklist`.
+
+mod common;
+
+use common::{status, stderr, stdout, Scratch};
+
+const HISTORY: &str =
+    ": 1:0;git status\n: 2:0;git push\n: 3:0;vault login x\n: 4:0;cargo test\n: 5:0;ls\n";
+
+fn run(args: &[&str]) -> (String, String) {
+    let scratch = Scratch::new();
+    scratch.write("zsh_history", HISTORY);
+    scratch.write("allow", "# what may be synced\ngit\nvault\nre:^cargo\n");
+    scratch.write("block", "vault\n");
+    scratch.convert(&[&["--to", "plain"], args, &["zsh_history"]].concat())
+}
+
+#[test]
+fn blocklist_wins_over_allowlist() {
+    let (stdout, stderr) = run(&["--allowlist", "allow", "--blocklist", "block"]);
+    assert_eq!(stdout, "git status\ngit push\ncargo test\n");
+    assert!(stderr.contains("4 of 5 entries are on the --allowlist"), "{stderr}");
+    assert!(stderr.contains("dropped 1 entries on the --blocklist"), "{stderr}");
+}
+
+#[test]
+fn allowlist_and_match_both_apply() {
+    let (stdout, _) = run(&["--allowlist", "allow", "--match", "status|ls|test"]);
+    assert_eq!(stdout, "git status\ncargo test\n");
+}
+
+#[test]
+fn blocklist_wins_over_match() {
+    let (stdout, _) = run(&["--blocklist", "block", "--match", "^vault|^ls"]);
+    assert_eq!(stdout, "ls\n");
+    let (stdout, _) = run(&["--blocklist", "block", "--exclude", "^git"]);
+    assert_eq!(stdout, "cargo test\nls\n");
+}
+
+#[test]
+fn bad_lists_fail_before_converting() {
+    let scratch = Scratch::new();
+    scratch.write("zsh_history", HISTORY);
+    scratch.write("block", "vault\nre:[\n");
+    let output = scratch.run(&["convert", "--blocklist", "block", "zsh_history"]);
+    assert_ne!(status(&output), 0);
+    assert_eq!(stdout(&output), "");
+    assert!(stderr(&output).contains("invalid regex on line 2 of"), "{}", stderr(&output));
+}
@@ -0,0 +1,42 @@
+//! `--normalize` tidies up the end of commands before `--unique`, so the variants collapse, and
+//! `--normalize=aggressive` drops dangling `&&` and `||` too.
+
+mod common;
+
+const HISTORY: &str = concat!(
+    ": 1:0;make build ;\n",
+    ": 2:0;make build\n",
+    ": 3:0;cargo test &&\n",
+    ": 4:0;cargo test\n",
+    ": 5:0;echo \"a ;\"\n",
+    ": 6:0;cat <<EOF\\\nx ;\\\nEOF\n",
+);
+
+fn run(args: &[&str]) -> (String, String) {
+    common::convert(HISTORY, &[&["--to", "plain"], args].concat())
+}
+
+#[test]
+fn basic_before_unique() {
+    let (stdout, stderr) = run(&["--normalize", "--unique"]);
+    assert_eq!(
+        stdout,
+        "make build\ncargo test &&\ncargo test\necho \"a ;\"\ncat <<EOF\\nx ;\\nEOF\n"
+    );
+    assert!(stderr.contains("normalized the end of 1 commands"), "{stderr}");
+    assert!(stderr.contains("dropped 1 duplicates with --unique"), "{stderr}");
+}
+
+#[test]
+fn aggressive() {
+    let (stdout, stderr) = run(&["--normalize=aggressive", "--unique"]);
+    assert_eq!(stdout, "make build\ncargo test\necho \"a ;\"\ncat <<EOF\\nx ;\\nEOF\n");
+    assert!(stderr.contains("normalized the end of 2 commands"), "{stderr}");
+}
+
+#[test]
+fn not_by_default() {
+    let (stdout, stderr) = run(&["--unique"]);
+    assert!(stdout.starts_with("make build ;\nmake build\n"), "{stdout}");
+    assert!(!stderr.contains("normalized"), "{stderr}");
+}
@@ -0,0 +1,43 @@
+//! `--normalize-dedup` and `--dedup-case-insensitive` compare commands normalized, for
+//! `--unique` and for merging histories alike, while the entries kept keep their own text.
+
+mod common;
+
+use common::Scratch;
+
+const HISTORY: &str = ": 1:0;git  status\n: 2:0;git status\n: 3:0;GIT\tstatus\n: 4:0;ls\n";
+
+fn run(scratch: &Scratch, args: &[&str]) -> String {
+    scratch.convert(&[&["--to", "jsonl"], args].concat()).0
+}
+
+fn cmds(jsonl: &str) -> Vec<String> {
+    let entries = jsonl
+        .lines()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap());
+    entries
+        .map(|entry| entry["cmd"].as_str().unwrap().to_string())
+        .collect()
+}
+
+#[test]
+fn unique() {
+    let scratch = Scratch::new();
+    scratch.write("zsh_history", HISTORY);
+    let output = run(&scratch, &["--unique", "zsh_history"]);
+    assert_eq!(cmds(&output).len(), 4);
+    let output = run(&scratch, &["--unique", "--normalize-dedup", "zsh_history"]);
+    assert_eq!(cmds(&output), ["git status", "GIT\tstatus", "ls"]);
+    let args = ["--unique", "--normalize-dedup", "--dedup-case-insensitive", "zsh_history"];
+    assert_eq!(cmds(&run(&scratch, &args)), ["GIT\tstatus", "ls"]);
+}
+
+#[test]
+fn merged_histories() {
+    let scratch = Scratch::new();
+    scratch.write("zsh_history", HISTORY);
+    scratch.write("other", ": 5:0;git\t\tstatus\n");
+    let output = run(&scratch, &["--normalize-dedup", "zsh_history", "other"]);
+    // collapsed into the first occurrence, with the newest timestamp
+    assert_eq!(cmds(&output), ["GIT\tstatus", "ls", "git  status"]);
+}
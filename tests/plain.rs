@@ -0,0 +1,53 @@
+//! `--format plain` writes the commands alone, after the same ordering and filtering as the fish
+//! format.
+
+mod common;
+
+use common::{status, stderr, stdout, Scratch};
+
+const HISTORY: &str =
+    ": 1700000002:0;pwd\n: 1700000000:0;for i in 1\\\n  echo $i\\\nend\n: 1700000001:0;ls\n";
+
+fn plain(args: &[&str]) -> String {
+    let scratch = Scratch::new();
+    let history = scratch.write("zsh_history", HISTORY);
+    let output = scratch
+        .command()
+        .args(["convert", "--format", "plain"])
+        .args(args)
+        .arg(&history)
+        .output()
+        .unwrap();
+    assert_eq!(status(&output), 0, "{}", stderr(&output));
+    stdout(&output)
+}
+
+#[test]
+fn each_multiline_mode() {
+    assert_eq!(plain(&[]), "pwd\nfor i in 1\\n  echo $i\\nend\nls\n");
+    assert_eq!(plain(&["--multiline", "join"]), "pwd\nfor i in 1; echo $i; end\nls\n");
+    assert_eq!(plain(&["--multiline", "skip"]), "pwd\nls\n");
+}
+
+#[test]
+fn ordering_and_filters_apply() {
+    assert_eq!(plain(&["--sort", "when", "--multiline", "skip"]), "ls\npwd\n");
+    assert_eq!(plain(&["--reverse", "--multiline", "skip"]), "ls\npwd\n");
+    assert_eq!(
+        plain(&["--exclude", "^ls$", "--multiline", "join"]),
+        "pwd\nfor i in 1; echo $i; end\n"
+    );
+}
+
+#[test]
+fn with_time() {
+    let lines = plain(&["--with-time", "--multiline", "skip"]);
+    let lines = lines
+        .lines()
+        .map(|line| line.split_once('\t').unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(lines.iter().map(|(_, cmd)| *cmd).collect::<Vec<_>>(), ["pwd", "ls"]);
+    assert!(lines
+        .iter()
+        .all(|(time, _)| time.len() == "2023-11-14T22:13:20+00:00".len()));
+}
@@ -0,0 +1,55 @@
+//! `--from psreadline` reads PowerShell's `ConsoleHost_history.txt`, as written on Windows.
+
+mod common;
+
+use common::{status, stderr, stdout, Scratch};
+
+// With a BOM and CRLF line endings, as PSReadLine writes it on Windows.
+const HISTORY: &[u8] =
+    b"\xef\xbb\xbfGet-ChildItem\r\nGet-Process |`\r\n  Sort-Object CPU\r\n\r\nexit\r\n";
+
+#[test]
+fn windows_history() {
+    let scratch = Scratch::new();
+    let history = scratch.write("ConsoleHost_history.txt", HISTORY);
+    let output = scratch.run(&["convert", "--from", "psreadline", history.to_str().unwrap()]);
+    assert_eq!(status(&output), 0, "{}", stderr(&output));
+    assert_eq!(
+        stdout(&output),
+        concat!(
+            "- cmd: Get-ChildItem\n  when: 0\n",
+            "- cmd: Get-Process |\\n  Sort-Object CPU\n  when: 0\n",
+            "- cmd: exit\n  when: 0\n",
+        )
+    );
+}
+
+#[test]
+fn default_when_applies() {
+    let scratch = Scratch::new();
+    let history = scratch.write("ConsoleHost_history.txt", HISTORY);
+    let mtime = std::fs::metadata(&history).unwrap().modified().unwrap();
+    let mtime = mtime.duration_since(std::time::UNIX_EPOCH).unwrap().as_secs();
+    let args = ["convert", "--from", "psreadline", "--default-when", "spread", "--to", "jsonl"];
+    let output = scratch.command().args(args).arg(&history).output().unwrap();
+    assert_eq!(status(&output), 0, "{}", stderr(&output));
+    let whens = stdout(&output)
+        .lines()
+        .map(|line| {
+            let entry: serde_json::Value = serde_json::from_str(line).unwrap();
+            entry["when"].as_u64().unwrap()
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(whens, [mtime - 2, mtime - 1, mtime]);
+}
+
+#[test]
+fn filters_apply() {
+    let scratch = Scratch::new();
+    let history = scratch.write("ConsoleHost_history.txt", HISTORY);
+    let args = ["convert", "--from", "psreadline", "--exclude", "^exit$", "--to", "plain"];
+    let output = scratch.command().args(args).arg(&history).output().unwrap();
+    assert_eq!(status(&output), 0, "{}", stderr(&output));
+    assert!(!stdout(&output).contains("exit"));
+    assert!(stdout(&output).starts_with("Get-ChildItem\n"));
+}
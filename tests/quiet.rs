@@ -0,0 +1,102 @@
+//! With `--quiet`, stdout has the entries only, and stderr stays empty unless something fails.
+
+mod common;
+
+use common::{status, stderr, stdout, Scratch};
+
+// An undated entry, a line with a control character, an empty command, and a continuation cut
+// off at the end, each of which is warned about without `--quiet`.
+const HISTORY: &[u8] = concat!(
+    ": 1700000000:0;ls\n",
+    "undated\n",
+    ": 1700000001:0;printf \x01\n",
+    ": 1700000002:0;\n",
+    ": 1700000003:0;echo \\\n",
+)
+.as_bytes();
+
+const FISH: &str = concat!(
+    "- cmd: ls\n  when: 1700000000\n",
+    "- cmd: undated\n  when: 0\n",
+    "- cmd: printf \\\\x01\n  when: 1700000001\n",
+    "- cmd: echo\n  when: 1700000003\n",
+);
+
+#[test]
+fn quiet_leaves_stderr_empty() {
+    let scratch = Scratch::new();
+    let history = scratch.write("zsh_history", HISTORY);
+    let output = scratch.run(&["convert", "--quiet", history.to_str().unwrap()]);
+    assert_eq!(status(&output), 0);
+    assert_eq!(stderr(&output), "");
+    assert_eq!(stdout(&output), FISH);
+}
+
+#[test]
+fn warnings_go_to_stderr_only() {
+    let scratch = Scratch::new();
+    let history = scratch.write("zsh_history", HISTORY);
+    let output = scratch.run(&["convert", "-v", history.to_str().unwrap()]);
+    assert_eq!(status(&output), 0);
+    assert_eq!(stdout(&output), FISH);
+    assert!(stderr(&output).contains("no timestamp"), "{}", stderr(&output));
+}
+
+#[test]
+fn quiet_still_reports_errors() {
+    let scratch = Scratch::new();
+    let output = scratch.run(&["convert", "--quiet", "no_such_history"]);
+    assert_eq!(status(&output), 3);
+    assert_eq!(stdout(&output), "");
+    assert!(stderr(&output).starts_with("Error: "), "{}", stderr(&output));
+}
+
+#[test]
+fn quiet_with_every_format() {
+    let scratch = Scratch::new();
+    let history = scratch.write("zsh_history", HISTORY);
+    for format in ["fish", "json", "jsonl", "csv", "plain", "pretty", "bash", "zsh", "fish-script"]
+    {
+        let output = scratch.run(&["convert", "-q", "--format", format, history.to_str().unwrap()]);
+        assert_eq!((status(&output), stderr(&output)), (0, String::new()), "--format {format}");
+        assert!(!output.stdout.is_empty(), "--format {format}");
+    }
+}
+
+#[test]
+fn configuration_warnings_follow_the_verbosity() {
+    let scratch = Scratch::new();
+    let history = scratch.write("zsh_history", HISTORY);
+    let config = scratch.write("config.toml", "colour = 'never'\n");
+    let args = ["convert", "--config", config.to_str().unwrap(), history.to_str().unwrap()];
+    let warned = |extra: &[&str]| {
+        let output = scratch.run(&[&args[..], extra].concat());
+        assert_eq!(status(&output), 0);
+        stderr(&output).contains("colour is no option")
+    };
+    assert!(warned(&[]));
+    assert!(warned(&["-v"]));
+    assert!(!warned(&["--quiet"]));
+    // or quiet from the configuration itself
+    scratch.write("config.toml", "colour = 'never'\nquiet = true\n");
+    assert!(!warned(&[]));
+}
+
+#[test]
+fn invalid_sequences_are_warnings_once_replaced() {
+    let scratch = Scratch::new();
+    let history = scratch.write("zsh_history", b": 1700000000:0;echo \xff\n");
+    let args = ["convert", "--strict-utf8", history.to_str().unwrap()];
+    let sequence = "line 1: invalid UTF-8 sequence: ff";
+
+    // the details of the error, whatever the verbosity
+    let output = scratch.run(&[&args[..], &["--quiet"]].concat());
+    assert_eq!(status(&output), 4);
+    assert!(stderr(&output).contains(sequence), "{}", stderr(&output));
+
+    let output = scratch.run(&[&args[..], &["--lossy"]].concat());
+    assert_eq!(status(&output), 0);
+    assert!(stderr(&output).contains(sequence), "{}", stderr(&output));
+    let output = scratch.run(&[&args[..], &["--lossy", "--quiet"]].concat());
+    assert_eq!((status(&output), stderr(&output)), (0, String::new()));
+}
@@ -0,0 +1,39 @@
+//! `--redact` keeps likely credentials out of the converted history, and out of what it prints
+//! about them.
+
+mod common;
+
+const HISTORY: &str = concat!(
+    ": 1:0;ls\n",
+    ": 2:0;export AWS_SECRET_ACCESS_KEY=hunter2hunter2\n",
+    ": 3:0;curl -H 'Authorization: Bearer abcdef123456'\n",
+    ": 4:0;vault login corp-123456\n",
+);
+
+fn run(args: &[&str]) -> (String, String) {
+    common::convert(HISTORY, &[&["--to", "plain", "-v"], args].concat())
+}
+
+#[test]
+fn skipped_by_default() {
+    let (stdout, stderr) = run(&["--redact"]);
+    assert_eq!(stdout, "ls\nvault login corp-123456\n");
+    assert!(stderr.contains("skipped 2 entries with likely credentials"), "{stderr}");
+    assert!(!stderr.contains("hunter2") && !stderr.contains("abcdef"), "{stderr}");
+}
+
+#[test]
+fn masked() {
+    let (stdout, stderr) = run(&["--redact=mask", "--redact-pattern", "corp-[0-9]+"]);
+    assert_eq!(
+        stdout,
+        concat!(
+            "ls\n",
+            "export AWS_SECRET_ACCESS_KEY=***\n",
+            "curl -H 'Authorization: Bearer ***'\n",
+            "vault login ***\n",
+        )
+    );
+    assert!(stderr.contains("masked likely credentials in 3 entries"), "{stderr}");
+    assert!(!stderr.contains("hunter2") && !stderr.contains("corp-1"), "{stderr}");
+}
@@ -0,0 +1,83 @@
+//! `--report` and `--timing` are written however the run ends.
+
+mod common;
+
+use common::{status, stderr, Scratch};
+use zsh_history_to_fish::{RunReport, RunStatus, REPORT_VERSION};
+
+const HISTORY: &str = ": 1700000000:0;ls\n: 1700000001:0;git status\n";
+
+fn read_report(scratch: &Scratch) -> RunReport {
+    let json = std::fs::read_to_string(scratch.path("report.json")).expect("no report written");
+    serde_json::from_str(&json).unwrap()
+}
+
+#[test]
+fn written_on_success() {
+    let scratch = Scratch::new();
+    let history = scratch.write("zsh_history", HISTORY);
+    let output = scratch.run(&["convert", "--report", "report.json", history.to_str().unwrap()]);
+    assert_eq!(status(&output), 0);
+    let report = read_report(&scratch);
+    assert_eq!((report.report_version, report.status), (REPORT_VERSION, RunStatus::Ok));
+    assert_eq!((report.read.entries, report.written), (2, 2));
+}
+
+#[test]
+fn written_on_failure() {
+    let scratch = Scratch::new();
+    let output = scratch.run(&["convert", "--report", "report.json", "no_such_history"]);
+    assert_eq!(status(&output), 3);
+    let report = read_report(&scratch);
+    assert_eq!((report.status, report.exit_code), (RunStatus::Error, 3));
+    assert!(report.error.unwrap().contains("does not exist"));
+}
+
+#[test]
+fn written_with_diff() {
+    let scratch = Scratch::new();
+    let history = scratch.write("zsh_history", HISTORY);
+    let fish = scratch.path("fish_history");
+    let args = ["convert", "--diff", "-o", fish.to_str().unwrap(), "--report", "report.json"];
+    let output = scratch.run(&[&args[..], &[history.to_str().unwrap()]].concat());
+    assert_eq!(status(&output), 1);
+    assert_eq!(read_report(&scratch).exit_code, 1);
+}
+
+#[test]
+fn timing_printed_on_failure() {
+    let scratch = Scratch::new();
+    let history = scratch.write("zsh_history", HISTORY);
+    let fish = scratch.path("fish_history");
+    let args = ["convert", "--diff", "-o", fish.to_str().unwrap(), "--timing"];
+    let output = scratch.run(&[&args[..], &[history.to_str().unwrap()]].concat());
+    assert_eq!(status(&output), 1);
+    assert!(stderr(&output).contains("\ntotal "), "{}", stderr(&output));
+}
+
+#[test]
+fn paths_are_expanded() {
+    let scratch = Scratch::new();
+    std::fs::create_dir(scratch.path("out")).unwrap();
+    scratch.write("zsh_history", HISTORY);
+    let args = [
+        "convert",
+        "--report",
+        "~/out/report.json",
+        "--annotate-file",
+        "$HOME/out/annotated.jsonl",
+        "~/zsh_history",
+    ];
+    assert_eq!(status(&scratch.run(&args)), 0);
+    let report = std::fs::read(scratch.path("out/report.json")).unwrap();
+    let report = serde_json::from_slice::<RunReport>(&report).unwrap();
+    assert_eq!(report.written, 2);
+    let annotated = std::fs::read_to_string(scratch.path("out/annotated.jsonl")).unwrap();
+    assert_eq!(annotated.lines().count(), 2);
+
+    // taken literally, there is no `~` directory to write into
+    let output =
+        scratch.run(&["convert", "--no-expand", "--report", "~/report.json", "zsh_history"]);
+    assert_ne!(status(&output), 0);
+    assert!(!scratch.path("report.json").exists());
+}
@@ -0,0 +1,60 @@
+//! `--reverse` flips the entries last, after the filters, `--sort`, and `--limit`, in every
+//! format, and `--max-entries` bounds what it reads.
+
+mod common;
+
+use common::{status, stderr, Scratch};
+
+// Five entries out of order, one of them undated.
+const HISTORY: &str = concat!(
+    "undated\n",
+    ": 1700000002:0;git push\n",
+    ": 1700000001:0;git status\n",
+    ": 1700000004:0;ls\n",
+    ": 1700000003:0;git log\n",
+);
+
+fn run(args: &[&str]) -> String {
+    common::convert(HISTORY, args).0
+}
+
+#[test]
+fn newest_first() {
+    let stdout = run(&["--to", "plain", "--sort", "when", "--reverse"]);
+    assert_eq!(stdout, "ls\ngit log\ngit push\ngit status\nundated\n");
+    let stdout = run(&["--to", "plain", "--reverse"]);
+    assert_eq!(stdout, "git log\nls\ngit status\ngit push\nundated\n");
+}
+
+#[test]
+fn after_filters_sort_and_limit() {
+    let args = ["--to", "plain", "--match", "^git", "--sort", "when", "--limit", "2", "--reverse"];
+    // of the three `git` commands, the newest two, then newest first
+    assert_eq!(run(&args), "git log\ngit push\n");
+}
+
+#[test]
+fn jsonl() {
+    let stdout = run(&["--to", "jsonl", "--sort", "when", "--reverse"]);
+    let cmds = stdout
+        .lines()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap()["cmd"].clone())
+        .collect::<Vec<_>>();
+    assert_eq!(cmds, ["ls", "git log", "git push", "git status", "undated"]);
+}
+
+#[test]
+fn max_entries_bounds_what_is_read() {
+    let stdout = run(&["--to", "plain", "--max-entries", "3", "--reverse"]);
+    assert_eq!(stdout, "git status\ngit push\nundated\n");
+}
+
+#[test]
+fn not_with_merge() {
+    let scratch = Scratch::new();
+    scratch.write("zsh_history", HISTORY);
+    let output = scratch.run(&["convert", "--reverse", "--merge", "-o", "fish", "zsh_history"]);
+    assert_eq!(status(&output), 2);
+    assert!(stderr(&output).contains("--reverse does not work with --merge"));
+    assert!(!scratch.path("fish").exists());
+}
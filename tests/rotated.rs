@@ -0,0 +1,47 @@
+//! `--include-rotated` converts the rotated copies next to the zsh history too, merged by
+//! timestamp with the duplicates collapsed.
+
+mod common;
+
+use common::{status, stderr, stdout, Scratch};
+
+#[test]
+fn overlapping_histories_interleave() {
+    let scratch = Scratch::new();
+    scratch.write(".zsh_history.2", ": 100:0;oldest\n: 300:0;ls\n: 500:0;make\n");
+    scratch.write(".zsh_history.1", ": 200:0;cd\n: 400:0;ls\n: 600:0;git status\n");
+    let history = scratch.write(".zsh_history", ": 450:0;pwd\n: 700:0;ls\n");
+    let output = scratch.run(&["convert", "--include-rotated", history.to_str().unwrap()]);
+    assert_eq!(status(&output), 0, "{}", stderr(&output));
+    assert_eq!(
+        stdout(&output),
+        concat!(
+            "- cmd: oldest\n  when: 100\n",
+            "- cmd: cd\n  when: 200\n",
+            "- cmd: pwd\n  when: 450\n",
+            "- cmd: make\n  when: 500\n",
+            "- cmd: git status\n  when: 600\n",
+            "- cmd: ls\n  when: 700\n",
+        )
+    );
+}
+
+#[test]
+fn unreadable_copies_are_warned_about() {
+    let scratch = Scratch::new();
+    std::fs::create_dir(scratch.path(".zsh_history.1")).unwrap();
+    scratch.write(".zsh_history.old", ": 100:0;old\n");
+    let history = scratch.write(".zsh_history", ": 200:0;new\n");
+    let output = scratch.run(&["convert", "--include-rotated", history.to_str().unwrap()]);
+    assert_eq!(status(&output), 0);
+    assert_eq!(stdout(&output), "- cmd: old\n  when: 100\n- cmd: new\n  when: 200\n");
+    assert!(stderr(&output).contains("skipping "), "{}", stderr(&output));
+    assert!(stderr(&output).contains(".zsh_history.1"), "{}", stderr(&output));
+}
+
+#[test]
+fn only_with_a_file() {
+    let scratch = Scratch::new();
+    let output = scratch.run(&["convert", "--include-rotated", "-"]);
+    assert_eq!(status(&output), 2);
+}
@@ -0,0 +1,43 @@
+//! `--since` and `--until` keep the entries within them, both inclusive, and say how many fell
+//! outside.
+
+mod common;
+
+use common::{status, stderr, Scratch};
+
+const HISTORY: &str = ": 1600000000:0;old\nundated\n: 1700000000:0;in\n: 1800000000:0;new\n";
+
+fn run(args: &[&str]) -> (String, String) {
+    common::convert(HISTORY, &[&["--to", "plain"], args].concat())
+}
+
+#[test]
+fn window() {
+    let (stdout, stderr) = run(&["--since", "2023-01-01T00:00:00Z", "--until", "1700000000"]);
+    assert_eq!(stdout, "in\n");
+    assert!(stderr.contains(
+        "dropped 3 entries outside of --since and --until, 1 of them without a timestamp, which \
+         --include-undated keeps"
+    ));
+}
+
+#[test]
+fn include_undated() {
+    let (stdout, stderr) = run(&["--since", "1700000000", "--include-undated"]);
+    assert_eq!(stdout, "undated\nin\nnew\n");
+    assert!(stderr.contains("dropped 1 entries outside of --since and --until\n"), "{stderr}");
+}
+
+#[test]
+fn until_keeps_undated() {
+    let (stdout, _) = run(&["--until", "2025-01-01"]);
+    assert_eq!(stdout, "old\nundated\nin\n");
+}
+
+#[test]
+fn bad_times_are_usage_errors() {
+    let scratch = Scratch::new();
+    let output = scratch.run(&["convert", "--since", "last tuesday", "-"]);
+    assert_eq!(status(&output), 2);
+    assert!(stderr(&output).contains("invalid time: last tuesday"), "{}", stderr(&output));
+}
@@ -0,0 +1,49 @@
+//! `--min-length` and `--skip-common` skip the trivial commands, `--skip-common-list` replaces
+//! the built-in ones, and both compose with the other filters.
+
+mod common;
+
+use common::Scratch;
+
+const HISTORY: &str = ": 1:0;ls\n: 2:0;cd\n: 3:0;cd ..\n: 4:0;ls -la /etc\n: 5:0;clear\n\
+                       : 6:0;gs\n: 7:0;git status\n: 8:0;make\n";
+
+fn run(scratch: &Scratch, args: &[&str]) -> (String, String) {
+    scratch.write("zsh_history", HISTORY);
+    scratch.convert(&[&["--to", "plain"], args, &["zsh_history"]].concat())
+}
+
+#[test]
+fn cd_is_common_but_cd_up_is_not() {
+    let (stdout, stderr) = run(&Scratch::new(), &["--skip-common"]);
+    assert_eq!(stdout, "cd ..\nls -la /etc\ngs\ngit status\nmake\n");
+    assert!(stderr.contains("skipped 3 common commands"), "{stderr}");
+}
+
+#[test]
+fn min_length() {
+    let (stdout, stderr) = run(&Scratch::new(), &["--min-length", "5"]);
+    assert_eq!(stdout, "cd ..\nls -la /etc\nclear\ngit status\n");
+    assert!(stderr.contains("skipped 4 commands shorter than --min-length"), "{stderr}");
+}
+
+#[test]
+fn both_together() {
+    let (stdout, _) = run(&Scratch::new(), &["--min-length", "3", "--skip-common"]);
+    assert_eq!(stdout, "cd ..\nls -la /etc\ngit status\nmake\n");
+}
+
+#[test]
+fn a_list_replaces_the_built_in_commands() {
+    let scratch = Scratch::new();
+    scratch.write("common", "# my aliases\n\ngs\nmake\n");
+    let (stdout, stderr) = run(&scratch, &["--skip-common-list", "common"]);
+    assert_eq!(stdout, "ls\ncd\ncd ..\nls -la /etc\nclear\ngit status\n");
+    assert!(stderr.contains("skipped 2 common commands"), "{stderr}");
+}
+
+#[test]
+fn with_match() {
+    let (stdout, _) = run(&Scratch::new(), &["--skip-common", "--match", "^(ls|cd)"]);
+    assert_eq!(stdout, "cd ..\nls -la /etc\n");
+}
@@ -0,0 +1,583 @@
+# Print an optspec for argparse to handle cmd's options that are independent of any subcommand.
+function __fish_zsh_history_to_fish_global_optspecs
+    string join \n stdin include-rotated pick-largest no-expand from= tz= encoding= strict-utf8 lossy include-partial clamp-timestamps strict default-when= interpolate-timestamps bump-duplicate-timestamps max-line-bytes= max-entries= ignore-space keep-empty skip-invalid dedup-by= dup-policy= expand-aliases= expand-global-aliases strip-prefix= strip-env-assignments normalize= redact= redact-pattern= skip-multiline min-length= skip-common skip-common-list= head= tail= since= until= match= command= exclude= exclude-file= allowlist= blocklist= ignore-case include-undated unique keep= normalize-dedup dedup-case-insensitive format= columns= atuin-db= multiline= with-time color= full no-timestamps top= min-count= abbr-min-length= replace no-header annotate o/output= annotate-file= append overwrite sort= reverse limit= backup backup-dir= verify count fail-if-empty interactive dry-run session-name= lock-timeout= run-merge fish-bin= diff diff-limit= no-progress v/verbose q/quiet config= print-config report= timing into-fish merge list-commands h/help V/version
+end
+
+function __fish_zsh_history_to_fish_needs_command
+    # Figure out if the current invocation already has a command.
+    set -l cmd (commandline -opc)
+    set -e cmd[1]
+    argparse -s (__fish_zsh_history_to_fish_global_optspecs) -- $cmd 2>/dev/null
+    or return
+    if set -q argv[1]
+        # Also print the command, so this can be used to figure out what it is.
+        echo $argv[1]
+        return 1
+    end
+    return 0
+end
+
+function __fish_zsh_history_to_fish_using_subcommand
+    set -l cmd (__fish_zsh_history_to_fish_needs_command)
+    test -z "$cmd"
+    and return 1
+    contains -- $cmd[1] $argv
+end
+
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -l from -d 'The shell, or other program, which wrote the history. Everything said about the zsh history file applies to the others too' -r -f -a "auto\t'Tell the format from the first few KiB of the history'
+zsh\t'A zsh history file, with or without EXTENDED_HISTORY'
+bash\t'A bash history file, with or without `HISTTIMEFORMAT` timestamps'
+fish\t'A fish history file, e.g. to clean one up with `--dedup-by`'
+fc\t'The listing `fc -l` or `history` prints, preferably `fc -li` for the timestamps'
+psreadline\t'PowerShell\'s PSReadLine history, `ConsoleHost_history.txt`'
+xonsh\t'A xonsh JSON history session file, or the directory holding them, which are merged'
+histdb\t'The SQLite database of zsh-histdb, `~/.histdb/zsh-history.db`'
+atuin\t'The SQLite database of atuin, `~/.local/share/atuin/history.db`'
+nushell\t'nushell\'s history, either the plain text `history.txt` or the SQLite `history.sqlite3`'"
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -l tz -d 'The UTC offset the timestamps of `--from fc` are in, like `+09:00` or `UTC`. Defaults to the local time zone' -r
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -l encoding -d 'The encoding of the zsh history file, like `latin1`, `shift_jis`, or `euc-jp`, or `auto` to guess it from the contents' -r
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -l default-when -d 'The timestamp to give entries without one' -r -f -a "zero\t'Leave them at 0, the start of 1970'
+mtime\t'The modification time of the history file'
+spread\t'Increasing timestamps ending at the modification time of the history file, so fish keeps them in order'"
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -l max-line-bytes -d 'Skip records, including all lines of a multi-line command, longer than the given number of bytes. Defaults to 1 MiB' -r
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -l max-entries -d 'Stop reading the history after N entries, 0 for no limit, counting those parsed, before `--redact` and the like drop any. Unlike `--limit`, this keeps the oldest entries, so combined with it the newest of those first N are kept' -r
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -l dedup-by -d 'What makes two entries the same when merging into the destination or several histories with `--dup-policy keep-both`. The other policies collapse every occurrence of a command' -r -f -a "cmd\t'Entries with the same command are duplicates, regardless of when they were executed'
+cmd+when\t'Entries are duplicates only if both the command and the timestamp match'"
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -l dup-policy -d 'Which entry survives when merging duplicates. Unless both are kept, the destination is rewritten sorted by timestamp if any of its entries changed' -r -f -a "keep-newest\t'Collapse duplicates into one entry with the newest timestamp'
+keep-oldest\t'Collapse duplicates into one entry with the oldest timestamp'
+keep-both\t'Keep the existing history as is and append every entry not already present'"
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -l expand-aliases -d 'Expand the zsh aliases defined in FILE, as `alias` lines or what `alias` prints, in the first word of the commands, for the shell they are converted for, which does not know them' -r -F
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -l strip-prefix -d 'Strip WORD, like `sudo`, and its options off the front of the commands, if a command follows them, along with the wrappers `time`, `nice`, `command`, and `env`. A prefix of several words, like `poetry run`, is stripped as a whole. May be given several times' -r
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -l normalize -d 'Remove trailing whitespace and bare `;`s from the commands, and with `aggressive` dangling `&&` and `||` too, before looking for duplicates. Nothing quoted is touched, and neither are commands with a heredoc' -r -f -a "basic\t'Trailing whitespace and bare `;`s'
+aggressive\t'Also `&&` and `||` left dangling with nothing after them'"
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -l redact -d 'Skip the entries whose command looks like it contains a password, a token, or a key, or mask them with `***`. Known variable and option names, services\' tokens, private keys, and long random-looking tokens are detected' -r -f -a "skip\t'Drop the entries'
+mask\t'Replace the credentials with `***`, keeping the rest of the command'"
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -l redact-pattern -d 'Also take what REGEX matches for a credential, or its group named `secret` if it has one, with `--redact`, which it implies. May be given several times' -r
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -l min-length -d 'Skip commands shorter than N characters, not counting the whitespace around them' -r
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -l skip-common-list -d 'Skip the commands in FILE, one per line, with `--skip-common`, which it implies, instead of the built-in ones. Empty lines and lines starting with `#` are skipped' -r -F
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -l head -d 'Keep only the first N entries, in the order of the history, after `--since` and `--until` and before sorting and the other filters' -r
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -l tail -d 'Keep only the last N entries, in the order of the history, after `--since` and `--until` and before sorting and the other filters' -r
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -l since -d 'Keep only the entries from this time on: a Unix time, a `YYYY-MM-DD [HH:MM[:SS]]` in the local time zone, an RFC 3339 timestamp, or a time ago like `90d`, `6months`, or `2y`' -r
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -l until -d 'Keep only the entries up to this time, inclusive, given like `--since`' -r
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -l match -d 'Keep only the entries whose command matches REGEX, in the syntax of the `regex` crate. If given several times, the entries matching any of them are kept' -r
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -l command -d 'Keep only the entries whose command runs NAME, as its first word after any variable assignments, and after the wrappers `--strip-prefix` strips. May be given several times' -r
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -l exclude -d 'Drop the entries whose command matches REGEX, even if they match `--match`. May be given several times' -r
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -l exclude-file -d 'Drop the entries whose command matches any of the regexes in FILE, one per line, like `--exclude`. Empty lines and lines starting with `#` are skipped. May be given several times' -r -F
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -l allowlist -d 'Keep only the entries whose command starts with any of the prefixes in FILE, one per line, or matches any of its regexes, given as `re:<regex>`. Empty lines and lines starting with `#` are skipped. Applies together with `--match`, so an entry has to pass both' -r -F
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -l blocklist -d 'Drop the entries whose command starts with any of the prefixes in FILE, or matches any of its regexes, given like in `--allowlist`. Applies together with `--exclude`, and drops entries even if `--match` or `--allowlist` keep them' -r -F
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -l keep -d 'Which occurrence of a command `--unique` keeps, the one with the oldest or the newest timestamp. It stays where that occurrence was' -r -f -a "first\t'The occurrence with the oldest timestamp'
+last\t'The occurrence with the newest timestamp, which fish ranks by recency'"
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -l format -l to -d 'The format to write the converted history in' -r -f -a "fish\t'The fish history file format'
+fish-script\t'A fish script which adds the entries with `builtin history append` when sourced'
+json\t'A JSON array of objects with the `cmd`, `when`, `duration`, and `paths` of each entry, and with `--annotate` the `line` and `raw` record it was read from'
+jsonl\t'The objects of `json`, one per line'
+csv\t'Comma-separated values as RFC 4180 describes them, with the `--columns`'
+tsv\t'Tab-separated values, quoted like `csv`'
+nushell\t'nushell\'s plain text `history.txt`, or its SQLite `history.sqlite3` if the `--output` ends in `.sqlite3` or `.db`, or already is an SQLite database'
+plain\t'The commands alone, one per line, with the multi-line ones written as `--multiline` says'
+plain0\t'The commands alone, each ended by a NUL byte rather than a newline, for `fzf --read0` and `xargs -0`. Commands with a NUL byte of their own are skipped'
+pretty\t'A line per entry to read in a terminal, the time dimmed before the command, with the lines of a multi-line command under it, cut off at the width of the terminal unless `--full` is given. Colored as `--color` says'
+zsh\t'A zsh history file with `EXTENDED_HISTORY`, to go back from fish with `--from fish`'
+bash\t'A bash history file, with a `#<when>` comment before each command as bash writes with `HISTTIMEFORMAT` set, unless `--no-timestamps` is given'
+abbr\t'`abbr -a` lines to source in fish, for the commands typed most often, see `--top`'
+zoxide\t'The directories changed into with `cd`, `pushd`, or `AUTO_CD`, with their visit count and last visit, as the `path|rank|time` lines of z\'s data file `zoxide import --from z` reads'
+sqlite\t'An SQLite database with the entries in a table of their own, to query with any SQLite client, written to the `--output`'
+mcfly\t'Rows of the `commands` table of McFly, added to the database the `--output` names'
+atuin\t'Rows of the `history` table of atuin, written into the database `--atuin-db` names'"
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -l columns -d 'The columns to write with `--format csv` or `tsv`, separated by commas' -r -f -a "when\t''
+cmd\t''
+duration\t''
+paths\t''
+line\t'The line the entry was read from'
+raw\t'The record of a zsh history the entry was parsed from'"
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -l atuin-db -d 'The atuin database to write into with `--format atuin`, usually `~/.local/share/atuin/history.db`. It is created if it does not exist. Entries already written by an earlier run are skipped. Tested with atuin 18.4' -r -F
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -l multiline -d 'How to write commands spanning several lines with `--format plain`' -r -f -a "escape\t'Write its newlines as `\\n`, and its backslashes as `\\\\`, like the fish history does'
+join\t'Join its lines with `; `'
+skip\t'Leave it out'"
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -l color -d 'When to color `--format pretty`' -r -f -a "auto\t'When writing to a terminal, unless `NO_COLOR` is set'
+always\t'Even when writing to a file or a pipe'
+never\t'Not even when writing to a terminal'"
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -l top -d 'Suggest at most N abbreviations with `--format abbr`' -r
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -l min-count -d 'Suggest abbreviations only for commands typed at least N times with `--format abbr`' -r
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -l abbr-min-length -d 'Suggest abbreviations only for commands at least N characters long with `--format abbr`' -r
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -s o -l output -d 'Write the converted history to the given file instead of stdout' -r -F
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -l annotate-file -d 'Write the entries written, with the line and the record each was read from, to PATH as JSON lines, whatever the format' -r -F
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -l sort -d 'The order to write the entries in, once `--head` and `--tail` picked them by where they are in the history, and before `--limit` picks some of them' -r -f -a "when\t'Oldest first, with the entries without a timestamp before all others, and entries sharing a timestamp in the order they were read in'
+input\t'The order the entries were read in, after merging several histories by timestamp'"
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -l limit -d 'Keep only the newest N entries. When merging, the limit applies to the merged history, dropping the oldest entries of the destination if necessary' -r
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -l backup-dir -d 'Put the backup into the given directory instead of next to the destination file, creating it if needed. Implies `--backup`' -r -F
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -l session-name -d 'The fish history session to write into with `--into-fish`, like `set fish_history <NAME>` in fish. Defaults to `$fish_history`, or `fish` if that is not set' -r
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -l lock-timeout -d 'How many seconds to wait for the lock on the destination file' -r
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -l fish-bin -d 'The fish binary to run with `--run-merge`' -r -F
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -l diff-limit -d 'How many added lines `--diff` shows before summarizing the rest' -r
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -l config -d 'Read the default options from PATH instead of `~/.config/zsh-history-to-fish/config.toml`, whose keys are the long names of the options, or from `$Z2F_CONFIG`. Those given on the command line, or by the `Z2F_` environment variables, win' -r -F
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -l report -d 'Write what the run did as JSON to PATH, or to stderr with `-`, at its end, even if it failed: the histories read, the entries each filter dropped, the duplicates removed, the entries written and where, and the lines skipped' -r -F
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -l stdin -d 'Read the zsh history from stdin'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -l include-rotated -d 'Also convert the rotated copies of the zsh history file next to it, like `.zsh_history.1`, `.zsh_history.old`, or `.zsh_history-20240131`, collapsing duplicates across them'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -l pick-largest -d 'Convert the largest history file found in a directory given as the zsh history, instead of erroring if there are several'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -l no-expand -d 'Take the paths given, like the zsh history, `--output`, `--backup-dir`, and `--report`, literally, instead of expanding a leading `~` and `$VAR` or `${VAR}` in them'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -l strict-utf8 -d 'Report every line which is not valid in the encoding of the zsh history file, with the offending bytes, and fail after reading the whole file'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -l lossy -d 'With `--strict-utf8`, replace the invalid bytes and carry on instead of failing'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -l include-partial -d 'Convert an incomplete last entry, which zsh may still be writing, instead of skipping it'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -l clamp-timestamps -d 'Clamp timestamps before 1970 or in the future to between 0 and now, instead of only reporting them'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -l strict -d 'Fail on every line which cannot be cleanly converted, like records which cannot be, invalid bytes, timestamps before 1970 or in the future, a cut off last entry, or control characters, listing them all once the histories are read, and write nothing'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -l interpolate-timestamps -d 'Give entries without a timestamp one between those of the entries around them. Any left without one, when no entry has a timestamp, get the `--default-when` one'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -l bump-duplicate-timestamps -d 'Move entries sharing a second with the entry before them a second later each, so fish keeps them in order, without crossing into the next later timestamp'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -l ignore-space -d 'Skip commands starting with a space, like zsh\'s HIST_IGNORE_SPACE option'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -l keep-empty -d 'Keep timestamped entries with an empty command instead of skipping them'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -l skip-invalid -d 'Skip entries with NUL bytes or other control characters instead of stripping NULs and escaping the rest'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -l expand-global-aliases -d 'Also expand the global aliases, defined with `alias -g`, in any word of the commands, with `--expand-aliases`'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -l strip-env-assignments -d 'Strip variable assignments, like `FOO=bar`, off the front of the commands, along with the wrappers `--strip-prefix` strips'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -l skip-multiline -d 'Skip commands which span several lines, rather than writing their newlines as `<\\n>` with `--format nushell`, or as the other formats do'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -l skip-common -d 'Skip the commands which are typed all the time without arguments, like `ls`, `cd`, and `clear`. A command only counts if it is exactly one of them, so `cd ..` is kept'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -l ignore-case -d 'Match `--match` and `--exclude` regardless of case, as `(?i)` does'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -l include-undated -d 'Keep the entries without a timestamp, which `--since` and `--until` would otherwise judge to be from 1970'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -l unique -d 'Collapse the entries with the same command into one, dropping the duplicates even without `--merge`'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -l normalize-dedup -d 'Compare commands for duplicates with their runs of whitespace, newlines included, collapsed into single spaces and their ends trimmed, with `--unique` and when merging. The entry kept keeps its command as it was'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -l dedup-case-insensitive -d 'Compare commands for duplicates regardless of case, like `--normalize-dedup`'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -l with-time -d 'Start each line with the time of the command with `--format plain`'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -l full -d 'Write the commands in full with `--format pretty`, instead of cutting them off at the width of the terminal'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -l no-timestamps -d 'Leave out the `#<when>` comments with `--format bash`. Without them bash reads each line as a command, so a command spanning several lines fails the conversion, unless `--skip-multiline` drops it'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -l replace -d 'Replace the `entries` table of an existing database with `--format sqlite`, rather than adding to it'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -l no-header -d 'Leave out the row of column names with `--format csv` or `tsv`'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -l annotate -d 'Write the line of the zsh history each entry was read from, and the record on it, beside the entry: as the `line` and `raw` fields with `--format json` or `jsonl`, as the last columns with `csv` and `tsv`, and dimmed under the command with `pretty`. The other formats have no room for them, so they need `--annotate-file`'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -l append -d 'Append to the output file if it already exists'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -l overwrite -l force -d 'Replace the output file if it already exists'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -l reverse -d 'Write the entries in reverse, after sorting, filtering, and `--limit`, so `--sort when --reverse` writes the newest first. The entries are all read before writing any, so `--max-entries` bounds the memory this takes, like it does for sorting'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -l backup -d 'Back up the destination file, as `<name>.bak.<unix-ts>`, before modifying it'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -l verify -d 'Check that the converted entries read back identically from the fish history format before writing them, and fail if any does not'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -l count -d 'Print only how many entries would be written, after all the filtering, instead of converting. With `--merge`, how many the destination does not have yet'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -l fail-if-empty -d 'Fail with exit status 6 if no entries are left to write, saying whether none were parsed, or how many each filter dropped'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -l interactive -d 'Show the entries newest first on the terminal, to pick the ones to write, before writing them. Aborting writes nothing'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -l dry-run -d 'Go through everything up to writing, then print what would be written where, and what backed up, without writing anything, taking locks, or running fish'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -l run-merge -d 'Run `fish -c \'builtin history merge\'` after writing, so running fish sessions pick up the imported entries'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -l diff -d 'Preview the entries which would be appended to the destination as a diff, without writing anything. Exits with 1 if there are entries to append, 0 otherwise'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -l no-progress -d 'Show no progress while reading a large history, which is otherwise shown on stderr, as a bar if it is a terminal'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -s v -l verbose -d 'Report every line skipped, or converted despite looking wrong, on stderr, with its line number, why, and the start of it. Given twice, also dump the bytes of the line in hex'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -s q -l quiet -d 'Report nothing on stderr but errors, no warnings, summaries, or progress, for scripts'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -l print-config -d 'Print the options in effect, from the command line, the configuration file, or the defaults, as a configuration file, and exit'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -l timing -d 'Print how long each phase of the run took on stderr at its end, reading, sorting, filtering, dropping duplicates, and writing, with the megabytes and entries read per second. With `--report`, the report has them too'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -l into-fish -d 'Append the converted history to the fish history file of the current user'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -l merge -d 'Only append the entries which are not already in the destination fish history'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -l list-commands -d 'Print the distinct commands run, the first words `--command` compares, with how often each was run, most often first, instead of converting'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -s h -l help -d 'Print help (see more with \'--help\')'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -s V -l version -d 'Print version'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -a "convert" -d 'Convert a zsh history, which running without a command does too'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -a "merge" -d 'Merge a zsh history into the fish history, collapsing the duplicates, like `convert --merge --into-fish`, or into `--output`'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -a "fish-to-zsh" -d 'Convert a fish history into a zsh history, like `convert --from fish --to zsh`'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -a "stats" -d 'Print how many entries a history has, over which time, and its most run commands'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -a "check" -d 'Check an existing fish history file for problems'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -a "completions" -d 'Print the completion script for a shell, or install the one for fish'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -a "man" -d 'Print the manual page, or write one for each command into a directory'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_needs_command" -a "help" -d 'Print this message or the help of the given subcommand(s)'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand convert" -l from -d 'The shell, or other program, which wrote the history. Everything said about the zsh history file applies to the others too' -r -f -a "auto\t'Tell the format from the first few KiB of the history'
+zsh\t'A zsh history file, with or without EXTENDED_HISTORY'
+bash\t'A bash history file, with or without `HISTTIMEFORMAT` timestamps'
+fish\t'A fish history file, e.g. to clean one up with `--dedup-by`'
+fc\t'The listing `fc -l` or `history` prints, preferably `fc -li` for the timestamps'
+psreadline\t'PowerShell\'s PSReadLine history, `ConsoleHost_history.txt`'
+xonsh\t'A xonsh JSON history session file, or the directory holding them, which are merged'
+histdb\t'The SQLite database of zsh-histdb, `~/.histdb/zsh-history.db`'
+atuin\t'The SQLite database of atuin, `~/.local/share/atuin/history.db`'
+nushell\t'nushell\'s history, either the plain text `history.txt` or the SQLite `history.sqlite3`'"
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand convert" -l tz -d 'The UTC offset the timestamps of `--from fc` are in, like `+09:00` or `UTC`. Defaults to the local time zone' -r
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand convert" -l encoding -d 'The encoding of the zsh history file, like `latin1`, `shift_jis`, or `euc-jp`, or `auto` to guess it from the contents' -r
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand convert" -l default-when -d 'The timestamp to give entries without one' -r -f -a "zero\t'Leave them at 0, the start of 1970'
+mtime\t'The modification time of the history file'
+spread\t'Increasing timestamps ending at the modification time of the history file, so fish keeps them in order'"
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand convert" -l max-line-bytes -d 'Skip records, including all lines of a multi-line command, longer than the given number of bytes. Defaults to 1 MiB' -r
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand convert" -l max-entries -d 'Stop reading the history after N entries, 0 for no limit, counting those parsed, before `--redact` and the like drop any. Unlike `--limit`, this keeps the oldest entries, so combined with it the newest of those first N are kept' -r
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand convert" -l dedup-by -d 'What makes two entries the same when merging into the destination or several histories with `--dup-policy keep-both`. The other policies collapse every occurrence of a command' -r -f -a "cmd\t'Entries with the same command are duplicates, regardless of when they were executed'
+cmd+when\t'Entries are duplicates only if both the command and the timestamp match'"
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand convert" -l dup-policy -d 'Which entry survives when merging duplicates. Unless both are kept, the destination is rewritten sorted by timestamp if any of its entries changed' -r -f -a "keep-newest\t'Collapse duplicates into one entry with the newest timestamp'
+keep-oldest\t'Collapse duplicates into one entry with the oldest timestamp'
+keep-both\t'Keep the existing history as is and append every entry not already present'"
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand convert" -l expand-aliases -d 'Expand the zsh aliases defined in FILE, as `alias` lines or what `alias` prints, in the first word of the commands, for the shell they are converted for, which does not know them' -r -F
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand convert" -l strip-prefix -d 'Strip WORD, like `sudo`, and its options off the front of the commands, if a command follows them, along with the wrappers `time`, `nice`, `command`, and `env`. A prefix of several words, like `poetry run`, is stripped as a whole. May be given several times' -r
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand convert" -l normalize -d 'Remove trailing whitespace and bare `;`s from the commands, and with `aggressive` dangling `&&` and `||` too, before looking for duplicates. Nothing quoted is touched, and neither are commands with a heredoc' -r -f -a "basic\t'Trailing whitespace and bare `;`s'
+aggressive\t'Also `&&` and `||` left dangling with nothing after them'"
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand convert" -l redact -d 'Skip the entries whose command looks like it contains a password, a token, or a key, or mask them with `***`. Known variable and option names, services\' tokens, private keys, and long random-looking tokens are detected' -r -f -a "skip\t'Drop the entries'
+mask\t'Replace the credentials with `***`, keeping the rest of the command'"
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand convert" -l redact-pattern -d 'Also take what REGEX matches for a credential, or its group named `secret` if it has one, with `--redact`, which it implies. May be given several times' -r
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand convert" -l min-length -d 'Skip commands shorter than N characters, not counting the whitespace around them' -r
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand convert" -l skip-common-list -d 'Skip the commands in FILE, one per line, with `--skip-common`, which it implies, instead of the built-in ones. Empty lines and lines starting with `#` are skipped' -r -F
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand convert" -l head -d 'Keep only the first N entries, in the order of the history, after `--since` and `--until` and before sorting and the other filters' -r
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand convert" -l tail -d 'Keep only the last N entries, in the order of the history, after `--since` and `--until` and before sorting and the other filters' -r
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand convert" -l since -d 'Keep only the entries from this time on: a Unix time, a `YYYY-MM-DD [HH:MM[:SS]]` in the local time zone, an RFC 3339 timestamp, or a time ago like `90d`, `6months`, or `2y`' -r
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand convert" -l until -d 'Keep only the entries up to this time, inclusive, given like `--since`' -r
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand convert" -l match -d 'Keep only the entries whose command matches REGEX, in the syntax of the `regex` crate. If given several times, the entries matching any of them are kept' -r
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand convert" -l command -d 'Keep only the entries whose command runs NAME, as its first word after any variable assignments, and after the wrappers `--strip-prefix` strips. May be given several times' -r
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand convert" -l exclude -d 'Drop the entries whose command matches REGEX, even if they match `--match`. May be given several times' -r
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand convert" -l exclude-file -d 'Drop the entries whose command matches any of the regexes in FILE, one per line, like `--exclude`. Empty lines and lines starting with `#` are skipped. May be given several times' -r -F
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand convert" -l allowlist -d 'Keep only the entries whose command starts with any of the prefixes in FILE, one per line, or matches any of its regexes, given as `re:<regex>`. Empty lines and lines starting with `#` are skipped. Applies together with `--match`, so an entry has to pass both' -r -F
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand convert" -l blocklist -d 'Drop the entries whose command starts with any of the prefixes in FILE, or matches any of its regexes, given like in `--allowlist`. Applies together with `--exclude`, and drops entries even if `--match` or `--allowlist` keep them' -r -F
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand convert" -l keep -d 'Which occurrence of a command `--unique` keeps, the one with the oldest or the newest timestamp. It stays where that occurrence was' -r -f -a "first\t'The occurrence with the oldest timestamp'
+last\t'The occurrence with the newest timestamp, which fish ranks by recency'"
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand convert" -l format -l to -d 'The format to write the converted history in' -r -f -a "fish\t'The fish history file format'
+fish-script\t'A fish script which adds the entries with `builtin history append` when sourced'
+json\t'A JSON array of objects with the `cmd`, `when`, `duration`, and `paths` of each entry, and with `--annotate` the `line` and `raw` record it was read from'
+jsonl\t'The objects of `json`, one per line'
+csv\t'Comma-separated values as RFC 4180 describes them, with the `--columns`'
+tsv\t'Tab-separated values, quoted like `csv`'
+nushell\t'nushell\'s plain text `history.txt`, or its SQLite `history.sqlite3` if the `--output` ends in `.sqlite3` or `.db`, or already is an SQLite database'
+plain\t'The commands alone, one per line, with the multi-line ones written as `--multiline` says'
+plain0\t'The commands alone, each ended by a NUL byte rather than a newline, for `fzf --read0` and `xargs -0`. Commands with a NUL byte of their own are skipped'
+pretty\t'A line per entry to read in a terminal, the time dimmed before the command, with the lines of a multi-line command under it, cut off at the width of the terminal unless `--full` is given. Colored as `--color` says'
+zsh\t'A zsh history file with `EXTENDED_HISTORY`, to go back from fish with `--from fish`'
+bash\t'A bash history file, with a `#<when>` comment before each command as bash writes with `HISTTIMEFORMAT` set, unless `--no-timestamps` is given'
+abbr\t'`abbr -a` lines to source in fish, for the commands typed most often, see `--top`'
+zoxide\t'The directories changed into with `cd`, `pushd`, or `AUTO_CD`, with their visit count and last visit, as the `path|rank|time` lines of z\'s data file `zoxide import --from z` reads'
+sqlite\t'An SQLite database with the entries in a table of their own, to query with any SQLite client, written to the `--output`'
+mcfly\t'Rows of the `commands` table of McFly, added to the database the `--output` names'
+atuin\t'Rows of the `history` table of atuin, written into the database `--atuin-db` names'"
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand convert" -l columns -d 'The columns to write with `--format csv` or `tsv`, separated by commas' -r -f -a "when\t''
+cmd\t''
+duration\t''
+paths\t''
+line\t'The line the entry was read from'
+raw\t'The record of a zsh history the entry was parsed from'"
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand convert" -l atuin-db -d 'The atuin database to write into with `--format atuin`, usually `~/.local/share/atuin/history.db`. It is created if it does not exist. Entries already written by an earlier run are skipped. Tested with atuin 18.4' -r -F
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand convert" -l multiline -d 'How to write commands spanning several lines with `--format plain`' -r -f -a "escape\t'Write its newlines as `\\n`, and its backslashes as `\\\\`, like the fish history does'
+join\t'Join its lines with `; `'
+skip\t'Leave it out'"
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand convert" -l color -d 'When to color `--format pretty`' -r -f -a "auto\t'When writing to a terminal, unless `NO_COLOR` is set'
+always\t'Even when writing to a file or a pipe'
+never\t'Not even when writing to a terminal'"
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand convert" -l top -d 'Suggest at most N abbreviations with `--format abbr`' -r
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand convert" -l min-count -d 'Suggest abbreviations only for commands typed at least N times with `--format abbr`' -r
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand convert" -l abbr-min-length -d 'Suggest abbreviations only for commands at least N characters long with `--format abbr`' -r
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand convert" -s o -l output -d 'Write the converted history to the given file instead of stdout' -r -F
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand convert" -l annotate-file -d 'Write the entries written, with the line and the record each was read from, to PATH as JSON lines, whatever the format' -r -F
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand convert" -l sort -d 'The order to write the entries in, once `--head` and `--tail` picked them by where they are in the history, and before `--limit` picks some of them' -r -f -a "when\t'Oldest first, with the entries without a timestamp before all others, and entries sharing a timestamp in the order they were read in'
+input\t'The order the entries were read in, after merging several histories by timestamp'"
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand convert" -l limit -d 'Keep only the newest N entries. When merging, the limit applies to the merged history, dropping the oldest entries of the destination if necessary' -r
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand convert" -l backup-dir -d 'Put the backup into the given directory instead of next to the destination file, creating it if needed. Implies `--backup`' -r -F
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand convert" -l session-name -d 'The fish history session to write into with `--into-fish`, like `set fish_history <NAME>` in fish. Defaults to `$fish_history`, or `fish` if that is not set' -r
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand convert" -l lock-timeout -d 'How many seconds to wait for the lock on the destination file' -r
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand convert" -l fish-bin -d 'The fish binary to run with `--run-merge`' -r -F
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand convert" -l diff-limit -d 'How many added lines `--diff` shows before summarizing the rest' -r
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand convert" -l config -d 'Read the default options from PATH instead of `~/.config/zsh-history-to-fish/config.toml`, whose keys are the long names of the options, or from `$Z2F_CONFIG`. Those given on the command line, or by the `Z2F_` environment variables, win' -r -F
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand convert" -l report -d 'Write what the run did as JSON to PATH, or to stderr with `-`, at its end, even if it failed: the histories read, the entries each filter dropped, the duplicates removed, the entries written and where, and the lines skipped' -r -F
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand convert" -l stdin -d 'Read the zsh history from stdin'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand convert" -l include-rotated -d 'Also convert the rotated copies of the zsh history file next to it, like `.zsh_history.1`, `.zsh_history.old`, or `.zsh_history-20240131`, collapsing duplicates across them'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand convert" -l pick-largest -d 'Convert the largest history file found in a directory given as the zsh history, instead of erroring if there are several'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand convert" -l no-expand -d 'Take the paths given, like the zsh history, `--output`, `--backup-dir`, and `--report`, literally, instead of expanding a leading `~` and `$VAR` or `${VAR}` in them'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand convert" -l strict-utf8 -d 'Report every line which is not valid in the encoding of the zsh history file, with the offending bytes, and fail after reading the whole file'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand convert" -l lossy -d 'With `--strict-utf8`, replace the invalid bytes and carry on instead of failing'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand convert" -l include-partial -d 'Convert an incomplete last entry, which zsh may still be writing, instead of skipping it'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand convert" -l clamp-timestamps -d 'Clamp timestamps before 1970 or in the future to between 0 and now, instead of only reporting them'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand convert" -l strict -d 'Fail on every line which cannot be cleanly converted, like records which cannot be, invalid bytes, timestamps before 1970 or in the future, a cut off last entry, or control characters, listing them all once the histories are read, and write nothing'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand convert" -l interpolate-timestamps -d 'Give entries without a timestamp one between those of the entries around them. Any left without one, when no entry has a timestamp, get the `--default-when` one'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand convert" -l bump-duplicate-timestamps -d 'Move entries sharing a second with the entry before them a second later each, so fish keeps them in order, without crossing into the next later timestamp'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand convert" -l ignore-space -d 'Skip commands starting with a space, like zsh\'s HIST_IGNORE_SPACE option'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand convert" -l keep-empty -d 'Keep timestamped entries with an empty command instead of skipping them'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand convert" -l skip-invalid -d 'Skip entries with NUL bytes or other control characters instead of stripping NULs and escaping the rest'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand convert" -l expand-global-aliases -d 'Also expand the global aliases, defined with `alias -g`, in any word of the commands, with `--expand-aliases`'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand convert" -l strip-env-assignments -d 'Strip variable assignments, like `FOO=bar`, off the front of the commands, along with the wrappers `--strip-prefix` strips'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand convert" -l skip-multiline -d 'Skip commands which span several lines, rather than writing their newlines as `<\\n>` with `--format nushell`, or as the other formats do'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand convert" -l skip-common -d 'Skip the commands which are typed all the time without arguments, like `ls`, `cd`, and `clear`. A command only counts if it is exactly one of them, so `cd ..` is kept'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand convert" -l ignore-case -d 'Match `--match` and `--exclude` regardless of case, as `(?i)` does'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand convert" -l include-undated -d 'Keep the entries without a timestamp, which `--since` and `--until` would otherwise judge to be from 1970'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand convert" -l unique -d 'Collapse the entries with the same command into one, dropping the duplicates even without `--merge`'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand convert" -l normalize-dedup -d 'Compare commands for duplicates with their runs of whitespace, newlines included, collapsed into single spaces and their ends trimmed, with `--unique` and when merging. The entry kept keeps its command as it was'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand convert" -l dedup-case-insensitive -d 'Compare commands for duplicates regardless of case, like `--normalize-dedup`'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand convert" -l with-time -d 'Start each line with the time of the command with `--format plain`'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand convert" -l full -d 'Write the commands in full with `--format pretty`, instead of cutting them off at the width of the terminal'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand convert" -l no-timestamps -d 'Leave out the `#<when>` comments with `--format bash`. Without them bash reads each line as a command, so a command spanning several lines fails the conversion, unless `--skip-multiline` drops it'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand convert" -l replace -d 'Replace the `entries` table of an existing database with `--format sqlite`, rather than adding to it'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand convert" -l no-header -d 'Leave out the row of column names with `--format csv` or `tsv`'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand convert" -l annotate -d 'Write the line of the zsh history each entry was read from, and the record on it, beside the entry: as the `line` and `raw` fields with `--format json` or `jsonl`, as the last columns with `csv` and `tsv`, and dimmed under the command with `pretty`. The other formats have no room for them, so they need `--annotate-file`'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand convert" -l append -d 'Append to the output file if it already exists'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand convert" -l overwrite -l force -d 'Replace the output file if it already exists'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand convert" -l reverse -d 'Write the entries in reverse, after sorting, filtering, and `--limit`, so `--sort when --reverse` writes the newest first. The entries are all read before writing any, so `--max-entries` bounds the memory this takes, like it does for sorting'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand convert" -l backup -d 'Back up the destination file, as `<name>.bak.<unix-ts>`, before modifying it'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand convert" -l verify -d 'Check that the converted entries read back identically from the fish history format before writing them, and fail if any does not'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand convert" -l count -d 'Print only how many entries would be written, after all the filtering, instead of converting. With `--merge`, how many the destination does not have yet'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand convert" -l fail-if-empty -d 'Fail with exit status 6 if no entries are left to write, saying whether none were parsed, or how many each filter dropped'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand convert" -l interactive -d 'Show the entries newest first on the terminal, to pick the ones to write, before writing them. Aborting writes nothing'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand convert" -l dry-run -d 'Go through everything up to writing, then print what would be written where, and what backed up, without writing anything, taking locks, or running fish'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand convert" -l run-merge -d 'Run `fish -c \'builtin history merge\'` after writing, so running fish sessions pick up the imported entries'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand convert" -l diff -d 'Preview the entries which would be appended to the destination as a diff, without writing anything. Exits with 1 if there are entries to append, 0 otherwise'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand convert" -l no-progress -d 'Show no progress while reading a large history, which is otherwise shown on stderr, as a bar if it is a terminal'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand convert" -s v -l verbose -d 'Report every line skipped, or converted despite looking wrong, on stderr, with its line number, why, and the start of it. Given twice, also dump the bytes of the line in hex'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand convert" -s q -l quiet -d 'Report nothing on stderr but errors, no warnings, summaries, or progress, for scripts'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand convert" -l print-config -d 'Print the options in effect, from the command line, the configuration file, or the defaults, as a configuration file, and exit'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand convert" -l timing -d 'Print how long each phase of the run took on stderr at its end, reading, sorting, filtering, dropping duplicates, and writing, with the megabytes and entries read per second. With `--report`, the report has them too'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand convert" -l into-fish -d 'Append the converted history to the fish history file of the current user'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand convert" -l merge -d 'Only append the entries which are not already in the destination fish history'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand convert" -l list-commands -d 'Print the distinct commands run, the first words `--command` compares, with how often each was run, most often first, instead of converting'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand convert" -s h -l help -d 'Print help (see more with \'--help\')'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand merge" -l from -d 'The shell, or other program, which wrote the history. Everything said about the zsh history file applies to the others too' -r -f -a "auto\t'Tell the format from the first few KiB of the history'
+zsh\t'A zsh history file, with or without EXTENDED_HISTORY'
+bash\t'A bash history file, with or without `HISTTIMEFORMAT` timestamps'
+fish\t'A fish history file, e.g. to clean one up with `--dedup-by`'
+fc\t'The listing `fc -l` or `history` prints, preferably `fc -li` for the timestamps'
+psreadline\t'PowerShell\'s PSReadLine history, `ConsoleHost_history.txt`'
+xonsh\t'A xonsh JSON history session file, or the directory holding them, which are merged'
+histdb\t'The SQLite database of zsh-histdb, `~/.histdb/zsh-history.db`'
+atuin\t'The SQLite database of atuin, `~/.local/share/atuin/history.db`'
+nushell\t'nushell\'s history, either the plain text `history.txt` or the SQLite `history.sqlite3`'"
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand merge" -l tz -d 'The UTC offset the timestamps of `--from fc` are in, like `+09:00` or `UTC`. Defaults to the local time zone' -r
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand merge" -l encoding -d 'The encoding of the zsh history file, like `latin1`, `shift_jis`, or `euc-jp`, or `auto` to guess it from the contents' -r
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand merge" -l default-when -d 'The timestamp to give entries without one' -r -f -a "zero\t'Leave them at 0, the start of 1970'
+mtime\t'The modification time of the history file'
+spread\t'Increasing timestamps ending at the modification time of the history file, so fish keeps them in order'"
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand merge" -l max-line-bytes -d 'Skip records, including all lines of a multi-line command, longer than the given number of bytes. Defaults to 1 MiB' -r
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand merge" -l max-entries -d 'Stop reading the history after N entries, 0 for no limit, counting those parsed, before `--redact` and the like drop any. Unlike `--limit`, this keeps the oldest entries, so combined with it the newest of those first N are kept' -r
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand merge" -l dedup-by -d 'What makes two entries the same when merging into the destination or several histories with `--dup-policy keep-both`. The other policies collapse every occurrence of a command' -r -f -a "cmd\t'Entries with the same command are duplicates, regardless of when they were executed'
+cmd+when\t'Entries are duplicates only if both the command and the timestamp match'"
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand merge" -l dup-policy -d 'Which entry survives when merging duplicates. Unless both are kept, the destination is rewritten sorted by timestamp if any of its entries changed' -r -f -a "keep-newest\t'Collapse duplicates into one entry with the newest timestamp'
+keep-oldest\t'Collapse duplicates into one entry with the oldest timestamp'
+keep-both\t'Keep the existing history as is and append every entry not already present'"
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand merge" -l expand-aliases -d 'Expand the zsh aliases defined in FILE, as `alias` lines or what `alias` prints, in the first word of the commands, for the shell they are converted for, which does not know them' -r -F
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand merge" -l strip-prefix -d 'Strip WORD, like `sudo`, and its options off the front of the commands, if a command follows them, along with the wrappers `time`, `nice`, `command`, and `env`. A prefix of several words, like `poetry run`, is stripped as a whole. May be given several times' -r
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand merge" -l normalize -d 'Remove trailing whitespace and bare `;`s from the commands, and with `aggressive` dangling `&&` and `||` too, before looking for duplicates. Nothing quoted is touched, and neither are commands with a heredoc' -r -f -a "basic\t'Trailing whitespace and bare `;`s'
+aggressive\t'Also `&&` and `||` left dangling with nothing after them'"
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand merge" -l redact -d 'Skip the entries whose command looks like it contains a password, a token, or a key, or mask them with `***`. Known variable and option names, services\' tokens, private keys, and long random-looking tokens are detected' -r -f -a "skip\t'Drop the entries'
+mask\t'Replace the credentials with `***`, keeping the rest of the command'"
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand merge" -l redact-pattern -d 'Also take what REGEX matches for a credential, or its group named `secret` if it has one, with `--redact`, which it implies. May be given several times' -r
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand merge" -l min-length -d 'Skip commands shorter than N characters, not counting the whitespace around them' -r
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand merge" -l skip-common-list -d 'Skip the commands in FILE, one per line, with `--skip-common`, which it implies, instead of the built-in ones. Empty lines and lines starting with `#` are skipped' -r -F
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand merge" -l head -d 'Keep only the first N entries, in the order of the history, after `--since` and `--until` and before sorting and the other filters' -r
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand merge" -l tail -d 'Keep only the last N entries, in the order of the history, after `--since` and `--until` and before sorting and the other filters' -r
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand merge" -l since -d 'Keep only the entries from this time on: a Unix time, a `YYYY-MM-DD [HH:MM[:SS]]` in the local time zone, an RFC 3339 timestamp, or a time ago like `90d`, `6months`, or `2y`' -r
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand merge" -l until -d 'Keep only the entries up to this time, inclusive, given like `--since`' -r
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand merge" -l match -d 'Keep only the entries whose command matches REGEX, in the syntax of the `regex` crate. If given several times, the entries matching any of them are kept' -r
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand merge" -l command -d 'Keep only the entries whose command runs NAME, as its first word after any variable assignments, and after the wrappers `--strip-prefix` strips. May be given several times' -r
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand merge" -l exclude -d 'Drop the entries whose command matches REGEX, even if they match `--match`. May be given several times' -r
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand merge" -l exclude-file -d 'Drop the entries whose command matches any of the regexes in FILE, one per line, like `--exclude`. Empty lines and lines starting with `#` are skipped. May be given several times' -r -F
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand merge" -l allowlist -d 'Keep only the entries whose command starts with any of the prefixes in FILE, one per line, or matches any of its regexes, given as `re:<regex>`. Empty lines and lines starting with `#` are skipped. Applies together with `--match`, so an entry has to pass both' -r -F
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand merge" -l blocklist -d 'Drop the entries whose command starts with any of the prefixes in FILE, or matches any of its regexes, given like in `--allowlist`. Applies together with `--exclude`, and drops entries even if `--match` or `--allowlist` keep them' -r -F
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand merge" -l keep -d 'Which occurrence of a command `--unique` keeps, the one with the oldest or the newest timestamp. It stays where that occurrence was' -r -f -a "first\t'The occurrence with the oldest timestamp'
+last\t'The occurrence with the newest timestamp, which fish ranks by recency'"
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand merge" -s o -l output -d 'Write the converted history to the given file instead of stdout' -r -F
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand merge" -l annotate-file -d 'Write the entries written, with the line and the record each was read from, to PATH as JSON lines, whatever the format' -r -F
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand merge" -l sort -d 'The order to write the entries in, once `--head` and `--tail` picked them by where they are in the history, and before `--limit` picks some of them' -r -f -a "when\t'Oldest first, with the entries without a timestamp before all others, and entries sharing a timestamp in the order they were read in'
+input\t'The order the entries were read in, after merging several histories by timestamp'"
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand merge" -l limit -d 'Keep only the newest N entries. When merging, the limit applies to the merged history, dropping the oldest entries of the destination if necessary' -r
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand merge" -l backup-dir -d 'Put the backup into the given directory instead of next to the destination file, creating it if needed. Implies `--backup`' -r -F
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand merge" -l session-name -d 'The fish history session to write into with `--into-fish`, like `set fish_history <NAME>` in fish. Defaults to `$fish_history`, or `fish` if that is not set' -r
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand merge" -l lock-timeout -d 'How many seconds to wait for the lock on the destination file' -r
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand merge" -l fish-bin -d 'The fish binary to run with `--run-merge`' -r -F
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand merge" -l diff-limit -d 'How many added lines `--diff` shows before summarizing the rest' -r
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand merge" -l config -d 'Read the default options from PATH instead of `~/.config/zsh-history-to-fish/config.toml`, whose keys are the long names of the options, or from `$Z2F_CONFIG`. Those given on the command line, or by the `Z2F_` environment variables, win' -r -F
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand merge" -l report -d 'Write what the run did as JSON to PATH, or to stderr with `-`, at its end, even if it failed: the histories read, the entries each filter dropped, the duplicates removed, the entries written and where, and the lines skipped' -r -F
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand merge" -l stdin -d 'Read the zsh history from stdin'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand merge" -l include-rotated -d 'Also convert the rotated copies of the zsh history file next to it, like `.zsh_history.1`, `.zsh_history.old`, or `.zsh_history-20240131`, collapsing duplicates across them'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand merge" -l pick-largest -d 'Convert the largest history file found in a directory given as the zsh history, instead of erroring if there are several'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand merge" -l no-expand -d 'Take the paths given, like the zsh history, `--output`, `--backup-dir`, and `--report`, literally, instead of expanding a leading `~` and `$VAR` or `${VAR}` in them'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand merge" -l strict-utf8 -d 'Report every line which is not valid in the encoding of the zsh history file, with the offending bytes, and fail after reading the whole file'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand merge" -l lossy -d 'With `--strict-utf8`, replace the invalid bytes and carry on instead of failing'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand merge" -l include-partial -d 'Convert an incomplete last entry, which zsh may still be writing, instead of skipping it'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand merge" -l clamp-timestamps -d 'Clamp timestamps before 1970 or in the future to between 0 and now, instead of only reporting them'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand merge" -l strict -d 'Fail on every line which cannot be cleanly converted, like records which cannot be, invalid bytes, timestamps before 1970 or in the future, a cut off last entry, or control characters, listing them all once the histories are read, and write nothing'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand merge" -l interpolate-timestamps -d 'Give entries without a timestamp one between those of the entries around them. Any left without one, when no entry has a timestamp, get the `--default-when` one'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand merge" -l bump-duplicate-timestamps -d 'Move entries sharing a second with the entry before them a second later each, so fish keeps them in order, without crossing into the next later timestamp'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand merge" -l ignore-space -d 'Skip commands starting with a space, like zsh\'s HIST_IGNORE_SPACE option'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand merge" -l keep-empty -d 'Keep timestamped entries with an empty command instead of skipping them'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand merge" -l skip-invalid -d 'Skip entries with NUL bytes or other control characters instead of stripping NULs and escaping the rest'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand merge" -l expand-global-aliases -d 'Also expand the global aliases, defined with `alias -g`, in any word of the commands, with `--expand-aliases`'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand merge" -l strip-env-assignments -d 'Strip variable assignments, like `FOO=bar`, off the front of the commands, along with the wrappers `--strip-prefix` strips'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand merge" -l skip-multiline -d 'Skip commands which span several lines, rather than writing their newlines as `<\\n>` with `--format nushell`, or as the other formats do'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand merge" -l skip-common -d 'Skip the commands which are typed all the time without arguments, like `ls`, `cd`, and `clear`. A command only counts if it is exactly one of them, so `cd ..` is kept'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand merge" -l ignore-case -d 'Match `--match` and `--exclude` regardless of case, as `(?i)` does'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand merge" -l include-undated -d 'Keep the entries without a timestamp, which `--since` and `--until` would otherwise judge to be from 1970'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand merge" -l unique -d 'Collapse the entries with the same command into one, dropping the duplicates even without `--merge`'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand merge" -l normalize-dedup -d 'Compare commands for duplicates with their runs of whitespace, newlines included, collapsed into single spaces and their ends trimmed, with `--unique` and when merging. The entry kept keeps its command as it was'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand merge" -l dedup-case-insensitive -d 'Compare commands for duplicates regardless of case, like `--normalize-dedup`'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand merge" -l append -d 'Append to the output file if it already exists'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand merge" -l overwrite -l force -d 'Replace the output file if it already exists'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand merge" -l reverse -d 'Write the entries in reverse, after sorting, filtering, and `--limit`, so `--sort when --reverse` writes the newest first. The entries are all read before writing any, so `--max-entries` bounds the memory this takes, like it does for sorting'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand merge" -l backup -d 'Back up the destination file, as `<name>.bak.<unix-ts>`, before modifying it'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand merge" -l verify -d 'Check that the converted entries read back identically from the fish history format before writing them, and fail if any does not'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand merge" -l count -d 'Print only how many entries would be written, after all the filtering, instead of converting. With `--merge`, how many the destination does not have yet'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand merge" -l fail-if-empty -d 'Fail with exit status 6 if no entries are left to write, saying whether none were parsed, or how many each filter dropped'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand merge" -l interactive -d 'Show the entries newest first on the terminal, to pick the ones to write, before writing them. Aborting writes nothing'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand merge" -l dry-run -d 'Go through everything up to writing, then print what would be written where, and what backed up, without writing anything, taking locks, or running fish'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand merge" -l run-merge -d 'Run `fish -c \'builtin history merge\'` after writing, so running fish sessions pick up the imported entries'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand merge" -l diff -d 'Preview the entries which would be appended to the destination as a diff, without writing anything. Exits with 1 if there are entries to append, 0 otherwise'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand merge" -l no-progress -d 'Show no progress while reading a large history, which is otherwise shown on stderr, as a bar if it is a terminal'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand merge" -s v -l verbose -d 'Report every line skipped, or converted despite looking wrong, on stderr, with its line number, why, and the start of it. Given twice, also dump the bytes of the line in hex'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand merge" -s q -l quiet -d 'Report nothing on stderr but errors, no warnings, summaries, or progress, for scripts'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand merge" -l print-config -d 'Print the options in effect, from the command line, the configuration file, or the defaults, as a configuration file, and exit'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand merge" -l timing -d 'Print how long each phase of the run took on stderr at its end, reading, sorting, filtering, dropping duplicates, and writing, with the megabytes and entries read per second. With `--report`, the report has them too'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand merge" -s h -l help -d 'Print help (see more with \'--help\')'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand fish-to-zsh" -l from -d 'The shell, or other program, which wrote the history. Everything said about the zsh history file applies to the others too' -r -f -a "auto\t'Tell the format from the first few KiB of the history'
+zsh\t'A zsh history file, with or without EXTENDED_HISTORY'
+bash\t'A bash history file, with or without `HISTTIMEFORMAT` timestamps'
+fish\t'A fish history file, e.g. to clean one up with `--dedup-by`'
+fc\t'The listing `fc -l` or `history` prints, preferably `fc -li` for the timestamps'
+psreadline\t'PowerShell\'s PSReadLine history, `ConsoleHost_history.txt`'
+xonsh\t'A xonsh JSON history session file, or the directory holding them, which are merged'
+histdb\t'The SQLite database of zsh-histdb, `~/.histdb/zsh-history.db`'
+atuin\t'The SQLite database of atuin, `~/.local/share/atuin/history.db`'
+nushell\t'nushell\'s history, either the plain text `history.txt` or the SQLite `history.sqlite3`'"
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand fish-to-zsh" -l tz -d 'The UTC offset the timestamps of `--from fc` are in, like `+09:00` or `UTC`. Defaults to the local time zone' -r
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand fish-to-zsh" -l encoding -d 'The encoding of the zsh history file, like `latin1`, `shift_jis`, or `euc-jp`, or `auto` to guess it from the contents' -r
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand fish-to-zsh" -l default-when -d 'The timestamp to give entries without one' -r -f -a "zero\t'Leave them at 0, the start of 1970'
+mtime\t'The modification time of the history file'
+spread\t'Increasing timestamps ending at the modification time of the history file, so fish keeps them in order'"
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand fish-to-zsh" -l max-line-bytes -d 'Skip records, including all lines of a multi-line command, longer than the given number of bytes. Defaults to 1 MiB' -r
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand fish-to-zsh" -l max-entries -d 'Stop reading the history after N entries, 0 for no limit, counting those parsed, before `--redact` and the like drop any. Unlike `--limit`, this keeps the oldest entries, so combined with it the newest of those first N are kept' -r
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand fish-to-zsh" -l dedup-by -d 'What makes two entries the same when merging into the destination or several histories with `--dup-policy keep-both`. The other policies collapse every occurrence of a command' -r -f -a "cmd\t'Entries with the same command are duplicates, regardless of when they were executed'
+cmd+when\t'Entries are duplicates only if both the command and the timestamp match'"
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand fish-to-zsh" -l dup-policy -d 'Which entry survives when merging duplicates. Unless both are kept, the destination is rewritten sorted by timestamp if any of its entries changed' -r -f -a "keep-newest\t'Collapse duplicates into one entry with the newest timestamp'
+keep-oldest\t'Collapse duplicates into one entry with the oldest timestamp'
+keep-both\t'Keep the existing history as is and append every entry not already present'"
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand fish-to-zsh" -l expand-aliases -d 'Expand the zsh aliases defined in FILE, as `alias` lines or what `alias` prints, in the first word of the commands, for the shell they are converted for, which does not know them' -r -F
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand fish-to-zsh" -l strip-prefix -d 'Strip WORD, like `sudo`, and its options off the front of the commands, if a command follows them, along with the wrappers `time`, `nice`, `command`, and `env`. A prefix of several words, like `poetry run`, is stripped as a whole. May be given several times' -r
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand fish-to-zsh" -l normalize -d 'Remove trailing whitespace and bare `;`s from the commands, and with `aggressive` dangling `&&` and `||` too, before looking for duplicates. Nothing quoted is touched, and neither are commands with a heredoc' -r -f -a "basic\t'Trailing whitespace and bare `;`s'
+aggressive\t'Also `&&` and `||` left dangling with nothing after them'"
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand fish-to-zsh" -l redact -d 'Skip the entries whose command looks like it contains a password, a token, or a key, or mask them with `***`. Known variable and option names, services\' tokens, private keys, and long random-looking tokens are detected' -r -f -a "skip\t'Drop the entries'
+mask\t'Replace the credentials with `***`, keeping the rest of the command'"
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand fish-to-zsh" -l redact-pattern -d 'Also take what REGEX matches for a credential, or its group named `secret` if it has one, with `--redact`, which it implies. May be given several times' -r
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand fish-to-zsh" -l min-length -d 'Skip commands shorter than N characters, not counting the whitespace around them' -r
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand fish-to-zsh" -l skip-common-list -d 'Skip the commands in FILE, one per line, with `--skip-common`, which it implies, instead of the built-in ones. Empty lines and lines starting with `#` are skipped' -r -F
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand fish-to-zsh" -l head -d 'Keep only the first N entries, in the order of the history, after `--since` and `--until` and before sorting and the other filters' -r
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand fish-to-zsh" -l tail -d 'Keep only the last N entries, in the order of the history, after `--since` and `--until` and before sorting and the other filters' -r
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand fish-to-zsh" -l since -d 'Keep only the entries from this time on: a Unix time, a `YYYY-MM-DD [HH:MM[:SS]]` in the local time zone, an RFC 3339 timestamp, or a time ago like `90d`, `6months`, or `2y`' -r
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand fish-to-zsh" -l until -d 'Keep only the entries up to this time, inclusive, given like `--since`' -r
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand fish-to-zsh" -l match -d 'Keep only the entries whose command matches REGEX, in the syntax of the `regex` crate. If given several times, the entries matching any of them are kept' -r
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand fish-to-zsh" -l command -d 'Keep only the entries whose command runs NAME, as its first word after any variable assignments, and after the wrappers `--strip-prefix` strips. May be given several times' -r
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand fish-to-zsh" -l exclude -d 'Drop the entries whose command matches REGEX, even if they match `--match`. May be given several times' -r
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand fish-to-zsh" -l exclude-file -d 'Drop the entries whose command matches any of the regexes in FILE, one per line, like `--exclude`. Empty lines and lines starting with `#` are skipped. May be given several times' -r -F
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand fish-to-zsh" -l allowlist -d 'Keep only the entries whose command starts with any of the prefixes in FILE, one per line, or matches any of its regexes, given as `re:<regex>`. Empty lines and lines starting with `#` are skipped. Applies together with `--match`, so an entry has to pass both' -r -F
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand fish-to-zsh" -l blocklist -d 'Drop the entries whose command starts with any of the prefixes in FILE, or matches any of its regexes, given like in `--allowlist`. Applies together with `--exclude`, and drops entries even if `--match` or `--allowlist` keep them' -r -F
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand fish-to-zsh" -l keep -d 'Which occurrence of a command `--unique` keeps, the one with the oldest or the newest timestamp. It stays where that occurrence was' -r -f -a "first\t'The occurrence with the oldest timestamp'
+last\t'The occurrence with the newest timestamp, which fish ranks by recency'"
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand fish-to-zsh" -s o -l output -d 'Write the converted history to the given file instead of stdout' -r -F
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand fish-to-zsh" -l annotate-file -d 'Write the entries written, with the line and the record each was read from, to PATH as JSON lines, whatever the format' -r -F
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand fish-to-zsh" -l sort -d 'The order to write the entries in, once `--head` and `--tail` picked them by where they are in the history, and before `--limit` picks some of them' -r -f -a "when\t'Oldest first, with the entries without a timestamp before all others, and entries sharing a timestamp in the order they were read in'
+input\t'The order the entries were read in, after merging several histories by timestamp'"
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand fish-to-zsh" -l limit -d 'Keep only the newest N entries. When merging, the limit applies to the merged history, dropping the oldest entries of the destination if necessary' -r
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand fish-to-zsh" -l backup-dir -d 'Put the backup into the given directory instead of next to the destination file, creating it if needed. Implies `--backup`' -r -F
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand fish-to-zsh" -l config -d 'Read the default options from PATH instead of `~/.config/zsh-history-to-fish/config.toml`, whose keys are the long names of the options, or from `$Z2F_CONFIG`. Those given on the command line, or by the `Z2F_` environment variables, win' -r -F
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand fish-to-zsh" -l report -d 'Write what the run did as JSON to PATH, or to stderr with `-`, at its end, even if it failed: the histories read, the entries each filter dropped, the duplicates removed, the entries written and where, and the lines skipped' -r -F
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand fish-to-zsh" -l stdin -d 'Read the zsh history from stdin'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand fish-to-zsh" -l include-rotated -d 'Also convert the rotated copies of the zsh history file next to it, like `.zsh_history.1`, `.zsh_history.old`, or `.zsh_history-20240131`, collapsing duplicates across them'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand fish-to-zsh" -l pick-largest -d 'Convert the largest history file found in a directory given as the zsh history, instead of erroring if there are several'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand fish-to-zsh" -l no-expand -d 'Take the paths given, like the zsh history, `--output`, `--backup-dir`, and `--report`, literally, instead of expanding a leading `~` and `$VAR` or `${VAR}` in them'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand fish-to-zsh" -l strict-utf8 -d 'Report every line which is not valid in the encoding of the zsh history file, with the offending bytes, and fail after reading the whole file'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand fish-to-zsh" -l lossy -d 'With `--strict-utf8`, replace the invalid bytes and carry on instead of failing'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand fish-to-zsh" -l include-partial -d 'Convert an incomplete last entry, which zsh may still be writing, instead of skipping it'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand fish-to-zsh" -l clamp-timestamps -d 'Clamp timestamps before 1970 or in the future to between 0 and now, instead of only reporting them'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand fish-to-zsh" -l strict -d 'Fail on every line which cannot be cleanly converted, like records which cannot be, invalid bytes, timestamps before 1970 or in the future, a cut off last entry, or control characters, listing them all once the histories are read, and write nothing'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand fish-to-zsh" -l interpolate-timestamps -d 'Give entries without a timestamp one between those of the entries around them. Any left without one, when no entry has a timestamp, get the `--default-when` one'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand fish-to-zsh" -l bump-duplicate-timestamps -d 'Move entries sharing a second with the entry before them a second later each, so fish keeps them in order, without crossing into the next later timestamp'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand fish-to-zsh" -l ignore-space -d 'Skip commands starting with a space, like zsh\'s HIST_IGNORE_SPACE option'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand fish-to-zsh" -l keep-empty -d 'Keep timestamped entries with an empty command instead of skipping them'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand fish-to-zsh" -l skip-invalid -d 'Skip entries with NUL bytes or other control characters instead of stripping NULs and escaping the rest'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand fish-to-zsh" -l expand-global-aliases -d 'Also expand the global aliases, defined with `alias -g`, in any word of the commands, with `--expand-aliases`'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand fish-to-zsh" -l strip-env-assignments -d 'Strip variable assignments, like `FOO=bar`, off the front of the commands, along with the wrappers `--strip-prefix` strips'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand fish-to-zsh" -l skip-multiline -d 'Skip commands which span several lines, rather than writing their newlines as `<\\n>` with `--format nushell`, or as the other formats do'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand fish-to-zsh" -l skip-common -d 'Skip the commands which are typed all the time without arguments, like `ls`, `cd`, and `clear`. A command only counts if it is exactly one of them, so `cd ..` is kept'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand fish-to-zsh" -l ignore-case -d 'Match `--match` and `--exclude` regardless of case, as `(?i)` does'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand fish-to-zsh" -l include-undated -d 'Keep the entries without a timestamp, which `--since` and `--until` would otherwise judge to be from 1970'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand fish-to-zsh" -l unique -d 'Collapse the entries with the same command into one, dropping the duplicates even without `--merge`'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand fish-to-zsh" -l normalize-dedup -d 'Compare commands for duplicates with their runs of whitespace, newlines included, collapsed into single spaces and their ends trimmed, with `--unique` and when merging. The entry kept keeps its command as it was'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand fish-to-zsh" -l dedup-case-insensitive -d 'Compare commands for duplicates regardless of case, like `--normalize-dedup`'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand fish-to-zsh" -l append -d 'Append to the output file if it already exists'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand fish-to-zsh" -l overwrite -l force -d 'Replace the output file if it already exists'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand fish-to-zsh" -l reverse -d 'Write the entries in reverse, after sorting, filtering, and `--limit`, so `--sort when --reverse` writes the newest first. The entries are all read before writing any, so `--max-entries` bounds the memory this takes, like it does for sorting'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand fish-to-zsh" -l backup -d 'Back up the destination file, as `<name>.bak.<unix-ts>`, before modifying it'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand fish-to-zsh" -l verify -d 'Check that the converted entries read back identically from the fish history format before writing them, and fail if any does not'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand fish-to-zsh" -l count -d 'Print only how many entries would be written, after all the filtering, instead of converting. With `--merge`, how many the destination does not have yet'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand fish-to-zsh" -l fail-if-empty -d 'Fail with exit status 6 if no entries are left to write, saying whether none were parsed, or how many each filter dropped'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand fish-to-zsh" -l interactive -d 'Show the entries newest first on the terminal, to pick the ones to write, before writing them. Aborting writes nothing'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand fish-to-zsh" -l dry-run -d 'Go through everything up to writing, then print what would be written where, and what backed up, without writing anything, taking locks, or running fish'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand fish-to-zsh" -l no-progress -d 'Show no progress while reading a large history, which is otherwise shown on stderr, as a bar if it is a terminal'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand fish-to-zsh" -s v -l verbose -d 'Report every line skipped, or converted despite looking wrong, on stderr, with its line number, why, and the start of it. Given twice, also dump the bytes of the line in hex'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand fish-to-zsh" -s q -l quiet -d 'Report nothing on stderr but errors, no warnings, summaries, or progress, for scripts'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand fish-to-zsh" -l print-config -d 'Print the options in effect, from the command line, the configuration file, or the defaults, as a configuration file, and exit'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand fish-to-zsh" -l timing -d 'Print how long each phase of the run took on stderr at its end, reading, sorting, filtering, dropping duplicates, and writing, with the megabytes and entries read per second. With `--report`, the report has them too'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand fish-to-zsh" -s h -l help -d 'Print help (see more with \'--help\')'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand stats" -l from -d 'The shell, or other program, which wrote the history. Everything said about the zsh history file applies to the others too' -r -f -a "auto\t'Tell the format from the first few KiB of the history'
+zsh\t'A zsh history file, with or without EXTENDED_HISTORY'
+bash\t'A bash history file, with or without `HISTTIMEFORMAT` timestamps'
+fish\t'A fish history file, e.g. to clean one up with `--dedup-by`'
+fc\t'The listing `fc -l` or `history` prints, preferably `fc -li` for the timestamps'
+psreadline\t'PowerShell\'s PSReadLine history, `ConsoleHost_history.txt`'
+xonsh\t'A xonsh JSON history session file, or the directory holding them, which are merged'
+histdb\t'The SQLite database of zsh-histdb, `~/.histdb/zsh-history.db`'
+atuin\t'The SQLite database of atuin, `~/.local/share/atuin/history.db`'
+nushell\t'nushell\'s history, either the plain text `history.txt` or the SQLite `history.sqlite3`'"
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand stats" -l tz -d 'The UTC offset the timestamps of `--from fc` are in, like `+09:00` or `UTC`. Defaults to the local time zone' -r
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand stats" -l encoding -d 'The encoding of the zsh history file, like `latin1`, `shift_jis`, or `euc-jp`, or `auto` to guess it from the contents' -r
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand stats" -l default-when -d 'The timestamp to give entries without one' -r -f -a "zero\t'Leave them at 0, the start of 1970'
+mtime\t'The modification time of the history file'
+spread\t'Increasing timestamps ending at the modification time of the history file, so fish keeps them in order'"
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand stats" -l max-line-bytes -d 'Skip records, including all lines of a multi-line command, longer than the given number of bytes. Defaults to 1 MiB' -r
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand stats" -l max-entries -d 'Stop reading the history after N entries, 0 for no limit, counting those parsed, before `--redact` and the like drop any. Unlike `--limit`, this keeps the oldest entries, so combined with it the newest of those first N are kept' -r
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand stats" -l dedup-by -d 'What makes two entries the same when merging into the destination or several histories with `--dup-policy keep-both`. The other policies collapse every occurrence of a command' -r -f -a "cmd\t'Entries with the same command are duplicates, regardless of when they were executed'
+cmd+when\t'Entries are duplicates only if both the command and the timestamp match'"
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand stats" -l dup-policy -d 'Which entry survives when merging duplicates. Unless both are kept, the destination is rewritten sorted by timestamp if any of its entries changed' -r -f -a "keep-newest\t'Collapse duplicates into one entry with the newest timestamp'
+keep-oldest\t'Collapse duplicates into one entry with the oldest timestamp'
+keep-both\t'Keep the existing history as is and append every entry not already present'"
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand stats" -l expand-aliases -d 'Expand the zsh aliases defined in FILE, as `alias` lines or what `alias` prints, in the first word of the commands, for the shell they are converted for, which does not know them' -r -F
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand stats" -l strip-prefix -d 'Strip WORD, like `sudo`, and its options off the front of the commands, if a command follows them, along with the wrappers `time`, `nice`, `command`, and `env`. A prefix of several words, like `poetry run`, is stripped as a whole. May be given several times' -r
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand stats" -l normalize -d 'Remove trailing whitespace and bare `;`s from the commands, and with `aggressive` dangling `&&` and `||` too, before looking for duplicates. Nothing quoted is touched, and neither are commands with a heredoc' -r -f -a "basic\t'Trailing whitespace and bare `;`s'
+aggressive\t'Also `&&` and `||` left dangling with nothing after them'"
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand stats" -l redact -d 'Skip the entries whose command looks like it contains a password, a token, or a key, or mask them with `***`. Known variable and option names, services\' tokens, private keys, and long random-looking tokens are detected' -r -f -a "skip\t'Drop the entries'
+mask\t'Replace the credentials with `***`, keeping the rest of the command'"
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand stats" -l redact-pattern -d 'Also take what REGEX matches for a credential, or its group named `secret` if it has one, with `--redact`, which it implies. May be given several times' -r
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand stats" -l min-length -d 'Skip commands shorter than N characters, not counting the whitespace around them' -r
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand stats" -l skip-common-list -d 'Skip the commands in FILE, one per line, with `--skip-common`, which it implies, instead of the built-in ones. Empty lines and lines starting with `#` are skipped' -r -F
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand stats" -l head -d 'Keep only the first N entries, in the order of the history, after `--since` and `--until` and before sorting and the other filters' -r
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand stats" -l tail -d 'Keep only the last N entries, in the order of the history, after `--since` and `--until` and before sorting and the other filters' -r
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand stats" -l since -d 'Keep only the entries from this time on: a Unix time, a `YYYY-MM-DD [HH:MM[:SS]]` in the local time zone, an RFC 3339 timestamp, or a time ago like `90d`, `6months`, or `2y`' -r
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand stats" -l until -d 'Keep only the entries up to this time, inclusive, given like `--since`' -r
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand stats" -l match -d 'Keep only the entries whose command matches REGEX, in the syntax of the `regex` crate. If given several times, the entries matching any of them are kept' -r
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand stats" -l command -d 'Keep only the entries whose command runs NAME, as its first word after any variable assignments, and after the wrappers `--strip-prefix` strips. May be given several times' -r
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand stats" -l exclude -d 'Drop the entries whose command matches REGEX, even if they match `--match`. May be given several times' -r
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand stats" -l exclude-file -d 'Drop the entries whose command matches any of the regexes in FILE, one per line, like `--exclude`. Empty lines and lines starting with `#` are skipped. May be given several times' -r -F
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand stats" -l allowlist -d 'Keep only the entries whose command starts with any of the prefixes in FILE, one per line, or matches any of its regexes, given as `re:<regex>`. Empty lines and lines starting with `#` are skipped. Applies together with `--match`, so an entry has to pass both' -r -F
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand stats" -l blocklist -d 'Drop the entries whose command starts with any of the prefixes in FILE, or matches any of its regexes, given like in `--allowlist`. Applies together with `--exclude`, and drops entries even if `--match` or `--allowlist` keep them' -r -F
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand stats" -l keep -d 'Which occurrence of a command `--unique` keeps, the one with the oldest or the newest timestamp. It stays where that occurrence was' -r -f -a "first\t'The occurrence with the oldest timestamp'
+last\t'The occurrence with the newest timestamp, which fish ranks by recency'"
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand stats" -l config -d 'Read the default options from PATH instead of `~/.config/zsh-history-to-fish/config.toml`, whose keys are the long names of the options, or from `$Z2F_CONFIG`. Those given on the command line, or by the `Z2F_` environment variables, win' -r -F
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand stats" -l report -d 'Write what the run did as JSON to PATH, or to stderr with `-`, at its end, even if it failed: the histories read, the entries each filter dropped, the duplicates removed, the entries written and where, and the lines skipped' -r -F
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand stats" -l stdin -d 'Read the zsh history from stdin'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand stats" -l include-rotated -d 'Also convert the rotated copies of the zsh history file next to it, like `.zsh_history.1`, `.zsh_history.old`, or `.zsh_history-20240131`, collapsing duplicates across them'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand stats" -l pick-largest -d 'Convert the largest history file found in a directory given as the zsh history, instead of erroring if there are several'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand stats" -l no-expand -d 'Take the paths given, like the zsh history, `--output`, `--backup-dir`, and `--report`, literally, instead of expanding a leading `~` and `$VAR` or `${VAR}` in them'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand stats" -l strict-utf8 -d 'Report every line which is not valid in the encoding of the zsh history file, with the offending bytes, and fail after reading the whole file'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand stats" -l lossy -d 'With `--strict-utf8`, replace the invalid bytes and carry on instead of failing'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand stats" -l include-partial -d 'Convert an incomplete last entry, which zsh may still be writing, instead of skipping it'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand stats" -l clamp-timestamps -d 'Clamp timestamps before 1970 or in the future to between 0 and now, instead of only reporting them'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand stats" -l strict -d 'Fail on every line which cannot be cleanly converted, like records which cannot be, invalid bytes, timestamps before 1970 or in the future, a cut off last entry, or control characters, listing them all once the histories are read, and write nothing'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand stats" -l interpolate-timestamps -d 'Give entries without a timestamp one between those of the entries around them. Any left without one, when no entry has a timestamp, get the `--default-when` one'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand stats" -l bump-duplicate-timestamps -d 'Move entries sharing a second with the entry before them a second later each, so fish keeps them in order, without crossing into the next later timestamp'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand stats" -l ignore-space -d 'Skip commands starting with a space, like zsh\'s HIST_IGNORE_SPACE option'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand stats" -l keep-empty -d 'Keep timestamped entries with an empty command instead of skipping them'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand stats" -l skip-invalid -d 'Skip entries with NUL bytes or other control characters instead of stripping NULs and escaping the rest'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand stats" -l expand-global-aliases -d 'Also expand the global aliases, defined with `alias -g`, in any word of the commands, with `--expand-aliases`'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand stats" -l strip-env-assignments -d 'Strip variable assignments, like `FOO=bar`, off the front of the commands, along with the wrappers `--strip-prefix` strips'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand stats" -l skip-multiline -d 'Skip commands which span several lines, rather than writing their newlines as `<\\n>` with `--format nushell`, or as the other formats do'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand stats" -l skip-common -d 'Skip the commands which are typed all the time without arguments, like `ls`, `cd`, and `clear`. A command only counts if it is exactly one of them, so `cd ..` is kept'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand stats" -l ignore-case -d 'Match `--match` and `--exclude` regardless of case, as `(?i)` does'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand stats" -l include-undated -d 'Keep the entries without a timestamp, which `--since` and `--until` would otherwise judge to be from 1970'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand stats" -l unique -d 'Collapse the entries with the same command into one, dropping the duplicates even without `--merge`'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand stats" -l normalize-dedup -d 'Compare commands for duplicates with their runs of whitespace, newlines included, collapsed into single spaces and their ends trimmed, with `--unique` and when merging. The entry kept keeps its command as it was'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand stats" -l dedup-case-insensitive -d 'Compare commands for duplicates regardless of case, like `--normalize-dedup`'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand stats" -l no-progress -d 'Show no progress while reading a large history, which is otherwise shown on stderr, as a bar if it is a terminal'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand stats" -s v -l verbose -d 'Report every line skipped, or converted despite looking wrong, on stderr, with its line number, why, and the start of it. Given twice, also dump the bytes of the line in hex'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand stats" -s q -l quiet -d 'Report nothing on stderr but errors, no warnings, summaries, or progress, for scripts'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand stats" -l print-config -d 'Print the options in effect, from the command line, the configuration file, or the defaults, as a configuration file, and exit'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand stats" -l timing -d 'Print how long each phase of the run took on stderr at its end, reading, sorting, filtering, dropping duplicates, and writing, with the megabytes and entries read per second. With `--report`, the report has them too'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand stats" -l json -d 'Print the statistics as a JSON object, for other tools to read'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand stats" -s h -l help -d 'Print help (see more with \'--help\')'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand check" -l warnings-as-errors -d 'Treat warnings as errors for the exit status'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand check" -s h -l help -d 'Print help'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand completions" -l install -d 'Write the fish completions into `~/.config/fish/completions` instead, where fish loads them from'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand completions" -l force -d 'Replace the fish completions installed before with `--install`'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand completions" -s h -l help -d 'Print help'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand man" -l out-dir -d 'Write the manual page of the command line, and one of each command, like `zsh-history-to-fish-convert.1`, into DIR instead, creating it' -r -F
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand man" -s h -l help -d 'Print help'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand help; and not __fish_seen_subcommand_from convert merge fish-to-zsh stats check completions man help" -f -a "convert" -d 'Convert a zsh history, which running without a command does too'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand help; and not __fish_seen_subcommand_from convert merge fish-to-zsh stats check completions man help" -f -a "merge" -d 'Merge a zsh history into the fish history, collapsing the duplicates, like `convert --merge --into-fish`, or into `--output`'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand help; and not __fish_seen_subcommand_from convert merge fish-to-zsh stats check completions man help" -f -a "fish-to-zsh" -d 'Convert a fish history into a zsh history, like `convert --from fish --to zsh`'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand help; and not __fish_seen_subcommand_from convert merge fish-to-zsh stats check completions man help" -f -a "stats" -d 'Print how many entries a history has, over which time, and its most run commands'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand help; and not __fish_seen_subcommand_from convert merge fish-to-zsh stats check completions man help" -f -a "check" -d 'Check an existing fish history file for problems'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand help; and not __fish_seen_subcommand_from convert merge fish-to-zsh stats check completions man help" -f -a "completions" -d 'Print the completion script for a shell, or install the one for fish'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand help; and not __fish_seen_subcommand_from convert merge fish-to-zsh stats check completions man help" -f -a "man" -d 'Print the manual page, or write one for each command into a directory'
+complete -c zsh-history-to-fish -n "__fish_zsh_history_to_fish_using_subcommand help; and not __fish_seen_subcommand_from convert merge fish-to-zsh stats check completions man help" -f -a "help" -d 'Print this message or the help of the given subcommand(s)'
@@ -0,0 +1,50 @@
+//! `--sort when` sorts by timestamp, keeping the order of entries sharing one, with those
+//! without one first, while `--sort input` keeps the order of each history. Several histories
+//! are merged before either.
+
+mod common;
+
+use common::Scratch;
+
+fn run(args: &[&str]) -> String {
+    let scratch = Scratch::new();
+    scratch.write("a", "undated\n: 1700000002:0;b\n: 1700000001:0;a\n: 1700000002:0;b2\n");
+    scratch.write("b", ": 1700000003:0;c\n: 1700000000:0;z\n");
+    scratch.convert(&[&["--to", "plain"], args].concat()).0
+}
+
+#[test]
+fn input_keeps_the_order() {
+    assert_eq!(run(&["--sort", "input", "a"]), "undated\nb\na\nb2\n");
+    assert_eq!(run(&["--sort", "none", "a"]), "undated\nb\na\nb2\n");
+}
+
+#[test]
+fn when_is_stable_with_undated_entries_first() {
+    assert_eq!(run(&["--sort", "when", "a"]), "undated\na\nb\nb2\n");
+}
+
+#[test]
+fn after_merging() {
+    assert_eq!(run(&["--sort", "when", "a", "b"]), "undated\nz\na\nb\nb2\nc\n");
+}
+
+#[test]
+fn interpolated_timestamps_sort_in_place() {
+    let args = ["--sort", "when", "--interpolate-timestamps", "a", "b"];
+    assert_eq!(run(&args), "z\nundated\na\nb\nb2\nc\n");
+}
+
+#[test]
+fn before_the_limit() {
+    assert_eq!(run(&["--sort", "when", "--limit", "3", "a"]), "a\nb\nb2\n");
+    assert_eq!(run(&["--sort", "input", "--limit", "3", "a"]), "b\na\nb2\n");
+}
+
+#[test]
+fn after_head_and_tail() {
+    // which entries they pick is where they are in the history
+    assert_eq!(run(&["--sort", "when", "--tail", "2", "a"]), "a\nb2\n");
+    assert_eq!(run(&["--sort", "when", "--head", "2", "a"]), "undated\nb\n");
+    assert_eq!(run(&["--sort", "when", "--tail", "3", "a", "b"]), "b\nb2\nc\n");
+}
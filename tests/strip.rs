@@ -0,0 +1,41 @@
+//! `--strip-prefix` and `--strip-env-assignments` strip wrappers off the front of commands,
+//! along with `time`, `nice`, `command`, and `env`, before `--unique` compares them.
+
+mod common;
+
+const HISTORY: &str = concat!(
+    ": 1:0;sudo env FOO=1 make install\n",
+    ": 2:0;make install\n",
+    ": 3:0;time cargo build\n",
+    ": 4:0;sudo -i\n",
+    ": 5:0;RUST_LOG=debug cargo run\n",
+    ": 6:0;echo sudo ls\n",
+);
+
+fn run(args: &[&str]) -> (String, String) {
+    common::convert(HISTORY, &[&["--to", "plain"], args].concat())
+}
+
+#[test]
+fn stacked_wrappers_before_unique() {
+    let args = ["--strip-prefix", "sudo", "--strip-env-assignments", "--unique"];
+    let (stdout, stderr) = run(&args);
+    assert_eq!(stdout, "make install\ncargo build\nsudo -i\ncargo run\necho sudo ls\n");
+    assert!(stderr.contains("stripped wrapper prefixes from 3 commands"), "{stderr}");
+    assert!(stderr.contains("dropped 1 duplicates with --unique"), "{stderr}");
+}
+
+#[test]
+fn assignments_only_when_asked() {
+    let (stdout, _) = run(&["--strip-prefix", "sudo"]);
+    let expected = "FOO=1 make install\nmake install\ncargo build\nsudo -i\n\
+                    RUST_LOG=debug cargo run\necho sudo ls\n";
+    assert_eq!(stdout, expected);
+}
+
+#[test]
+fn not_by_default() {
+    let (stdout, stderr) = run(&[]);
+    assert!(stdout.starts_with("sudo env FOO=1 make install\n"), "{stdout}");
+    assert!(!stderr.contains("stripped"), "{stderr}");
+}
@@ -0,0 +1,155 @@
+//! The commands, and the invocations from before there were any, which keep working unchanged.
+
+mod common;
+
+use std::{io::Write, process::Stdio};
+
+use common::{status, stderr, stdout, Scratch};
+
+const HISTORY: &str = ": 1700000000:0;ls -la\n: 1700000001:0;echo a\\\necho b\n";
+const FISH: &str =
+    "- cmd: ls -la\n  when: 1700000000\n- cmd: echo a\\necho b\n  when: 1700000001\n";
+
+#[test]
+fn history_without_a_command() {
+    let scratch = Scratch::new();
+    let history = scratch.write("zsh_history", HISTORY);
+    let output = scratch.run(&[history.to_str().unwrap()]);
+    assert_eq!(status(&output), 0);
+    assert_eq!(stdout(&output), FISH);
+    // the same as `convert`
+    assert_eq!(scratch.run(&["convert", history.to_str().unwrap()]).stdout, output.stdout);
+}
+
+#[test]
+fn options_without_a_command() {
+    let scratch = Scratch::new();
+    let history = scratch.write("zsh_history", HISTORY);
+    let output = scratch.run(&["--format", "plain", "--head", "1", history.to_str().unwrap()]);
+    assert_eq!((status(&output), stdout(&output)), (0, "ls -la\n".to_string()));
+    let out = scratch.path("out");
+    let output = scratch.run(&[history.to_str().unwrap(), "-o", out.to_str().unwrap()]);
+    assert_eq!(status(&output), 0);
+    assert_eq!(std::fs::read_to_string(out).unwrap(), FISH);
+}
+
+#[test]
+fn histfile_without_arguments() {
+    let scratch = Scratch::new();
+    let history = scratch.write("zsh_history", HISTORY);
+    let output = scratch.command().env("HISTFILE", &history).output().unwrap();
+    assert_eq!((status(&output), stdout(&output)), (0, FISH.to_string()));
+    // or ~/.zsh_history
+    scratch.write(".zsh_history", HISTORY);
+    assert_eq!(stdout(&scratch.run(&[])), FISH);
+}
+
+#[test]
+fn stdin_without_a_command() {
+    let scratch = Scratch::new();
+    let mut child = scratch
+        .command()
+        .arg("-")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(HISTORY.as_bytes()).unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert_eq!((status(&output), stdout(&output)), (0, FISH.to_string()));
+}
+
+#[test]
+fn merge_into_a_fish_history() {
+    let scratch = Scratch::new();
+    let history = scratch.write("zsh_history", HISTORY);
+    let fish = scratch.write("fish_history", "- cmd: pwd\n  when: 1600000000\n");
+    let output = scratch.run(&["merge", "-o", fish.to_str().unwrap(), history.to_str().unwrap()]);
+    assert_eq!(status(&output), 0);
+    let merged = std::fs::read_to_string(fish).unwrap();
+    assert_eq!(merged, format!("- cmd: pwd\n  when: 1600000000\n{FISH}"));
+}
+
+#[test]
+fn stats_and_check() {
+    let scratch = Scratch::new();
+    let history = scratch.write("zsh_history", HISTORY);
+    let output = scratch.run(&["stats", "--json", history.to_str().unwrap()]);
+    assert_eq!(status(&output), 0);
+    let stats = serde_json::from_slice::<serde_json::Value>(&output.stdout).unwrap();
+    assert_eq!(stats["entries"], 2, "{stats}");
+    let fish = scratch.write("fish_history", FISH);
+    assert_eq!(status(&scratch.run(&["check", fish.to_str().unwrap()])), 0);
+}
+
+#[test]
+fn fish_to_zsh_round_trips() {
+    let scratch = Scratch::new();
+    let fish = scratch.write("fish_history", FISH);
+    let output = scratch.run(&["fish-to-zsh", fish.to_str().unwrap()]);
+    assert_eq!((status(&output), stdout(&output)), (0, HISTORY.to_string()));
+}
+
+#[test]
+fn man_prints_and_writes_pages() {
+    let scratch = Scratch::new();
+    let output = scratch.run(&["man"]);
+    assert_eq!(status(&output), 0);
+    assert!(stdout(&output).starts_with(".ie \\n(.g .ds Aq \\(aq"));
+    assert!(stdout(&output).contains("\n.SH \"EXIT STATUS\"\n"));
+
+    assert_eq!(status(&scratch.run(&["man", "--out-dir", "man1"])), 0);
+    for name in ["zsh-history-to-fish.1", "zsh-history-to-fish-convert.1"] {
+        let page = std::fs::read_to_string(scratch.path("man1").join(name)).unwrap();
+        assert!(page.contains("\n.SH SYNOPSIS\n"), "no SYNOPSIS in {name}");
+    }
+}
+
+#[test]
+fn commands_take_only_their_options() {
+    let scratch = Scratch::new();
+    let history = scratch.write("zsh_history", HISTORY);
+    let history = history.to_str().unwrap();
+    for option in [["--format", "csv"], ["--sort", "when"], ["--count", "--json"]] {
+        let output = scratch.run(&["stats", option[0], option[1], history]);
+        assert_eq!(status(&output), 2, "stats takes {}", option[0]);
+        assert!(stderr(&output).contains("unexpected argument"), "{}", stderr(&output));
+    }
+    assert_eq!(status(&scratch.run(&["fish-to-zsh", "--format", "csv", history])), 2);
+
+    // but what they take from each other, they can check at run time
+    let fish = scratch.write("fish_history", "");
+    let output = scratch.run(&["merge", "--backup", "-o", fish.to_str().unwrap(), history]);
+    assert_eq!(status(&output), 0, "{}", stderr(&output));
+    let output = scratch.run(&["--backup", history]);
+    assert_eq!(status(&output), 2);
+    assert!(stderr(&output).contains("--backup needs a file to write to"), "{}", stderr(&output));
+}
+
+#[test]
+fn configuration_for_other_commands_is_left_out() {
+    let scratch = Scratch::new();
+    let history = scratch.write("zsh_history", HISTORY);
+    let config = scratch.write("config.toml", "format = \"plain\"\nsort = \"when\"\ncolour = 1\n");
+    let output = scratch.run(&[
+        "stats",
+        "--json",
+        "--config",
+        config.to_str().unwrap(),
+        history.to_str().unwrap(),
+    ]);
+    assert_eq!(status(&output), 0, "{}", stderr(&output));
+    let warnings = stderr(&output);
+    assert!(warnings.contains("colour is no option"), "{warnings}");
+    assert!(!warnings.contains("format") && !warnings.contains("sort"), "{warnings}");
+
+    let output = scratch
+        .command()
+        .args(["stats", "--json", history.to_str().unwrap()])
+        .env("Z2F_FORMAT", "plain")
+        .env("Z2F_JSON", "1")
+        .output()
+        .unwrap();
+    assert_eq!(status(&output), 0, "{}", stderr(&output));
+    assert_eq!(stderr(&output), "");
+}
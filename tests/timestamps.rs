@@ -0,0 +1,43 @@
+//! Timestamps before 1970 or in the future are reported with their lines, clamped with
+//! `--clamp-timestamps`, or fail the conversion with `--strict`.
+
+mod common;
+
+use common::{status, stderr, stdout, Scratch};
+
+const HISTORY: &str = ": 1700000000:0;ls\n: 99999999999999:0;future\n: 1700000001:0;pwd\n";
+
+#[test]
+fn reported_with_their_lines() {
+    let scratch = Scratch::new();
+    let history = scratch.write("zsh_history", HISTORY);
+    let output = scratch.run(&["convert", history.to_str().unwrap()]);
+    assert_eq!(status(&output), 0);
+    assert!(stdout(&output).contains("- cmd: future\n  when: 99999999999999\n"));
+    assert!(stderr(&output).contains(
+        "1 entries have a timestamp before 1970 or in the future, on lines 2, pass \
+         --clamp-timestamps to clamp them"
+    ));
+}
+
+#[test]
+fn clamped() {
+    let scratch = Scratch::new();
+    let history = scratch.write("zsh_history", HISTORY);
+    let output = scratch.run(&["convert", "--clamp-timestamps", history.to_str().unwrap()]);
+    assert_eq!(status(&output), 0);
+    assert!(!stdout(&output).contains("99999999999999"));
+    assert!(stderr(&output).contains("clamped them to [0, "), "{}", stderr(&output));
+}
+
+#[test]
+fn strict_lists_the_lines() {
+    let scratch = Scratch::new();
+    let history = scratch.write("zsh_history", HISTORY);
+    let output = scratch.run(&["convert", "--strict", history.to_str().unwrap()]);
+    assert_eq!(status(&output), 4);
+    assert_eq!(stdout(&output), "");
+    let stderr = stderr(&output);
+    assert!(stderr.contains("line 2 of "), "{stderr}");
+    assert!(stderr.contains(": bad timestamp: \"future\""), "{stderr}");
+}
@@ -0,0 +1,37 @@
+//! `--verify` reads what it would write back, and writes it only if every entry comes back whole.
+
+mod common;
+
+use common::{status, stderr, stdout, Scratch};
+
+const HISTORY: &str = concat!(
+    ": 1700000000:0;echo \"it's\" 'a \"quote\"'\n",
+    ": 1700000001:0;grep foo \\*.rs\n",
+    ": 1700000002:0;printf '\\n'\n",
+    ": 1700000003:0;echo 日本 😀\n",
+    ": 1700000004:0;cat <<EOF\\\n",
+    "  when: 12\\\n",
+    "EOF\n",
+);
+
+#[test]
+fn writes_what_round_trips() {
+    let scratch = Scratch::new();
+    scratch.write("zsh_history", HISTORY);
+    let output = scratch.run(&["convert", "--verify", "-o", "fish_history", "zsh_history"]);
+    assert_eq!(status(&output), 0, "{}", stderr(&output));
+    let unverified = scratch.run(&["convert", "zsh_history"]);
+    let fish = std::fs::read_to_string(scratch.path("fish_history")).unwrap();
+    assert_eq!(fish, stdout(&unverified));
+    assert_eq!(fish.matches("- cmd: ").count(), 5);
+    assert!(fish.contains("- cmd: cat <<EOF\\n  when: 12\\nEOF\n  when: 1700000004\n"), "{fish}");
+}
+
+#[test]
+fn to_stdout() {
+    let scratch = Scratch::new();
+    scratch.write("zsh_history", HISTORY);
+    let output = scratch.run(&["convert", "--verify", "zsh_history"]);
+    assert_eq!(status(&output), 0, "{}", stderr(&output));
+    assert_eq!(stdout(&output), stdout(&scratch.run(&["convert", "zsh_history"])));
+}